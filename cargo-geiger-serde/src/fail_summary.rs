@@ -0,0 +1,42 @@
+use crate::PackageId;
+use serde::{Deserialize, Serialize};
+
+/// Gate of the scan which made cargo-geiger exit with an error
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum FailGate {
+    /// `--fail-if-no-forbid`
+    FailIfNoForbid,
+    /// `--fail-on-any-unsafe`
+    FailOnAnyUnsafe,
+    /// `--fail-threshold`
+    FailThreshold,
+    /// `--max-total-crates`
+    MaxTotalCrates,
+    /// `--quiet-unsafe-free`, when unsafe usage was found
+    QuietUnsafeFree,
+    /// `--require-source-url`
+    RequireSourceUrl,
+    /// Warnings were printed during the scan
+    Warnings,
+}
+
+/// Crate which contributed to the failure of a gate
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FailSummaryCrate {
+    pub package: PackageId,
+    /// The unsafe expressions used by the build, for `--fail-threshold`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unsafe_exprs: Option<u64>,
+}
+
+/// Summary of the gate a run failed on, written by `--fail-summary-file`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FailSummary {
+    pub gate: FailGate,
+    /// The error message of the gate, when it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// The crates which made the gate fail, empty when the gate is about the
+    /// whole dependency tree
+    pub crates: Vec<FailSummaryCrate>,
+}
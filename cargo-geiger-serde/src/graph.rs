@@ -0,0 +1,22 @@
+use crate::{DependencyKind, PackageId};
+use serde::{Deserialize, Serialize};
+
+/// Dependency of a package on another package
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct DependencyEdge {
+    /// The dependent package
+    pub from: PackageId,
+    /// The package depended on
+    pub to: PackageId,
+    pub kind: DependencyKind,
+}
+
+/// Dependency graph of the packages resolved for the scan, written by
+/// `--dump-graph-json`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DependencyGraph {
+    /// The package the graph was built from
+    pub root: PackageId,
+    pub nodes: Vec<PackageId>,
+    pub edges: Vec<DependencyEdge>,
+}
@@ -1,18 +1,29 @@
 //! cargo-geiger-serde ☢
 //! ========
 //!
-//! This crate provides definitions to serialize the unsafety report.
+//! This crate provides definitions to serialize the unsafety report, the
+//! dependency graph and the summary of a failed run.
 
 #![forbid(unsafe_code)]
 #![deny(warnings)]
 
+mod fail_summary;
+mod graph;
 mod package_id;
 mod report;
 mod source;
 
+pub use fail_summary::{FailGate, FailSummary, FailSummaryCrate};
+pub use graph::{DependencyEdge, DependencyGraph};
 pub use package_id::PackageId;
 pub use report::{
-    Count, CounterBlock, DependencyKind, PackageInfo, QuickReportEntry,
-    QuickSafetyReport, ReportEntry, SafetyReport, UnsafeInfo,
+    AllowedUnsafeCounts, CategoryDelta, Count, CounterBlock, DependencyKind,
+    ModuleUnsafety, PackageAdvisories, PackageCustomPatternCounts,
+    PackageDelta, PackageInfo, PackageNightlyFeatures, PackageRiskScore,
+    PackageSourceHash, PackageTestUnsafety, PackageVersionChange,
+    QuickReportEntry, QuickSafetyReport, ReportDelta, ReportEntry,
+    ReportMetadata, SafetyGrade, SafetyReport, StaticMutCounts,
+    StdCrateUnsafety, UnsafeChange, UnsafeFreeCounts, UnsafeInfo,
+    UnsafeKindCounts, UnsafeKinds,
 };
 pub use source::Source;
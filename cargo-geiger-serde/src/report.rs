@@ -1,7 +1,8 @@
 use crate::PackageId;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ops::{Add, AddAssign},
     path::PathBuf,
 };
@@ -60,19 +61,303 @@ pub struct QuickSafetyReport {
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ReportEntry {
     pub package: PackageInfo,
+    /// Features of the package enabled by the dependency resolution, sorted
+    #[serde(default)]
+    pub active_features: Vec<String>,
     /// Unsafety scan results
     pub unsafety: UnsafeInfo,
 }
 
+/// Advisories affecting a package, from a local RustSec advisory database
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageAdvisories {
+    pub package: PackageId,
+    /// Ids of the advisories, e.g. `RUSTSEC-2020-0001`
+    pub advisory_ids: Vec<String>,
+}
+
+/// Weighted unsafe usage of a package, from the weights given by `--weights`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageRiskScore {
+    pub package: PackageId,
+    /// Weighted sum of the unsafe usage in code used by the build
+    pub risk_score: u64,
+}
+
+/// Features enabled with `#![feature(...)]` by a package, which then only
+/// builds with a nightly toolchain, for `--flag-nightly-features`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageNightlyFeatures {
+    pub package: PackageId,
+    /// Features enabled in code used by the build, sorted
+    pub features: Vec<String>,
+}
+
+/// Counts of `static mut` usage
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StaticMutCounts {
+    /// `static mut` declarations
+    pub definitions: u64,
+    /// Accesses to the `static mut` declared in the same file
+    pub accesses: u64,
+}
+
+/// Hash of the source of a package, for `--json-include-source-hash`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageSourceHash {
+    pub package: PackageId,
+    /// Hex encoded Sha-256 of the content of the scanned files, concatenated
+    /// in the order of their paths
+    pub source_hash: String,
+}
+
+/// Counts of unsafe usage by `unsafe_code` lint level
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AllowedUnsafeCounts {
+    /// Unsafe usage under an explicit `#[allow(unsafe_code)]` or
+    /// `#[expect(unsafe_code)]`
+    pub allowed: u64,
+    /// The other unsafe usage
+    pub unannotated: u64,
+}
+
+/// Unsafe usage inside `#[cfg(test)]` modules and `#[test]` functions of a
+/// package, for `--count-cfg-test-separately`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageTestUnsafety {
+    pub package: PackageId,
+    /// Unsafe usage statistics for test code in files used by the project
+    pub used: CounterBlock,
+    /// Unsafe usage statistics for test code in files not used by the project
+    pub unused: CounterBlock,
+}
+
+/// Matches of the patterns given by `--custom-pattern` in a package
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageCustomPatternCounts {
+    pub package: PackageId,
+    /// Number of matches of each pattern, by pattern name
+    pub counts: BTreeMap<String, u64>,
+}
+
+/// Unsafe usage found in the source of a crate of the standard library, for
+/// `--include-std`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StdCrateUnsafety {
+    /// Name of the crate, e.g. `core`
+    pub name: String,
+    /// Unsafe usage statistics for all the code of the crate, which is not
+    /// built
+    pub unsafety: CounterBlock,
+}
+
+/// How the unsafe usage in code used by the build changed between two
+/// versions of a crate
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum UnsafeChange {
+    Increased,
+    Decreased,
+    Unchanged,
+    /// The earlier version used no `unsafe`
+    New,
+}
+
+/// Change of the unsafe usage of a package whose version differs from the
+/// baseline report given to `--diff`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageVersionChange {
+    pub package: PackageId,
+    /// Version of the package in the baseline report
+    pub baseline_version: Version,
+    pub change: UnsafeChange,
+}
+
+/// Differences between the baseline report given to `--diff` and the report
+/// of the scan, for `--diff-format`. The packages of both reports are matched
+/// by name and version.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ReportDelta {
+    /// Packages which are not in the baseline report
+    pub added: Vec<PackageId>,
+    /// Packages of the baseline report which are no longer scanned
+    pub removed: Vec<PackageId>,
+    /// Packages of both reports whose unsafe usage in code used by the build
+    /// changed
+    pub changed: Vec<PackageDelta>,
+    /// Entries of the report of the scan for the added and changed packages,
+    /// only included with `--diff-full`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<ReportEntry>,
+}
+
+/// Change of the unsafe usage in code used by the build of a package of both
+/// reports
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageDelta {
+    pub package: PackageId,
+    /// The categories whose number of unsafe items changed
+    pub categories: Vec<CategoryDelta>,
+}
+
+/// Number of unsafe items of a category of `CounterBlock`, e.g. `exprs`, in
+/// the baseline report and in the report of the scan
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CategoryDelta {
+    pub category: String,
+    pub baseline: u64,
+    pub current: u64,
+}
+
+/// Grade of the unsafe usage of a dependency tree, from `A` when no crate
+/// uses `unsafe` to `F`
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub enum SafetyGrade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+/// Number of packages free of `unsafe` usage, out of the packages which are
+/// not ignored
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+pub struct UnsafeFreeCounts {
+    /// Number of packages which are not ignored
+    pub package_count: u64,
+    /// Number of packages without `unsafe` usage in code used by the build
+    pub used_unsafe_free_count: u64,
+    /// Number of packages without `unsafe` usage at all, used by the build or
+    /// not
+    pub unsafe_free_count: u64,
+}
+
+/// Provenance of a report, recording how it was generated
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ReportMetadata {
+    /// Version of cargo-geiger that generated the report
+    pub geiger_version: String,
+    /// Version of the rustc used for the build, if it could be determined
+    pub rustc_version: Option<String>,
+    /// Command line flags cargo-geiger was invoked with
+    pub flags: Vec<String>,
+}
+
 /// Report generated from scanning for the use of `unsafe`
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct SafetyReport {
+    #[serde(default)]
+    pub metadata: ReportMetadata,
     #[serde(with = "entry_serde")]
     pub packages: HashMap<PackageId, ReportEntry>,
+    /// Packages only used by build scripts, reported apart from `packages`
+    /// when scanned with `--separate-build-report`
+    #[serde(
+        default,
+        with = "entry_serde",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub build_packages: HashMap<PackageId, ReportEntry>,
     #[serde(serialize_with = "set_serde::serialize")]
     pub packages_without_metrics: HashSet<PackageId>,
     #[serde(serialize_with = "set_serde::serialize")]
     pub used_but_not_scanned_files: HashSet<PathBuf>,
+    /// Generated files which were not scanned, with `--exclude-generated`
+    #[serde(
+        default,
+        serialize_with = "set_serde::serialize",
+        skip_serializing_if = "HashSet::is_empty"
+    )]
+    pub excluded_files: HashSet<PathBuf>,
+    /// Files larger than `--max-file-size` which were not scanned
+    #[serde(
+        default,
+        serialize_with = "set_serde::serialize",
+        skip_serializing_if = "HashSet::is_empty"
+    )]
+    pub oversized_files: HashSet<PathBuf>,
+    /// Packages whose unsafe usage was ignored with `--ignore-unsafe-in`
+    #[serde(
+        default,
+        serialize_with = "set_serde::serialize",
+        skip_serializing_if = "HashSet::is_empty"
+    )]
+    pub ignored_packages: HashSet<PackageId>,
+    /// Packages only pulled by optional dependencies whose feature is not
+    /// enabled, when scanned with `--include-optional`
+    #[serde(
+        default,
+        serialize_with = "set_serde::serialize",
+        skip_serializing_if = "HashSet::is_empty"
+    )]
+    pub inactive_optional_packages: HashSet<PackageId>,
+    /// Packages affected by advisories, when scanned with `--advisories`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub advisories: Vec<PackageAdvisories>,
+    /// Risk score of each package, when scanned with `--weights`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub risk_scores: Vec<PackageRiskScore>,
+    /// Features enabled with `#![feature(...)]` by the packages enabling
+    /// any, when scanned with `--flag-nightly-features`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nightly_features: Vec<PackageNightlyFeatures>,
+    /// Hash of the source of each package, when scanned with
+    /// `--json-include-source-hash`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_hashes: Vec<PackageSourceHash>,
+    /// Unsafe usage in the test code of each package, counted apart from the
+    /// other code when scanned with `--count-cfg-test-separately`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub test_unsafety: Vec<PackageTestUnsafety>,
+    /// Matches of the custom patterns in each package, when scanned with
+    /// `--custom-pattern`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_pattern_counts: Vec<PackageCustomPatternCounts>,
+    /// Packages whose version differs from the baseline report, when scanned
+    /// with `--diff`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub version_changes: Vec<PackageVersionChange>,
+    /// Unsafe usage of the `core`, `alloc` and `std` crates, left out of the
+    /// totals, when scanned with `--include-std`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub std_crates: Vec<StdCrateUnsafety>,
+    /// Grade of the unsafe usage of the packages which are not ignored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_grade: Option<SafetyGrade>,
+    /// Number of packages free of unsafe usage, out of the packages which are
+    /// not ignored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unsafe_free_counts: Option<UnsafeFreeCounts>,
+    /// Whether the scan stopped before all the packages were scanned, when
+    /// the `--deadline` was exceeded, or before all the files of a package
+    /// were scanned, when the `--scan-timeout-per-crate` was exceeded
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub partial: bool,
+    /// Packages which were not scanned because the `--deadline` was exceeded
+    #[serde(
+        default,
+        serialize_with = "set_serde::serialize",
+        skip_serializing_if = "HashSet::is_empty"
+    )]
+    pub unscanned_packages: HashSet<PackageId>,
+    /// Packages whose scan was stopped because the
+    /// `--scan-timeout-per-crate` was exceeded, with the metrics of the files
+    /// scanned before, if any
+    #[serde(
+        default,
+        serialize_with = "set_serde::serialize",
+        skip_serializing_if = "HashSet::is_empty"
+    )]
+    pub timed_out_packages: HashSet<PackageId>,
+}
+
+/// Whether a flag of the report is unset, to leave it out of the Json
+fn is_false(value: &bool) -> bool {
+    !value
 }
 
 /// Unsafety usage in a package
@@ -84,6 +369,78 @@ pub struct UnsafeInfo {
     pub unused: CounterBlock,
     /// Whether this package forbids the use of `unsafe`
     pub forbids_unsafe: bool,
+    /// Unsafe usage statistics of each module of the package, by module path,
+    /// only counted with `--module-level`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub modules: BTreeMap<String, ModuleUnsafety>,
+    /// Kinds of unsafe usage of the package, only counted with the flags
+    /// enabling them, e.g. `--raw-derefs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kinds: Option<UnsafeKinds>,
+}
+
+/// Unsafety usage in a module of a package, including its submodules
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ModuleUnsafety {
+    /// Unsafe usage statistics for code used by the project
+    pub used: CounterBlock,
+    /// Unsafe usage statistics for code not used by the project
+    pub unused: CounterBlock,
+}
+
+/// Kinds of unsafe usage in a package
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UnsafeKinds {
+    /// Kinds of unsafe usage in code used by the project
+    pub used: UnsafeKindCounts,
+    /// Kinds of unsafe usage in code not used by the project
+    pub unused: UnsafeKindCounts,
+}
+
+/// Counts of the kinds of unsafe usage, `None` for the kinds which were not
+/// counted
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct UnsafeKindCounts {
+    /// Dereferences of raw pointers in `unsafe` code, with `--raw-derefs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_derefs: Option<u64>,
+    /// `unsafe` tokens inside `macro_rules!` definitions, with
+    /// `--latent-macro-unsafe`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latent_macro_unsafe: Option<u64>,
+    /// Functions, methods and statics exported with `#[no_mangle]` or
+    /// `#[export_name]`, with `--exported-symbols`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exported_symbols: Option<u64>,
+    /// Calls in unsafe code to the functions managing the lifecycle of
+    /// values by hand, such as `MaybeUninit::assume_init`, with
+    /// `--memory-lifecycle-unsafe`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_lifecycle_unsafe: Option<u64>,
+    /// `static mut` declarations and the accesses to them, with
+    /// `--static-mut`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_mut: Option<StaticMutCounts>,
+    /// Unsafe usage under an explicit `#[allow(unsafe_code)]` and
+    /// unannotated, with `--allowed-unsafe`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_unsafe: Option<AllowedUnsafeCounts>,
+    /// References to unsafe traits in bounds and trait objects, with
+    /// `--unsafe-trait-usage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unsafe_trait_usage: Option<u64>,
+    /// Unsafe usage inside `impl Drop for` blocks, with `--drop-unsafe`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drop_unsafe: Option<u64>,
+    /// Functions passed as `extern` function pointers to calls in unsafe
+    /// code, the callbacks given to foreign code, with `--ffi-callbacks`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ffi_callbacks: Option<u64>,
+    /// Calls of `transmute` on types declared with `#[repr(...)]`, which
+    /// depend on their layout, with `--layout-dependent-unsafe`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout_dependent_unsafe: Option<u64>,
 }
 
 /// Kind of dependency for a package
@@ -134,8 +491,10 @@ impl AddAssign for Count {
     }
 }
 
-/// Unsafe usage metrics collection.
+/// Unsafe usage metrics collection. The categories left out of the report
+/// by `--compact-json` are deserialized to a zero count.
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
 pub struct CounterBlock {
     pub functions: Count,
     pub exprs: Count,
@@ -152,6 +511,15 @@ impl CounterBlock {
             || self.item_traits.unsafe_ > 0
             || self.methods.unsafe_ > 0
     }
+
+    /// The total number of unsafe items
+    pub fn unsafe_count(&self) -> u64 {
+        self.functions.unsafe_
+            + self.exprs.unsafe_
+            + self.item_impls.unsafe_
+            + self.item_traits.unsafe_
+            + self.methods.unsafe_
+    }
 }
 
 impl Add for CounterBlock {
@@ -174,6 +542,80 @@ impl AddAssign for CounterBlock {
     }
 }
 
+impl Add for StaticMutCounts {
+    type Output = StaticMutCounts;
+
+    fn add(self, other: StaticMutCounts) -> StaticMutCounts {
+        StaticMutCounts {
+            definitions: self.definitions + other.definitions,
+            accesses: self.accesses + other.accesses,
+        }
+    }
+}
+
+impl Add for AllowedUnsafeCounts {
+    type Output = AllowedUnsafeCounts;
+
+    fn add(self, other: AllowedUnsafeCounts) -> AllowedUnsafeCounts {
+        AllowedUnsafeCounts {
+            allowed: self.allowed + other.allowed,
+            unannotated: self.unannotated + other.unannotated,
+        }
+    }
+}
+
+/// The kinds counted in either count only are kept as they are.
+impl Add for UnsafeKindCounts {
+    type Output = UnsafeKindCounts;
+
+    fn add(self, other: UnsafeKindCounts) -> UnsafeKindCounts {
+        UnsafeKindCounts {
+            raw_derefs: add_counts(self.raw_derefs, other.raw_derefs),
+            latent_macro_unsafe: add_counts(
+                self.latent_macro_unsafe,
+                other.latent_macro_unsafe,
+            ),
+            exported_symbols: add_counts(
+                self.exported_symbols,
+                other.exported_symbols,
+            ),
+            memory_lifecycle_unsafe: add_counts(
+                self.memory_lifecycle_unsafe,
+                other.memory_lifecycle_unsafe,
+            ),
+            static_mut: add_counts(self.static_mut, other.static_mut),
+            allowed_unsafe: add_counts(
+                self.allowed_unsafe,
+                other.allowed_unsafe,
+            ),
+            unsafe_trait_usage: add_counts(
+                self.unsafe_trait_usage,
+                other.unsafe_trait_usage,
+            ),
+            drop_unsafe: add_counts(self.drop_unsafe, other.drop_unsafe),
+            ffi_callbacks: add_counts(self.ffi_callbacks, other.ffi_callbacks),
+            layout_dependent_unsafe: add_counts(
+                self.layout_dependent_unsafe,
+                other.layout_dependent_unsafe,
+            ),
+        }
+    }
+}
+
+impl AddAssign for UnsafeKindCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.clone() + rhs;
+    }
+}
+
+fn add_counts<T: Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
 trait Entry {
     fn package_id(&self) -> &PackageId;
 }
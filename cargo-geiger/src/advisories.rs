@@ -0,0 +1,275 @@
+use cargo_metadata::semver::{Version, VersionReq};
+use cargo_metadata::Metadata;
+use cargo_metadata::PackageId;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A RustSec advisory, reduced to what is needed to match it against the
+/// resolved crate versions.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Advisory {
+    pub id: String,
+    pub package: String,
+    patched: Vec<VersionReq>,
+    unaffected: Vec<VersionReq>,
+}
+
+impl Advisory {
+    /// Whether the given crate version is affected by this advisory, i.e.
+    /// neither patched nor unaffected.
+    pub fn affects(&self, name: &str, version: &Version) -> bool {
+        name == self.package
+            && !self
+                .patched
+                .iter()
+                .chain(self.unaffected.iter())
+                .any(|version_req| version_req.matches(version))
+    }
+}
+
+/// The advisories of a local clone of the RustSec advisory database. The
+/// database is only read, never fetched or updated.
+#[derive(Debug, Default)]
+pub struct AdvisoryDatabase {
+    advisories: Vec<Advisory>,
+}
+
+impl AdvisoryDatabase {
+    /// Load every advisory under the `crates` directory of the database.
+    /// Withdrawn advisories are skipped.
+    pub fn load(path: &Path) -> Result<AdvisoryDatabase, AdvisoryError> {
+        let crates_path = path.join("crates");
+        if !crates_path.is_dir() {
+            return Err(AdvisoryError::NotADatabase(path.to_path_buf()));
+        }
+
+        let mut advisories = Vec::new();
+        for entry in WalkDir::new(&crates_path) {
+            let entry = entry.map_err(|e| {
+                AdvisoryError::Io(e.into(), crates_path.clone())
+            })?;
+            let is_markdown_file = entry.file_type().is_file()
+                && entry.path().extension().map_or(false, |ext| ext == "md");
+            if !is_markdown_file {
+                continue;
+            }
+            if let Some(advisory) = parse_advisory_file(entry.path())? {
+                advisories.push(advisory);
+            }
+        }
+        advisories.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(AdvisoryDatabase { advisories })
+    }
+
+    /// The ids of the advisories affecting the given crate version.
+    pub fn advisory_ids_for(
+        &self,
+        name: &str,
+        version: &Version,
+    ) -> Vec<String> {
+        self.advisories
+            .iter()
+            .filter(|advisory| advisory.affects(name, version))
+            .map(|advisory| advisory.id.clone())
+            .collect()
+    }
+
+    /// The ids of the advisories affecting each package of the metadata, for
+    /// the packages affected by at least one advisory.
+    pub fn advisory_ids_by_package(
+        &self,
+        metadata: &Metadata,
+    ) -> HashMap<PackageId, Vec<String>> {
+        metadata
+            .packages
+            .iter()
+            .filter_map(|package| {
+                let advisory_ids =
+                    self.advisory_ids_for(&package.name, &package.version);
+                if advisory_ids.is_empty() {
+                    None
+                } else {
+                    Some((package.id.clone(), advisory_ids))
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum AdvisoryError {
+    Io(io::Error, PathBuf),
+    MissingFrontMatter(PathBuf),
+    NotADatabase(PathBuf),
+    Toml(toml::de::Error, PathBuf),
+    VersionReq(cargo_metadata::semver::Error, PathBuf),
+}
+
+impl Error for AdvisoryError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for AdvisoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Deserialize)]
+struct AdvisoryFile {
+    advisory: AdvisorySection,
+    #[serde(default)]
+    versions: VersionsSection,
+}
+
+#[derive(Deserialize)]
+struct AdvisorySection {
+    id: String,
+    package: String,
+    withdrawn: Option<toml::Value>,
+}
+
+#[derive(Default, Deserialize)]
+struct VersionsSection {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// Each advisory is a markdown file, starting with a fenced TOML block.
+fn extract_front_matter(content: &str) -> Option<&str> {
+    let start = content.find("```toml")? + "```toml".len();
+    let length = content[start..].find("```")?;
+    Some(&content[start..start + length])
+}
+
+fn parse_advisory_file(path: &Path) -> Result<Option<Advisory>, AdvisoryError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| AdvisoryError::Io(e, path.to_path_buf()))?;
+    let front_matter = extract_front_matter(&content)
+        .ok_or_else(|| AdvisoryError::MissingFrontMatter(path.to_path_buf()))?;
+    let advisory_file = toml::from_str::<AdvisoryFile>(front_matter)
+        .map_err(|e| AdvisoryError::Toml(e, path.to_path_buf()))?;
+
+    if advisory_file.advisory.withdrawn.is_some() {
+        return Ok(None);
+    }
+
+    let parse_version_reqs = |version_reqs: &[String]| {
+        version_reqs
+            .iter()
+            .map(|version_req| {
+                VersionReq::parse(version_req).map_err(|e| {
+                    AdvisoryError::VersionReq(e, path.to_path_buf())
+                })
+            })
+            .collect::<Result<Vec<VersionReq>, AdvisoryError>>()
+    };
+
+    Ok(Some(Advisory {
+        id: advisory_file.advisory.id,
+        package: advisory_file.advisory.package,
+        patched: parse_version_reqs(&advisory_file.versions.patched)?,
+        unaffected: parse_version_reqs(&advisory_file.versions.unaffected)?,
+    }))
+}
+
+#[cfg(test)]
+mod advisories_tests {
+    use super::*;
+
+    use rstest::*;
+    use tempfile::tempdir;
+
+    const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0001"
+package = "fixture-crate"
+date = "2020-01-01"
+
+[versions]
+patched = [">= 0.3.0"]
+unaffected = ["< 0.1.0"]
+```
+
+# Fixture advisory
+
+Affects the 0.1 and 0.2 releases.
+"#;
+
+    const WITHDRAWN_ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0002"
+package = "fixture-crate"
+date = "2020-01-01"
+withdrawn = "2020-02-01"
+
+[versions]
+patched = []
+```
+"#;
+
+    #[rstest(
+        input_name,
+        input_version,
+        expected_advisory_ids,
+        case("fixture-crate", "0.2.1", vec!["RUSTSEC-2020-0001"]),
+        case("fixture-crate", "0.3.0", vec![]),
+        case("fixture-crate", "0.0.9", vec![]),
+        case("other-crate", "0.2.1", vec![])
+    )]
+    fn advisory_database_advisory_ids_for_test(
+        input_name: &str,
+        input_version: &str,
+        expected_advisory_ids: Vec<&str>,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let crate_dir = temp_dir.path().join("crates").join("fixture-crate");
+        fs::create_dir_all(&crate_dir).unwrap();
+        fs::write(crate_dir.join("RUSTSEC-2020-0001.md"), ADVISORY).unwrap();
+        fs::write(crate_dir.join("RUSTSEC-2020-0002.md"), WITHDRAWN_ADVISORY)
+            .unwrap();
+
+        let advisory_database =
+            AdvisoryDatabase::load(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            advisory_database.advisory_ids_for(
+                input_name,
+                &Version::parse(input_version).unwrap()
+            ),
+            expected_advisory_ids
+        );
+    }
+
+    #[rstest]
+    fn advisory_database_load_errors_without_crates_dir_test() {
+        let temp_dir = tempdir().unwrap();
+
+        assert!(matches!(
+            AdvisoryDatabase::load(temp_dir.path()),
+            Err(AdvisoryError::NotADatabase(_))
+        ));
+    }
+
+    #[rstest(
+        input_content,
+        expected_front_matter,
+        case("```toml\nid = 1\n```\n# Title", Some("\nid = 1\n")),
+        case("# Title", None)
+    )]
+    fn extract_front_matter_test(
+        input_content: &str,
+        expected_front_matter: Option<&str>,
+    ) {
+        assert_eq!(extract_front_matter(input_content), expected_front_matter);
+    }
+}
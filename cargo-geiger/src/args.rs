@@ -1,10 +1,17 @@
 use crate::args::Verbosity::{Normal, Quiet, Verbose};
-use crate::format::print_config::OutputFormat;
+use crate::custom_patterns::CustomPattern;
+use crate::format::pattern::PLACEHOLDERS;
+use crate::format::print_config::{OutputFormat, PrintConfig};
+use crate::format::report_fields::REPORT_ENTRY_FIELDS;
 
 use cargo::core::shell::ColorChoice;
 use cargo::{CliResult, GlobalContext};
+use krates::PkgSpec;
 use pico_args::Arguments;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use strum_macros::EnumString;
 
 /// Constant `&str` containing help text
 pub const HELP: &str =
@@ -15,10 +22,32 @@ USAGE:
 
 OPTIONS:
     -p, --package <SPEC>          Package to be used as the root of the tree.
+        --workspace               Scan every member of the workspace, each as
+                                  the root of its own tree. Either this or
+                                  --package is required in a virtual
+                                  workspace.
         --features <FEATURES>     Space-separated list of features to activate.
         --all-features            Activate all available features.
         --no-default-features     Do not activate the `default` feature.
+        --strict-features         Exit with an error, listing the valid
+                                  features, if a feature given to --features
+                                  is not declared by the root package.
+        --feature-impact <FEATURE>
+                                  List the crates pulled in by a feature of
+                                  the root package, with the unsafe usage
+                                  found in them, instead of displaying the
+                                  tree. The dependencies are resolved with
+                                  the other features, and with and without
+                                  FEATURE.
         --target <TARGET>         Set the target triple.
+        --target-from-config      Use the `[build] target` of the cargo
+                                  configuration as the target triple, unless
+                                  --target is provided.
+        --compare-targets <TARGET>,<TARGET>
+                                  Compare the unsafe usage found in the
+                                  dependencies of two target triples, instead
+                                  of displaying the tree. --target and
+                                  --all-targets are ignored.
         --all-targets             Return dependencies for all targets. By
                                   default only the host target is matched.
         --manifest-path <PATH>    Path to Cargo.toml.
@@ -27,67 +56,513 @@ OPTIONS:
                                   than a tree).
         --prefix-depth            Display the dependencies as a list (rather
                                   than a tree), but prefixed with the depth.
+        --rank                    Prefix each crate of the list displayed with
+                                  --no-indent with its position in the list
+                                  and its number of unsafe items used by the
+                                  build.
     -a, --all                     Don't truncate dependencies that have already
                                   been displayed.
+        --depth <N>               Only display the dependencies up to N levels
+                                  below the root package, with
+                                  --output-format Tree.
+        --max-depth-warn <N>      Warn about dependency paths from the root
+                                  package deeper than N.
+        --max-total-crates <N>    Exit with an error if the dependency graph
+                                  has more than N crates, including the root
+                                  package.
+        --require-source-url      Exit with an error if a crate of the
+                                  dependency graph has neither a repository
+                                  in its manifest nor a git source, listing
+                                  these crates.
+        --deadline <SECONDS>      Stop scanning further crates once the run
+                                  has lasted SECONDS, and print the partial
+                                  report of the crates scanned so far, along
+                                  with the crates left unscanned.
+        --scan-timeout-per-crate <SECONDS>
+                                  Stop scanning a crate once its scan has
+                                  lasted SECONDS, leaving its files not
+                                  scanned yet out of the report, and move on
+                                  to the other crates.
+        --jobs <N>                Number of threads scanning the files in
+                                  parallel, at least 1, the report is the
+                                  same for any number. [default: the number
+                                  of CPUs]
+        --no-cache                Parse every file, rather than reusing the
+                                  metrics of the files which did not change
+                                  since an earlier scan, cached in the
+                                  target directory.
+        --only-path-deps          Only include the dependencies whose source
+                                  is a local path, leaving out the registry
+                                  and git dependencies.
+        --exclude <SPEC>          Leave the crates matching the package spec
+                                  SPEC, as for --package, e.g. name or
+                                  name:version, and the dependencies only
+                                  reachable through them, out of the scan,
+                                  from the report as from the tree. Warns if
+                                  no crate matches. Can be repeated.
+        --min-unsafe <N>          Hide the crates with less than N unsafe
+                                  items used by the build from the report,
+                                  in every output format. The totals, the
+                                  safety grade and the unsafe-free counts
+                                  still cover all the crates. Best combined
+                                  with --no-indent.
     --format <FORMAT>             Format string used for printing dependencies
                                   [default: {p}].
+        --format-help             Prints the placeholders supported by
+                                  --format and exits.
+        --print-config            Prints the settings of the run, once the
+                                  defaults and the cargo configuration are
+                                  applied, and exits.
     --output-format               Output format for the report: Ascii, GitHubMarkdown,
-                                  Json, Utf8, Ratio [default: Utf8]
-    --update-readme               Writes output to ./README.md. Looks for a Safety
-                                  Report section, replaces if found, adds if not.
+                                  Json, Prometheus, DenyToml, GitHubActions,
+                                  Csv, Tree, Utf8, Ratio [default: Utf8].
+                                  GitHubActions prints a workflow command for
+                                  each unsafe block, function, method, impl
+                                  and trait used by the build, which
+                                  annotates its lines in the pull request.
+                                  Csv prints a row with the unsafe usage of
+                                  each crate used by the build, and of each
+                                  kind, normal or build with
+                                  --separate-build-report. Tree prints
+                                  the tree as cargo tree does, with the
+                                  number of unsafe items used by the build
+                                  after each crate.
+        --deny-threshold <N>      List the crates with more than N unsafe
+                                  items used by the build in the [bans]
+                                  section of --output-format DenyToml, to
+                                  paste in a cargo-deny configuration
+                                  [default: 0].
+        --fields-deny <FIELDS>    Comma separated list of fields to remove
+                                  from each package entry of the Json report,
+                                  e.g. package.dependencies,unsafety.unused.
+        --path-base <BASE>        Write the paths of the files of the Json
+                                  report relative to BASE instead of absolute,
+                                  and those of --output-format GitHubActions
+                                  relative to BASE instead of the workspace.
+                                  Supported: workspace (the workspace root),
+                                  crate (the root of the crate of each file),
+                                  cwd (the current directory).
+        --json-array              Print only the package entries of the Json
+                                  report, as a top-level array.
+        --ndjson                  Print the package entries of the Json
+                                  report one per line, then a last line with
+                                  the status of the scan: complete, or
+                                  deadline-truncated along with the crates
+                                  left unscanned by --deadline.
+        --json-include-source-hash
+                                  Add the Sha-256 of the scanned files of each
+                                  crate to the Json report, to tell whether a
+                                  report matches a given source.
+        --compact-json            Leave the categories of unsafe usage with
+                                  no safe nor unsafe item out of the Json
+                                  report.
+        --list-unsafe-crates      Print only the crates using unsafe code, as
+                                  sorted name@version lines, instead of the
+                                  report. The ignored crates are left out.
+        --group-by <GROUPING>     Also print the totals of the crates grouped
+                                  by GROUPING, below the tree. Supported:
+                                  source (registry, git or path).
+        --group-unsafe-by-kind    Also print the totals split by kind of
+                                  unsafe usage (functions, expressions,
+                                  impls, traits and methods) below the tree.
+        --print-grade             Print the safety grade (A to F) of the
+                                  packages below the tree. The grade is
+                                  always included in the Json report.
+        --print-unsafe-free       Print the number of crates free of unsafe
+                                  usage below the tree, in the code used by
+                                  the build and in all the code. The numbers
+                                  are always included in the Json report.
+        --on-complete <COMMAND>   Run COMMAND with the Json report on its
+                                  stdin once it is printed, and exit with
+                                  its exit code if it fails. COMMAND is split
+                                  on whitespace and run without a shell.
+                                  Requires --output-format Json, and
+                                  conflicts with --template, --diff-format
+                                  and --list-unsafe-crates.
+        --sqlite <PATH>           Append the crates of the report and their
+                                  unsafe usage by category to the SQLite
+                                  database at PATH, created if missing, to
+                                  query the scans over time with SQL.
+                                  Requires --output-format Json, and
+                                  cargo-geiger built with the sqlite
+                                  feature.
+        --sqlite-report-name <NAME>
+                                  The report name of the rows appended by
+                                  --sqlite, alongside the scan timestamp.
+                                  Defaults to the name@version of the root
+                                  package.
+        --template <PATH>         Render the report through the template at
+                                  PATH instead of printing the Json report.
+                                  Implies --output-format Json, and
+                                  conflicts with the other formats. See the
+                                  README for the template variables.
+        --message-format <FMT>    Print each unsafe block, function, method,
+                                  impl and trait used by the build as a
+                                  cargo compiler message instead of the
+                                  report, for editors and other tools reading
+                                  cargo diagnostics. Supported: human (the
+                                  report), json.
+        --dump-graph-json <PATH>  Also write the resolved dependency graph to
+                                  PATH as Json: the root package, the packages
+                                  and the dependency edges with their kind.
+    --update-readme               Writes output to the README.md next to the
+                                  manifest given to --manifest-path, or to
+                                  ./README.md. Looks for a Safety Report
+                                  section, replaces if found, adds if not.
                                   Throws an error if no README.md exists.
-        --readme-path <PATH>      Path of README.md file to be written to.
+        --readme-path <PATH>      Path of README.md file to be written to,
+                                  relative to the current directory.
         --section-name <NAME>     The section name in the README.md to be written
                                   to.
+        --relative-to <PATH>      Rewrite the absolute paths written to the
+                                  README.md relative to the directory PATH,
+                                  e.g. the directory of the README.md.
     -v, --verbose                 Use verbose output (-vv very verbose/build.rs
                                   output).
     -q, --quiet                   No output printed to stdout other than the
                                   tree.
+        --quiet-unsafe-free       Print nothing and exit successfully if no
+                                  unsafe usage is found, or with
+                                  --fail-threshold no more than the
+                                  threshold, otherwise print the report and
+                                  exit with an error.
         --color <WHEN>            Coloring: auto, always, never.
+        --ci                      Use defaults suited to CI pipelines:
+                                  --output-format Json, --color never and
+                                  --path-base workspace. Explicit flags take
+                                  precedence.
         --frozen                  Require Cargo.lock and cache are up to date.
         --locked                  Require Cargo.lock is up to date.
         --offline                 Run without accessing the network.
+        --verify-checksums        Before scanning, verify the registry crates
+                                  against the checksums of Cargo.lock, both
+                                  their .crate archive and the files
+                                  extracted from it, and exit with an error
+                                  on mismatch.
     -Z \"<FLAG>...\"                Unstable (nightly-only) flags to Cargo.
-        --include-tests           Count unsafe usage in tests.
+        --exclude-generated       Don't scan the files which look generated:
+                                  the files in target/ or OUT_DIR
+                                  directories, or named *.generated.rs.
+        --generated-pattern <REGEX>
+                                  Exclude the files whose path relative to the
+                                  package root matches REGEX with
+                                  --exclude-generated, instead of the default
+                                  patterns. Can be repeated.
+        --scan-generated-includes Scan the files generated in OUT_DIR by a
+                                  build script and included with include!
+                                  and env!(OUT_DIR), counting them in the
+                                  crate including them, even with
+                                  --exclude-generated.
+        --max-file-size <BYTES>   Don't scan the files larger than BYTES,
+                                  which are listed in a warning, as parsing
+                                  them may run out of memory. [default:
+                                  10485760, 0 for no limit]
+        --include-tests           Count unsafe usage in tests, both `#[test]`
+                                  code and test targets.
+        --count-cfg-test-separately
+                                  Count unsafe usage in `#[cfg(test)]` modules
+                                  and `#[test]` functions apart from the other
+                                  code, printed after each crate and added to
+                                  the Json report.
+        --include-benches         Count unsafe usage in bench targets.
+        --include-examples        Count unsafe usage in example targets.
+        --include-optional        Also scan the optional dependencies of the
+                                  workspace packages whose feature is not
+                                  enabled, tagged as optional/inactive.
+        --include-std             Also scan the source of the core, alloc and
+                                  std crates, reported apart and left out of
+                                  the totals. Requires the rust-src component
+                                  of the toolchain.
+        --count-std-macros        Count each invocation of the std macros which
+                                  expand to unsafe code, e.g. `thread_local!`,
+                                  as an unsafe expression.
+        --std-macro <NAME>        Also count the invocations of the macro NAME
+                                  with --count-std-macros. Can be repeated.
         --build-dependencies      Also analyze build dependencies.
         --dev-dependencies        Also analyze dev dependencies.
         --all-dependencies        Analyze all dependencies, including build and
                                   dev.
+        --separate-build-report   Report the build dependencies apart from
+                                  the run time dependencies: in the
+                                  build_packages of the Json report, and in
+                                  separate totals below the tree. Implies
+                                  --build-dependencies.
+        --ignore-unsafe-in <CRATE[@VERSION]>
+                                  Still display the unsafe usage of a crate,
+                                  but leave it out of the totals. Can be
+                                  repeated.
+        --exclude-self            Still display the unsafe usage of the root
+                                  package, but leave it out of the totals, as
+                                  --ignore-unsafe-in does.
+        --advisories <PATH>       Path to a local clone of the RustSec advisory
+                                  database, used to mark the crates affected
+                                  by an advisory.
+        --diff <REPORT>           Path to a Json report from an earlier run.
+                                  The crates whose version differs from the
+                                  report are marked with whether their unsafe
+                                  usage increased, decreased or is new.
+        --diff-format <FORMAT>    Print the crates added and removed since
+                                  the report given to --diff, and the change
+                                  of each category of unsafe usage of the
+                                  others, instead of the report. Supported:
+                                  table, unified (diff style) and json.
+        --diff-full               Also print the current entry of each crate
+                                  added or changed since the report given to
+                                  --diff, with --diff-format.
+        --compare-published       Compare the unsafe usage found in the root
+                                  crate to the one of the latest version of
+                                  the crate published on crates.io, which is
+                                  downloaded, and print the delta in the
+                                  format of --diff-format, table by default.
+                                  Nothing is built, so the unsafe usage of
+                                  all the files of both versions counts.
+        --dry-run                 Resolve the dependencies and the files used
+                                  by the build, then list the packages and
+                                  the number of files that would be scanned,
+                                  without scanning them.
+        --raw-derefs              Print the number of dereferences of raw
+                                  pointers in unsafe code of each crate, also
+                                  added to the Json report. Pointers are
+                                  recognized from the syntax only.
+        --latent-macro-unsafe     Print the number of unsafe tokens inside
+                                  the macro_rules! definitions of each crate,
+                                  also added to the Json report. The macros
+                                  are not expanded, so this unsafe usage may
+                                  or may not be used.
+        --exported-symbols        Print the number of functions, methods and
+                                  statics exported with #[no_mangle] or
+                                  #[export_name] in each crate, also added to
+                                  the Json report.
+        --memory-lifecycle-unsafe Print the number of calls in unsafe code of
+                                  each crate to the functions managing the
+                                  lifecycle of values by hand, such as
+                                  MaybeUninit::assume_init,
+                                  ManuallyDrop::drop or Pin::new_unchecked,
+                                  also added to the Json report.
+        --memory-lifecycle-fn <NAME>
+                                  Also count the calls of the function NAME,
+                                  e.g. `reclaim` or `Box::from_raw`, with
+                                  --memory-lifecycle-unsafe. Can be repeated.
+        --static-mut              Print the number of static mut declared in
+                                  each crate and of the accesses to them,
+                                  also added to the Json report. Only the
+                                  accesses from the file declaring the static
+                                  mut are counted.
+        --unsafe-trait-usage      Print the number of references to unsafe
+                                  traits in the bounds and trait objects of
+                                  each crate, e.g. `T: Send` or `dyn Sync`,
+                                  also added to the Json report. The traits
+                                  are Send, Sync and the unsafe traits
+                                  declared in the same file.
+        --drop-unsafe             Print the unsafe usage inside the impl Drop
+                                  blocks of each crate, a common source of
+                                  double frees and use after frees, also
+                                  added to the Json report.
+        --ffi-callbacks           Print the number of functions of each crate
+                                  passed as extern fn pointers to calls in
+                                  unsafe code, the callbacks given to foreign
+                                  code, also added to the Json report.
+        --layout-dependent-unsafe Print the number of transmute calls of
+                                  each crate on its types declared with
+                                  #[repr(...)], which depend on their
+                                  layout, e.g. casts between #[repr(C)]
+                                  types, also added to the Json report.
+        --flag-nightly-features   Warn about the crates enabling unstable
+                                  features with #![feature(...)], which only
+                                  build with a nightly toolchain, and print
+                                  their features, also added to the Json
+                                  report.
+        --allowed-unsafe          Print the unsafe usage of each crate under
+                                  an explicit #[allow(unsafe_code)] apart from
+                                  the unannotated unsafe usage, also added to
+                                  the Json report.
+        --module-level            Add the unsafe usage of each module of each
+                                  crate to the Json report, by module path.
+                                  The module of a file is derived from its
+                                  path.
+        --custom-pattern <REGEX>:<NAME>
+                                  Also count the matches of REGEX in the
+                                  scanned files of each crate, reported as
+                                  NAME. REGEX is matched on each line of the
+                                  source text, not on the syntax tree, so
+                                  comments and strings match too. Can be
+                                  repeated.
+        --weights <PATH>          Path to a TOML file of weights for the unsafe
+                                  usage categories (functions, exprs, impls,
+                                  traits, methods), used to compute a risk
+                                  score per crate.
+        --severity-map <PATH>     Path to a TOML file of severities (info,
+                                  low, medium, high, critical) of the kinds
+                                  of unsafe code (block, transmute, asm,
+                                  function, method, impl, trait), setting the
+                                  level of the findings of --output-format
+                                  GitHubActions and --message-format json.
+                                  By default transmute and asm blocks are
+                                  high, plain blocks low, the others medium.
         --forbid-only             Don't build or clean anything, only scan
                                   entry point .rs source files for.
                                   forbid(unsafe_code) flags. This is
                                   significantly faster than the default
-                                  scanning mode. TODO: Add ability to combine
-                                  this with a whitelist for use in CI.
+                                  scanning mode.
+        --forbid-whitelist <PATH> With --forbid-only, count the crates listed
+                                  in the file at PATH, one name or
+                                  name:version per line, # starting a
+                                  comment, as forbidding unsafe code, and
+                                  exit with an error if any other crate
+                                  doesn't, or if a listed crate isn't in the
+                                  tree.
+        --fail-if-no-forbid       Exit with an error if the entry points of the
+                                  root package don't declare
+                                  #![forbid(unsafe_code)], regardless of its
+                                  dependencies.
+        --fail-on-forbid-regression <PATH>
+                                  Exit with an error if a crate which
+                                  forbids unsafe code in the Json report of
+                                  an earlier --forbid-only run at PATH no
+                                  longer does. Implies --forbid-only.
+        --fail-on-any-unsafe      Exit with an error if any crate not given
+                                  to --ignore-unsafe-in uses unsafe code in
+                                  the build, or has files used by the build
+                                  which could not be scanned.
+        --fail-threshold <N>      Exit with an error if more than N unsafe
+                                  expressions are used by the build, in the
+                                  crates of --threshold-scope. With
+                                  --forbid-only, which doesn't build, the
+                                  expressions of every .rs file of the
+                                  crates are counted. The error names the 3
+                                  crates using the most, with their unsafe
+                                  usage by category.
+        --threshold-scope <SCOPE> The crates whose unsafe expressions are
+                                  counted by --fail-threshold. Supported:
+                                  tree (the crates of the dependency tree
+                                  not given to --ignore-unsafe-in, nor to
+                                  --exclude-self), root (the root crate
+                                  only, which conflicts with
+                                  --exclude-self) [default: tree].
+                                  With --workspace, each member is compared
+                                  on its own.
+        --fail-summary-file <PATH>
+                                  When --fail-if-no-forbid,
+                                  --fail-on-any-unsafe, --fail-threshold,
+                                  --quiet-unsafe-free, --max-total-crates,
+                                  --require-source-url or a warning makes
+                                  the run fail, write the gate, its message
+                                  and the crates which made it fail to PATH
+                                  as Json, whatever the output format.
     -h, --help                    Prints help information.
     -V, --version                 Prints version information.
 ";
 
 #[derive(Default)]
 pub struct Args {
+    pub advisories: Option<PathBuf>,
     pub all: bool,
+    pub allowed_unsafe: bool,
+    pub ci: bool,
     pub color: Option<String>,
+    pub compact_json: bool,
+    pub compare_published: bool,
+    pub compare_targets: Option<(String, String)>,
+    pub count_cfg_test_separately: bool,
+    pub count_std_macros: bool,
+    pub custom_patterns: Vec<CustomPattern>,
+    /// The end of the `--deadline`, from the start of the run.
+    pub deadline: Option<Instant>,
+    pub deny_threshold: Option<u64>,
+    pub depth: Option<usize>,
     pub deps_args: DepsArgs,
+    pub diff: Option<PathBuf>,
+    pub diff_format: Option<DiffFormat>,
+    pub diff_full: bool,
+    pub drop_unsafe: bool,
+    pub dry_run: bool,
+    pub dump_graph_json: Option<PathBuf>,
+    pub exclude: Vec<String>,
+    pub exclude_generated: bool,
+    pub exclude_self: bool,
+    pub exported_symbols: bool,
+    pub fail_if_no_forbid: bool,
+    pub fail_on_any_unsafe: bool,
+    pub fail_on_forbid_regression: Option<PathBuf>,
+    pub fail_summary_file: Option<PathBuf>,
+    pub fail_threshold: Option<u64>,
+    pub feature_impact: Option<String>,
     pub features_args: FeaturesArgs,
+    pub ffi_callbacks: bool,
+    pub fields_deny: Vec<String>,
+    pub flag_nightly_features: bool,
     pub forbid_only: bool,
+    pub forbid_whitelist: Option<PathBuf>,
     pub format: String,
+    pub format_help: bool,
     pub frozen: bool,
+    pub generated_patterns: Vec<String>,
+    pub group_by: Option<GroupBy>,
+    pub group_unsafe_by_kind: bool,
     pub help: bool,
+    pub ignore_unsafe_in: Vec<String>,
+    pub include_benches: bool,
+    pub include_examples: bool,
+    pub include_optional: bool,
+    pub include_std: bool,
     pub include_tests: bool,
     pub invert: bool,
+    pub jobs: Option<usize>,
+    pub json_array: bool,
+    pub json_include_source_hash: bool,
+    pub latent_macro_unsafe: bool,
+    pub layout_dependent_unsafe: bool,
+    pub list_unsafe_crates: bool,
     pub locked: bool,
     pub manifest_path: Option<PathBuf>,
+    pub max_depth_warn: Option<usize>,
+    pub max_file_size: Option<u64>,
+    pub max_total_crates: Option<usize>,
+    pub memory_lifecycle_functions: Vec<String>,
+    pub memory_lifecycle_unsafe: bool,
+    pub message_format: Option<MessageFormat>,
+    pub min_unsafe: Option<u64>,
+    pub module_level: bool,
+    pub ndjson: bool,
+    pub no_cache: bool,
     pub no_indent: bool,
     pub offline: bool,
+    pub on_complete: Option<String>,
+    pub only_path_deps: bool,
     pub output_format: OutputFormat,
     pub package: Option<String>,
+    pub path_base: Option<PathBase>,
     pub prefix_depth: bool,
+    pub print_config: bool,
+    pub print_grade: bool,
+    pub print_unsafe_free: bool,
     pub quiet: bool,
+    pub quiet_unsafe_free: bool,
+    pub rank: bool,
+    pub raw_derefs: bool,
     pub readme_args: ReadmeArgs,
+    pub require_source_url: bool,
+    pub scan_generated_includes: bool,
+    /// The longest time spent scanning a single crate, for
+    /// `--scan-timeout-per-crate`.
+    pub scan_timeout_per_crate: Option<Duration>,
+    pub separate_build_report: bool,
+    pub severity_map: Option<PathBuf>,
+    pub sqlite: Option<PathBuf>,
+    pub sqlite_report_name: Option<String>,
+    pub static_mut: bool,
+    pub std_macros: Vec<String>,
+    pub strict_features: bool,
     pub target_args: TargetArgs,
+    pub template: Option<PathBuf>,
+    pub threshold_scope: Option<ThresholdScope>,
+    pub unsafe_trait_usage: bool,
     pub unstable_flags: Vec<String>,
     pub verbosity: Verbosity,
+    pub verify_checksums: bool,
     pub version: bool,
+    pub weights: Option<PathBuf>,
+    pub workspace: bool,
 }
 
 impl Args {
@@ -101,14 +576,57 @@ impl Args {
     pub fn parse_args(
         mut raw_args: Arguments,
     ) -> Result<Args, Box<dyn std::error::Error>> {
+        // `--ci` changes the default output format.
+        let ci = raw_args.contains("--ci");
+        // The flags built from the Json report check the explicit format.
+        let output_format: Option<OutputFormat> =
+            raw_args.opt_value_from_str("--output-format")?;
         let mut args = Args {
+            advisories: raw_args.opt_value_from_str("--advisories")?,
             all: raw_args.contains(["-a", "--all"]),
+            allowed_unsafe: raw_args.contains("--allowed-unsafe"),
+            ci,
             color: raw_args.opt_value_from_str("--color")?,
+            compact_json: raw_args.contains("--compact-json"),
+            compare_published: raw_args.contains("--compare-published"),
+            compare_targets: raw_args.opt_value_from_fn(
+                "--compare-targets",
+                parse_compare_targets,
+            )?,
+            count_cfg_test_separately: raw_args
+                .contains("--count-cfg-test-separately"),
+            count_std_macros: raw_args.contains("--count-std-macros"),
+            custom_patterns: raw_args.values_from_str("--custom-pattern")?,
+            deadline: raw_args.opt_value_from_str("--deadline")?.map(
+                |seconds: u64| Instant::now() + Duration::from_secs(seconds),
+            ),
+            deny_threshold: raw_args.opt_value_from_str("--deny-threshold")?,
+            depth: raw_args.opt_value_from_str("--depth")?,
             deps_args: DepsArgs {
                 all_deps: raw_args.contains("--all-dependencies"),
                 build_deps: raw_args.contains("--build-dependencies"),
                 dev_deps: raw_args.contains("--dev-dependencies"),
             },
+            diff: raw_args.opt_value_from_str("--diff")?,
+            diff_format: raw_args.opt_value_from_str("--diff-format")?,
+            diff_full: raw_args.contains("--diff-full"),
+            drop_unsafe: raw_args.contains("--drop-unsafe"),
+            dry_run: raw_args.contains("--dry-run"),
+            dump_graph_json: raw_args
+                .opt_value_from_str("--dump-graph-json")?,
+            exclude: raw_args
+                .values_from_fn("--exclude", parse_package_spec)?,
+            exclude_generated: raw_args.contains("--exclude-generated"),
+            exclude_self: raw_args.contains("--exclude-self"),
+            exported_symbols: raw_args.contains("--exported-symbols"),
+            fail_if_no_forbid: raw_args.contains("--fail-if-no-forbid"),
+            fail_on_any_unsafe: raw_args.contains("--fail-on-any-unsafe"),
+            fail_on_forbid_regression: raw_args
+                .opt_value_from_str("--fail-on-forbid-regression")?,
+            fail_summary_file: raw_args
+                .opt_value_from_str("--fail-summary-file")?,
+            fail_threshold: raw_args.opt_value_from_str("--fail-threshold")?,
+            feature_impact: raw_args.opt_value_from_str("--feature-impact")?,
             features_args: FeaturesArgs {
                 all_features: raw_args.contains("--all-features"),
                 features: parse_features(
@@ -116,36 +634,107 @@ impl Args {
                 ),
                 no_default_features: raw_args.contains("--no-default-features"),
             },
+            ffi_callbacks: raw_args.contains("--ffi-callbacks"),
+            fields_deny: raw_args
+                .opt_value_from_fn("--fields-deny", parse_fields_deny)?
+                .unwrap_or_default(),
+            flag_nightly_features: raw_args.contains("--flag-nightly-features"),
             forbid_only: raw_args.contains(["-f", "--forbid-only"]),
+            forbid_whitelist: raw_args
+                .opt_value_from_str("--forbid-whitelist")?,
             format: raw_args
                 .opt_value_from_str("--format")?
                 .unwrap_or_else(|| "{p}".to_string()),
+            format_help: raw_args.contains("--format-help"),
             frozen: raw_args.contains("--frozen"),
+            generated_patterns: raw_args
+                .values_from_str("--generated-pattern")?,
+            group_by: raw_args.opt_value_from_str("--group-by")?,
+            group_unsafe_by_kind: raw_args.contains("--group-unsafe-by-kind"),
             help: raw_args.contains(["-h", "--help"]),
+            ignore_unsafe_in: raw_args.values_from_str("--ignore-unsafe-in")?,
+            include_benches: raw_args.contains("--include-benches"),
+            include_examples: raw_args.contains("--include-examples"),
+            include_optional: raw_args.contains("--include-optional"),
+            include_std: raw_args.contains("--include-std"),
             include_tests: raw_args.contains("--include-tests"),
             invert: raw_args.contains(["-i", "--invert"]),
+            jobs: raw_args.opt_value_from_str("--jobs")?,
+            json_array: raw_args.contains("--json-array"),
+            json_include_source_hash: raw_args
+                .contains("--json-include-source-hash"),
+            latent_macro_unsafe: raw_args.contains("--latent-macro-unsafe"),
+            layout_dependent_unsafe: raw_args
+                .contains("--layout-dependent-unsafe"),
+            list_unsafe_crates: raw_args.contains("--list-unsafe-crates"),
             locked: raw_args.contains("--locked"),
             manifest_path: raw_args.opt_value_from_str("--manifest-path")?,
+            max_depth_warn: raw_args.opt_value_from_str("--max-depth-warn")?,
+            max_file_size: raw_args.opt_value_from_str("--max-file-size")?,
+            max_total_crates: raw_args
+                .opt_value_from_str("--max-total-crates")?,
+            memory_lifecycle_functions: raw_args
+                .values_from_str("--memory-lifecycle-fn")?,
+            memory_lifecycle_unsafe: raw_args
+                .contains("--memory-lifecycle-unsafe"),
+            message_format: raw_args.opt_value_from_str("--message-format")?,
+            min_unsafe: raw_args.opt_value_from_str("--min-unsafe")?,
+            module_level: raw_args.contains("--module-level"),
+            ndjson: raw_args.contains("--ndjson"),
+            no_cache: raw_args.contains("--no-cache"),
             no_indent: raw_args.contains("--no-indent"),
             offline: raw_args.contains("--offline"),
+            on_complete: raw_args.opt_value_from_str("--on-complete")?,
+            only_path_deps: raw_args.contains("--only-path-deps"),
             package: raw_args.opt_value_from_str(["-p", "--package"])?,
+            path_base: raw_args.opt_value_from_str("--path-base")?,
             prefix_depth: raw_args.contains("--prefix-depth"),
+            print_config: raw_args.contains("--print-config"),
+            print_grade: raw_args.contains("--print-grade"),
+            print_unsafe_free: raw_args.contains("--print-unsafe-free"),
             quiet: raw_args.contains(["-q", "--quiet"]),
+            quiet_unsafe_free: raw_args.contains("--quiet-unsafe-free"),
+            rank: raw_args.contains("--rank"),
+            raw_derefs: raw_args.contains("--raw-derefs"),
             readme_args: ReadmeArgs {
                 readme_path: raw_args.opt_value_from_str("--readme-path")?,
+                relative_to: raw_args.opt_value_from_str("--relative-to")?,
                 section_name: raw_args.opt_value_from_str("--section-name")?,
                 update_readme: raw_args.contains("--update-readme"),
             },
+            require_source_url: raw_args.contains("--require-source-url"),
+            scan_generated_includes: raw_args
+                .contains("--scan-generated-includes"),
+            scan_timeout_per_crate: raw_args
+                .opt_value_from_str("--scan-timeout-per-crate")?
+                .map(Duration::from_secs),
+            separate_build_report: raw_args.contains("--separate-build-report"),
+            severity_map: raw_args.opt_value_from_str("--severity-map")?,
+            sqlite: raw_args.opt_value_from_str("--sqlite")?,
+            sqlite_report_name: raw_args
+                .opt_value_from_str("--sqlite-report-name")?,
+            static_mut: raw_args.contains("--static-mut"),
+            std_macros: raw_args.values_from_str("--std-macro")?,
+            strict_features: raw_args.contains("--strict-features"),
             target_args: TargetArgs {
                 all_targets: raw_args.contains("--all-targets"),
+                rustflags: Vec::new(),
                 target: raw_args.opt_value_from_str("--target")?,
+                target_from_config: raw_args.contains("--target-from-config"),
             },
+            template: raw_args.opt_value_from_str("--template")?,
+            threshold_scope: raw_args
+                .opt_value_from_str("--threshold-scope")?,
+            unsafe_trait_usage: raw_args.contains("--unsafe-trait-usage"),
             unstable_flags: raw_args
                 .opt_value_from_str("-Z")?
                 .map(|s: String| s.split(' ').map(|s| s.to_owned()).collect())
                 .unwrap_or_else(Vec::new),
 
+            verify_checksums: raw_args.contains("--verify-checksums"),
             version: raw_args.contains(["-V", "--version"]),
+            weights: raw_args.opt_value_from_str("--weights")?,
+            workspace: raw_args.contains("--workspace"),
             verbosity: match (
                 raw_args.contains("-vv"),
                 raw_args.contains(["-v", "--verbose"]),
@@ -154,11 +743,19 @@ impl Args {
                 (false, true) => Normal,
                 (true, _) => Verbose,
             },
-            output_format: raw_args
-                .opt_value_from_str("--output-format")?
-                .unwrap_or(OutputFormat::Utf8),
+            output_format: output_format.unwrap_or(if ci {
+                OutputFormat::Json
+            } else {
+                OutputFormat::Utf8
+            }),
         };
 
+        // The other defaults of `--ci`, the explicit flags taking precedence.
+        if args.ci {
+            args.color.get_or_insert_with(|| String::from("never"));
+            args.path_base.get_or_insert(PathBase::Workspace);
+        }
+
         if args.readme_args.update_readme
             && args.output_format != OutputFormat::GitHubMarkdown
         {
@@ -170,6 +767,242 @@ impl Args {
             args.output_format = OutputFormat::GitHubMarkdown
         }
 
+        // The build dependencies are only part of the graph when they are
+        // analyzed.
+        if args.separate_build_report {
+            if args.deps_args.dev_deps {
+                args.deps_args.all_deps = true;
+            } else {
+                args.deps_args.build_deps = true;
+            }
+        }
+
+        if cfg!(not(feature = "sqlite"))
+            && (args.sqlite.is_some() || args.sqlite_report_name.is_some())
+        {
+            return Err(
+                "`--sqlite` and `--sqlite-report-name` require cargo-geiger to be built with the `sqlite` feature"
+                    .into(),
+            );
+        }
+
+        if args.sqlite_report_name.is_some() && args.sqlite.is_none() {
+            return Err(
+                "`--sqlite-report-name` requires `--sqlite`, the database the rows are appended to"
+                    .into(),
+            );
+        }
+
+        // The dependencies are resolved with the feature, then without it.
+        if let Some(feature) = &args.feature_impact {
+            if !args.features_args.features.contains(feature) {
+                args.features_args.features.push(feature.clone());
+            }
+        }
+
+        // Only the forbid scan tells which crates forbid unsafe code.
+        if args.fail_on_forbid_regression.is_some() {
+            args.forbid_only = true;
+        }
+
+        if args.forbid_whitelist.is_some() && !args.forbid_only {
+            return Err(
+                "`--forbid-whitelist` requires `--forbid-only`, the scan for crates which don't forbid unsafe code"
+                    .into(),
+            );
+        }
+
+        if args.forbid_only && args.output_format == OutputFormat::Prometheus {
+            return Err(
+                "`--output-format Prometheus` only reports the unsafe usage, which `--forbid-only` doesn't scan"
+                    .into(),
+            );
+        }
+
+        if args.forbid_only && args.output_format == OutputFormat::DenyToml {
+            return Err(
+                "`--output-format DenyToml` lists the crates by unsafe usage, which `--forbid-only` doesn't scan"
+                    .into(),
+            );
+        }
+
+        if args.forbid_only && args.output_format == OutputFormat::GitHubActions
+        {
+            return Err(
+                "`--output-format GitHubActions` annotates the unsafe usage, which `--forbid-only` doesn't scan"
+                    .into(),
+            );
+        }
+
+        if args.forbid_only && args.output_format == OutputFormat::Csv {
+            return Err(
+                "`--output-format Csv` reports the unsafe usage, which `--forbid-only` doesn't scan"
+                    .into(),
+            );
+        }
+
+        if args.forbid_only && args.output_format == OutputFormat::Tree {
+            return Err(
+                "`--output-format Tree` displays the unsafe usage, which `--forbid-only` doesn't scan"
+                    .into(),
+            );
+        }
+
+        // The thread pool of rayon gets as many threads as CPUs for 0, which
+        // is not what a cap on the threads means.
+        if args.jobs == Some(0) {
+            return Err("`--jobs` requires at least one thread".into());
+        }
+
+        if args.depth.is_some() && args.output_format != OutputFormat::Tree {
+            return Err("`--depth` requires `--output-format Tree`".into());
+        }
+
+        if args.sqlite.is_some() && args.forbid_only {
+            return Err(
+                "`--sqlite` stores the unsafe usage, which `--forbid-only` doesn't scan"
+                    .into(),
+            );
+        }
+
+        if args.fail_on_any_unsafe && (args.forbid_only || args.dry_run) {
+            return Err(
+                "`--fail-on-any-unsafe` requires the unsafe usage, which `--forbid-only` and `--dry-run` don't scan"
+                    .into(),
+            );
+        }
+
+        if args.threshold_scope.is_some() && args.fail_threshold.is_none() {
+            return Err(
+                "`--threshold-scope` requires `--fail-threshold`, the count compared in the scope"
+                    .into(),
+            );
+        }
+
+        if args.exclude_self
+            && args.threshold_scope == Some(ThresholdScope::Root)
+        {
+            return Err(
+                "`--exclude-self` leaves the root crate out of the count, which is all `--threshold-scope root` counts"
+                    .into(),
+            );
+        }
+
+        if args.fail_threshold.is_some()
+            && (args.dry_run
+                || args.compare_published
+                || args.compare_targets.is_some()
+                || args.feature_impact.is_some())
+        {
+            return Err(
+                "`--fail-threshold` requires the unsafe usage of the tree, which `--dry-run`, `--compare-published`, `--compare-targets` and `--feature-impact` don't report"
+                    .into(),
+            );
+        }
+
+        if args.deny_threshold.is_some()
+            && args.output_format != OutputFormat::DenyToml
+        {
+            return Err(
+                "`--deny-threshold` requires `--output-format DenyToml`".into(),
+            );
+        }
+
+        if args.severity_map.is_some()
+            && args.output_format != OutputFormat::GitHubActions
+            && args.message_format != Some(MessageFormat::Json)
+        {
+            return Err(
+                "`--severity-map` requires `--output-format GitHubActions` or `--message-format json`, the outputs with a severity per finding"
+                    .into(),
+            );
+        }
+
+        if args.rank && !args.no_indent {
+            return Err(
+                "`--rank` requires `--no-indent`, the ranking being a flat list"
+                    .into(),
+            );
+        }
+
+        // The template is rendered from the report built for the Json output.
+        if args.template.is_some() {
+            require_json_output_format("--template", output_format)?;
+            args.output_format = OutputFormat::Json;
+        }
+
+        if args.compare_published && (args.offline || args.frozen) {
+            return Err(
+                "`--compare-published` downloads the published version of the crate, which `--offline` and `--frozen` forbid"
+                    .into(),
+            );
+        }
+
+        if args.diff_format.is_some() {
+            if args.diff.is_none() && !args.compare_published {
+                return Err(
+                    "`--diff-format` requires `--diff`, the baseline report to compare the scan to"
+                        .into(),
+                );
+            }
+            // The delta is computed from the report built for the Json
+            // output.
+            require_json_output_format("--diff-format", output_format)?;
+            args.output_format = OutputFormat::Json;
+        } else if args.diff_full {
+            return Err(
+                "`--diff-full` requires `--diff-format`, the format of the delta including the entries"
+                    .into(),
+            );
+        }
+
+        // The list is taken from the report built for the Json output.
+        if args.list_unsafe_crates {
+            require_json_output_format("--list-unsafe-crates", output_format)?;
+            args.output_format = OutputFormat::Json;
+        }
+
+        if args.on_complete.is_some()
+            && (args.template.is_some()
+                || args.diff_format.is_some()
+                || args.list_unsafe_crates)
+        {
+            return Err(
+                "`--on-complete` is given the Json report, which `--template`, `--diff-format` and `--list-unsafe-crates` print otherwise"
+                    .into(),
+            );
+        }
+
+        if args.on_complete.is_some()
+            && args.output_format != OutputFormat::Json
+        {
+            return Err(
+                "`--on-complete` requires `--output-format Json`, as the command is given the Json report"
+                    .into(),
+            );
+        }
+
+        // The rows are taken from the report built for the Json output.
+        if args.sqlite.is_some() && args.output_format != OutputFormat::Json {
+            return Err("`--sqlite` requires `--output-format Json`".into());
+        }
+
+        if args.ndjson
+            && (args.output_format != OutputFormat::Json || args.forbid_only)
+        {
+            return Err(
+                "`--ndjson` requires `--output-format Json`, the report whose package entries are printed one per line"
+                    .into(),
+            );
+        }
+
+        if args.ndjson && (args.json_array || args.template.is_some()) {
+            return Err(
+                "`--ndjson` prints the package entries one per line, which `--json-array` and `--template` print otherwise"
+                    .into(),
+            );
+        }
+
         Ok(args)
     }
 
@@ -214,6 +1047,87 @@ impl Args {
     }
 }
 
+/// Construct the text printed by `--format-help`, listing every placeholder
+/// supported by `--format` along with a short description
+pub fn format_help() -> String {
+    let mut lines = vec![
+        String::from("Placeholders supported by --format:"),
+        String::new(),
+    ];
+    for (placeholder, description) in PLACEHOLDERS.iter() {
+        lines.push(format!("    {{{}}}    {}", placeholder, description));
+    }
+    lines.push(String::new());
+    lines.push(String::from(
+        "Any other text is printed as is, use {{ to print a literal `{`.",
+    ));
+    lines.join("\n")
+}
+
+/// Construct the text printed by `--print-config`, the settings deciding what
+/// is scanned and how it is reported, as resolved from the command line, the
+/// presets such as `--ci` and the cargo configuration.
+pub fn effective_config(args: &Args, print_config: &PrintConfig) -> String {
+    let list = |values: &[String]| {
+        if values.is_empty() {
+            String::from("none")
+        } else {
+            values.join(" ")
+        }
+    };
+    let optional =
+        |value: Option<String>| value.unwrap_or_else(|| String::from("none"));
+    vec![
+        format!("output-format: {}", <&str>::from(args.output_format)),
+        format!("features: {}", list(&args.features_args.features)),
+        format!("all-features: {}", args.features_args.all_features),
+        format!(
+            "no-default-features: {}",
+            args.features_args.no_default_features
+        ),
+        format!("all-deps: {}", args.deps_args.all_deps),
+        format!("build-deps: {}", args.deps_args.build_deps),
+        format!("dev-deps: {}", args.deps_args.dev_deps),
+        format!("include-optional: {}", args.include_optional),
+        format!("include-tests: {:?}", print_config.include_tests),
+        format!("include-benches: {}", args.include_benches),
+        format!("include-examples: {}", args.include_examples),
+        format!("target: {}", optional(args.target_args.target.clone())),
+        format!("all-targets: {}", args.target_args.all_targets),
+        format!("rustflags: {}", list(&args.target_args.rustflags)),
+        format!("exclude: {}", list(&args.exclude)),
+        format!("ignore-unsafe-in: {}", list(&args.ignore_unsafe_in)),
+        format!("exclude-self: {}", args.exclude_self),
+        format!(
+            "min-unsafe: {}",
+            optional(args.min_unsafe.map(|min_unsafe| min_unsafe.to_string()))
+        ),
+        format!(
+            "max-total-crates: {}",
+            optional(args.max_total_crates.map(|max| max.to_string()))
+        ),
+        format!(
+            "max-depth-warn: {}",
+            optional(args.max_depth_warn.map(|max| max.to_string()))
+        ),
+        format!(
+            "max-file-size: {}",
+            optional(print_config.max_file_size.map(|max| max.to_string()))
+        ),
+        format!(
+            "scan-timeout-per-crate: {}",
+            optional(
+                print_config
+                    .scan_timeout_per_crate
+                    .map(|timeout| timeout.as_secs().to_string())
+            )
+        ),
+        format!("jobs: {}", optional(args.jobs.map(|jobs| jobs.to_string()))),
+        format!("no-cache: {}", print_config.no_cache),
+    ]
+    .join("\n")
+}
+
 #[derive(Debug, Default)]
 pub struct DepsArgs {
     pub all_deps: bool,
@@ -231,12 +1145,80 @@ pub struct FeaturesArgs {
 #[derive(Debug, Default)]
 pub struct TargetArgs {
     pub all_targets: bool,
+    /// The flags passed to rustc by a build, read from the environment and
+    /// the cargo configuration rather than from the command line.
+    pub rustflags: Vec<String>,
     pub target: Option<String>,
+    pub target_from_config: bool,
+}
+
+/// How the differences with the baseline report are printed, for
+/// `--diff-format`
+#[derive(Clone, Copy, Debug, EnumString, Eq, PartialEq)]
+pub enum DiffFormat {
+    /// A table of the added and removed crates and of the changed counts.
+    #[strum(serialize = "table")]
+    Table,
+    /// A text in the style of a unified diff.
+    #[strum(serialize = "unified")]
+    Unified,
+    /// A Json object.
+    #[strum(serialize = "json")]
+    Json,
+}
+
+/// The grouping of the crates totals, for `--group-by`
+#[derive(Clone, Copy, Debug, EnumString, Eq, PartialEq)]
+pub enum GroupBy {
+    /// Where the crates come from: a registry, a git repository or a path.
+    #[strum(serialize = "source")]
+    Source,
+}
+
+/// How the results are printed, for `--message-format`
+#[derive(Clone, Copy, Debug, EnumString, Eq, PartialEq)]
+pub enum MessageFormat {
+    /// The report, in the format given to `--output-format`.
+    #[strum(serialize = "human")]
+    Human,
+    /// A cargo compiler message for each occurrence of unsafe code.
+    #[strum(serialize = "json")]
+    Json,
+}
+
+/// The crates whose unsafe expressions are counted by `--fail-threshold`, for
+/// `--threshold-scope`
+#[derive(Clone, Copy, Debug, EnumString, Eq, PartialEq)]
+pub enum ThresholdScope {
+    /// The root package only.
+    #[strum(serialize = "root")]
+    Root,
+    /// The packages of the dependency tree which are not ignored.
+    #[strum(serialize = "tree")]
+    Tree,
+}
+
+/// The directory the paths of the files of the Json report are relative to,
+/// for `--path-base`
+#[derive(Clone, Copy, Debug, EnumString, Eq, PartialEq)]
+pub enum PathBase {
+    /// The root of the workspace.
+    #[strum(serialize = "workspace")]
+    Workspace,
+    /// The root of the crate the file belongs to.
+    #[strum(serialize = "crate")]
+    Crate,
+    /// The current directory.
+    #[strum(serialize = "cwd")]
+    Cwd,
 }
 
 #[derive(Debug, Default)]
 pub struct ReadmeArgs {
     pub readme_path: Option<PathBuf>,
+    /// The directory the paths written to the README are relative to, for
+    /// `--relative-to`.
+    pub relative_to: Option<PathBuf>,
     pub section_name: Option<String>,
     pub update_readme: bool,
 }
@@ -254,6 +1236,70 @@ impl Default for Verbosity {
     }
 }
 
+/// The flags printing from the report built for the Json output imply
+/// `--output-format Json`, which an explicit other format conflicts with.
+fn require_json_output_format(
+    flag: &str,
+    output_format: Option<OutputFormat>,
+) -> Result<(), String> {
+    match output_format {
+        Some(output_format) if output_format != OutputFormat::Json => {
+            Err(format!(
+                "`{}` prints from the Json report, which `--output-format {}` conflicts with",
+                flag,
+                <&str>::from(output_format)
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn parse_compare_targets(
+    raw_compare_targets: &str,
+) -> Result<(String, String), String> {
+    match raw_compare_targets.split_once(',') {
+        Some((first_target, second_target))
+            if !first_target.is_empty() && !second_target.is_empty() =>
+        {
+            Ok((first_target.to_owned(), second_target.to_owned()))
+        }
+        _ => Err(format!(
+            "expected two comma separated target triples, got `{}`",
+            raw_compare_targets
+        )),
+    }
+}
+
+/// A package spec, as given to `--package`, e.g. `name` or `name:version`.
+fn parse_package_spec(raw_package_spec: &str) -> Result<String, String> {
+    PkgSpec::from_str(raw_package_spec)
+        .map(|_| raw_package_spec.to_owned())
+        .map_err(|e| {
+            format!("invalid package spec `{}`: {}", raw_package_spec, e)
+        })
+}
+
+fn parse_fields_deny(raw_fields_deny: &str) -> Result<Vec<String>, String> {
+    raw_fields_deny
+        .split(',')
+        .map(|field| {
+            if REPORT_ENTRY_FIELDS.contains(&field) {
+                Ok(field.to_owned())
+            } else {
+                Err(format!(
+                    "unknown report entry field `{}`, expected one of: {}",
+                    field,
+                    REPORT_ENTRY_FIELDS.join(", ")
+                ))
+            }
+        })
+        .collect()
+}
+
+/// The features given to `--features`, separated by spaces. Each feature is
+/// left as is, so that cargo parses the features of dependencies,
+/// `dep/feature` and `dep?/feature`, and reports the errors, as it does for
+/// its own `--features`.
 fn parse_features(raw_features: Option<String>) -> Vec<String> {
     raw_features
         .as_ref()
@@ -297,6 +1343,12 @@ pub mod args_tests {
             OutputFormat::Ascii,
             Quiet
         ),
+        case(
+            vec![OsString::from("--output-format"), OsString::from("json")],
+            false,
+            OutputFormat::Json,
+            Quiet
+        ),
         case(
             vec![OsString::from("-v")],
             false,
@@ -339,6 +1391,199 @@ pub mod args_tests {
         assert_eq!(args.verbosity, expected_verbosity)
     }
 
+    #[rstest(
+        input_argument_vector,
+        expected_output_format,
+        expected_color,
+        expected_path_base,
+        case(
+            vec![OsString::from("--ci")],
+            OutputFormat::Json,
+            Some(String::from("never")),
+            Some(PathBase::Workspace)
+        ),
+        case(
+            vec![
+                OsString::from("--ci"),
+                OsString::from("--output-format"),
+                OsString::from("Ascii"),
+                OsString::from("--color"),
+                OsString::from("always"),
+                OsString::from("--path-base"),
+                OsString::from("crate")
+            ],
+            OutputFormat::Ascii,
+            Some(String::from("always")),
+            Some(PathBase::Crate)
+        ),
+        case(vec![], OutputFormat::Utf8, None, None)
+    )]
+    fn parse_args_ci_test(
+        input_argument_vector: Vec<OsString>,
+        expected_output_format: OutputFormat,
+        expected_color: Option<String>,
+        expected_path_base: Option<PathBase>,
+    ) {
+        let args = Args::parse_args(Arguments::from_vec(input_argument_vector))
+            .unwrap();
+
+        assert_eq!(args.output_format, expected_output_format);
+        assert_eq!(args.color, expected_color);
+        assert_eq!(args.path_base, expected_path_base);
+    }
+
+    #[rstest]
+    fn parse_args_test_with_zero_jobs() {
+        let error = Args::parse_args(Arguments::from_vec(vec![
+            OsString::from("--jobs"),
+            OsString::from("0"),
+        ]))
+        .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("`--jobs` requires at least one thread"));
+    }
+
+    #[rstest]
+    fn parse_args_test_with_unknown_output_format() {
+        let error = Args::parse_args(Arguments::from_vec(vec![
+            OsString::from("--output-format"),
+            OsString::from("yaml"),
+        ]))
+        .unwrap_err();
+
+        assert!(error.to_string().contains(
+            "unknown output format `yaml`, expected one of: Ascii, Json, GitHubMarkdown, Prometheus, DenyToml, GitHubActions, Csv, Tree, Ratio, Utf8"
+        ));
+    }
+
+    #[rstest]
+    fn parse_args_test_with_exclude_self_and_root_threshold_scope() {
+        let error = Args::parse_args(Arguments::from_vec(vec![
+            OsString::from("--fail-threshold"),
+            OsString::from("0"),
+            OsString::from("--threshold-scope"),
+            OsString::from("root"),
+            OsString::from("--exclude-self"),
+        ]))
+        .unwrap_err();
+
+        assert!(error.to_string().contains(
+            "`--exclude-self` leaves the root crate out of the count"
+        ));
+    }
+
+    #[rstest(
+        input_args,
+        expected_error,
+        case(
+            vec!["--template", "report.hbs", "--output-format", "Ascii"],
+            "`--template` prints from the Json report, which `--output-format Ascii` conflicts with"
+        ),
+        case(
+            vec!["--template", "report.hbs", "--on-complete", "cat"],
+            "`--on-complete` is given the Json report, which `--template`, `--diff-format` and `--list-unsafe-crates` print otherwise"
+        ),
+        case(
+            vec!["--diff", "base.json", "--diff-format", "table", "--output-format", "Utf8"],
+            "`--diff-format` prints from the Json report, which `--output-format Utf8` conflicts with"
+        ),
+        case(
+            vec!["--list-unsafe-crates", "--output-format", "Csv"],
+            "`--list-unsafe-crates` prints from the Json report, which `--output-format Csv` conflicts with"
+        ),
+        case(
+            vec!["--list-unsafe-crates", "--output-format", "Json", "--on-complete", "cat"],
+            "`--on-complete` is given the Json report, which `--template`, `--diff-format` and `--list-unsafe-crates` print otherwise"
+        ),
+        case(
+            vec!["--diff", "base.json", "--diff-format", "json", "--on-complete", "cat"],
+            "`--on-complete` is given the Json report, which `--template`, `--diff-format` and `--list-unsafe-crates` print otherwise"
+        )
+    )]
+    fn parse_args_test_with_json_report_conflicts(
+        input_args: Vec<&str>,
+        expected_error: &str,
+    ) {
+        let error = Args::parse_args(Arguments::from_vec(
+            input_args.into_iter().map(OsString::from).collect(),
+        ))
+        .unwrap_err();
+
+        assert!(error.to_string().contains(expected_error));
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    #[rstest]
+    fn parse_args_test_with_sqlite_without_sqlite_feature() {
+        let error = Args::parse_args(Arguments::from_vec(vec![
+            OsString::from("--sqlite"),
+            OsString::from("geiger.db"),
+        ]))
+        .unwrap_err();
+
+        assert!(error.to_string().contains(
+            "require cargo-geiger to be built with the `sqlite` feature"
+        ));
+    }
+
+    #[rstest]
+    fn format_help_lists_all_placeholders_test() {
+        let format_help = format_help();
+        for (placeholder, description) in PLACEHOLDERS.iter() {
+            assert!(format_help.contains(&format!("{{{}}}", placeholder)));
+            assert!(format_help.contains(description));
+        }
+    }
+
+    #[rstest(
+        input_raw_compare_targets,
+        expected_compare_targets,
+        case(
+            "wasm32-unknown-unknown,x86_64-unknown-linux-gnu",
+            Some((
+                String::from("wasm32-unknown-unknown"),
+                String::from("x86_64-unknown-linux-gnu")
+            ))
+        ),
+        case("wasm32-unknown-unknown", None),
+        case("wasm32-unknown-unknown,", None)
+    )]
+    fn parse_compare_targets_test(
+        input_raw_compare_targets: &str,
+        expected_compare_targets: Option<(String, String)>,
+    ) {
+        assert_eq!(
+            parse_compare_targets(input_raw_compare_targets).ok(),
+            expected_compare_targets
+        );
+    }
+
+    #[rstest(
+        input_raw_fields_deny,
+        expected_fields_deny,
+        case(
+            "package.dependencies,unsafety.unused",
+            Some(vec![
+                String::from("package.dependencies"),
+                String::from("unsafety.unused")
+            ])
+        ),
+        case("unsafety", Some(vec![String::from("unsafety")])),
+        case("unsafety.unknown", None),
+        case("", None)
+    )]
+    fn parse_fields_deny_test(
+        input_raw_fields_deny: &str,
+        expected_fields_deny: Option<Vec<String>>,
+    ) {
+        assert_eq!(
+            parse_fields_deny(input_raw_fields_deny).ok(),
+            expected_fields_deny
+        );
+    }
+
     #[rstest(
         input_raw_features,
         expected_features,
@@ -361,6 +1606,26 @@ pub mod args_tests {
         case(
             None,
             vec![]
+        ),
+        case(
+            Some(String::from("dep/feat")),
+            vec![String::from("dep/feat")]
+        ),
+        case(
+            Some(String::from("dep:name")),
+            vec![String::from("dep:name")]
+        ),
+        case(
+            Some(String::from("dep?/feat")),
+            vec![String::from("dep?/feat")]
+        ),
+        case(
+            Some(String::from("test dep/feat,dep?/feat  dep:name")),
+            vec![
+                String::from("test"),
+                String::from("dep/feat,dep?/feat"),
+                String::from("dep:name")
+            ]
         )
     )]
     fn parse_features_test(
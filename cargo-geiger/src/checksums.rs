@@ -0,0 +1,298 @@
+use cargo_metadata::{Metadata, Package};
+use cargo_util::Sha256;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// The file written by cargo into the directory of each extracted crate,
+/// which is not part of the `.crate` archive.
+const CARGO_OK_FILE_NAME: &str = ".cargo-ok";
+
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    checksum: Option<String>,
+}
+
+/// Verify the registry crates of the dependency graph against the checksums
+/// of the `Cargo.lock` of the workspace, before their source is parsed.
+///
+/// Cargo checks the `.crate` archive of a crate when it is downloaded, but
+/// the scanned files are the ones extracted from it, which may have been
+/// modified since. So both the archive in the cache of the registry is hashed
+/// and compared to the checksum of the lockfile, and the extracted files are
+/// compared to the content of the archive. The crates which are not
+/// extracted in the layout of a registry cache, e.g. vendored ones, are
+/// skipped.
+pub fn verify_checksums(metadata: &Metadata) -> Result<(), ChecksumError> {
+    let lockfile_path = metadata.workspace_root.join("Cargo.lock");
+    let content = fs::read_to_string(&lockfile_path)
+        .map_err(|e| ChecksumError::Io(e, lockfile_path.clone().into()))?;
+    let lockfile = toml::from_str::<Lockfile>(&content)
+        .map_err(|e| ChecksumError::Toml(e, lockfile_path.into()))?;
+    let checksums = lockfile
+        .package
+        .iter()
+        .filter_map(|locked_package| {
+            Some((
+                (
+                    locked_package.name.as_str(),
+                    locked_package.version.as_str(),
+                    locked_package.source.as_deref()?,
+                ),
+                locked_package.checksum.as_deref(),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+
+    for package in metadata.packages.iter().filter(|p| is_registry_package(p)) {
+        let source = package.source.as_ref().unwrap().repr.as_str();
+        let version = package.version.to_string();
+        let expected_checksum = checksums
+            .get(&(package.name.as_str(), version.as_str(), source))
+            .copied()
+            .flatten()
+            .ok_or_else(|| {
+                ChecksumError::MissingChecksum(package.id.repr.clone())
+            })?;
+        let package_root = match package.manifest_path.parent() {
+            Some(package_root) => package_root.as_std_path(),
+            None => continue,
+        };
+        let archive_path = match crate_archive_path(package_root) {
+            Some(archive_path) => archive_path,
+            None => continue,
+        };
+        verify_crate_source(&archive_path, expected_checksum, package_root)?;
+    }
+    Ok(())
+}
+
+/// Verify that the `.crate` archive at `archive_path` has the
+/// `expected_checksum`, and that the files extracted from it to
+/// `package_root` are unchanged.
+pub fn verify_crate_source(
+    archive_path: &Path,
+    expected_checksum: &str,
+    package_root: &Path,
+) -> Result<(), ChecksumError> {
+    let archive = fs::read(archive_path)
+        .map_err(|e| ChecksumError::Io(e, archive_path.to_path_buf()))?;
+    let actual_checksum = Sha256::new().update(&archive).finish_hex();
+    if actual_checksum != expected_checksum {
+        return Err(ChecksumError::ArchiveMismatch {
+            path: archive_path.to_path_buf(),
+            expected: expected_checksum.to_string(),
+            actual: actual_checksum,
+        });
+    }
+
+    let archive_files = read_archive_files(&archive)
+        .map_err(|e| ChecksumError::Io(e, archive_path.to_path_buf()))?;
+    for (relative_path, archive_content) in &archive_files {
+        let path = package_root.join(relative_path);
+        let content =
+            fs::read(&path).map_err(|e| ChecksumError::Io(e, path.clone()))?;
+        if &content != archive_content {
+            return Err(ChecksumError::SourceMismatch(path));
+        }
+    }
+    let archive_paths = archive_files.keys().collect::<HashSet<_>>();
+    for entry in WalkDir::new(package_root)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let relative_path = entry
+            .path()
+            .strip_prefix(package_root)
+            .unwrap()
+            .to_path_buf();
+        if relative_path != Path::new(CARGO_OK_FILE_NAME)
+            && !archive_paths.contains(&relative_path)
+        {
+            return Err(ChecksumError::SourceMismatch(
+                entry.path().to_path_buf(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The path of the `.crate` archive of a crate extracted to
+/// `<cargo home>/registry/src/<index>/<name>-<version>`, which cargo keeps in
+/// `<cargo home>/registry/cache/<index>/<name>-<version>.crate`.
+fn crate_archive_path(package_root: &Path) -> Option<PathBuf> {
+    let index_directory = package_root.parent()?;
+    let src_directory = index_directory.parent()?;
+    if src_directory.file_name()? != "src" {
+        return None;
+    }
+    let archive_file_name =
+        format!("{}.crate", package_root.file_name()?.to_str()?);
+    Some(
+        src_directory
+            .parent()?
+            .join("cache")
+            .join(index_directory.file_name()?)
+            .join(archive_file_name),
+    )
+}
+
+fn is_registry_package(package: &Package) -> bool {
+    package.source.as_ref().map_or(false, |source| {
+        source.repr.starts_with("registry+")
+            || source.repr.starts_with("sparse+")
+    })
+}
+
+/// The regular files of a `.crate` archive, by their path relative to the
+/// `<name>-<version>` directory at the root of the archive.
+fn read_archive_files(archive: &[u8]) -> io::Result<HashMap<PathBuf, Vec<u8>>> {
+    let mut archive_files = HashMap::new();
+    let mut tar_archive = tar::Archive::new(GzDecoder::new(archive));
+    for entry in tar_archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let relative_path =
+            entry.path()?.components().skip(1).collect::<PathBuf>();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        archive_files.insert(relative_path, content);
+    }
+    Ok(archive_files)
+}
+
+#[derive(Debug)]
+pub enum ChecksumError {
+    ArchiveMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    Io(io::Error, PathBuf),
+    MissingChecksum(String),
+    SourceMismatch(PathBuf),
+    Toml(toml::de::Error, PathBuf),
+}
+
+impl Error for ChecksumError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod checksums_tests {
+    use super::*;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use rstest::*;
+    use tempfile::tempdir;
+
+    const LIB_RS: &[u8] = b"pub fn f() {}\n";
+
+    /// Lay out a crate as cargo does in its registry cache, returning the
+    /// package root and the checksum of the archive.
+    fn create_cached_crate(cargo_home: &Path) -> (PathBuf, String) {
+        let mut builder =
+            tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(LIB_RS.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "a-1.0.0/src/lib.rs", LIB_RS)
+            .unwrap();
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let cache_directory = cargo_home.join("registry/cache/index");
+        fs::create_dir_all(&cache_directory).unwrap();
+        fs::write(cache_directory.join("a-1.0.0.crate"), &archive).unwrap();
+        let package_root = cargo_home.join("registry/src/index/a-1.0.0");
+        fs::create_dir_all(package_root.join("src")).unwrap();
+        fs::write(package_root.join("src/lib.rs"), LIB_RS).unwrap();
+        fs::write(package_root.join(CARGO_OK_FILE_NAME), "").unwrap();
+
+        (package_root, Sha256::new().update(&archive).finish_hex())
+    }
+
+    #[rstest]
+    fn crate_archive_path_test() {
+        assert_eq!(
+            crate_archive_path(Path::new("/home/registry/src/index/a-1.0.0")),
+            Some(PathBuf::from("/home/registry/cache/index/a-1.0.0.crate"))
+        );
+        assert_eq!(crate_archive_path(Path::new("/vendor/a-1.0.0")), None);
+    }
+
+    #[rstest]
+    fn verify_crate_source_test() {
+        let cargo_home = tempdir().unwrap();
+        let (package_root, checksum) = create_cached_crate(cargo_home.path());
+        let archive_path = crate_archive_path(&package_root).unwrap();
+
+        assert!(verify_crate_source(&archive_path, &checksum, &package_root)
+            .is_ok());
+    }
+
+    #[rstest]
+    fn verify_crate_source_test_with_corrupted_archive() {
+        let cargo_home = tempdir().unwrap();
+        let (package_root, checksum) = create_cached_crate(cargo_home.path());
+        let archive_path = crate_archive_path(&package_root).unwrap();
+        let mut archive = fs::read(&archive_path).unwrap();
+        let last_byte = archive.len() - 1;
+        archive[last_byte] ^= 0xff;
+        fs::write(&archive_path, archive).unwrap();
+
+        assert!(matches!(
+            verify_crate_source(&archive_path, &checksum, &package_root),
+            Err(ChecksumError::ArchiveMismatch { .. })
+        ));
+    }
+
+    #[rstest(
+        input_file_name,
+        input_content,
+        case("src/lib.rs", "pub fn f() { unsafe {} }\n"),
+        case("src/extra.rs", "")
+    )]
+    fn verify_crate_source_test_with_tampered_source(
+        input_file_name: &str,
+        input_content: &str,
+    ) {
+        let cargo_home = tempdir().unwrap();
+        let (package_root, checksum) = create_cached_crate(cargo_home.path());
+        let archive_path = crate_archive_path(&package_root).unwrap();
+        fs::write(package_root.join(input_file_name), input_content).unwrap();
+
+        match verify_crate_source(&archive_path, &checksum, &package_root) {
+            Err(ChecksumError::SourceMismatch(path)) => {
+                assert_eq!(path, package_root.join(input_file_name))
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -6,23 +6,90 @@
 // TODO: Investigate how cargo-clippy is implemented. Is it using syn?  Is is
 // using rustc? Is it implementing a compiler plugin?
 
-use crate::args::Args;
+use crate::args::{Args, FeaturesArgs};
+use crate::mapping::QueryResolve;
 
 // TODO: Consider making this a lib.rs (again) and expose a full API, excluding
 // only the terminal output..? That API would be dependent on cargo.
+use anyhow::Context;
 use cargo::core::Workspace;
 use cargo::util::{important_paths, CargoResult};
-use cargo::GlobalContext as Config;
-use cargo_metadata::{CargoOpt, Metadata, MetadataCommand};
+use cargo::{CliError, CliResult, GlobalContext as Config};
+use cargo_metadata::{CargoOpt, Metadata, MetadataCommand, PackageId};
 use cargo_platform::Cfg;
 use krates::Builder as KratesBuilder;
 use krates::Krates;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::path::PathBuf;
 use std::str::{self, FromStr};
 
+/// Get the cargo metadata of the workspace, resolved with the features of
+/// `args`, or with all the features for `--include-optional` so that the
+/// optional dependencies are part of the resolved graph.
 pub fn get_cargo_metadata(
     args: &Args,
     config: &Config,
+) -> CargoResult<Metadata> {
+    exec_metadata_command(
+        args,
+        config,
+        &args.features_args,
+        args.include_optional,
+    )
+}
+
+/// Get the cargo metadata of the workspace, resolved with the features of
+/// `args` but without `feature`, for `--feature-impact`. The features of the
+/// root package which enable `feature` are left out too, `default` included,
+/// and with `--all-features` every other feature of the root package is
+/// enabled.
+pub fn get_cargo_metadata_without_feature(
+    args: &Args,
+    config: &Config,
+    cargo_metadata: &Metadata,
+    root_package_id: &PackageId,
+    feature: &str,
+) -> CargoResult<Metadata> {
+    let features_args = FeaturesArgs {
+        all_features: false,
+        features: get_features_without_feature(
+            &cargo_metadata[root_package_id].features,
+            &args.features_args,
+            feature,
+        ),
+        no_default_features: true,
+    };
+    exec_metadata_command(args, config, &features_args, false)
+}
+
+/// The packages of the resolved graph of `cargo_metadata` which are only
+/// pulled by optional dependencies whose feature is not enabled by `args`.
+/// Always empty without `--include-optional`.
+pub fn get_inactive_optional_package_ids(
+    args: &Args,
+    config: &Config,
+    cargo_metadata: &Metadata,
+) -> CargoResult<HashSet<PackageId>> {
+    if !args.include_optional {
+        return Ok(HashSet::new());
+    }
+    let active_package_ids = resolved_package_ids(&exec_metadata_command(
+        args,
+        config,
+        &args.features_args,
+        false,
+    )?);
+    Ok(resolved_package_ids(cargo_metadata)
+        .difference(&active_package_ids)
+        .cloned()
+        .collect())
+}
+
+fn exec_metadata_command(
+    args: &Args,
+    config: &Config,
+    features_args: &FeaturesArgs,
+    all_features: bool,
 ) -> CargoResult<Metadata> {
     let root_manifest_path = match args.manifest_path.clone() {
         Some(path) => path,
@@ -32,33 +99,71 @@ pub fn get_cargo_metadata(
     let mut metadata_command = MetadataCommand::new();
     metadata_command.manifest_path(root_manifest_path);
 
-    if let Some(metadata_command_features) = match &args.features_args {
-        features_args if features_args.all_features => {
-            Some(CargoOpt::AllFeatures)
-        }
-        features_args if features_args.no_default_features => {
-            Some(CargoOpt::NoDefaultFeatures)
+    if all_features || features_args.all_features {
+        metadata_command.features(CargoOpt::AllFeatures);
+    } else {
+        if features_args.no_default_features {
+            metadata_command.features(CargoOpt::NoDefaultFeatures);
         }
-        features_args if !features_args.features.is_empty() => {
-            Some(CargoOpt::SomeFeatures(args.features_args.features.clone()))
+        if !features_args.features.is_empty() {
+            metadata_command.features(CargoOpt::SomeFeatures(
+                features_args.features.clone(),
+            ));
         }
-        _ => None,
-    } {
-        metadata_command.features(metadata_command_features);
     }
+    metadata_command.other_options(network_options(args));
 
-    Ok(metadata_command.exec()?)
+    let metadata = metadata_command.exec();
+    if args.frozen || args.offline {
+        metadata.context(
+            "Failed to resolve the dependencies without accessing the \
+             network, every dependency must be in the lockfile and in the \
+             local registry cache (see `cargo fetch`)",
+        )
+    } else {
+        Ok(metadata?)
+    }
+}
+
+/// The `--frozen`, `--locked` and `--offline` flags for the `cargo metadata`
+/// invocation, which doesn't use the configuration of cargo-geiger, so that
+/// resolving the dependencies doesn't access the network either.
+fn network_options(args: &Args) -> Vec<String> {
+    [
+        (args.frozen, "--frozen"),
+        (args.locked, "--locked"),
+        (args.offline, "--offline"),
+    ]
+    .iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, option)| option.to_string())
+    .collect()
+}
+
+fn resolved_package_ids(cargo_metadata: &Metadata) -> HashSet<PackageId> {
+    cargo_metadata
+        .resolve
+        .iter()
+        .flat_map(|resolve| resolve.nodes.iter().map(|node| node.id.clone()))
+        .collect()
 }
 
 /// TODO: Write proper documentation for this.
 /// This function seems to be looking up the active flags for conditional
 /// compilation (`cargo_platform::Cfg` instances).
+///
+/// The `rustflags` are passed to rustc, so that the `--cfg` flags of the cargo
+/// configuration are part of the active flags, as they are for a build.
 pub fn get_cfgs(
     global_rustc_path: &PathBuf,
     target: &Option<String>,
+    rustflags: &[String],
 ) -> CargoResult<Option<Vec<Cfg>>> {
     let mut process = cargo_util::ProcessBuilder::new(global_rustc_path);
-    process.arg("--print=cfg").env_remove("RUST_LOG");
+    process
+        .arg("--print=cfg")
+        .args(rustflags)
+        .env_remove("RUST_LOG");
     if let Some(ref s) = *target {
         process.arg("--target").arg(s);
     }
@@ -75,11 +180,221 @@ pub fn get_cfgs(
     ))
 }
 
+/// Read the build target from the `[build] target` setting of the cargo
+/// configuration, e.g. `.cargo/config.toml`. If several targets are
+/// configured, only the first one is used.
+pub fn get_target_from_config(config: &Config) -> CargoResult<Option<String>> {
+    match &config.build_config()?.target {
+        Some(build_target_config) => {
+            Ok(build_target_config.values(config)?.into_iter().next())
+        }
+        None => Ok(None),
+    }
+}
+
+/// Read the flags passed to rustc by a build, in the order of precedence of
+/// cargo: the `CARGO_ENCODED_RUSTFLAGS` and `RUSTFLAGS` environment variables,
+/// then the `[build] rustflags` setting of the cargo configuration, e.g.
+/// `.cargo/config.toml`. The `[target] rustflags` settings are not read.
+pub fn get_rustflags_from_config(config: &Config) -> CargoResult<Vec<String>> {
+    if let Some(encoded_rustflags) =
+        config.get_env_os("CARGO_ENCODED_RUSTFLAGS")
+    {
+        let encoded_rustflags = encoded_rustflags.to_string_lossy();
+        return Ok(if encoded_rustflags.is_empty() {
+            Vec::new()
+        } else {
+            encoded_rustflags
+                .split('\x1f')
+                .map(str::to_string)
+                .collect()
+        });
+    }
+    if let Some(rustflags) = config.get_env_os("RUSTFLAGS") {
+        return Ok(rustflags
+            .to_string_lossy()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect());
+    }
+    Ok(match &config.build_config()?.rustflags {
+        Some(rustflags) => rustflags.as_slice().to_vec(),
+        None => Vec::new(),
+    })
+}
+
 pub fn get_krates(cargo_metadata: &Metadata) -> CargoResult<Krates> {
     Ok(KratesBuilder::new()
         .build_with_metadata(cargo_metadata.clone(), |_| ())?)
 }
 
+/// The packages to be used as the roots of the scan: every workspace member
+/// with `--workspace`, otherwise the root package of the workspace, or the
+/// package given to `--package` in a virtual workspace. `None` for a virtual
+/// workspace if neither is provided, or if no package matches `--package`.
+pub fn get_root_package_ids(
+    args: &Args,
+    cargo_metadata: &Metadata,
+    krates: &Krates,
+) -> Option<Vec<PackageId>> {
+    if args.workspace {
+        return Some(cargo_metadata.workspace_members.clone());
+    }
+    match (cargo_metadata.root_package(), &args.package) {
+        (Some(root_package), _) => Some(vec![root_package.id.clone()]),
+        (None, Some(package_query)) => krates
+            .query_resolve(package_query)
+            .map(|package_id| vec![package_id]),
+        (None, None) => None,
+    }
+}
+
+/// The names of the workspace members, sorted, to list them when a virtual
+/// workspace is scanned without `--package` or `--workspace`.
+pub fn get_workspace_member_names(cargo_metadata: &Metadata) -> Vec<String> {
+    let mut workspace_member_names = cargo_metadata
+        .workspace_members
+        .iter()
+        .map(|package_id| cargo_metadata[package_id].name.clone())
+        .collect::<Vec<String>>();
+    workspace_member_names.sort();
+    workspace_member_names
+}
+
+/// Exit with an error if a feature given to `--features` is not declared by
+/// any of the root packages, for `--strict-features`. The valid features are
+/// listed, so that a typo doesn't silently scan another configuration.
+pub fn check_strict_features(
+    args: &Args,
+    cargo_metadata: &Metadata,
+    root_package_ids: &[PackageId],
+) -> CliResult {
+    let declared_features =
+        get_declared_features(cargo_metadata, root_package_ids);
+    let unknown_features =
+        get_unknown_features(&args.features_args.features, &declared_features);
+    if unknown_features.is_empty() {
+        return Ok(());
+    }
+    eprintln!(
+        "Unknown features given to --features: {}
+Valid features: {}",
+        unknown_features.join(", "),
+        match declared_features.is_empty() {
+            true => String::from("(none)"),
+            false => declared_features
+                .into_iter()
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+    );
+    Err(CliError::code(1))
+}
+
+/// The features declared by the root packages, including the implicit
+/// features of their optional dependencies.
+fn get_declared_features(
+    cargo_metadata: &Metadata,
+    root_package_ids: &[PackageId],
+) -> BTreeSet<String> {
+    root_package_ids
+        .iter()
+        .flat_map(|package_id| cargo_metadata[package_id].features.keys())
+        .cloned()
+        .collect()
+}
+
+/// The features which are not declared, in the order they are given. Like
+/// cargo, features can also be separated by commas. The features of a
+/// dependency, `dependency/feature`, are left to cargo, as is `default`,
+/// which is always accepted.
+fn get_unknown_features(
+    features: &[String],
+    declared_features: &BTreeSet<String>,
+) -> Vec<String> {
+    features
+        .iter()
+        .flat_map(|feature| feature.split(','))
+        .filter(|feature| {
+            !feature.is_empty()
+                && !feature.contains('/')
+                && *feature != "default"
+                && !declared_features.contains(*feature)
+        })
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The features of the root package enabled by `features_args`, `default`
+/// included unless `--no-default-features` is given, without `feature` and
+/// the features which enable it, directly or through other features. The
+/// features of the dependencies are kept.
+fn get_features_without_feature(
+    declared_features: &BTreeMap<String, Vec<String>>,
+    features_args: &FeaturesArgs,
+    feature: &str,
+) -> Vec<String> {
+    let enabled_features = if features_args.all_features {
+        declared_features.keys().cloned().collect::<Vec<String>>()
+    } else {
+        let mut enabled_features = features_args
+            .features
+            .iter()
+            .flat_map(|feature| feature.split(','))
+            .filter(|feature| !feature.is_empty())
+            .map(str::to_owned)
+            .collect::<Vec<String>>();
+        if !features_args.no_default_features
+            && declared_features.contains_key("default")
+        {
+            enabled_features.push(String::from("default"));
+        }
+        enabled_features
+    };
+    enabled_features
+        .into_iter()
+        .filter(|enabled_feature| {
+            !enables_feature(
+                declared_features,
+                enabled_feature,
+                feature,
+                &mut HashSet::new(),
+            )
+        })
+        .collect()
+}
+
+/// Whether `enabled_feature` is `feature` or enables it, through the features
+/// it lists. `dependency/feature` enables the implicit feature of an optional
+/// dependency, unlike the weak `dependency?/feature`.
+fn enables_feature<'a>(
+    declared_features: &'a BTreeMap<String, Vec<String>>,
+    enabled_feature: &'a str,
+    feature: &str,
+    visited_features: &mut HashSet<&'a str>,
+) -> bool {
+    if enabled_feature == feature {
+        return true;
+    }
+    if !visited_features.insert(enabled_feature) {
+        return false;
+    }
+    declared_features
+        .get(enabled_feature)
+        .into_iter()
+        .flatten()
+        .filter(|listed_feature| !listed_feature.starts_with("dep:"))
+        .filter_map(|listed_feature| listed_feature.split('/').next())
+        .any(|listed_feature| {
+            enables_feature(
+                declared_features,
+                listed_feature,
+                feature,
+                visited_features,
+            )
+        })
+}
+
 pub fn get_workspace(
     config: &Config,
     manifest_path: Option<PathBuf>,
@@ -96,7 +411,12 @@ pub fn get_workspace(
 #[cfg(test)]
 mod cli_tests {
     use super::*;
+
+    use crate::lib_tests::construct_krates_and_metadata;
+    use cargo::core::Shell;
     use rstest::*;
+    use std::fs;
+    use tempfile::tempdir;
 
     #[rstest]
     fn get_cargo_metadata_test() {
@@ -108,6 +428,66 @@ mod cli_tests {
         assert!(cargo_metadata_result.is_ok());
     }
 
+    #[rstest(
+        input_features_args,
+        expected_features,
+        case(
+            FeaturesArgs {
+                all_features: true,
+                ..Default::default()
+            },
+            vec!["b"]
+        ),
+        case(FeaturesArgs::default(), vec![]),
+        case(
+            FeaturesArgs {
+                features: vec![String::from("b,c")],
+                no_default_features: true,
+                ..Default::default()
+            },
+            vec!["b"]
+        ),
+        case(
+            FeaturesArgs {
+                features: vec![String::from("a")],
+                no_default_features: true,
+                ..Default::default()
+            },
+            vec![]
+        )
+    )]
+    fn get_features_without_feature_test(
+        input_features_args: FeaturesArgs,
+        expected_features: Vec<&str>,
+    ) {
+        let declared_features = vec![
+            ("a", vec!["dep:unsafe_dep"]),
+            ("b", vec!["unsafe_dep?/std"]),
+            ("c", vec!["a"]),
+            ("default", vec!["c"]),
+        ]
+        .into_iter()
+        .map(|(feature, listed_features)| {
+            (
+                feature.to_string(),
+                listed_features
+                    .into_iter()
+                    .map(str::to_owned)
+                    .collect::<Vec<String>>(),
+            )
+        })
+        .collect::<BTreeMap<String, Vec<String>>>();
+
+        assert_eq!(
+            get_features_without_feature(
+                &declared_features,
+                &input_features_args,
+                "a"
+            ),
+            expected_features
+        );
+    }
+
     #[rstest]
     fn get_cfgs_test() {
         let config = Config::default().unwrap();
@@ -118,7 +498,7 @@ mod cli_tests {
 
         let global_rustc = config.load_global_rustc(Some(&workspace)).unwrap();
 
-        let cfgs = get_cfgs(&global_rustc.path, &target);
+        let cfgs = get_cfgs(&global_rustc.path, &target, &[]);
 
         assert!(cfgs.is_ok());
         let cfg_vec_option = cfgs.unwrap();
@@ -136,6 +516,146 @@ mod cli_tests {
         assert!(key_pairs.next().is_some());
     }
 
+    #[rstest(
+        input_config_toml,
+        expected_target,
+        case(
+            "[build]\ntarget = \"wasm32-unknown-unknown\"\n",
+            Some("wasm32-unknown-unknown")
+        ),
+        case("[build]\njobs = 1\n", None)
+    )]
+    fn get_target_from_config_test(
+        input_config_toml: &str,
+        expected_target: Option<&str>,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let cargo_dir = temp_dir.path().join(".cargo");
+        fs::create_dir(&cargo_dir).unwrap();
+        fs::write(cargo_dir.join("config.toml"), input_config_toml).unwrap();
+
+        let config = Config::new(
+            Shell::new(),
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("cargo_home"),
+        );
+
+        let target = get_target_from_config(&config).unwrap();
+        assert_eq!(target.as_deref(), expected_target);
+    }
+
+    #[rstest(
+        input_config_toml,
+        expected_rustflags,
+        case(
+            "[build]\nrustflags = [\"--cfg\", \"geiger_cfg\"]\n",
+            vec!["--cfg", "geiger_cfg"]
+        ),
+        case(
+            "[build]\nrustflags = \"-C opt-level=1\"\n",
+            vec!["-C", "opt-level=1"]
+        ),
+        case("[build]\njobs = 1\n", vec![])
+    )]
+    fn get_rustflags_from_config_test(
+        input_config_toml: &str,
+        expected_rustflags: Vec<&str>,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let cargo_dir = temp_dir.path().join(".cargo");
+        fs::create_dir(&cargo_dir).unwrap();
+        fs::write(cargo_dir.join("config.toml"), input_config_toml).unwrap();
+
+        let mut config = Config::new(
+            Shell::new(),
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("cargo_home"),
+        );
+        // The environment variables would take precedence over the config.
+        config.set_env(
+            std::env::vars()
+                .filter(|(key, _)| {
+                    key != "RUSTFLAGS" && key != "CARGO_ENCODED_RUSTFLAGS"
+                })
+                .collect(),
+        );
+
+        let rustflags = get_rustflags_from_config(&config).unwrap();
+        assert_eq!(rustflags, expected_rustflags);
+    }
+
+    #[rstest]
+    fn get_cfgs_test_with_rustflags() {
+        let config = Config::default().unwrap();
+        let root =
+            important_paths::find_root_manifest_for_wd(config.cwd()).unwrap();
+        let workspace = Workspace::new(&root, &config).unwrap();
+        let global_rustc = config.load_global_rustc(Some(&workspace)).unwrap();
+
+        let cfgs = get_cfgs(
+            &global_rustc.path,
+            &None,
+            &[String::from("--cfg"), String::from("geiger_cfg")],
+        )
+        .unwrap()
+        .unwrap();
+
+        assert!(cfgs.contains(&Cfg::Name(String::from("geiger_cfg"))));
+    }
+
+    #[rstest(
+        input_frozen,
+        input_locked,
+        input_offline,
+        expected_network_options,
+        case(false, false, false, vec![]),
+        case(true, false, false, vec!["--frozen"]),
+        case(false, true, true, vec!["--locked", "--offline"]),
+        case(true, true, true, vec!["--frozen", "--locked", "--offline"])
+    )]
+    fn network_options_test(
+        input_frozen: bool,
+        input_locked: bool,
+        input_offline: bool,
+        expected_network_options: Vec<&str>,
+    ) {
+        let args = Args {
+            frozen: input_frozen,
+            locked: input_locked,
+            offline: input_offline,
+            ..Default::default()
+        };
+
+        assert_eq!(network_options(&args), expected_network_options);
+    }
+
+    #[rstest(
+        input_features,
+        expected_unknown_features,
+        case(vec!["vendored-openssl"], vec![]),
+        case(vec!["vendored-opensll"], vec!["vendored-opensll"]),
+        case(vec!["default,typo", "cargo/foo"], vec!["typo"])
+    )]
+    fn get_unknown_features_test(
+        input_features: Vec<&str>,
+        expected_unknown_features: Vec<&str>,
+    ) {
+        let (_, metadata) = construct_krates_and_metadata();
+        let declared_features = get_declared_features(
+            &metadata,
+            &[metadata.root_package().unwrap().id.clone()],
+        );
+        let features = input_features
+            .iter()
+            .map(|feature| feature.to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            get_unknown_features(&features, &declared_features),
+            expected_unknown_features
+        );
+    }
+
     #[rstest]
     fn get_krates_test() {
         let args = Args::default();
@@ -146,6 +666,37 @@ mod cli_tests {
         assert!(krates_result.is_ok());
     }
 
+    #[rstest(
+        input_workspace,
+        expected_root_package_names,
+        case(false, vec!["cargo-geiger"]),
+        case(true, vec!["cargo-geiger", "cargo-geiger-serde", "geiger"])
+    )]
+    fn get_root_package_ids_test(
+        input_workspace: bool,
+        expected_root_package_names: Vec<&str>,
+    ) {
+        let args = Args {
+            workspace: input_workspace,
+            ..Default::default()
+        };
+        let (krates, metadata) = construct_krates_and_metadata();
+
+        let root_package_ids =
+            get_root_package_ids(&args, &metadata, &krates).unwrap();
+        let mut root_package_names = root_package_ids
+            .iter()
+            .map(|package_id| metadata[package_id].name.as_str())
+            .collect::<Vec<&str>>();
+        root_package_names.sort_unstable();
+
+        assert_eq!(root_package_names, expected_root_package_names);
+        assert_eq!(
+            get_workspace_member_names(&metadata),
+            vec!["cargo-geiger", "cargo-geiger-serde", "geiger"]
+        );
+    }
+
     #[rstest]
     fn get_workspace_test() {
         let config = Config::default().unwrap();
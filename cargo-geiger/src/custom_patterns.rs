@@ -0,0 +1,177 @@
+use crate::scan::GeigerContext;
+
+use cargo_metadata::PackageId;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A regular expression counted in the scanned source files, given as
+/// `<REGEX>:<NAME>` to `--custom-pattern`. The regular expression is matched
+/// line by line against the raw source text, so unlike the unsafe usage
+/// metrics it also matches comments and string literals, and can't match
+/// across lines.
+#[derive(Clone, Debug)]
+pub struct CustomPattern {
+    pub name: String,
+    pub regex: Regex,
+}
+
+impl FromStr for CustomPattern {
+    type Err = CustomPatternError;
+
+    /// The name follows the last `:`, so the regular expression may itself
+    /// contain a `:`.
+    fn from_str(s: &str) -> Result<CustomPattern, CustomPatternError> {
+        match s.rsplit_once(':') {
+            Some((raw_regex, name))
+                if !raw_regex.is_empty() && !name.is_empty() =>
+            {
+                Ok(CustomPattern {
+                    name: name.to_owned(),
+                    regex: Regex::new(raw_regex)
+                        .map_err(CustomPatternError::Regex)?,
+                })
+            }
+            _ => Err(CustomPatternError::MissingName(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CustomPatternError {
+    MissingName(String),
+    Regex(regex::Error),
+}
+
+impl Error for CustomPatternError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for CustomPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The number of matches of each custom pattern in the scanned files of each
+/// package, by pattern name. The matches of patterns sharing a name are
+/// summed. Empty if no custom pattern is given.
+pub fn custom_pattern_counts(
+    custom_patterns: &[CustomPattern],
+    geiger_context: &GeigerContext,
+) -> HashMap<PackageId, BTreeMap<String, u64>> {
+    if custom_patterns.is_empty() {
+        return HashMap::new();
+    }
+    geiger_context
+        .package_id_to_metrics
+        .iter()
+        .map(|(package_id, package_metrics)| {
+            (
+                package_id.clone(),
+                count_in_files(
+                    custom_patterns,
+                    package_metrics.rs_path_to_metrics.keys(),
+                ),
+            )
+        })
+        .collect()
+}
+
+fn count_in_files<'a, I>(
+    custom_patterns: &[CustomPattern],
+    rs_file_paths: I,
+) -> BTreeMap<String, u64>
+where
+    I: IntoIterator<Item = &'a PathBuf>,
+{
+    let mut counts = custom_patterns
+        .iter()
+        .map(|custom_pattern| (custom_pattern.name.clone(), 0))
+        .collect::<BTreeMap<String, u64>>();
+    for rs_file_path in rs_file_paths {
+        // The files were just scanned, a file which can't be read anymore is
+        // left out of the counts.
+        let content = match fs::read_to_string(rs_file_path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        for line in content.lines() {
+            for custom_pattern in custom_patterns {
+                *counts.get_mut(&custom_pattern.name).unwrap() +=
+                    custom_pattern.regex.find_iter(line).count() as u64;
+            }
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod custom_patterns_tests {
+    use super::*;
+
+    use rstest::*;
+    use tempfile::tempdir;
+
+    #[rstest(
+        input_custom_pattern,
+        expected_name_and_regex,
+        case("unwrap\\(\\):unwraps", Some(("unwraps", "unwrap\\(\\)"))),
+        case(
+            "std::mem::transmute:transmutes",
+            Some(("transmutes", "std::mem::transmute"))
+        ),
+        case("unwrap", None),
+        case("unwrap:", None),
+        case(":unwraps", None),
+        case("unwrap(:unwraps", None)
+    )]
+    fn custom_pattern_from_str_test(
+        input_custom_pattern: &str,
+        expected_name_and_regex: Option<(&str, &str)>,
+    ) {
+        let custom_pattern = input_custom_pattern.parse::<CustomPattern>();
+        assert_eq!(
+            custom_pattern.as_ref().ok().map(|custom_pattern| (
+                custom_pattern.name.as_str(),
+                custom_pattern.regex.as_str()
+            )),
+            expected_name_and_regex
+        );
+    }
+
+    #[rstest]
+    fn count_in_files_test() {
+        let temp_dir = tempdir().unwrap();
+        let first_path = temp_dir.path().join("first.rs");
+        let second_path = temp_dir.path().join("second.rs");
+        fs::write(&first_path, "a.unwrap().unwrap();\n// todo!()\n").unwrap();
+        fs::write(&second_path, "b.unwrap();\ntodo!()\n").unwrap();
+        let custom_patterns = vec![
+            "unwrap\\(\\):unwraps".parse::<CustomPattern>().unwrap(),
+            "todo!:todos".parse::<CustomPattern>().unwrap(),
+            "panic!:panics".parse::<CustomPattern>().unwrap(),
+            "unimplemented!:todos".parse::<CustomPattern>().unwrap(),
+        ];
+
+        let counts = count_in_files(
+            &custom_patterns,
+            &[first_path, second_path, temp_dir.path().join("missing.rs")],
+        );
+
+        assert_eq!(
+            counts,
+            vec![
+                (String::from("panics"), 0),
+                (String::from("todos"), 2),
+                (String::from("unwraps"), 3),
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+}
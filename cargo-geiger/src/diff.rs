@@ -0,0 +1,499 @@
+use crate::scan::{unsafe_stats, GeigerContext};
+
+use cargo_geiger_serde::{
+    CategoryDelta, Count, CounterBlock, PackageDelta, QuickSafetyReport,
+    ReportDelta, ReportEntry, SafetyReport, UnsafeChange,
+};
+use cargo_metadata::semver::Version;
+use cargo_metadata::{Metadata, PackageId};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The unsafe usage of the crates of a Json report from an earlier run, given
+/// to `--diff`.
+#[derive(Debug, Default)]
+pub struct Baseline {
+    /// The versions of each crate in the baseline, with their number of
+    /// unsafe items in code used by the build.
+    used_unsafe_counts_by_name: HashMap<String, Vec<(Version, u64)>>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Baseline, BaselineError> {
+        Ok(Baseline::from_report(&load_report(path)?))
+    }
+
+    pub fn from_report(report: &SafetyReport) -> Baseline {
+        let mut used_unsafe_counts_by_name = HashMap::new();
+        for entry in report
+            .packages
+            .values()
+            .chain(report.build_packages.values())
+        {
+            used_unsafe_counts_by_name
+                .entry(entry.package.id.name.clone())
+                .or_insert_with(Vec::new)
+                .push((
+                    entry.package.id.version.clone(),
+                    entry.unsafety.used.unsafe_count(),
+                ));
+        }
+        Baseline {
+            used_unsafe_counts_by_name,
+        }
+    }
+
+    /// How the unsafe usage of a crate changed since the baseline, along with
+    /// the baseline version it is compared to. `None` unless the baseline
+    /// only has other versions of the crate, in which case the highest of
+    /// them is used.
+    pub fn version_change(
+        &self,
+        name: &str,
+        version: &Version,
+        used: &CounterBlock,
+    ) -> Option<(Version, UnsafeChange)> {
+        let versions = self.used_unsafe_counts_by_name.get(name)?;
+        if versions
+            .iter()
+            .any(|(baseline_version, _)| baseline_version == version)
+        {
+            return None;
+        }
+        let (baseline_version, baseline_count) =
+            versions.iter().max_by(|a, b| a.0.cmp(&b.0))?;
+        let count = used.unsafe_count();
+        let change = if *baseline_count == 0 && count > 0 {
+            UnsafeChange::New
+        } else if count > *baseline_count {
+            UnsafeChange::Increased
+        } else if count < *baseline_count {
+            UnsafeChange::Decreased
+        } else {
+            UnsafeChange::Unchanged
+        };
+        Some((baseline_version.clone(), change))
+    }
+}
+
+/// The Json report from an earlier run, given to `--diff`.
+pub fn load_report(path: &Path) -> Result<SafetyReport, BaselineError> {
+    let content =
+        fs::read(path).map_err(|e| BaselineError::Io(e, path.to_path_buf()))?;
+    serde_json::from_slice::<SafetyReport>(&content)
+        .map_err(|e| BaselineError::Json(e, path.to_path_buf()))
+}
+
+/// The packages added, removed and whose unsafe usage in code used by the
+/// build changed since the baseline report, for `--diff-format`, sorted. With
+/// `full_entries`, for `--diff-full`, the entries of the report for the added
+/// and changed packages are included.
+pub fn report_delta(
+    baseline_report: &SafetyReport,
+    report: &SafetyReport,
+    full_entries: bool,
+) -> ReportDelta {
+    let baseline_entries = entries_by_name_and_version(baseline_report);
+    let entries = entries_by_name_and_version(report);
+
+    let mut report_delta = ReportDelta::default();
+    for (name_and_version, entry) in &entries {
+        let is_delta = match baseline_entries.get(name_and_version) {
+            None => {
+                report_delta.added.push(entry.package.id.clone());
+                true
+            }
+            Some(baseline_entry) => {
+                let categories = category_deltas(
+                    &baseline_entry.unsafety.used,
+                    &entry.unsafety.used,
+                );
+                let is_changed = !categories.is_empty();
+                if is_changed {
+                    report_delta.changed.push(PackageDelta {
+                        package: entry.package.id.clone(),
+                        categories,
+                    });
+                }
+                is_changed
+            }
+        };
+        if is_delta && full_entries {
+            report_delta.entries.push((*entry).clone());
+        }
+    }
+    for (name_and_version, baseline_entry) in &baseline_entries {
+        if !entries.contains_key(name_and_version) {
+            report_delta.removed.push(baseline_entry.package.id.clone());
+        }
+    }
+    report_delta.added.sort();
+    report_delta.removed.sort();
+    report_delta
+        .changed
+        .sort_by(|a, b| a.package.cmp(&b.package));
+    report_delta
+        .entries
+        .sort_by(|a, b| a.package.id.cmp(&b.package.id));
+    report_delta
+}
+
+fn entries_by_name_and_version(
+    report: &SafetyReport,
+) -> HashMap<(&str, &Version), &ReportEntry> {
+    report
+        .packages
+        .values()
+        .chain(report.build_packages.values())
+        .map(|entry| {
+            (
+                (entry.package.id.name.as_str(), &entry.package.id.version),
+                entry,
+            )
+        })
+        .collect()
+}
+
+/// The categories whose number of unsafe items differs between the counters.
+pub fn category_deltas(
+    baseline: &CounterBlock,
+    current: &CounterBlock,
+) -> Vec<CategoryDelta> {
+    let categories: [(&str, &Count, &Count); 5] = [
+        ("functions", &baseline.functions, &current.functions),
+        ("exprs", &baseline.exprs, &current.exprs),
+        ("item_impls", &baseline.item_impls, &current.item_impls),
+        ("item_traits", &baseline.item_traits, &current.item_traits),
+        ("methods", &baseline.methods, &current.methods),
+    ];
+    categories
+        .iter()
+        .filter(|(_, baseline, current)| baseline.unsafe_ != current.unsafe_)
+        .map(|(category, baseline, current)| CategoryDelta {
+            category: category.to_string(),
+            baseline: baseline.unsafe_,
+            current: current.unsafe_,
+        })
+        .collect()
+}
+
+/// Whether the crates of a Json report from an earlier `--forbid-only` run
+/// forbid unsafe code, given to `--fail-on-forbid-regression`.
+#[derive(Debug, Default)]
+pub struct ForbidBaseline {
+    /// The versions of each crate in the baseline, with whether they forbid
+    /// unsafe code.
+    forbids_unsafe_by_name: HashMap<String, Vec<(Version, bool)>>,
+}
+
+impl ForbidBaseline {
+    pub fn load(path: &Path) -> Result<ForbidBaseline, BaselineError> {
+        let content = fs::read(path)
+            .map_err(|e| BaselineError::Io(e, path.to_path_buf()))?;
+        let report = serde_json::from_slice::<QuickSafetyReport>(&content)
+            .map_err(|e| BaselineError::Json(e, path.to_path_buf()))?;
+        Ok(ForbidBaseline::from_report(&report))
+    }
+
+    pub fn from_report(report: &QuickSafetyReport) -> ForbidBaseline {
+        let mut forbids_unsafe_by_name = HashMap::new();
+        for entry in report.packages.values() {
+            forbids_unsafe_by_name
+                .entry(entry.package.id.name.clone())
+                .or_insert_with(Vec::new)
+                .push((entry.package.id.version.clone(), entry.forbids_unsafe));
+        }
+        ForbidBaseline {
+            forbids_unsafe_by_name,
+        }
+    }
+
+    /// The baseline version of a crate which forbade unsafe code, if the
+    /// crate no longer does. The crate is compared to the same version in the
+    /// baseline, otherwise to the highest one, so that upgrades are covered.
+    pub fn regression(
+        &self,
+        name: &str,
+        version: &Version,
+        forbids_unsafe: bool,
+    ) -> Option<Version> {
+        if forbids_unsafe {
+            return None;
+        }
+        let versions = self.forbids_unsafe_by_name.get(name)?;
+        let (baseline_version, baseline_forbids_unsafe) = versions
+            .iter()
+            .find(|(baseline_version, _)| baseline_version == version)
+            .or_else(|| versions.iter().max_by(|a, b| a.0.cmp(&b.0)))?;
+        match baseline_forbids_unsafe {
+            true => Some(baseline_version.clone()),
+            false => None,
+        }
+    }
+}
+
+/// The packages of a `--forbid-only` report which forbade unsafe code in the
+/// baseline and no longer do, with the baseline version, sorted.
+pub fn forbid_regressions(
+    forbid_baseline: &ForbidBaseline,
+    report: &QuickSafetyReport,
+) -> Vec<(cargo_geiger_serde::PackageId, Version)> {
+    let mut forbid_regressions = report
+        .packages
+        .values()
+        .filter_map(|entry| {
+            forbid_baseline
+                .regression(
+                    &entry.package.id.name,
+                    &entry.package.id.version,
+                    entry.forbids_unsafe,
+                )
+                .map(|baseline_version| {
+                    (entry.package.id.clone(), baseline_version)
+                })
+        })
+        .collect::<Vec<_>>();
+    forbid_regressions.sort();
+    forbid_regressions
+}
+
+/// The change of the unsafe usage of each scanned package whose version
+/// differs from the baseline.
+pub fn version_changes(
+    baseline: &Baseline,
+    geiger_context: &GeigerContext,
+    metadata: &Metadata,
+    rs_files_used: &HashSet<PathBuf>,
+) -> HashMap<PackageId, (Version, UnsafeChange)> {
+    metadata
+        .packages
+        .iter()
+        .filter_map(|package| {
+            let package_metrics =
+                geiger_context.package_id_to_metrics.get(&package.id)?;
+            let used = unsafe_stats(package_metrics, rs_files_used).used;
+            baseline
+                .version_change(&package.name, &package.version, &used)
+                .map(|change| (package.id.clone(), change))
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum BaselineError {
+    Io(io::Error, PathBuf),
+    Json(serde_json::Error, PathBuf),
+}
+
+impl Error for BaselineError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for BaselineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+
+    use cargo_geiger_serde::{
+        PackageInfo, QuickReportEntry, Source, UnsafeInfo,
+    };
+    use rstest::*;
+    use url::Url;
+
+    fn baseline_entry(
+        name: &str,
+        version: &str,
+        unsafe_exprs: u64,
+    ) -> ReportEntry {
+        ReportEntry {
+            package: PackageInfo::new(cargo_geiger_serde::PackageId {
+                name: name.to_string(),
+                version: Version::parse(version).unwrap(),
+                source: Source::Path(Url::parse("file:///a").unwrap()),
+            }),
+            active_features: vec![],
+            unsafety: UnsafeInfo {
+                used: CounterBlock {
+                    exprs: Count {
+                        safe: 0,
+                        unsafe_: unsafe_exprs,
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    #[rstest(
+        input_name,
+        input_version,
+        input_unsafe_exprs,
+        expected_version_change,
+        case("a", "1.1.0", 3, Some(("1.0.0", UnsafeChange::Increased))),
+        case("a", "1.1.0", 1, Some(("1.0.0", UnsafeChange::Decreased))),
+        case("a", "0.9.0", 2, Some(("1.0.0", UnsafeChange::Unchanged))),
+        case("a", "1.0.0", 3, None),
+        case("b", "0.2.0", 1, Some(("0.1.0", UnsafeChange::New))),
+        case("b", "0.2.0", 0, Some(("0.1.0", UnsafeChange::Unchanged))),
+        case("c", "1.0.0", 1, None)
+    )]
+    fn baseline_version_change_test(
+        input_name: &str,
+        input_version: &str,
+        input_unsafe_exprs: u64,
+        expected_version_change: Option<(&str, UnsafeChange)>,
+    ) {
+        let mut report = SafetyReport::default();
+        for entry in vec![
+            baseline_entry("a", "0.5.0", 7),
+            baseline_entry("a", "1.0.0", 2),
+            baseline_entry("b", "0.1.0", 0),
+        ] {
+            report.packages.insert(entry.package.id.clone(), entry);
+        }
+        let baseline = Baseline::from_report(&report);
+
+        let version_change = baseline.version_change(
+            input_name,
+            &Version::parse(input_version).unwrap(),
+            &CounterBlock {
+                exprs: Count {
+                    safe: 0,
+                    unsafe_: input_unsafe_exprs,
+                },
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            version_change,
+            expected_version_change.map(|(baseline_version, change)| (
+                Version::parse(baseline_version).unwrap(),
+                change
+            ))
+        );
+    }
+
+    #[rstest]
+    fn report_delta_test() {
+        let to_report = |entries: Vec<ReportEntry>| {
+            let mut report = SafetyReport::default();
+            for entry in entries {
+                report.packages.insert(entry.package.id.clone(), entry);
+            }
+            report
+        };
+        let baseline_report = to_report(vec![
+            baseline_entry("a", "1.0.0", 2),
+            baseline_entry("b", "1.0.0", 1),
+            baseline_entry("c", "0.1.0", 0),
+        ]);
+        let report = to_report(vec![
+            baseline_entry("a", "1.0.0", 5),
+            baseline_entry("b", "1.0.0", 1),
+            baseline_entry("c", "0.2.0", 0),
+        ]);
+
+        assert_eq!(
+            report_delta(&baseline_report, &report, false),
+            ReportDelta {
+                added: vec![baseline_entry("c", "0.2.0", 0).package.id],
+                removed: vec![baseline_entry("c", "0.1.0", 0).package.id],
+                changed: vec![PackageDelta {
+                    package: baseline_entry("a", "1.0.0", 0).package.id,
+                    categories: vec![CategoryDelta {
+                        category: String::from("exprs"),
+                        baseline: 2,
+                        current: 5,
+                    }],
+                }],
+                entries: vec![],
+            }
+        );
+    }
+
+    #[rstest]
+    fn report_delta_test_with_full_entries() {
+        let to_report = |entries: Vec<ReportEntry>| {
+            let mut report = SafetyReport::default();
+            for entry in entries {
+                report.packages.insert(entry.package.id.clone(), entry);
+            }
+            report
+        };
+        let baseline_report = to_report(vec![
+            baseline_entry("a", "1.0.0", 2),
+            baseline_entry("b", "1.0.0", 1),
+            baseline_entry("c", "0.1.0", 0),
+        ]);
+        let report = to_report(vec![
+            baseline_entry("a", "1.0.0", 5),
+            baseline_entry("b", "1.0.0", 1),
+            baseline_entry("c", "0.2.0", 0),
+        ]);
+
+        let delta = report_delta(&baseline_report, &report, true);
+
+        // The changed `a` and the added `c`, the unchanged `b` is left out.
+        assert_eq!(
+            delta.entries,
+            vec![
+                baseline_entry("a", "1.0.0", 5),
+                baseline_entry("c", "0.2.0", 0),
+            ]
+        );
+        assert_eq!(delta.changed.len(), 1);
+        assert_eq!(delta.added.len(), 1);
+    }
+
+    #[rstest(
+        input_name,
+        input_version,
+        input_forbids_unsafe,
+        expected_regression,
+        case("a", "1.1.0", false, Some("1.0.0")),
+        case("a", "1.1.0", true, None),
+        case("a", "0.5.0", false, None),
+        case("b", "0.2.0", false, None),
+        case("c", "1.0.0", false, None)
+    )]
+    fn forbid_baseline_regression_test(
+        input_name: &str,
+        input_version: &str,
+        input_forbids_unsafe: bool,
+        expected_regression: Option<&str>,
+    ) {
+        let mut report = QuickSafetyReport::default();
+        for (name, version, forbids_unsafe) in vec![
+            ("a", "0.5.0", false),
+            ("a", "1.0.0", true),
+            ("b", "0.1.0", false),
+        ] {
+            let entry = QuickReportEntry {
+                package: baseline_entry(name, version, 0).package,
+                forbids_unsafe,
+            };
+            report.packages.insert(entry.package.id.clone(), entry);
+        }
+        let forbid_baseline = ForbidBaseline::from_report(&report);
+
+        assert_eq!(
+            forbid_baseline.regression(
+                input_name,
+                &Version::parse(input_version).unwrap(),
+                input_forbids_unsafe,
+            ),
+            expected_regression.map(|version| Version::parse(version).unwrap())
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::mapping::ToCargoGeigerPackageId;
+
+use cargo::CliError;
+use cargo_geiger_serde::{FailGate, FailSummary, FailSummaryCrate};
+use cargo_metadata::{Metadata, PackageId};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Write the summary of the gate which made the run fail to the
+/// `--fail-summary-file`, when given one, and return the error of the gate.
+/// The summary is written whatever the output format, so that CI can tell
+/// which gate failed without parsing the report. A failure to write it is
+/// printed, the run exiting with the error of the gate still.
+pub fn with_fail_summary(
+    fail_summary_file: Option<&Path>,
+    gate: FailGate,
+    crates: Vec<FailSummaryCrate>,
+    error: CliError,
+) -> CliError {
+    let path = match fail_summary_file {
+        Some(path) => path,
+        None => return error,
+    };
+    let fail_summary = FailSummary {
+        gate,
+        message: error.error.as_ref().map(|e| e.to_string()),
+        crates,
+    };
+    if let Err(e) = write_fail_summary(path, &fail_summary) {
+        eprintln!(
+            "Failed to write the fail summary to file: {}: {}",
+            path.display(),
+            e
+        );
+    }
+    error
+}
+
+pub fn write_fail_summary(
+    path: &Path,
+    fail_summary: &FailSummary,
+) -> io::Result<()> {
+    let json = serde_json::to_string(fail_summary).unwrap();
+    fs::write(path, json)
+}
+
+/// The crates which made a gate fail, for the gates which don't count the
+/// unsafe usage of each crate.
+pub fn fail_summary_crates<'a>(
+    package_ids: impl IntoIterator<Item = &'a PackageId>,
+    metadata: &Metadata,
+) -> Vec<FailSummaryCrate> {
+    package_ids
+        .into_iter()
+        .filter_map(|package_id| {
+            package_id.to_cargo_geiger_package_id(metadata)
+        })
+        .map(|package| FailSummaryCrate {
+            package,
+            unsafe_exprs: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod fail_summary_tests {
+    use super::*;
+
+    use anyhow::anyhow;
+    use rstest::*;
+    use tempfile::tempdir;
+
+    #[rstest(
+        input_error,
+        expected_message,
+        case(
+            CliError::new(anyhow!("Found 3 warnings"), 1),
+            Some("Found 3 warnings")
+        ),
+        case(CliError::code(1), None)
+    )]
+    fn with_fail_summary_test(
+        input_error: CliError,
+        expected_message: Option<&str>,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("fail-summary.json");
+
+        let error = with_fail_summary(
+            Some(&path),
+            FailGate::Warnings,
+            vec![],
+            input_error,
+        );
+
+        assert_eq!(error.exit_code, 1);
+        let fail_summary = serde_json::from_str::<FailSummary>(
+            &fs::read_to_string(&path).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            fail_summary,
+            FailSummary {
+                gate: FailGate::Warnings,
+                message: expected_message.map(String::from),
+                crates: vec![],
+            }
+        );
+    }
+
+    #[rstest]
+    fn with_fail_summary_test_without_fail_summary_file() {
+        let error = with_fail_summary(
+            None,
+            FailGate::Warnings,
+            vec![],
+            CliError::code(2),
+        );
+
+        assert_eq!(error.exit_code, 2);
+    }
+}
@@ -0,0 +1,170 @@
+use cargo_metadata::semver::{self, Version};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The crates which `--forbid-only` counts as forbidding unsafe code even when
+/// their entry points don't declare `#![forbid(unsafe_code)]`, e.g. audited
+/// crates, so that CI only fails on the other ones.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ForbidWhitelist {
+    entries: Vec<WhitelistEntry>,
+}
+
+/// A crate of the whitelist, any of its versions when none is given.
+#[derive(Debug, Eq, PartialEq)]
+pub struct WhitelistEntry {
+    pub name: String,
+    pub version: Option<Version>,
+}
+
+impl ForbidWhitelist {
+    /// Load the whitelist from a file with a `name` or `name:version` entry
+    /// per line, e.g.
+    /// ```text
+    /// # Audited.
+    /// libc
+    /// memchr:2.7.1
+    /// ```
+    pub fn load(path: &Path) -> Result<ForbidWhitelist, ForbidWhitelistError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| ForbidWhitelistError::Io(e, path.to_path_buf()))?;
+        ForbidWhitelist::parse(&content)
+            .map_err(|e| ForbidWhitelistError::Version(e, path.to_path_buf()))
+    }
+
+    /// Everything after a `#` is a comment, and blank lines are skipped.
+    pub fn parse(content: &str) -> Result<ForbidWhitelist, semver::Error> {
+        let mut entries = Vec::new();
+        for line in content.lines() {
+            let entry = match line.split('#').next() {
+                Some(entry) if !entry.trim().is_empty() => entry.trim(),
+                _ => continue,
+            };
+            entries.push(match entry.split_once(':') {
+                Some((name, version)) => WhitelistEntry {
+                    name: name.trim().to_string(),
+                    version: Some(Version::parse(version.trim())?),
+                },
+                None => WhitelistEntry {
+                    name: entry.to_string(),
+                    version: None,
+                },
+            });
+        }
+        Ok(ForbidWhitelist { entries })
+    }
+
+    pub fn contains(&self, name: &str, version: &Version) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.matches(name, version))
+    }
+
+    /// The entries matching none of the given crates, which are the ones of
+    /// the dependency tree.
+    pub fn missing_entries<'a>(
+        &self,
+        crates: impl Iterator<Item = (&'a str, &'a Version)> + Clone,
+    ) -> Vec<&WhitelistEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                !crates
+                    .clone()
+                    .any(|(name, version)| entry.matches(name, version))
+            })
+            .collect()
+    }
+}
+
+impl WhitelistEntry {
+    fn matches(&self, name: &str, version: &Version) -> bool {
+        self.name == name
+            && self.version.as_ref().map_or(true, |v| v == version)
+    }
+}
+
+impl fmt::Display for WhitelistEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}:{}", self.name, version),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ForbidWhitelistError {
+    Io(io::Error, PathBuf),
+    Version(semver::Error, PathBuf),
+}
+
+impl Error for ForbidWhitelistError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for ForbidWhitelistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod forbid_whitelist_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest(
+        input_name,
+        input_version,
+        expected_contains,
+        case("a", "1.0.0", true),
+        case("a", "2.0.0", true),
+        case("b", "1.2.3", true),
+        case("b", "1.2.4", false),
+        case("c", "1.0.0", false),
+        case("d", "1.0.0", false)
+    )]
+    fn forbid_whitelist_contains_test(
+        input_name: &str,
+        input_version: &str,
+        expected_contains: bool,
+    ) {
+        let forbid_whitelist =
+            ForbidWhitelist::parse("# Audited\na\n\n  b:1.2.3  # pinned\n#c\n")
+                .unwrap();
+
+        assert_eq!(
+            forbid_whitelist
+                .contains(input_name, &Version::parse(input_version).unwrap()),
+            expected_contains
+        );
+    }
+
+    #[rstest]
+    fn forbid_whitelist_parse_test_with_invalid_version() {
+        assert!(ForbidWhitelist::parse("a:latest\n").is_err());
+    }
+
+    #[rstest]
+    fn forbid_whitelist_missing_entries_test() {
+        let forbid_whitelist =
+            ForbidWhitelist::parse("a\nb:1.2.3\nc\n").unwrap();
+        let version = Version::new(1, 0, 0);
+
+        assert_eq!(
+            forbid_whitelist
+                .missing_entries(
+                    vec![("a", &version), ("b", &version)].into_iter()
+                )
+                .iter()
+                .map(|entry| entry.to_string())
+                .collect::<Vec<String>>(),
+            vec!["b:1.2.3", "c"]
+        );
+    }
+}
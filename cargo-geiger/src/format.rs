@@ -1,7 +1,14 @@
+pub mod csv;
+pub mod deny_toml;
 pub mod emoji_symbols;
+pub mod path_base;
 pub mod pattern;
 pub mod print_config;
+pub mod prometheus;
+pub mod report_delta;
+pub mod report_fields;
 pub mod table;
+pub mod template;
 
 mod display;
 mod parse;
@@ -0,0 +1,168 @@
+//! Rendering of the unsafe usage of each crate as CSV, for
+//! `--output-format Csv`. The rows of several runs can be concatenated,
+//! without their header, and pivoted in a spreadsheet, by the `name`,
+//! `version` and `kind` of their crate.
+
+use cargo_geiger_serde::{CounterBlock, PackageId, SafetyReport};
+
+const HEADER: &str =
+    "name,version,kind,functions,exprs,item_impls,item_traits,methods,total";
+
+/// The `kind` of the rows of the crates used by the build scripts only, which
+/// `--separate-build-report` reports apart. A crate which is also a normal
+/// dependency gets a row of each kind.
+const BUILD_KIND: &str = "build";
+
+/// The `kind` of the rows of the other crates, including the crates without
+/// metrics, which are not reported apart.
+const NORMAL_KIND: &str = "normal";
+
+/// Render a header, then a row for each crate and kind, sorted, with its
+/// unsafe usage in the code used by the build. The crates without metrics get
+/// a row with empty cells, so that the rows of the runs line up by crate.
+pub fn render_csv(report: &SafetyReport) -> String {
+    let mut rows = report
+        .packages
+        .values()
+        .map(|entry| {
+            (&entry.package.id, NORMAL_KIND, Some(&entry.unsafety.used))
+        })
+        .chain(report.build_packages.values().map(|entry| {
+            (&entry.package.id, BUILD_KIND, Some(&entry.unsafety.used))
+        }))
+        .chain(
+            report
+                .packages_without_metrics
+                .iter()
+                .map(|package_id| (package_id, NORMAL_KIND, None)),
+        )
+        .collect::<Vec<(&PackageId, &str, Option<&CounterBlock>)>>();
+    rows.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    let mut lines = vec![String::from(HEADER)];
+    for (package_id, kind, used) in rows {
+        let mut cells = vec![
+            escape_field(&package_id.name),
+            escape_field(&package_id.version.to_string()),
+            String::from(kind),
+        ];
+        match used {
+            Some(used) => cells.extend(
+                [
+                    used.functions.unsafe_,
+                    used.exprs.unsafe_,
+                    used.item_impls.unsafe_,
+                    used.item_traits.unsafe_,
+                    used.methods.unsafe_,
+                    used.unsafe_count(),
+                ]
+                .iter()
+                .map(u64::to_string),
+            ),
+            None => cells.extend(vec![String::new(); 6]),
+        }
+        lines.push(cells.join(","));
+    }
+    lines.join("\n")
+}
+
+/// Fields with a comma, a double quote or a line break are quoted, with their
+/// double quotes doubled.
+fn escape_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\r', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod csv_tests {
+    use super::*;
+
+    use cargo_geiger_serde::{
+        Count, PackageInfo, ReportEntry, Source, UnsafeInfo,
+    };
+    use cargo_metadata::semver::Version;
+    use rstest::*;
+    use url::Url;
+
+    fn package_id(name: &str) -> PackageId {
+        PackageId {
+            name: name.to_string(),
+            version: Version::new(1, 0, 0),
+            source: Source::Path(Url::parse("file:///a").unwrap()),
+        }
+    }
+
+    fn report_entry(name: &str, functions: u64, exprs: u64) -> ReportEntry {
+        ReportEntry {
+            package: PackageInfo::new(package_id(name)),
+            active_features: vec![],
+            unsafety: UnsafeInfo {
+                used: CounterBlock {
+                    functions: Count {
+                        safe: 1,
+                        unsafe_: functions,
+                    },
+                    exprs: Count {
+                        safe: 1,
+                        unsafe_: exprs,
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    #[rstest]
+    fn render_csv_test() {
+        let mut report = SafetyReport::default();
+        for entry in [report_entry("c", 1, 2), report_entry("a", 0, 3)] {
+            report.packages.insert(entry.package.id.clone(), entry);
+        }
+        report.packages_without_metrics.insert(package_id("b"));
+
+        assert_eq!(
+            render_csv(&report),
+            "\
+name,version,kind,functions,exprs,item_impls,item_traits,methods,total
+a,1.0.0,normal,0,3,0,0,0,3
+b,1.0.0,normal,,,,,,
+c,1.0.0,normal,1,2,0,0,0,3"
+        );
+    }
+
+    #[rstest]
+    fn render_csv_test_with_build_packages() {
+        let mut report = SafetyReport::default();
+        let entry = report_entry("a", 0, 3);
+        report.packages.insert(entry.package.id.clone(), entry);
+        let build_entry = report_entry("a", 1, 1);
+        report
+            .build_packages
+            .insert(build_entry.package.id.clone(), build_entry);
+
+        // The crate used by the build scripts too gets a row of each kind.
+        assert_eq!(
+            render_csv(&report),
+            "\
+name,version,kind,functions,exprs,item_impls,item_traits,methods,total
+a,1.0.0,build,1,1,0,0,0,2
+a,1.0.0,normal,0,3,0,0,0,3"
+        );
+    }
+
+    #[rstest(
+        input_field,
+        expected_escaped_field,
+        case("plain", "plain"),
+        case("a,b", "\"a,b\""),
+        case("a\"b", "\"a\"\"b\""),
+        case("a\nb", "\"a\nb\"")
+    )]
+    fn escape_field_test(input_field: &str, expected_escaped_field: &str) {
+        assert_eq!(escape_field(input_field), expected_escaped_field);
+    }
+}
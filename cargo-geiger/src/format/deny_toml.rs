@@ -0,0 +1,138 @@
+//! Rendering of the crates using unsafe code as a TOML snippet in the style
+//! of the configuration of cargo-deny, for `--output-format DenyToml`. The
+//! snippet can be pasted as is in a `deny.toml`.
+
+use cargo_geiger_serde::{ReportEntry, SafetyReport};
+
+/// Render the `[bans]` section denying the crates, which are not ignored,
+/// with more than `deny_threshold` unsafe items used by the build.
+pub fn render_deny_toml(report: &SafetyReport, deny_threshold: u64) -> String {
+    let mut entries = report
+        .packages
+        .values()
+        .chain(report.build_packages.values())
+        .filter(|entry| !report.ignored_packages.contains(&entry.package.id))
+        .filter(|entry| entry.unsafety.used.unsafe_count() > deny_threshold)
+        .collect::<Vec<&ReportEntry>>();
+    entries.sort_by(|a, b| a.package.id.cmp(&b.package.id));
+
+    let mut lines = vec![
+        format!(
+            "# The crates with more than {} unsafe items used by the build, found by cargo-geiger.",
+            deny_threshold
+        ),
+        String::from("[bans]"),
+    ];
+    if entries.is_empty() {
+        lines.push(String::from("deny = []"));
+        return lines.join("\n");
+    }
+    lines.push(String::from("deny = ["));
+    for entry in entries {
+        let unsafe_count = entry.unsafety.used.unsafe_count();
+        lines.push(format!(
+            "    {{ crate = {}, reason = {} }},",
+            toml_string(&format!(
+                "{}@{}",
+                entry.package.id.name, entry.package.id.version
+            )),
+            toml_string(&format!(
+                "{} unsafe {} used by the build",
+                unsafe_count,
+                if unsafe_count == 1 { "item" } else { "items" }
+            ))
+        ));
+    }
+    lines.push(String::from("]"));
+    lines.join("\n")
+}
+
+/// A TOML basic string, quoted and escaped.
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+#[cfg(test)]
+mod deny_toml_tests {
+    use super::*;
+
+    use cargo_geiger_serde::{
+        Count, CounterBlock, PackageId, PackageInfo, Source, UnsafeInfo,
+    };
+    use rstest::*;
+    use semver::Version;
+    use url::Url;
+
+    fn report_entry(name: &str, used: u64) -> ReportEntry {
+        ReportEntry {
+            package: PackageInfo::new(PackageId {
+                name: name.to_string(),
+                version: Version::new(1, 0, 0),
+                source: Source::Path(Url::parse("file:///a").unwrap()),
+            }),
+            active_features: vec![],
+            unsafety: UnsafeInfo {
+                used: CounterBlock {
+                    exprs: Count {
+                        safe: 1,
+                        unsafe_: used,
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    fn report() -> SafetyReport {
+        let mut report = SafetyReport::default();
+        for entry in [
+            report_entry("b", 1),
+            report_entry("a", 3),
+            report_entry("c", 4),
+            report_entry("ignored", 5),
+        ] {
+            if entry.package.id.name == "ignored" {
+                report.ignored_packages.insert(entry.package.id.clone());
+            }
+            report.packages.insert(entry.package.id.clone(), entry);
+        }
+        report
+    }
+
+    #[rstest]
+    fn render_deny_toml_test() {
+        assert_eq!(
+            render_deny_toml(&report(), 0),
+            "# The crates with more than 0 unsafe items used by the build, found by cargo-geiger.\n\
+             [bans]\n\
+             deny = [\n\
+             \x20   { crate = \"a@1.0.0\", reason = \"3 unsafe items used by the build\" },\n\
+             \x20   { crate = \"b@1.0.0\", reason = \"1 unsafe item used by the build\" },\n\
+             \x20   { crate = \"c@1.0.0\", reason = \"4 unsafe items used by the build\" },\n\
+             ]"
+        );
+    }
+
+    #[rstest(
+        input_deny_threshold,
+        expected_crates,
+        case(3, vec!["c@1.0.0"]),
+        case(4, vec![])
+    )]
+    fn render_deny_toml_test_threshold(
+        input_deny_threshold: u64,
+        expected_crates: Vec<&str>,
+    ) {
+        let deny_toml = render_deny_toml(&report(), input_deny_threshold);
+
+        let value = deny_toml.parse::<toml::Table>().unwrap();
+        let crates = value["bans"]["deny"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["crate"].as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(crates, expected_crates);
+    }
+}
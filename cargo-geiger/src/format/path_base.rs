@@ -0,0 +1,115 @@
+use crate::args::PathBase;
+use crate::readme::path_relative_to;
+
+use cargo_metadata::Metadata;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The directories the paths of the files of the Json report can be made
+/// relative to with `--path-base`. The scanned paths are canonical, so are
+/// these directories.
+pub struct PathBases {
+    workspace_root: PathBuf,
+    cwd: PathBuf,
+    crate_roots: Vec<PathBuf>,
+}
+
+impl PathBases {
+    pub fn new(metadata: &Metadata, cwd: &Path) -> PathBases {
+        PathBases {
+            workspace_root: canonical(metadata.workspace_root.as_std_path()),
+            cwd: canonical(cwd),
+            crate_roots: metadata
+                .packages
+                .iter()
+                .filter_map(|package| package.manifest_path.parent())
+                .map(|crate_root| canonical(crate_root.as_std_path()))
+                .collect(),
+        }
+    }
+
+    /// Rewrite absolute paths relative to the given base.
+    pub fn relative_paths(
+        &self,
+        path_base: PathBase,
+        paths: &HashSet<PathBuf>,
+    ) -> HashSet<PathBuf> {
+        paths
+            .iter()
+            .map(|path| self.relative_path(path_base, path))
+            .collect()
+    }
+
+    /// With `PathBase::Crate`, the crate of a file is the one with the
+    /// deepest root containing it, so the files of a workspace member are
+    /// not attributed to the root package of the workspace. A path outside of
+    /// every crate is kept as is.
+    pub fn relative_path(&self, path_base: PathBase, path: &Path) -> PathBuf {
+        let base = match path_base {
+            PathBase::Workspace => Some(&self.workspace_root),
+            PathBase::Cwd => Some(&self.cwd),
+            PathBase::Crate => self
+                .crate_roots
+                .iter()
+                .filter(|crate_root| path.starts_with(crate_root))
+                .max_by_key(|crate_root| crate_root.components().count()),
+        };
+        match base {
+            Some(base) if path.is_absolute() => path_relative_to(base, path),
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod path_base_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest(
+        input_path_base,
+        input_path,
+        expected_path,
+        case(
+            PathBase::Workspace,
+            "/ws/member/src/lib.rs",
+            "member/src/lib.rs"
+        ),
+        case(
+            PathBase::Workspace,
+            "/deps/dep/src/lib.rs",
+            "../deps/dep/src/lib.rs"
+        ),
+        case(PathBase::Crate, "/ws/member/src/lib.rs", "src/lib.rs"),
+        case(PathBase::Crate, "/ws/src/main.rs", "src/main.rs"),
+        case(PathBase::Crate, "/deps/dep/src/lib.rs", "src/lib.rs"),
+        case(PathBase::Crate, "/other/src/lib.rs", "/other/src/lib.rs"),
+        case(PathBase::Cwd, "/ws/member/src/lib.rs", "src/lib.rs"),
+        case(PathBase::Cwd, "/ws/src/main.rs", "../src/main.rs")
+    )]
+    fn relative_path_test(
+        input_path_base: PathBase,
+        input_path: &str,
+        expected_path: &str,
+    ) {
+        let path_bases = PathBases {
+            workspace_root: PathBuf::from("/ws"),
+            cwd: PathBuf::from("/ws/member"),
+            crate_roots: vec![
+                PathBuf::from("/ws"),
+                PathBuf::from("/ws/member"),
+                PathBuf::from("/deps/dep"),
+            ],
+        };
+
+        assert_eq!(
+            path_bases.relative_path(input_path_base, Path::new(input_path)),
+            PathBuf::from(expected_path)
+        );
+    }
+}
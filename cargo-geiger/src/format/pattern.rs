@@ -7,6 +7,14 @@ use super::display::Display;
 use cargo_metadata::PackageId;
 use std::error::Error;
 
+/// Every placeholder understood by `--format`, paired with a one-line
+/// description used by `--format-help`.
+pub const PLACEHOLDERS: [(&str, &str); 3] = [
+    ("p", "Package name and version, e.g. `cargo-geiger 0.11.7`"),
+    ("l", "Package license, as declared in its Cargo.toml"),
+    ("r", "Package repository URL, as declared in its Cargo.toml"),
+];
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct Pattern {
     pub chunks: Vec<Chunk>,
@@ -84,4 +92,15 @@ mod pattern_tests {
         assert!(pattern_result.is_ok());
         assert_eq!(pattern_result.unwrap(), expected_pattern);
     }
+
+    #[rstest]
+    fn pattern_try_build_accepts_all_placeholders_test() {
+        for (placeholder, _) in PLACEHOLDERS.iter() {
+            let pattern_result =
+                Pattern::try_build(&format!("{{{}}}", placeholder));
+            assert!(pattern_result.is_ok());
+            let pattern = pattern_result.unwrap();
+            assert!(!matches!(pattern.chunks.as_slice(), [Chunk::Raw(_)]));
+        }
+    }
 }
@@ -4,9 +4,28 @@ use crate::format::{CrateDetectionStatus, FormatError};
 
 use cargo::util::errors::CliError;
 use colored::{ColoredString, Colorize};
-use geiger::IncludeTests;
+use geiger::{IncludeTests, MEMORY_LIFECYCLE_FUNCTIONS, STD_UNSAFE_MACROS};
 use petgraph::{Direction, EdgeDirection};
-use strum_macros::EnumString;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
+use strum_macros::{EnumIter, IntoStaticStr};
+
+/// Regular expressions matching the paths of generated files, relative to the
+/// package root, excluded by `--exclude-generated` unless `--generated-pattern`
+/// is given.
+pub const DEFAULT_GENERATED_FILE_PATTERNS: &[&str] = &[
+    r"(^|/)target/",
+    r"(^|/)build/[^/]+/out/",
+    r"\.generated\.rs$",
+];
+
+/// The size in bytes above which a file is not scanned, unless another limit
+/// is given to `--max-file-size`. The whole syntax tree of a file is built to
+/// scan it, which takes many times the size of the file in memory.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Prefix {
@@ -15,11 +34,16 @@ pub enum Prefix {
     None,
 }
 
-#[derive(Clone, Copy, Debug, EnumString, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, EnumIter, Eq, IntoStaticStr, PartialEq)]
 pub enum OutputFormat {
     Ascii,
     Json,
     GitHubMarkdown,
+    Prometheus,
+    DenyToml,
+    GitHubActions,
+    Csv,
+    Tree,
     Ratio,
     Utf8,
 }
@@ -30,21 +54,89 @@ impl Default for OutputFormat {
     }
 }
 
+/// The name of an output format, ignoring the case, e.g. `json` or `Json`.
+impl FromStr for OutputFormat {
+    type Err = UnknownOutputFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::iter()
+            .find(|output_format| {
+                <&str>::from(*output_format).eq_ignore_ascii_case(s)
+            })
+            .ok_or_else(|| UnknownOutputFormatError(s.to_string()))
+    }
+}
+
+/// An output format which doesn't exist, displayed with the valid ones, as
+/// it is shown to the user by the argument parsing.
+#[derive(Debug, Eq, PartialEq)]
+pub struct UnknownOutputFormatError(pub String);
+
+impl Error for UnknownOutputFormatError {}
+
+impl fmt::Display for UnknownOutputFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown output format `{}`, expected one of: {}",
+            self.0,
+            OutputFormat::iter()
+                .map(<&str>::from)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct PrintConfig {
     /// Don't truncate dependencies that have already been displayed.
     pub all: bool,
 
     pub allow_partial_results: bool,
+
+    /// The time after which no further package is scanned, for
+    /// `--deadline`.
+    pub deadline: Option<Instant>,
+
     pub direction: EdgeDirection,
 
     // Is anyone using this? This is a carry-over from cargo-tree.
     // TODO: Open a github issue to discuss deprecation.
     pub format: Pattern,
 
+    /// Regular expressions matching the paths, relative to the package root,
+    /// of the files which are not scanned, for `--exclude-generated`.
+    pub generated_file_patterns: Vec<String>,
+
     pub include_tests: IncludeTests,
+
+    /// The deepest level of the dependencies displayed below the root
+    /// package, for `--depth`, without a limit if `None`.
+    pub max_depth: Option<usize>,
+
+    /// The size in bytes above which a file is not scanned, for
+    /// `--max-file-size`, without a limit if `None`.
+    pub max_file_size: Option<u64>,
+
+    /// Functions whose calls in unsafe code are counted as memory lifecycle
+    /// unsafe usage, for `--memory-lifecycle-unsafe`.
+    pub memory_lifecycle_functions: Vec<String>,
+
+    /// Don't reuse nor store the metrics of the files in the on-disk cache
+    /// of the target directory, for `--no-cache`.
+    pub no_cache: bool,
+
     pub prefix: Prefix,
     pub output_format: OutputFormat,
+
+    /// The longest time spent scanning a single package, for
+    /// `--scan-timeout-per-crate`.
+    pub scan_timeout_per_crate: Option<Duration>,
+
+    /// Macros whose invocations are counted as unsafe usage, for
+    /// `--count-std-macros`.
+    pub unsafe_macros: Vec<String>,
 }
 
 impl PrintConfig {
@@ -67,11 +159,46 @@ impl PrintConfig {
             )
         })?;
 
-        let include_tests = match args.include_tests {
-            true => IncludeTests::Yes,
-            false => IncludeTests::No,
+        let generated_file_patterns = match (
+            args.exclude_generated,
+            args.generated_patterns.is_empty(),
+        ) {
+            (false, _) => vec![],
+            (true, true) => DEFAULT_GENERATED_FILE_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            (true, false) => args.generated_patterns.clone(),
+        };
+
+        let include_tests =
+            match (args.count_cfg_test_separately, args.include_tests) {
+                (true, _) => IncludeTests::Separately,
+                (false, true) => IncludeTests::Yes,
+                (false, false) => IncludeTests::No,
+            };
+
+        let unsafe_macros = match args.count_std_macros {
+            true => STD_UNSAFE_MACROS
+                .iter()
+                .map(|name| name.to_string())
+                .chain(args.std_macros.iter().cloned())
+                .collect(),
+            false => vec![],
+        };
+
+        let max_file_size = match args.max_file_size {
+            Some(0) => None,
+            Some(max_file_size) => Some(max_file_size),
+            None => Some(DEFAULT_MAX_FILE_SIZE),
         };
 
+        let memory_lifecycle_functions = MEMORY_LIFECYCLE_FUNCTIONS
+            .iter()
+            .map(|name| name.to_string())
+            .chain(args.memory_lifecycle_functions.iter().cloned())
+            .collect();
+
         let prefix = match (args.prefix_depth, args.no_indent) {
             (true, _) => Prefix::Depth,
             (false, true) => Prefix::None,
@@ -81,11 +208,19 @@ impl PrintConfig {
         Ok(PrintConfig {
             all: args.all,
             allow_partial_results,
+            deadline: args.deadline,
             direction,
             format,
+            generated_file_patterns,
             include_tests,
+            max_depth: args.depth,
+            max_file_size,
+            memory_lifecycle_functions,
+            no_cache: args.no_cache,
             output_format: args.output_format,
             prefix,
+            scan_timeout_per_crate: args.scan_timeout_per_crate,
+            unsafe_macros,
         })
     }
 }
@@ -95,11 +230,19 @@ impl Default for PrintConfig {
         PrintConfig {
             all: false,
             allow_partial_results: false,
+            deadline: None,
             direction: Direction::Outgoing,
             format: Pattern::try_build("p").unwrap(),
+            generated_file_patterns: vec![],
             include_tests: IncludeTests::Yes,
+            max_depth: None,
+            max_file_size: None,
+            memory_lifecycle_functions: vec![],
+            no_cache: false,
             prefix: Prefix::Depth,
             output_format: Default::default(),
+            scan_timeout_per_crate: None,
+            unsafe_macros: vec![],
         }
     }
 }
@@ -127,7 +270,6 @@ mod print_config_tests {
     use crate::format::Chunk;
 
     use rstest::*;
-    use std::str::FromStr;
 
     #[rstest(
         input_invert_bool,
@@ -191,15 +333,20 @@ mod print_config_tests {
 
     #[rstest(
         input_include_tests_bool,
+        input_count_cfg_test_separately_bool,
         expected_include_tests,
-        case(true, IncludeTests::Yes),
-        case(false, IncludeTests::No)
+        case(true, false, IncludeTests::Yes),
+        case(false, false, IncludeTests::No),
+        case(true, true, IncludeTests::Separately),
+        case(false, true, IncludeTests::Separately)
     )]
     fn print_config_new_test_include_tests(
         input_include_tests_bool: bool,
+        input_count_cfg_test_separately_bool: bool,
         expected_include_tests: IncludeTests,
     ) {
         let args = Args {
+            count_cfg_test_separately: input_count_cfg_test_separately_bool,
             include_tests: input_include_tests_bool,
             ..Default::default()
         };
@@ -213,6 +360,31 @@ mod print_config_tests {
         );
     }
 
+    #[rstest(
+        input_max_file_size,
+        expected_max_file_size,
+        case(None, Some(DEFAULT_MAX_FILE_SIZE)),
+        case(Some(1024), Some(1024)),
+        case(Some(0), None)
+    )]
+    fn print_config_new_test_max_file_size(
+        input_max_file_size: Option<u64>,
+        expected_max_file_size: Option<u64>,
+    ) {
+        let args = Args {
+            max_file_size: input_max_file_size,
+            ..Default::default()
+        };
+
+        let print_config_result = PrintConfig::new(&args);
+
+        assert!(print_config_result.is_ok());
+        assert_eq!(
+            print_config_result.unwrap().max_file_size,
+            expected_max_file_size
+        );
+    }
+
     #[rstest(
         input_prefix_depth_bool,
         input_no_indent_bool,
@@ -245,17 +417,40 @@ mod print_config_tests {
         case("Ascii", Ok(OutputFormat::Ascii)),
         case("Json", Ok(OutputFormat::Json)),
         case("GitHubMarkdown", Ok(OutputFormat::GitHubMarkdown)),
+        case("Prometheus", Ok(OutputFormat::Prometheus)),
         case("Utf8", Ok(OutputFormat::Utf8)),
-        case("unknown_variant", Err(strum::ParseError::VariantNotFound))
+        case("json", Ok(OutputFormat::Json)),
+        case("githubmarkdown", Ok(OutputFormat::GitHubMarkdown)),
+        case("RATIO", Ok(OutputFormat::Ratio)),
+        case("denytoml", Ok(OutputFormat::DenyToml)),
+        case("githubactions", Ok(OutputFormat::GitHubActions)),
+        case("csv", Ok(OutputFormat::Csv)),
+        case("tree", Ok(OutputFormat::Tree)),
+        case("uTf8", Ok(OutputFormat::Utf8)),
+        case(
+            "unknown_variant",
+            Err(UnknownOutputFormatError(String::from("unknown_variant")))
+        )
     )]
     fn output_format_from_str_test(
         input_raw_str: &str,
-        expected_output_format_result: Result<OutputFormat, strum::ParseError>,
+        expected_output_format_result: Result<
+            OutputFormat,
+            UnknownOutputFormatError,
+        >,
     ) {
         let output_format = OutputFormat::from_str(input_raw_str);
         assert_eq!(output_format, expected_output_format_result);
     }
 
+    #[rstest]
+    fn unknown_output_format_error_display_test() {
+        assert_eq!(
+            UnknownOutputFormatError(String::from("yaml")).to_string(),
+            "unknown output format `yaml`, expected one of: Ascii, Json, GitHubMarkdown, Prometheus, DenyToml, GitHubActions, Csv, Tree, Ratio, Utf8"
+        );
+    }
+
     #[rstest(
         input_crate_detection_status,
         input_output_format,
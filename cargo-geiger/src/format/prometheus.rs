@@ -0,0 +1,204 @@
+//! Rendering of the report in the Prometheus text exposition format, for
+//! `--output-format Prometheus`. The output can be pushed as is to a
+//! pushgateway.
+
+use cargo_geiger_serde::{ReportEntry, SafetyReport};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Render the report, with a comment giving the time of the scan.
+pub fn render_prometheus(report: &SafetyReport) -> String {
+    let timestamp_millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    render(report, timestamp_millis)
+}
+
+fn render(report: &SafetyReport, timestamp_millis: u128) -> String {
+    let mut entries = report
+        .packages
+        .values()
+        .chain(report.build_packages.values())
+        .collect::<Vec<&ReportEntry>>();
+    entries.sort_by(|a, b| a.package.id.cmp(&b.package.id));
+
+    let mut lines = vec![format!(
+        "# Scraped by cargo-geiger at {} (milliseconds since the Unix epoch)",
+        timestamp_millis
+    )];
+    push_package_metric(
+        &mut lines,
+        "cargo_geiger_unsafe_total",
+        "Unsafe items of the crate used by the build.",
+        &entries,
+        |entry| entry.unsafety.used.unsafe_count(),
+    );
+    push_package_metric(
+        &mut lines,
+        "cargo_geiger_unsafe_unused_total",
+        "Unsafe items of the crate not used by the build.",
+        &entries,
+        |entry| entry.unsafety.unused.unsafe_count(),
+    );
+
+    let counted_entries = entries
+        .iter()
+        .filter(|entry| !report.ignored_packages.contains(&entry.package.id))
+        .collect::<Vec<_>>();
+    push_metric(
+        &mut lines,
+        "cargo_geiger_unsafe_sum",
+        "Unsafe items used by the build, summed over the crates which are \
+         not ignored.",
+        counted_entries
+            .iter()
+            .map(|entry| entry.unsafety.used.unsafe_count())
+            .sum(),
+    );
+    push_metric(
+        &mut lines,
+        "cargo_geiger_unsafe_unused_sum",
+        "Unsafe items not used by the build, summed over the crates which are \
+         not ignored.",
+        counted_entries
+            .iter()
+            .map(|entry| entry.unsafety.unused.unsafe_count())
+            .sum(),
+    );
+    push_metric(
+        &mut lines,
+        "cargo_geiger_unsafe_crates",
+        "Crates which are not ignored using unsafe in code used by the build.",
+        counted_entries
+            .iter()
+            .filter(|entry| entry.unsafety.used.has_unsafe())
+            .count() as u64,
+    );
+    lines.join("\n")
+}
+
+fn push_package_metric<F>(
+    lines: &mut Vec<String>,
+    name: &str,
+    help: &str,
+    entries: &[&ReportEntry],
+    value: F,
+) where
+    F: Fn(&ReportEntry) -> u64,
+{
+    push_metric_header(lines, name, help);
+    for entry in entries {
+        lines.push(format!(
+            "{}{{crate=\"{}\",version=\"{}\"}} {}",
+            name,
+            escape_label_value(&entry.package.id.name),
+            escape_label_value(&entry.package.id.version.to_string()),
+            value(entry)
+        ));
+    }
+}
+
+fn push_metric(lines: &mut Vec<String>, name: &str, help: &str, value: u64) {
+    push_metric_header(lines, name, help);
+    lines.push(format!("{} {}", name, value));
+}
+
+fn push_metric_header(lines: &mut Vec<String>, name: &str, help: &str) {
+    lines.push(format!("# HELP {} {}", name, help));
+    lines.push(format!("# TYPE {} gauge", name));
+}
+
+/// Label values escape backslashes, double quotes and line feeds.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod prometheus_tests {
+    use super::*;
+
+    use cargo_geiger_serde::{
+        Count, CounterBlock, PackageId, PackageInfo, Source, UnsafeInfo,
+    };
+    use cargo_metadata::semver::Version;
+    use rstest::*;
+    use url::Url;
+
+    fn report_entry(name: &str, used: u64, unused: u64) -> ReportEntry {
+        let counter_block = |unsafe_exprs| CounterBlock {
+            exprs: Count {
+                safe: 1,
+                unsafe_: unsafe_exprs,
+            },
+            ..Default::default()
+        };
+        ReportEntry {
+            package: PackageInfo::new(PackageId {
+                name: name.to_string(),
+                version: Version::new(1, 0, 0),
+                source: Source::Path(Url::parse("file:///a").unwrap()),
+            }),
+            active_features: vec![],
+            unsafety: UnsafeInfo {
+                used: counter_block(used),
+                unused: counter_block(unused),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[rstest]
+    fn render_test() {
+        let mut report = SafetyReport::default();
+        for entry in [
+            report_entry("b", 2, 1),
+            report_entry("a", 3, 0),
+            report_entry("c", 4, 0),
+        ] {
+            report.packages.insert(entry.package.id.clone(), entry);
+        }
+        let ignored_package_id = report_entry("c", 4, 0).package.id;
+        report.ignored_packages.insert(ignored_package_id);
+
+        assert_eq!(
+            render(&report, 1_700_000_000_000),
+            "\
+# Scraped by cargo-geiger at 1700000000000 (milliseconds since the Unix epoch)
+# HELP cargo_geiger_unsafe_total Unsafe items of the crate used by the build.
+# TYPE cargo_geiger_unsafe_total gauge
+cargo_geiger_unsafe_total{crate=\"a\",version=\"1.0.0\"} 3
+cargo_geiger_unsafe_total{crate=\"b\",version=\"1.0.0\"} 2
+cargo_geiger_unsafe_total{crate=\"c\",version=\"1.0.0\"} 4
+# HELP cargo_geiger_unsafe_unused_total Unsafe items of the crate not used by the build.
+# TYPE cargo_geiger_unsafe_unused_total gauge
+cargo_geiger_unsafe_unused_total{crate=\"a\",version=\"1.0.0\"} 0
+cargo_geiger_unsafe_unused_total{crate=\"b\",version=\"1.0.0\"} 1
+cargo_geiger_unsafe_unused_total{crate=\"c\",version=\"1.0.0\"} 0
+# HELP cargo_geiger_unsafe_sum Unsafe items used by the build, summed over the crates which are not ignored.
+# TYPE cargo_geiger_unsafe_sum gauge
+cargo_geiger_unsafe_sum 5
+# HELP cargo_geiger_unsafe_unused_sum Unsafe items not used by the build, summed over the crates which are not ignored.
+# TYPE cargo_geiger_unsafe_unused_sum gauge
+cargo_geiger_unsafe_unused_sum 1
+# HELP cargo_geiger_unsafe_crates Crates which are not ignored using unsafe in code used by the build.
+# TYPE cargo_geiger_unsafe_crates gauge
+cargo_geiger_unsafe_crates 2"
+        );
+    }
+
+    #[rstest(
+        input_value,
+        expected_escaped_value,
+        case("plain", "plain"),
+        case("a\"b", "a\\\"b"),
+        case("a\\b\nc", "a\\\\b\\nc")
+    )]
+    fn escape_label_value_test(
+        input_value: &str,
+        expected_escaped_value: &str,
+    ) {
+        assert_eq!(escape_label_value(input_value), expected_escaped_value);
+    }
+}
@@ -0,0 +1,332 @@
+//! Rendering of the differences between the baseline report given to `--diff`
+//! and the report of the scan, in the format given to `--diff-format`.
+
+use crate::args::DiffFormat;
+
+use cargo_geiger_serde::{
+    Count, CounterBlock, PackageId, ReportDelta, ReportEntry,
+};
+
+/// Render the report delta as a table, a unified diff or a Json object, along
+/// with the entries of the added and changed crates for `--diff-full`.
+pub fn render_report_delta(
+    diff_format: DiffFormat,
+    report_delta: &ReportDelta,
+) -> String {
+    match diff_format {
+        DiffFormat::Table => render_table(report_delta),
+        DiffFormat::Unified => render_unified(report_delta),
+        DiffFormat::Json => serde_json::to_string(report_delta).unwrap(),
+    }
+}
+
+fn render_table(report_delta: &ReportDelta) -> String {
+    let mut lines = Vec::new();
+    for (title, package_ids) in &[
+        ("Added crates:", &report_delta.added),
+        ("Removed crates:", &report_delta.removed),
+    ] {
+        if !package_ids.is_empty() {
+            lines.push(title.to_string());
+            lines.extend(
+                package_ids.iter().map(|package_id| {
+                    format!("    {}", crate_label(package_id))
+                }),
+            );
+            lines.push(String::new());
+        }
+    }
+
+    if !report_delta.changed.is_empty() {
+        let crate_width = report_delta
+            .changed
+            .iter()
+            .map(|package_delta| crate_label(&package_delta.package).len())
+            .chain(std::iter::once("Crate".len()))
+            .max()
+            .unwrap_or_default();
+        lines.push(String::from("Changed crates:"));
+        lines.push(format!(
+            "    {: <crate_width$}  {: <12} {: <9} {: <8} Delta",
+            "Crate",
+            "Category",
+            "Baseline",
+            "Current",
+            crate_width = crate_width
+        ));
+        for package_delta in &report_delta.changed {
+            for category_delta in &package_delta.categories {
+                lines.push(format!(
+                    "    {: <crate_width$}  {: <12} {: <9} {: <8} {:+}",
+                    crate_label(&package_delta.package),
+                    category_delta.category,
+                    category_delta.baseline,
+                    category_delta.current,
+                    category_delta.current as i64
+                        - category_delta.baseline as i64,
+                    crate_width = crate_width
+                ));
+            }
+        }
+        lines.push(String::new());
+    }
+
+    if !report_delta.entries.is_empty() {
+        lines.push(String::from("Current entries:"));
+        for entry in &report_delta.entries {
+            lines.push(format!("    {}", crate_label(&entry.package.id)));
+            lines.extend(
+                entry_lines(entry)
+                    .into_iter()
+                    .map(|line| format!("        {}", line)),
+            );
+        }
+        lines.push(String::new());
+    }
+
+    if lines.is_empty() {
+        lines.push(String::from(
+            "No change in the unsafe usage since the baseline report.",
+        ));
+    }
+    lines.join("\n")
+}
+
+fn render_unified(report_delta: &ReportDelta) -> String {
+    let mut lines =
+        vec![String::from("--- baseline"), String::from("+++ current")];
+    lines.extend(
+        report_delta
+            .removed
+            .iter()
+            .map(|package_id| format!("-{}", crate_label(package_id))),
+    );
+    lines.extend(
+        report_delta
+            .added
+            .iter()
+            .map(|package_id| format!("+{}", crate_label(package_id))),
+    );
+    for package_delta in &report_delta.changed {
+        lines.push(format!("@@ {} @@", crate_label(&package_delta.package)));
+        for category_delta in &package_delta.categories {
+            lines.push(format!(
+                "-{}: {}",
+                category_delta.category, category_delta.baseline
+            ));
+            lines.push(format!(
+                "+{}: {}",
+                category_delta.category, category_delta.current
+            ));
+        }
+    }
+    // The entries are context lines, they are the same on both sides.
+    for entry in &report_delta.entries {
+        lines.push(format!(
+            "@@ {} (current) @@",
+            crate_label(&entry.package.id)
+        ));
+        lines.extend(
+            entry_lines(entry)
+                .into_iter()
+                .map(|line| format!(" {}", line)),
+        );
+    }
+    lines.join("\n")
+}
+
+/// The features, forbids unsafe flag and unsafe usage of an entry, the unsafe
+/// usage as the number of unsafe items over the number of items.
+fn entry_lines(entry: &ReportEntry) -> Vec<String> {
+    let counts = |counter_block: &CounterBlock| {
+        let count = |name: &str, count: &Count| {
+            format!("{} {}/{}", name, count.unsafe_, count.safe + count.unsafe_)
+        };
+        vec![
+            count("functions", &counter_block.functions),
+            count("exprs", &counter_block.exprs),
+            count("impls", &counter_block.item_impls),
+            count("traits", &counter_block.item_traits),
+            count("methods", &counter_block.methods),
+        ]
+        .join(", ")
+    };
+    vec![
+        format!(
+            "features: {}",
+            if entry.active_features.is_empty() {
+                String::from("none")
+            } else {
+                entry.active_features.join(", ")
+            }
+        ),
+        format!("forbids unsafe: {}", entry.unsafety.forbids_unsafe),
+        format!("used: {}", counts(&entry.unsafety.used)),
+        format!("unused: {}", counts(&entry.unsafety.unused)),
+    ]
+}
+
+fn crate_label(package_id: &PackageId) -> String {
+    format!("{} {}", package_id.name, package_id.version)
+}
+
+#[cfg(test)]
+mod report_delta_tests {
+    use super::*;
+
+    use cargo_geiger_serde::{
+        CategoryDelta, PackageDelta, PackageInfo, Source, UnsafeInfo,
+    };
+    use rstest::*;
+    use semver::Version;
+    use url::Url;
+
+    fn package_id(name: &str, version: &str) -> PackageId {
+        PackageId {
+            name: name.to_string(),
+            version: Version::parse(version).unwrap(),
+            source: Source::Path(Url::parse("file:///a").unwrap()),
+        }
+    }
+
+    /// The delta between a baseline with `a 1.0.0` and `old 0.1.0`, and a
+    /// scan with `a 1.0.0`, whose unsafe usage changed, and `new 0.2.0`.
+    fn report_delta() -> ReportDelta {
+        ReportDelta {
+            added: vec![package_id("new", "0.2.0")],
+            removed: vec![package_id("old", "0.1.0")],
+            changed: vec![PackageDelta {
+                package: package_id("a", "1.0.0"),
+                categories: vec![
+                    CategoryDelta {
+                        category: String::from("functions"),
+                        baseline: 2,
+                        current: 1,
+                    },
+                    CategoryDelta {
+                        category: String::from("exprs"),
+                        baseline: 3,
+                        current: 7,
+                    },
+                ],
+            }],
+            entries: vec![],
+        }
+    }
+
+    /// The delta with the entry of `new 0.2.0`, for `--diff-full`.
+    fn report_delta_with_entries() -> ReportDelta {
+        ReportDelta {
+            added: vec![package_id("new", "0.2.0")],
+            entries: vec![ReportEntry {
+                package: PackageInfo::new(package_id("new", "0.2.0")),
+                active_features: vec![String::from("std")],
+                unsafety: UnsafeInfo {
+                    used: CounterBlock {
+                        exprs: Count {
+                            safe: 4,
+                            unsafe_: 3,
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    fn render_report_delta_test_table() {
+        assert_eq!(
+            render_report_delta(DiffFormat::Table, &report_delta()),
+            "Added crates:\n\
+             \x20   new 0.2.0\n\
+             \n\
+             Removed crates:\n\
+             \x20   old 0.1.0\n\
+             \n\
+             Changed crates:\n\
+             \x20   Crate    Category     Baseline  Current  Delta\n\
+             \x20   a 1.0.0  functions    2         1        -1\n\
+             \x20   a 1.0.0  exprs        3         7        +4\n"
+        );
+    }
+
+    #[rstest]
+    fn render_report_delta_test_table_without_change() {
+        assert_eq!(
+            render_report_delta(DiffFormat::Table, &ReportDelta::default()),
+            "No change in the unsafe usage since the baseline report."
+        );
+    }
+
+    #[rstest]
+    fn render_report_delta_test_table_with_entries() {
+        assert_eq!(
+            render_report_delta(DiffFormat::Table, &report_delta_with_entries()),
+            "Added crates:\n\
+             \x20   new 0.2.0\n\
+             \n\
+             Current entries:\n\
+             \x20   new 0.2.0\n\
+             \x20       features: std\n\
+             \x20       forbids unsafe: false\n\
+             \x20       used: functions 0/0, exprs 3/7, impls 0/0, traits 0/0, methods 0/0\n\
+             \x20       unused: functions 0/0, exprs 0/0, impls 0/0, traits 0/0, methods 0/0\n"
+        );
+    }
+
+    #[rstest]
+    fn render_report_delta_test_unified_with_entries() {
+        let unified = render_report_delta(
+            DiffFormat::Unified,
+            &report_delta_with_entries(),
+        );
+
+        assert!(unified.contains(
+            "+new 0.2.0\n\
+             @@ new 0.2.0 (current) @@\n\
+             \x20features: std\n"
+        ));
+    }
+
+    #[rstest]
+    fn render_report_delta_test_unified() {
+        assert_eq!(
+            render_report_delta(DiffFormat::Unified, &report_delta()),
+            "--- baseline\n\
+             +++ current\n\
+             -old 0.1.0\n\
+             +new 0.2.0\n\
+             @@ a 1.0.0 @@\n\
+             -functions: 2\n\
+             +functions: 1\n\
+             -exprs: 3\n\
+             +exprs: 7"
+        );
+    }
+
+    #[rstest]
+    fn render_report_delta_test_json() {
+        let json = render_report_delta(DiffFormat::Json, &report_delta());
+
+        assert_eq!(
+            serde_json::from_str::<ReportDelta>(&json).unwrap(),
+            report_delta()
+        );
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(value["added"][0]["name"], "new");
+        assert_eq!(value["removed"][0]["name"], "old");
+        assert_eq!(value["changed"][0]["categories"][1]["current"], 7);
+        // The entries are left out unless included with `--diff-full`.
+        assert!(value.get("entries").is_none());
+
+        let json =
+            render_report_delta(DiffFormat::Json, &report_delta_with_entries());
+        assert_eq!(
+            serde_json::from_str::<ReportDelta>(&json).unwrap(),
+            report_delta_with_entries()
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use serde_json::Value;
+
+/// The fields of each package entry of the Json report which can be removed
+/// with `--fields-deny`, nested fields being separated by a `.`
+pub const REPORT_ENTRY_FIELDS: [&str; 9] = [
+    "package",
+    "package.id",
+    "package.dependencies",
+    "package.dev_dependencies",
+    "package.build_dependencies",
+    "unsafety",
+    "unsafety.used",
+    "unsafety.unused",
+    "unsafety.forbids_unsafe",
+];
+
+/// The categories of a serialized `CounterBlock`
+const COUNTER_BLOCK_FIELDS: [&str; 5] =
+    ["functions", "exprs", "item_impls", "item_traits", "methods"];
+
+/// Remove the given fields from each package entry of a serialized
+/// `SafetyReport`, leaving the rest of the report untouched
+pub fn deny_report_entry_fields(report: &mut Value, fields_deny: &[String]) {
+    for entries_key in &["packages", "build_packages"] {
+        if let Some(entries) =
+            report.get_mut(*entries_key).and_then(Value::as_array_mut)
+        {
+            for entry in entries {
+                for field in fields_deny {
+                    remove_field(entry, field);
+                }
+            }
+        }
+    }
+}
+
+/// Remove, for `--compact-json`, the categories with no safe nor unsafe item
+/// from every `CounterBlock` of a serialized `SafetyReport`
+pub fn remove_zero_counts(value: &mut Value) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(remove_zero_counts),
+        Value::Object(object) => {
+            if COUNTER_BLOCK_FIELDS
+                .iter()
+                .all(|field| object.get(*field).map_or(false, is_count))
+            {
+                object.retain(|field, count| {
+                    !COUNTER_BLOCK_FIELDS.contains(&field.as_str())
+                        || !is_zero_count(count)
+                });
+            } else {
+                object.values_mut().for_each(remove_zero_counts);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_count(value: &Value) -> bool {
+    value.get("safe").map_or(false, Value::is_u64)
+        && value.get("unsafe_").map_or(false, Value::is_u64)
+}
+
+fn is_zero_count(value: &Value) -> bool {
+    value["safe"] == 0 && value["unsafe_"] == 0
+}
+
+fn remove_field(value: &mut Value, field: &str) {
+    let object = match value.as_object_mut() {
+        Some(object) => object,
+        None => return,
+    };
+    match field.split_once('.') {
+        Some((parent, child)) => {
+            if let Some(parent_value) = object.get_mut(parent) {
+                remove_field(parent_value, child);
+            }
+        }
+        None => {
+            object.remove(field);
+        }
+    }
+}
+
+#[cfg(test)]
+mod report_fields_tests {
+    use super::*;
+
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest(
+        input_fields_deny,
+        expected_entry,
+        case(
+            vec![],
+            json!({
+                "package": { "id": "a", "dependencies": [] },
+                "unsafety": { "used": 1, "unused": 2 }
+            })
+        ),
+        case(
+            vec![String::from("unsafety")],
+            json!({ "package": { "id": "a", "dependencies": [] } })
+        ),
+        case(
+            vec![
+                String::from("package.dependencies"),
+                String::from("unsafety.unused")
+            ],
+            json!({ "package": { "id": "a" }, "unsafety": { "used": 1 } })
+        )
+    )]
+    fn deny_report_entry_fields_test(
+        input_fields_deny: Vec<String>,
+        expected_entry: Value,
+    ) {
+        let mut report = json!({
+            "packages": [{
+                "package": { "id": "a", "dependencies": [] },
+                "unsafety": { "used": 1, "unused": 2 }
+            }],
+            "packages_without_metrics": []
+        });
+
+        deny_report_entry_fields(&mut report, &input_fields_deny);
+
+        assert_eq!(
+            report,
+            json!({
+                "packages": [expected_entry],
+                "packages_without_metrics": []
+            })
+        );
+    }
+
+    #[rstest]
+    fn remove_zero_counts_test() {
+        let mut report = json!({
+            "packages": [{
+                "package": { "id": "a" },
+                "unsafety": {
+                    "used": {
+                        "functions": { "safe": 2, "unsafe_": 0 },
+                        "exprs": { "safe": 0, "unsafe_": 3 },
+                        "item_impls": { "safe": 0, "unsafe_": 0 },
+                        "item_traits": { "safe": 0, "unsafe_": 0 },
+                        "methods": { "safe": 0, "unsafe_": 0 }
+                    },
+                    "forbids_unsafe": false
+                }
+            }],
+            "packages_without_metrics": []
+        });
+
+        remove_zero_counts(&mut report);
+
+        assert_eq!(
+            report,
+            json!({
+                "packages": [{
+                    "package": { "id": "a" },
+                    "unsafety": {
+                        "used": {
+                            "functions": { "safe": 2, "unsafe_": 0 },
+                            "exprs": { "safe": 0, "unsafe_": 3 }
+                        },
+                        "forbids_unsafe": false
+                    }
+                }],
+                "packages_without_metrics": []
+            })
+        );
+    }
+}
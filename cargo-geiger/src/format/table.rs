@@ -1,22 +1,35 @@
 mod handle_text_tree_line;
+mod kind_totals;
+mod source_totals;
+mod std_crates;
 mod total_package_counts;
 
+use crate::args::GroupBy;
 use crate::format::emoji_symbols::EmojiSymbols;
 use crate::format::print_config::{colorize, OutputFormat, PrintConfig};
 use crate::format::CrateDetectionStatus;
+use crate::grade::safety_grade;
 use crate::mapping::CargoMetadataParameters;
-use crate::scan::{GeigerContext, ScanResult};
+use crate::scan::{
+    unsafe_stats, GeigerContext, ScanOutputLines, ScanResult, UnsafeKindFlags,
+};
 use crate::tree::TextTreeLine;
+use crate::weights::UnsafeWeights;
 
 use handle_text_tree_line::{
     text_tree_line_extra_deps_group_to_table_line_string,
     text_tree_line_package_to_table_line_string, HandlePackageParameters,
 };
+use kind_totals::construct_kind_totals_lines;
+use source_totals::construct_source_totals_lines;
+use std_crates::construct_std_crates_lines;
 use total_package_counts::TotalPackageCounts;
 
-use cargo_geiger_serde::{Count, CounterBlock};
+use cargo_geiger_serde::{Count, CounterBlock, StdCrateUnsafety, UnsafeChange};
+use cargo_metadata::semver::Version;
+use cargo_metadata::PackageId;
 use colored::ColoredString;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 
 // TODO: use a table library, or factor the tableness out in a smarter way. This
@@ -35,14 +48,16 @@ pub fn create_table_from_text_tree_lines(
     cargo_metadata_parameters: &CargoMetadataParameters,
     table_parameters: &TableParameters,
     text_tree_lines: Vec<TextTreeLine>,
+    mut table_lines: ScanOutputLines,
 ) -> ScanResult {
-    let mut table_lines = Vec::<String>::new();
     let mut total_package_counts = TotalPackageCounts::new();
     let mut warning_count = 0;
     let mut visited_package_ids = HashSet::new();
+    let mut elided_occurrence_count = 0;
     let emoji_symbols =
         EmojiSymbols::new(table_parameters.print_config.output_format);
     let mut handle_package_parameters = HandlePackageParameters {
+        displayed_package_count: &mut 0,
         total_package_counts: &mut total_package_counts,
         visited_package_ids: &mut visited_package_ids,
         warning_count: &mut warning_count,
@@ -50,6 +65,12 @@ pub fn create_table_from_text_tree_lines(
 
     for table_line in text_tree_lines.into_iter().filter_map(|text_tree_line| {
         match text_tree_line {
+            // The groups would be left without their hidden packages.
+            TextTreeLine::ExtraDepsGroup { .. }
+                if table_parameters.min_unsafe.is_some() =>
+            {
+                None
+            }
             TextTreeLine::ExtraDepsGroup {
                 kind: dep_kind,
                 tree_vines,
@@ -59,45 +80,216 @@ pub fn create_table_from_text_tree_lines(
             TextTreeLine::Package {
                 id: package_id,
                 tree_vines,
-            } => text_tree_line_package_to_table_line_string(
-                cargo_metadata_parameters,
-                &emoji_symbols,
-                &mut handle_package_parameters,
-                package_id,
-                table_parameters,
-                tree_vines,
-            ),
+                dependencies_elided,
+            } => {
+                elided_occurrence_count += dependencies_elided as u64;
+                text_tree_line_package_to_table_line_string(
+                    cargo_metadata_parameters,
+                    &emoji_symbols,
+                    &mut handle_package_parameters,
+                    package_id,
+                    table_parameters,
+                    tree_vines,
+                )
+            }
         }
     }) {
         table_lines.push(table_line);
     }
 
     table_lines.push(String::new());
-    let total_detection_status =
-        total_package_counts.get_total_detection_status();
-
-    table_lines.push(format!(
-        "{}",
-        table_footer(
-            total_package_counts.total_counter_block,
-            total_package_counts.total_unused_counter_block,
-            table_parameters.print_config.output_format,
-            total_detection_status
-        )
-    ));
+    match table_parameters.build_only_package_ids {
+        Some(build_only_package_ids) => {
+            for (section_name, is_build_only) in &[
+                ("Run time dependencies", false),
+                ("Build dependencies", true),
+            ] {
+                let section_package_counts = section_package_counts(
+                    visited_package_ids.iter().filter(|package_id| {
+                        build_only_package_ids.contains(package_id)
+                            == *is_build_only
+                    }),
+                    table_parameters,
+                );
+                let section_detection_status =
+                    section_package_counts.get_total_detection_status();
+                table_lines.push(format!("{}:", section_name));
+                table_lines.push(format!(
+                    "{}",
+                    table_footer(
+                        section_package_counts.total_counter_block,
+                        section_package_counts.total_unused_counter_block,
+                        table_parameters.print_config.output_format,
+                        section_detection_status
+                    )
+                ));
+                table_lines.push(String::new());
+            }
+        }
+        None => {
+            let total_detection_status =
+                total_package_counts.get_total_detection_status();
+
+            table_lines.push(format!(
+                "{}",
+                table_footer(
+                    total_package_counts.total_counter_block.clone(),
+                    total_package_counts.total_unused_counter_block.clone(),
+                    table_parameters.print_config.output_format,
+                    total_detection_status
+                )
+            ));
 
-    table_lines.push(String::new());
+            table_lines.push(String::new());
+        }
+    }
+
+    if elided_occurrence_count > 0 {
+        table_lines.push(elided_occurrences_notice(elided_occurrence_count));
+        table_lines.push(String::new());
+    }
+
+    if table_parameters.print_grade {
+        table_lines.push(format!(
+            "Safety grade: {:?}",
+            safety_grade(
+                &total_package_counts.total_counter_block,
+                &total_package_counts.total_unused_counter_block,
+                total_package_counts.unsafe_detected as u64,
+            )
+        ));
+        table_lines.push(String::new());
+    }
+
+    if table_parameters.print_unsafe_free {
+        table_lines.push(unsafe_free_counts_line(&total_package_counts));
+        table_lines.push(String::new());
+    }
+
+    if table_parameters.group_unsafe_by_kind {
+        table_lines.append(construct_kind_totals_lines(
+            &total_package_counts.total_counter_block,
+            &total_package_counts.total_unused_counter_block,
+        ));
+    }
+
+    if table_parameters.group_by == Some(GroupBy::Source) {
+        table_lines.append(construct_source_totals_lines(
+            cargo_metadata_parameters,
+            &visited_package_ids,
+            table_parameters,
+        ));
+    }
+
+    if !table_parameters.std_crates.is_empty() {
+        table_lines.append(construct_std_crates_lines(
+            table_parameters.std_crates,
+            table_parameters.print_config.output_format,
+        ));
+    }
 
     ScanResult {
-        scan_output_lines: table_lines,
+        scan_output_lines: table_lines.into_lines(),
         warning_count,
+        unsafe_free: total_package_counts.unsafe_detected == 0
+            && warning_count == 0,
+        used_unsafe_expr_count: None,
     }
 }
 
 pub struct TableParameters<'a> {
+    /// The ids of the advisories affecting each package, for `--advisories`.
+    pub advisory_ids: &'a HashMap<PackageId, Vec<String>>,
+    /// The packages only used by build scripts, totaled apart from the other
+    /// packages, for `--separate-build-report`.
+    pub build_only_package_ids: Option<&'a HashSet<PackageId>>,
+    /// Whether to print the unsafe usage in the test code of each package,
+    /// for `--count-cfg-test-separately`.
+    pub count_cfg_test_separately: bool,
+    /// The matches of each pattern by name in each package, for
+    /// `--custom-pattern`.
+    pub custom_pattern_counts: &'a HashMap<PackageId, BTreeMap<String, u64>>,
+    /// Whether to print the features enabled with `#![feature(...)]` by each
+    /// package, for `--flag-nightly-features`.
+    pub flag_nightly_features: bool,
     pub geiger_context: &'a GeigerContext,
+    /// The grouping of the extra totals printed below the table, for
+    /// `--group-by`.
+    pub group_by: Option<GroupBy>,
+    /// Whether to print the totals split by kind of unsafe usage below the
+    /// table, for `--group-unsafe-by-kind`.
+    pub group_unsafe_by_kind: bool,
+    /// Packages matched by `--ignore-unsafe-in`, which are displayed but left
+    /// out of the totals.
+    pub ignored_package_ids: &'a HashSet<PackageId>,
+    /// Packages only pulled by inactive optional dependencies, displayed with
+    /// `--include-optional`.
+    pub inactive_optional_package_ids: &'a HashSet<PackageId>,
+    /// The number of unsafe items used by the build below which a package is
+    /// hidden, still counted in the totals, for `--min-unsafe`.
+    pub min_unsafe: Option<u64>,
     pub print_config: &'a PrintConfig,
+    /// Whether to print the safety grade below the table, for
+    /// `--print-grade`.
+    pub print_grade: bool,
+    /// Whether to print the number of packages free of unsafe usage below
+    /// the table, for `--print-unsafe-free`.
+    pub print_unsafe_free: bool,
+    /// Whether to prefix each package of the list with its position in the
+    /// list and its number of unsafe items used by the build, for `--rank`.
+    pub rank: bool,
     pub rs_files_used: &'a HashSet<PathBuf>,
+    /// The unsafe usage of the standard library crates, for `--include-std`.
+    pub std_crates: &'a [StdCrateUnsafety],
+    /// The kinds of unsafe usage to print for each package, e.g. the raw
+    /// pointer dereferences for `--raw-derefs`.
+    pub unsafe_kind_flags: UnsafeKindFlags,
+    /// The weights used to compute a risk score per package, for `--weights`.
+    pub unsafe_weights: Option<UnsafeWeights>,
+    /// The baseline version and the change of the unsafe usage of the
+    /// packages whose version differs from the baseline, for `--diff`.
+    pub version_changes: &'a HashMap<PackageId, (Version, UnsafeChange)>,
+}
+
+/// The totals of the given packages, leaving out the ignored ones.
+fn section_package_counts<'a, I>(
+    package_ids: I,
+    table_parameters: &TableParameters,
+) -> TotalPackageCounts
+where
+    I: Iterator<Item = &'a PackageId>,
+{
+    let mut section_package_counts = TotalPackageCounts::new();
+    for package_id in package_ids.filter(|package_id| {
+        !table_parameters.ignored_package_ids.contains(package_id)
+    }) {
+        let package_metrics = match table_parameters
+            .geiger_context
+            .package_id_to_metrics
+            .get(package_id)
+        {
+            Some(package_metrics) => package_metrics,
+            None => continue,
+        };
+        let unsafe_info =
+            unsafe_stats(package_metrics, table_parameters.rs_files_used);
+        section_package_counts.total_counter_block += unsafe_info.used.clone();
+        section_package_counts.total_unused_counter_block +=
+            unsafe_info.unused.clone();
+        match (unsafe_info.forbids_unsafe, unsafe_info.used.has_unsafe()) {
+            (_, true) => section_package_counts.unsafe_detected += 1,
+            (true, false) => {
+                section_package_counts.none_detected_forbids_unsafe += 1
+            }
+            (false, false) => {
+                section_package_counts.none_detected_allows_unsafe += 1
+            }
+        }
+        if !unsafe_info.used.has_unsafe() && !unsafe_info.unused.has_unsafe() {
+            section_package_counts.none_detected_including_unused += 1;
+        }
+    }
+    section_package_counts
 }
 
 fn table_footer_unsafe_counts(
@@ -168,6 +360,46 @@ fn table_footer(
     }
 }
 
+/// The note printed below the totals when crates displayed more than once
+/// have their dependencies left out, which `--all` displays.
+fn elided_occurrences_notice(elided_occurrence_count: u64) -> String {
+    format!(
+        "{} repeated crate {} displayed without {} dependencies, use -a to display all of them.",
+        elided_occurrence_count,
+        if elided_occurrence_count == 1 {
+            "occurrence is"
+        } else {
+            "occurrences are"
+        },
+        if elided_occurrence_count == 1 { "its" } else { "their" },
+    )
+}
+
+/// The number of packages free of unsafe usage, out of the packages which are
+/// not ignored, printed below the totals for `--print-unsafe-free`.
+fn unsafe_free_counts_line(
+    total_package_counts: &TotalPackageCounts,
+) -> String {
+    let package_count = total_package_counts.package_count();
+    let percentage = |count: i32| match package_count {
+        0 => 100,
+        _ => 100 * count / package_count,
+    };
+    let used_unsafe_free_count = total_package_counts
+        .none_detected_forbids_unsafe
+        + total_package_counts.none_detected_allows_unsafe;
+    let unsafe_free_count = total_package_counts.none_detected_including_unused;
+    format!(
+        "Unsafe-free crates: {}/{} ({}%) in the code used by the build, {}/{} ({}%) in all the code",
+        used_unsafe_free_count,
+        package_count,
+        percentage(used_unsafe_free_count),
+        unsafe_free_count,
+        package_count,
+        percentage(unsafe_free_count),
+    )
+}
+
 fn table_row(
     used: &CounterBlock,
     not_used: &CounterBlock,
@@ -297,6 +529,72 @@ mod table_tests {
         }
     }
 
+    #[rstest(
+        input_elided_occurrence_count,
+        expected_notice,
+        case(
+            1,
+            "1 repeated crate occurrence is displayed without its dependencies, use -a to display all of them."
+        ),
+        case(
+            3,
+            "3 repeated crate occurrences are displayed without their dependencies, use -a to display all of them."
+        )
+    )]
+    fn elided_occurrences_notice_test(
+        input_elided_occurrence_count: u64,
+        expected_notice: &str,
+    ) {
+        assert_eq!(
+            elided_occurrences_notice(input_elided_occurrence_count),
+            expected_notice
+        );
+    }
+
+    #[rstest(
+        input_none_detected_forbids_unsafe,
+        input_none_detected_allows_unsafe,
+        input_none_detected_including_unused,
+        input_unsafe_detected,
+        expected_line,
+        case(
+            1,
+            2,
+            1,
+            1,
+            "Unsafe-free crates: 3/4 (75%) in the code used by the build, 1/4 (25%) in all the code"
+        ),
+        case(
+            0,
+            0,
+            0,
+            0,
+            "Unsafe-free crates: 0/0 (100%) in the code used by the build, 0/0 (100%) in all the code"
+        )
+    )]
+    fn unsafe_free_counts_line_test(
+        input_none_detected_forbids_unsafe: i32,
+        input_none_detected_allows_unsafe: i32,
+        input_none_detected_including_unused: i32,
+        input_unsafe_detected: i32,
+        expected_line: &str,
+    ) {
+        let total_package_counts = TotalPackageCounts {
+            none_detected_forbids_unsafe: input_none_detected_forbids_unsafe,
+            none_detected_allows_unsafe: input_none_detected_allows_unsafe,
+            none_detected_including_unused:
+                input_none_detected_including_unused,
+            unsafe_detected: input_unsafe_detected,
+            total_counter_block: CounterBlock::default(),
+            total_unused_counter_block: CounterBlock::default(),
+        };
+
+        assert_eq!(
+            unsafe_free_counts_line(&total_package_counts),
+            expected_line
+        );
+    }
+
     #[rstest]
     fn table_row_test() {
         let mut rs_path_to_metrics =
@@ -356,6 +654,7 @@ mod table_tests {
         let total_detection_status = TotalPackageCounts {
             none_detected_forbids_unsafe: input_none_detected_forbids_unsafe,
             none_detected_allows_unsafe: input_none_detected_allows_unsafe,
+            none_detected_including_unused: 0,
             unsafe_detected: input_unsafe_detected,
             total_counter_block: CounterBlock::default(),
             total_unused_counter_block: CounterBlock::default(),
@@ -374,9 +673,25 @@ mod table_tests {
         RsFileMetricsWrapper {
             metrics: RsFileMetrics {
                 counters: create_counter_block(),
+                test_counters: Default::default(),
                 forbids_unsafe,
+                raw_pointer_derefs: 0,
+                latent_macro_unsafe: 0,
+                exported_symbols: 0,
+                memory_lifecycle_unsafe: 0,
+                static_mut_definitions: 0,
+                static_mut_accesses: 0,
+                allowed_unsafe: 0,
+                unsafe_trait_usage: 0,
+                drop_unsafe: 0,
+                ffi_callbacks: 0,
+                layout_dependent_transmutes: 0,
+                nightly_features: vec![],
+                out_dir_includes: vec![],
+                module_counters: Default::default(),
             },
             is_crate_entry_point,
+            target_kind: Default::default(),
         }
     }
 
@@ -2,18 +2,23 @@ use crate::format::emoji_symbols::EmojiSymbols;
 use crate::format::print_config::{colorize, OutputFormat};
 use crate::format::{get_kind_group_name, CrateDetectionStatus, SymbolKind};
 use crate::mapping::CargoMetadataParameters;
-use crate::scan::unsafe_stats;
+use crate::scan::{
+    nightly_features, test_unsafe_stats, unsafe_kinds, unsafe_stats,
+};
 
 use super::total_package_counts::TotalPackageCounts;
 use super::TableParameters;
 use super::{table_row, table_row_empty};
 
+use cargo_geiger_serde::{UnsafeChange, UnsafeKinds};
 use cargo_metadata::{DependencyKind, PackageId};
 use colored::ColoredString;
 use std::collections::HashSet;
 use std::fmt::Display;
 
 pub struct HandlePackageParameters<'a> {
+    /// The number of package lines displayed so far, for `--rank`.
+    pub displayed_package_count: &'a mut u64,
     pub total_package_counts: &'a mut TotalPackageCounts,
     pub visited_package_ids: &'a mut HashSet<PackageId>,
     pub warning_count: &'a mut u64,
@@ -53,7 +58,12 @@ pub fn text_tree_line_package_to_table_line_string(
     };
     let unsafe_info =
         unsafe_stats(package_metrics, table_parameters.rs_files_used);
-    if package_is_new {
+    let package_is_ignored =
+        table_parameters.ignored_package_ids.contains(&package_id);
+    let package_is_inactive_optional = table_parameters
+        .inactive_optional_package_ids
+        .contains(&package_id);
+    if package_is_new && !package_is_ignored {
         handle_package_parameters
             .total_package_counts
             .total_counter_block += unsafe_info.used.clone();
@@ -63,7 +73,7 @@ pub fn text_tree_line_package_to_table_line_string(
     }
     let unsafe_found = unsafe_info.used.has_unsafe();
     let crate_forbids_unsafe = unsafe_info.forbids_unsafe;
-    let total_inc = package_is_new as i32;
+    let total_inc = (package_is_new && !package_is_ignored) as i32;
     let crate_detection_status =
         get_crate_detection_status_and_update_package_counts(
             crate_forbids_unsafe,
@@ -71,6 +81,27 @@ pub fn text_tree_line_package_to_table_line_string(
             total_inc,
             unsafe_found,
         );
+    if !unsafe_found && !unsafe_info.unused.has_unsafe() {
+        handle_package_parameters
+            .total_package_counts
+            .none_detected_including_unused += total_inc;
+    }
+    if table_parameters.min_unsafe.map_or(false, |min_unsafe| {
+        unsafe_info.used.unsafe_count() < min_unsafe
+    }) {
+        return None;
+    }
+    let tree_vines = if table_parameters.rank {
+        *handle_package_parameters.displayed_package_count += 1;
+        format!(
+            "#{} ({} unsafe) {}",
+            handle_package_parameters.displayed_package_count,
+            unsafe_info.used.unsafe_count(),
+            tree_vines
+        )
+    } else {
+        tree_vines
+    };
 
     let icon = match crate_detection_status {
         CrateDetectionStatus::NoneDetectedForbidsUnsafe => {
@@ -84,15 +115,95 @@ pub fn text_tree_line_package_to_table_line_string(
         }
     };
 
+    let advisories = match table_parameters.advisory_ids.get(&package_id) {
+        Some(advisory_ids) => format!(" [{}]", advisory_ids.join(", ")),
+        None => String::new(),
+    };
+    let risk_score = match table_parameters.unsafe_weights {
+        Some(unsafe_weights) => {
+            format!(" (risk: {})", unsafe_weights.risk_score(&unsafe_info.used))
+        }
+        None => String::new(),
+    };
+    let unsafe_kind_counts = unsafe_kinds(
+        package_metrics,
+        table_parameters.rs_files_used,
+        &table_parameters.unsafe_kind_flags,
+    )
+    .map_or_else(String::new, |unsafe_kinds| {
+        unsafe_kind_suffixes(&unsafe_kinds)
+    });
+    let nightly_features = if table_parameters.flag_nightly_features {
+        let features =
+            nightly_features(package_metrics, table_parameters.rs_files_used);
+        if features.is_empty() {
+            String::new()
+        } else {
+            format!(" (nightly features: {})", features.join(", "))
+        }
+    } else {
+        String::new()
+    };
+    let test_unsafety = if table_parameters.count_cfg_test_separately {
+        let (used, unused) =
+            test_unsafe_stats(package_metrics, table_parameters.rs_files_used);
+        let used_count = used.unsafe_count();
+        format!(
+            " (cfg(test) unsafe: {}/{})",
+            used_count,
+            used_count + unused.unsafe_count()
+        )
+    } else {
+        String::new()
+    };
+    let custom_pattern_counts =
+        match table_parameters.custom_pattern_counts.get(&package_id) {
+            Some(counts) => format!(
+                " ({})",
+                counts
+                    .iter()
+                    .map(|(name, count)| format!("{}: {}", name, count))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            None => String::new(),
+        };
+    let version_change = match table_parameters.version_changes.get(&package_id)
+    {
+        Some((baseline_version, change)) => format!(
+            " (unsafe {} since {})",
+            match change {
+                UnsafeChange::Increased => "increased",
+                UnsafeChange::Decreased => "decreased",
+                UnsafeChange::Unchanged => "unchanged",
+                UnsafeChange::New => "introduced",
+            },
+            baseline_version
+        ),
+        None => String::new(),
+    };
     let package_name = colorize(
         &crate_detection_status,
         table_parameters.print_config.output_format,
         format!(
-            "{}",
+            "{}{}{}{}{}{}{}{}{}{}",
             table_parameters
                 .print_config
                 .format
-                .display(cargo_metadata_parameters, &package_id)
+                .display(cargo_metadata_parameters, &package_id),
+            if package_is_ignored { " (ignored)" } else { "" },
+            if package_is_inactive_optional {
+                " (optional/inactive)"
+            } else {
+                ""
+            },
+            risk_score,
+            unsafe_kind_counts,
+            nightly_features,
+            test_unsafety,
+            custom_pattern_counts,
+            version_change,
+            advisories
         ),
     );
     let unsafe_info = colorize(
@@ -116,6 +227,83 @@ pub fn text_tree_line_package_to_table_line_string(
     ))
 }
 
+/// The counts of the kinds of unsafe usage of a package counted with the
+/// flags enabling them, each as the count in code used by the build out of
+/// the total count.
+fn unsafe_kind_suffixes(unsafe_kinds: &UnsafeKinds) -> String {
+    let UnsafeKinds { used, unused } = unsafe_kinds;
+    let static_mut = match (&used.static_mut, &unused.static_mut) {
+        (Some(used), Some(unused)) => format!(
+            " (static mut: {}/{} declared, {}/{} accessed)",
+            used.definitions,
+            used.definitions + unused.definitions,
+            used.accesses,
+            used.accesses + unused.accesses
+        ),
+        _ => String::new(),
+    };
+    let allowed_unsafe = match (&used.allowed_unsafe, &unused.allowed_unsafe) {
+        (Some(used), Some(unused)) => format!(
+            " (allowed unsafe: {}/{}, unannotated: {}/{})",
+            used.allowed,
+            used.allowed + unused.allowed,
+            used.unannotated,
+            used.unannotated + unused.unannotated
+        ),
+        _ => String::new(),
+    };
+    [
+        used_out_of_total("raw derefs", used.raw_derefs, unused.raw_derefs),
+        used_out_of_total(
+            "macro unsafe",
+            used.latent_macro_unsafe,
+            unused.latent_macro_unsafe,
+        ),
+        used_out_of_total(
+            "exported symbols",
+            used.exported_symbols,
+            unused.exported_symbols,
+        ),
+        used_out_of_total(
+            "memory lifecycle unsafe",
+            used.memory_lifecycle_unsafe,
+            unused.memory_lifecycle_unsafe,
+        ),
+        static_mut,
+        allowed_unsafe,
+        used_out_of_total(
+            "unsafe trait usage",
+            used.unsafe_trait_usage,
+            unused.unsafe_trait_usage,
+        ),
+        used_out_of_total("drop unsafe", used.drop_unsafe, unused.drop_unsafe),
+        used_out_of_total(
+            "ffi callbacks",
+            used.ffi_callbacks,
+            unused.ffi_callbacks,
+        ),
+        used_out_of_total(
+            "layout-dependent unsafe",
+            used.layout_dependent_unsafe,
+            unused.layout_dependent_unsafe,
+        ),
+    ]
+    .concat()
+}
+
+fn used_out_of_total(
+    name: &str,
+    used: Option<u64>,
+    unused: Option<u64>,
+) -> String {
+    match (used, unused) {
+        (Some(used), Some(unused)) => {
+            format!(" ({}: {}/{})", name, used, used + unused)
+        }
+        _ => String::new(),
+    }
+}
+
 fn construct_package_text_tree_line(
     crate_detection_status: CrateDetectionStatus,
     emoji_symbols: &EmojiSymbols,
@@ -196,8 +384,11 @@ mod handle_text_tree_line_tests {
     use super::*;
 
     use crate::format::print_config::PrintConfig;
+    use crate::lib_tests::construct_krates_and_metadata;
+    use crate::scan::{GeigerContext, PackageMetrics, RsFileMetricsWrapper};
     use colored::Colorize;
     use rstest::*;
+    use std::path::PathBuf;
 
     #[rstest(
         input_dep_kind,
@@ -254,12 +445,29 @@ mod handle_text_tree_line_tests {
         let icon = emoji_symbols.emoji(input_symbol_kind);
         let package_name = String::from("package_name").normal();
         let table_parameters = TableParameters {
+            advisory_ids: &Default::default(),
+            build_only_package_ids: None,
+            count_cfg_test_separately: false,
+            custom_pattern_counts: &Default::default(),
+            flag_nightly_features: false,
             geiger_context: &Default::default(),
+            group_by: None,
+            group_unsafe_by_kind: false,
+            ignored_package_ids: &Default::default(),
+            inactive_optional_package_ids: &Default::default(),
+            min_unsafe: None,
             print_config: &PrintConfig {
                 output_format: input_output_format,
                 ..Default::default()
             },
+            print_grade: false,
+            print_unsafe_free: false,
+            rank: false,
             rs_files_used: &Default::default(),
+            std_crates: &[],
+            unsafe_kind_flags: Default::default(),
+            unsafe_weights: None,
+            version_changes: &Default::default(),
         };
         let tree_vines = String::from("tree_vines");
         let unsafe_info = ColoredString::from("unsafe_info").normal();
@@ -277,6 +485,111 @@ mod handle_text_tree_line_tests {
         assert_eq!(package_text_tree_line, expected_package_text_tree_line);
     }
 
+    #[rstest(
+        input_is_ignored,
+        expected_total_unsafe_functions,
+        expected_unsafe_detected,
+        case(false, 1, 1),
+        case(true, 0, 0)
+    )]
+    fn text_tree_line_package_to_table_line_string_ignored_package_test(
+        input_is_ignored: bool,
+        expected_total_unsafe_functions: u64,
+        expected_unsafe_detected: i32,
+    ) {
+        let (krates, metadata) = construct_krates_and_metadata();
+        let cargo_metadata_parameters = CargoMetadataParameters {
+            krates: &krates,
+            metadata: &metadata,
+        };
+        let package_id = metadata.root_package().unwrap().id.clone();
+
+        let rs_path = PathBuf::from("src/lib.rs");
+        let mut rs_file_metrics_wrapper = RsFileMetricsWrapper::default();
+        rs_file_metrics_wrapper.metrics.counters.functions.unsafe_ = 1;
+        let geiger_context = GeigerContext {
+            package_id_to_metrics: vec![(
+                package_id.clone(),
+                PackageMetrics {
+                    rs_path_to_metrics: vec![(
+                        rs_path.clone(),
+                        rs_file_metrics_wrapper,
+                    )]
+                    .into_iter()
+                    .collect(),
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ignored_paths: Default::default(),
+            excluded_paths: Default::default(),
+            oversized_paths: Default::default(),
+            unscanned_package_ids: Default::default(),
+            timed_out_package_ids: Default::default(),
+        };
+
+        let ignored_package_ids = if input_is_ignored {
+            vec![package_id.clone()].into_iter().collect()
+        } else {
+            HashSet::new()
+        };
+        let rs_files_used = vec![rs_path].into_iter().collect();
+        let table_parameters = TableParameters {
+            advisory_ids: &Default::default(),
+            build_only_package_ids: None,
+            count_cfg_test_separately: false,
+            custom_pattern_counts: &Default::default(),
+            flag_nightly_features: false,
+            geiger_context: &geiger_context,
+            group_by: None,
+            group_unsafe_by_kind: false,
+            ignored_package_ids: &ignored_package_ids,
+            inactive_optional_package_ids: &Default::default(),
+            min_unsafe: None,
+            print_config: &PrintConfig {
+                output_format: OutputFormat::GitHubMarkdown,
+                ..Default::default()
+            },
+            print_grade: false,
+            print_unsafe_free: false,
+            rank: false,
+            rs_files_used: &rs_files_used,
+            std_crates: &[],
+            unsafe_kind_flags: Default::default(),
+            unsafe_weights: None,
+            version_changes: &Default::default(),
+        };
+
+        let mut total_package_counts = TotalPackageCounts::new();
+        let mut handle_package_parameters = HandlePackageParameters {
+            displayed_package_count: &mut 0,
+            total_package_counts: &mut total_package_counts,
+            visited_package_ids: &mut Default::default(),
+            warning_count: &mut 0,
+        };
+
+        let table_line = text_tree_line_package_to_table_line_string(
+            &cargo_metadata_parameters,
+            &EmojiSymbols::new(OutputFormat::GitHubMarkdown),
+            &mut handle_package_parameters,
+            package_id,
+            &table_parameters,
+            String::new(),
+        )
+        .unwrap();
+
+        assert_eq!(table_line.contains("(ignored)"), input_is_ignored);
+        assert!(table_line.starts_with("1/1"));
+        assert_eq!(
+            total_package_counts.total_counter_block.functions.unsafe_,
+            expected_total_unsafe_functions
+        );
+        assert_eq!(
+            total_package_counts.unsafe_detected,
+            expected_unsafe_detected
+        );
+    }
+
     #[rstest(
         input_crate_forbids_unsafe,
         input_total_inc,
@@ -334,9 +647,11 @@ mod handle_text_tree_line_tests {
         expected_unsafe_detected: i32,
     ) {
         let mut handle_package_parameters = HandlePackageParameters {
+            displayed_package_count: &mut 0,
             total_package_counts: &mut TotalPackageCounts {
                 none_detected_forbids_unsafe: 0,
                 none_detected_allows_unsafe: 0,
+                none_detected_including_unused: 0,
                 unsafe_detected: 0,
                 total_counter_block: Default::default(),
                 total_unused_counter_block: Default::default(),
@@ -0,0 +1,96 @@
+use cargo_geiger_serde::{Count, CounterBlock};
+
+/// The totals of the unsafe usage of the displayed packages split by kind,
+/// for `--group-unsafe-by-kind`, with the share of each kind in the unsafe
+/// usage used by the build. The totals are the ones below the tree, so the
+/// ignored packages are left out.
+pub fn construct_kind_totals_lines(
+    used: &CounterBlock,
+    unused: &CounterBlock,
+) -> Vec<String> {
+    let used_unsafe_count = used.unsafe_count();
+    let kind_line = |kind: &str, used: u64, found: u64| {
+        format!(
+            "{: <12} {: <6} {: <6} {}%",
+            kind,
+            used,
+            found,
+            match used_unsafe_count {
+                0 => 0,
+                _ => 100 * used / used_unsafe_count,
+            }
+        )
+    };
+    let count_line = |kind: &str, used: &Count, unused: &Count| {
+        kind_line(kind, used.unsafe_, used.unsafe_ + unused.unsafe_)
+    };
+
+    vec![
+        String::from("Unsafe usage by kind:"),
+        String::new(),
+        String::from("Kind         Used   Found  Share of used"),
+        String::new(),
+        count_line("Functions", &used.functions, &unused.functions),
+        count_line("Expressions", &used.exprs, &unused.exprs),
+        count_line("Impls", &used.item_impls, &unused.item_impls),
+        count_line("Traits", &used.item_traits, &unused.item_traits),
+        count_line("Methods", &used.methods, &unused.methods),
+        kind_line(
+            "Total",
+            used_unsafe_count,
+            used_unsafe_count + unused.unsafe_count(),
+        ),
+        String::new(),
+    ]
+}
+
+#[cfg(test)]
+mod kind_totals_tests {
+    use super::*;
+
+    use rstest::*;
+
+    fn counter_block(functions: u64, exprs: u64, methods: u64) -> CounterBlock {
+        let count = |unsafe_| Count { safe: 1, unsafe_ };
+        CounterBlock {
+            functions: count(functions),
+            exprs: count(exprs),
+            methods: count(methods),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    fn construct_kind_totals_lines_test() {
+        // The totals of two crates, as summed below the tree.
+        let used = counter_block(1, 4, 0) + counter_block(0, 2, 1);
+        let unused = counter_block(2, 0, 0) + counter_block(0, 1, 0);
+
+        assert_eq!(
+            construct_kind_totals_lines(&used, &unused),
+            vec![
+                "Unsafe usage by kind:",
+                "",
+                "Kind         Used   Found  Share of used",
+                "",
+                "Functions    1      3      12%",
+                "Expressions  6      7      75%",
+                "Impls        0      0      0%",
+                "Traits       0      0      0%",
+                "Methods      1      1      12%",
+                "Total        8      11     100%",
+                "",
+            ]
+        );
+    }
+
+    #[rstest]
+    fn construct_kind_totals_lines_test_without_unsafe_usage() {
+        let lines = construct_kind_totals_lines(
+            &CounterBlock::default(),
+            &CounterBlock::default(),
+        );
+
+        assert_eq!(lines[9], "Total        0      0      0%");
+    }
+}
@@ -0,0 +1,146 @@
+use crate::mapping::{CargoMetadataParameters, ToCargoGeigerPackageId};
+use crate::scan::unsafe_stats;
+
+use super::{table_row, TableParameters, UNSAFE_COUNTERS_HEADER};
+
+use cargo_geiger_serde::{CounterBlock, Source};
+use cargo_metadata::PackageId;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Where a crate comes from, for `--group-by source`
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum SourceKind {
+    Git,
+    Path,
+    Registry,
+}
+
+impl From<&Source> for SourceKind {
+    fn from(source: &Source) -> Self {
+        match source {
+            Source::Git { .. } => SourceKind::Git,
+            Source::Path(_) => SourceKind::Path,
+            Source::Registry { .. } => SourceKind::Registry,
+        }
+    }
+}
+
+impl fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SourceKind::Git => write!(f, "git"),
+            SourceKind::Path => write!(f, "path"),
+            SourceKind::Registry => write!(f, "registry"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SourceTotals {
+    crate_count: usize,
+    used: CounterBlock,
+    unused: CounterBlock,
+}
+
+/// The totals of the unsafe usage of the displayed packages, grouped by where
+/// the packages come from. Ignored packages are left out, as they are out of
+/// the overall totals.
+pub fn construct_source_totals_lines(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    package_ids: &HashSet<PackageId>,
+    table_parameters: &TableParameters,
+) -> Vec<String> {
+    let mut totals_by_source_kind = BTreeMap::<SourceKind, SourceTotals>::new();
+
+    for package_id in package_ids {
+        if table_parameters.ignored_package_ids.contains(package_id) {
+            continue;
+        }
+        let package_metrics = match table_parameters
+            .geiger_context
+            .package_id_to_metrics
+            .get(package_id)
+        {
+            Some(package_metrics) => package_metrics,
+            None => continue,
+        };
+        let source = match package_id
+            .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+        {
+            Some(package) => package.source,
+            None => continue,
+        };
+
+        let unsafe_info =
+            unsafe_stats(package_metrics, table_parameters.rs_files_used);
+        let source_totals = totals_by_source_kind
+            .entry(SourceKind::from(&source))
+            .or_default();
+        source_totals.crate_count += 1;
+        source_totals.used += unsafe_info.used;
+        source_totals.unused += unsafe_info.unused;
+    }
+
+    let mut header =
+        UNSAFE_COUNTERS_HEADER[..UNSAFE_COUNTERS_HEADER.len() - 1].to_vec();
+    header.push("Source");
+
+    let mut source_totals_lines = vec![header.join(" "), String::new()];
+    for (source_kind, source_totals) in totals_by_source_kind {
+        source_totals_lines.push(format!(
+            "{}  {} ({} {})",
+            table_row(
+                &source_totals.used,
+                &source_totals.unused,
+                table_parameters.print_config.output_format,
+            ),
+            source_kind,
+            source_totals.crate_count,
+            if source_totals.crate_count == 1 {
+                "crate"
+            } else {
+                "crates"
+            }
+        ));
+    }
+    source_totals_lines.push(String::new());
+    source_totals_lines
+}
+
+#[cfg(test)]
+mod source_totals_tests {
+    use super::*;
+
+    use rstest::*;
+    use url::Url;
+
+    #[rstest(
+        input_source,
+        expected_source_kind,
+        case(
+            Source::Git {
+                url: Url::parse("https://github.com/rust-secure-code/cargo-geiger").unwrap(),
+                rev: String::from("abcdef")
+            },
+            SourceKind::Git
+        ),
+        case(
+            Source::Path(Url::parse("file:///path/to/crate").unwrap()),
+            SourceKind::Path
+        ),
+        case(
+            Source::Registry {
+                name: String::from("crates.io"),
+                url: Url::parse("https://github.com/rust-lang/crates.io-index").unwrap()
+            },
+            SourceKind::Registry
+        )
+    )]
+    fn source_kind_from_source_test(
+        input_source: Source,
+        expected_source_kind: SourceKind,
+    ) {
+        assert_eq!(SourceKind::from(&input_source), expected_source_kind);
+    }
+}
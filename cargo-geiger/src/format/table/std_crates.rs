@@ -0,0 +1,77 @@
+use crate::format::print_config::OutputFormat;
+
+use super::{table_row, UNSAFE_COUNTERS_HEADER};
+
+use cargo_geiger_serde::{CounterBlock, StdCrateUnsafety};
+
+/// The unsafe usage found in the standard library crates, for
+/// `--include-std`. The standard library is not built, so none of its unsafe
+/// usage is counted as used by the build, and none is part of the totals.
+pub fn construct_std_crates_lines(
+    std_crates: &[StdCrateUnsafety],
+    output_format: OutputFormat,
+) -> Vec<String> {
+    let mut header =
+        UNSAFE_COUNTERS_HEADER[..UNSAFE_COUNTERS_HEADER.len() - 1].to_vec();
+    header.push("Standard library crate");
+
+    let mut std_crates_lines = vec![
+        String::from("Standard library, left out of the totals:"),
+        String::new(),
+        header.join(" "),
+        String::new(),
+    ];
+    for std_crate in std_crates {
+        std_crates_lines.push(format!(
+            "{}  {}",
+            table_row(
+                &CounterBlock::default(),
+                &std_crate.unsafety,
+                output_format
+            ),
+            std_crate.name
+        ));
+    }
+    std_crates_lines.push(String::new());
+    std_crates_lines
+}
+
+#[cfg(test)]
+mod std_crates_tests {
+    use super::*;
+
+    use cargo_geiger_serde::Count;
+    use rstest::*;
+
+    #[rstest]
+    fn construct_std_crates_lines_test() {
+        let std_crates = vec![StdCrateUnsafety {
+            name: String::from("core"),
+            unsafety: CounterBlock {
+                functions: Count {
+                    safe: 4,
+                    unsafe_: 2,
+                },
+                exprs: Count {
+                    safe: 10,
+                    unsafe_: 7,
+                },
+                ..Default::default()
+            },
+        }];
+
+        assert_eq!(
+            construct_std_crates_lines(&std_crates, OutputFormat::Ascii),
+            vec![
+                String::from("Standard library, left out of the totals:"),
+                String::new(),
+                String::from(
+                    "Functions  Expressions  Impls  Traits  Methods  Standard library crate"
+                ),
+                String::new(),
+                String::from("0/2        0/7          0/0    0/0     0/0      core"),
+                String::new(),
+            ]
+        );
+    }
+}
@@ -5,6 +5,9 @@ use cargo_geiger_serde::CounterBlock;
 pub struct TotalPackageCounts {
     pub none_detected_forbids_unsafe: i32,
     pub none_detected_allows_unsafe: i32,
+    /// Packages without unsafe usage at all, in the code used by the build or
+    /// not.
+    pub none_detected_including_unused: i32,
     pub unsafe_detected: i32,
     pub total_counter_block: CounterBlock,
     pub total_unused_counter_block: CounterBlock,
@@ -15,12 +18,19 @@ impl TotalPackageCounts {
         TotalPackageCounts {
             none_detected_forbids_unsafe: 0,
             none_detected_allows_unsafe: 0,
+            none_detected_including_unused: 0,
             unsafe_detected: 0,
             total_counter_block: CounterBlock::default(),
             total_unused_counter_block: CounterBlock::default(),
         }
     }
 
+    pub fn package_count(&self) -> i32 {
+        self.none_detected_forbids_unsafe
+            + self.none_detected_allows_unsafe
+            + self.unsafe_detected
+    }
+
     pub fn get_total_detection_status(&self) -> CrateDetectionStatus {
         match (
             self.none_detected_forbids_unsafe > 0,
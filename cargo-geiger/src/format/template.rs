@@ -0,0 +1,247 @@
+//! Rendering of the report through a user provided template, given to
+//! `--template`. The template uses the [tinytemplate] syntax, values are
+//! written as is, without any escaping.
+//!
+//! The available variables are:
+//!
+//! - `packages`: the packages of the report, sorted by name and version,
+//!   without the build dependencies moved to `build_packages` by
+//!   `--separate-build-report`.
+//! - `build_packages`: the build dependencies, only set with
+//!   `--separate-build-report`.
+//! - `used`, `unused`: the counters of the `unsafe` usage of the packages which
+//!   are not ignored, summed over all the packages.
+//! - `used_unsafe_count`, `unused_unsafe_count`: the number of `unsafe` items
+//!   of `used` and `unused`, summed over all the categories.
+//! - `unsafe_package_count`: the number of packages which are not ignored
+//!   using `unsafe` in code used by the build.
+//! - `safety_grade`: the safety grade, from A to F.
+//! - `metadata`: the `geiger_version`, `rustc_version` and `flags` of the run.
+//!
+//! Each package of `packages` and `build_packages` has:
+//!
+//! - `name`, `version`.
+//! - `used`, `unused`: the counters of the `unsafe` usage of the package.
+//! - `used_unsafe_count`, `unused_unsafe_count`: as for the totals.
+//! - `forbids_unsafe`: whether the package has `#![forbid(unsafe_code)]`.
+//! - `ignored`: whether the package is ignored with `--ignore-unsafe-in`.
+//!
+//! Counters have the `functions`, `exprs`, `item_impls`, `item_traits` and
+//! `methods` categories, each with a `safe` and an `unsafe_` count, e.g.
+//! `{used.exprs.unsafe_}`.
+//!
+//! [tinytemplate]: https://docs.rs/tinytemplate
+
+use cargo_geiger_serde::{
+    CounterBlock, ReportEntry, ReportMetadata, SafetyGrade, SafetyReport,
+};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tinytemplate::TinyTemplate;
+
+const TEMPLATE_NAME: &str = "report";
+
+#[derive(Debug, Serialize)]
+struct TemplateContext<'a> {
+    packages: Vec<TemplatePackage>,
+    build_packages: Vec<TemplatePackage>,
+    used: CounterBlock,
+    unused: CounterBlock,
+    used_unsafe_count: u64,
+    unused_unsafe_count: u64,
+    unsafe_package_count: u64,
+    safety_grade: Option<SafetyGrade>,
+    metadata: &'a ReportMetadata,
+}
+
+#[derive(Debug, Serialize)]
+struct TemplatePackage {
+    name: String,
+    version: String,
+    used: CounterBlock,
+    unused: CounterBlock,
+    used_unsafe_count: u64,
+    unused_unsafe_count: u64,
+    forbids_unsafe: bool,
+    ignored: bool,
+}
+
+impl<'a> TemplateContext<'a> {
+    fn new(report: &'a SafetyReport) -> TemplateContext<'a> {
+        let mut used = CounterBlock::default();
+        let mut unused = CounterBlock::default();
+        let mut unsafe_package_count = 0;
+        for entry in report
+            .packages
+            .values()
+            .chain(report.build_packages.values())
+            .filter(|entry| {
+                !report.ignored_packages.contains(&entry.package.id)
+            })
+        {
+            used += entry.unsafety.used.clone();
+            unused += entry.unsafety.unused.clone();
+            unsafe_package_count += entry.unsafety.used.has_unsafe() as u64;
+        }
+        TemplateContext {
+            packages: template_packages(report, report.packages.values()),
+            build_packages: template_packages(
+                report,
+                report.build_packages.values(),
+            ),
+            used_unsafe_count: used.unsafe_count(),
+            unused_unsafe_count: unused.unsafe_count(),
+            used,
+            unused,
+            unsafe_package_count,
+            safety_grade: report.safety_grade,
+            metadata: &report.metadata,
+        }
+    }
+}
+
+/// Render the report through the template at `path`.
+pub fn render_template(
+    path: &Path,
+    report: &SafetyReport,
+) -> Result<String, TemplateError> {
+    let template = fs::read_to_string(path)
+        .map_err(|e| TemplateError::Io(e, path.to_path_buf()))?;
+    render(&template, report)
+}
+
+fn render(
+    template: &str,
+    report: &SafetyReport,
+) -> Result<String, TemplateError> {
+    let mut tiny_template = TinyTemplate::new();
+    tiny_template.set_default_formatter(&tinytemplate::format_unescaped);
+    tiny_template
+        .add_template(TEMPLATE_NAME, template)
+        .map_err(TemplateError::Render)?;
+    tiny_template
+        .render(TEMPLATE_NAME, &TemplateContext::new(report))
+        .map_err(TemplateError::Render)
+}
+
+fn template_packages<'a, I>(
+    report: &SafetyReport,
+    entries: I,
+) -> Vec<TemplatePackage>
+where
+    I: Iterator<Item = &'a ReportEntry>,
+{
+    let mut entries = entries.collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.package.id.cmp(&b.package.id));
+    entries
+        .into_iter()
+        .map(|entry| TemplatePackage {
+            name: entry.package.id.name.clone(),
+            version: entry.package.id.version.to_string(),
+            used: entry.unsafety.used.clone(),
+            unused: entry.unsafety.unused.clone(),
+            used_unsafe_count: entry.unsafety.used.unsafe_count(),
+            unused_unsafe_count: entry.unsafety.unused.unsafe_count(),
+            forbids_unsafe: entry.unsafety.forbids_unsafe,
+            ignored: report.ignored_packages.contains(&entry.package.id),
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    Io(io::Error, PathBuf),
+    Render(tinytemplate::error::Error),
+}
+
+impl Error for TemplateError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod template_tests {
+    use super::*;
+
+    use cargo_geiger_serde::{
+        Count, PackageId, PackageInfo, Source, UnsafeInfo,
+    };
+    use cargo_metadata::semver::Version;
+    use rstest::*;
+    use url::Url;
+
+    fn report_entry(name: &str, unsafe_exprs: u64) -> ReportEntry {
+        ReportEntry {
+            package: PackageInfo::new(PackageId {
+                name: name.to_string(),
+                version: Version::new(1, 0, 0),
+                source: Source::Path(Url::parse("file:///a").unwrap()),
+            }),
+            active_features: vec![],
+            unsafety: UnsafeInfo {
+                used: CounterBlock {
+                    exprs: Count {
+                        safe: 1,
+                        unsafe_: unsafe_exprs,
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    #[rstest]
+    fn render_test() {
+        let mut report = SafetyReport {
+            safety_grade: Some(SafetyGrade::B),
+            ..Default::default()
+        };
+        for entry in [report_entry("b", 2), report_entry("a", 3)] {
+            report.packages.insert(entry.package.id.clone(), entry);
+        }
+        let template = "{{ for package in packages }}\
+            {package.name} {package.version}: {package.used.exprs.unsafe_}\n\
+            {{ endfor }}\
+            total: {used_unsafe_count} in {unsafe_package_count} packages, \
+            grade {safety_grade}";
+
+        assert_eq!(
+            render(template, &report).unwrap(),
+            "a 1.0.0: 3\nb 1.0.0: 2\ntotal: 5 in 2 packages, grade B"
+        );
+    }
+
+    #[rstest]
+    fn render_test_with_ignored_package() {
+        let mut report = SafetyReport::default();
+        for entry in [report_entry("a", 3), report_entry("b", 2)] {
+            report.packages.insert(entry.package.id.clone(), entry);
+        }
+        let ignored_package_id = report_entry("b", 2).package.id;
+        report.ignored_packages.insert(ignored_package_id);
+        let template = "{{ for package in packages }}\
+            {package.name}: {package.ignored} \
+            {{ endfor }}\
+            total: {used_unsafe_count}";
+
+        assert_eq!(
+            render(template, &report).unwrap(),
+            "a: false b: true total: 3"
+        );
+    }
+
+    #[rstest]
+    fn render_test_with_unknown_variable() {
+        assert!(render("{unknown}", &SafetyReport::default()).is_err());
+    }
+}
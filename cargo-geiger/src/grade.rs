@@ -0,0 +1,102 @@
+use cargo_geiger_serde::{CounterBlock, SafetyGrade};
+
+/// The safety grade of a dependency tree, from the totals of its packages and
+/// the number of packages using `unsafe` in code used by the build. Ignored
+/// packages are expected to be left out of both.
+///
+/// The grade is the worse of the grade for the number of packages using
+/// `unsafe` and the grade for the ratio of the `unsafe` usage used by the
+/// build to all the `unsafe` usage found, i.e. the `x/y` of the table summed
+/// over all the categories:
+///
+/// | Grade | Packages using `unsafe` | Used / found `unsafe` usage |
+/// |-------|-------------------------|-----------------------------|
+/// | A     | 0                       | 0%                          |
+/// | B     | 1 to 2                  | at most 25%                 |
+/// | C     | 3 to 5                  | at most 50%                 |
+/// | D     | 6 to 10                 | at most 75%                 |
+/// | F     | more than 10            | more than 75%               |
+pub fn safety_grade(
+    used: &CounterBlock,
+    unused: &CounterBlock,
+    unsafe_package_count: u64,
+) -> SafetyGrade {
+    let used_unsafe_count = unsafe_count(used);
+    let found_unsafe_count = used_unsafe_count + unsafe_count(unused);
+
+    let package_count_grade = match unsafe_package_count {
+        0 => SafetyGrade::A,
+        1..=2 => SafetyGrade::B,
+        3..=5 => SafetyGrade::C,
+        6..=10 => SafetyGrade::D,
+        _ => SafetyGrade::F,
+    };
+    // Integer comparisons of the ratio, to keep the thresholds exact.
+    let ratio_grade = if used_unsafe_count == 0 {
+        SafetyGrade::A
+    } else if used_unsafe_count * 4 <= found_unsafe_count {
+        SafetyGrade::B
+    } else if used_unsafe_count * 2 <= found_unsafe_count {
+        SafetyGrade::C
+    } else if used_unsafe_count * 4 <= found_unsafe_count * 3 {
+        SafetyGrade::D
+    } else {
+        SafetyGrade::F
+    };
+
+    package_count_grade.max(ratio_grade)
+}
+
+fn unsafe_count(counter_block: &CounterBlock) -> u64 {
+    counter_block.functions.unsafe_
+        + counter_block.exprs.unsafe_
+        + counter_block.item_impls.unsafe_
+        + counter_block.item_traits.unsafe_
+        + counter_block.methods.unsafe_
+}
+
+#[cfg(test)]
+mod grade_tests {
+    use super::*;
+
+    use cargo_geiger_serde::Count;
+    use rstest::*;
+
+    #[rstest(
+        input_used_unsafe_exprs,
+        input_unused_unsafe_exprs,
+        input_unsafe_package_count,
+        expected_safety_grade,
+        case(0, 0, 0, SafetyGrade::A),
+        case(0, 10, 0, SafetyGrade::A),
+        case(1, 3, 1, SafetyGrade::B),
+        case(1, 3, 3, SafetyGrade::C),
+        case(2, 2, 1, SafetyGrade::C),
+        case(3, 1, 2, SafetyGrade::D),
+        case(3, 1, 11, SafetyGrade::F),
+        case(4, 1, 1, SafetyGrade::F)
+    )]
+    fn safety_grade_test(
+        input_used_unsafe_exprs: u64,
+        input_unused_unsafe_exprs: u64,
+        input_unsafe_package_count: u64,
+        expected_safety_grade: SafetyGrade,
+    ) {
+        let counter_block = |unsafe_exprs: u64| CounterBlock {
+            exprs: Count {
+                safe: 10,
+                unsafe_: unsafe_exprs,
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            safety_grade(
+                &counter_block(input_used_unsafe_exprs),
+                &counter_block(input_unused_unsafe_exprs),
+                input_unsafe_package_count,
+            ),
+            expected_safety_grade
+        );
+    }
+}
@@ -6,15 +6,24 @@ use crate::args::{Args, DepsArgs, TargetArgs};
 use crate::cli::get_cfgs;
 use crate::mapping::{
     CargoMetadataParameters, DepsNotReplaced, MatchesIgnoringSource,
+    ToCargoGeigerDependencyKind, ToCargoGeigerPackageId,
 };
 
 use cargo::util::CargoResult;
-use cargo_metadata::{Dependency, DependencyKind, Package, PackageId};
+use cargo::{CliError, CliResult};
+use cargo_geiger_serde::{DependencyEdge, DependencyGraph};
+use cargo_metadata::{
+    Dependency, DependencyKind, Metadata, Package, PackageId,
+};
 use cargo_platform::Cfg;
+use krates::PkgSpec;
 use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 /// Representation of the package dependency graph
 pub struct Graph {
@@ -37,7 +46,11 @@ pub fn build_graph<'a>(
         &args.deps_args,
         &args.target_args,
     );
-    let cfgs = get_cfgs(global_rustc_path, &args.target_args.target)?;
+    let cfgs = get_cfgs(
+        global_rustc_path,
+        &args.target_args.target,
+        &args.target_args.rustflags,
+    )?;
 
     let mut graph = Graph {
         graph: petgraph::Graph::new(),
@@ -54,6 +67,13 @@ pub fn build_graph<'a>(
         target,
         cfgs: cfgs.as_deref(),
         extra_deps,
+        only_path_deps: args.only_path_deps,
+        // The specs were checked when the arguments were parsed.
+        excluded_packages: args
+            .exclude
+            .iter()
+            .filter_map(|package_spec| PkgSpec::from_str(package_spec).ok())
+            .collect(),
     };
 
     while let Some(package_id) = pending_packages.pop() {
@@ -71,10 +91,285 @@ pub fn build_graph<'a>(
     Ok(graph)
 }
 
+/// Write the dependency graph to `path` as Json, for `--dump-graph-json`
+pub fn dump_graph_json(
+    graph: &Graph,
+    metadata: &Metadata,
+    path: &Path,
+    root_package_id: &PackageId,
+) -> CliResult {
+    let dependency_graph =
+        to_dependency_graph(graph, metadata, root_package_id);
+    let json = serde_json::to_string(&dependency_graph).unwrap();
+    fs::write(path, json).map_err(|e| {
+        eprintln!(
+            "Failed to write the dependency graph to file: {}",
+            path.display()
+        );
+        CliError::new(anyhow::Error::from(e), 1)
+    })
+}
+
+/// The serializable form of the dependency graph, with the nodes and edges
+/// sorted by package id for a stable output.
+pub fn to_dependency_graph(
+    graph: &Graph,
+    metadata: &Metadata,
+    root_package_id: &PackageId,
+) -> DependencyGraph {
+    let mut nodes = graph
+        .graph
+        .node_weights()
+        .filter_map(|package_id| {
+            package_id.to_cargo_geiger_package_id(metadata)
+        })
+        .collect::<Vec<_>>();
+    nodes.sort();
+    let mut edges = graph
+        .graph
+        .edge_references()
+        .filter_map(|edge| {
+            Some(DependencyEdge {
+                from: graph.graph[edge.source()]
+                    .to_cargo_geiger_package_id(metadata)?,
+                to: graph.graph[edge.target()]
+                    .to_cargo_geiger_package_id(metadata)?,
+                kind: edge.weight().to_cargo_geiger_dependency_kind()?,
+            })
+        })
+        .collect::<Vec<_>>();
+    edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+    DependencyGraph {
+        root: root_package_id
+            .to_cargo_geiger_package_id(metadata)
+            .expect("the root package is in the metadata"),
+        nodes,
+        edges,
+    }
+}
+
+/// Find the dependency paths from the root package which are deeper than
+/// `max_depth`, where the direct dependencies of the root package are at depth
+/// 1. Paths are cut off at the first package past `max_depth`, and only one
+/// path is returned for each such package.
+pub fn find_paths_deeper_than(
+    graph: &Graph,
+    root_package_id: &PackageId,
+    max_depth: usize,
+) -> Vec<Vec<PackageId>> {
+    let mut paths = Vec::new();
+    let mut path = vec![graph.nodes[root_package_id]];
+    let mut deepest_visits = HashMap::new();
+    let mut reported = HashSet::new();
+
+    visit_paths_deeper_than(
+        graph,
+        max_depth,
+        &mut path,
+        &mut deepest_visits,
+        &mut reported,
+        &mut paths,
+    );
+
+    paths
+}
+
+fn visit_paths_deeper_than(
+    graph: &Graph,
+    max_depth: usize,
+    path: &mut Vec<NodeIndex>,
+    deepest_visits: &mut HashMap<NodeIndex, usize>,
+    reported: &mut HashSet<NodeIndex>,
+    paths: &mut Vec<Vec<PackageId>>,
+) {
+    let node_index = *path.last().unwrap();
+    let depth = path.len() - 1;
+
+    if depth > max_depth {
+        if reported.insert(node_index) {
+            paths.push(
+                path.iter()
+                    .map(|index| graph.graph[*index].clone())
+                    .collect(),
+            );
+        }
+        return;
+    }
+
+    for dependency_index in graph.graph.neighbors(node_index) {
+        // A package which was already visited at the same depth or deeper
+        // can't lead to any path deeper than what was already found.
+        let already_visited_deeper = deepest_visits
+            .get(&dependency_index)
+            .map_or(false, |&deepest| deepest > depth);
+        if already_visited_deeper || path.contains(&dependency_index) {
+            continue;
+        }
+        deepest_visits.insert(dependency_index, depth + 1);
+
+        path.push(dependency_index);
+        visit_paths_deeper_than(
+            graph,
+            max_depth,
+            path,
+            deepest_visits,
+            reported,
+            paths,
+        );
+        path.pop();
+    }
+}
+
+/// Warn about the dependency paths from the root package deeper than
+/// `max_depth`, for `--max-depth-warn`, whatever the output format. Returns
+/// the number of warnings.
+pub fn warn_paths_deeper_than(
+    graph: &Graph,
+    root_package_id: &PackageId,
+    max_depth: usize,
+) -> u64 {
+    let paths = find_paths_deeper_than(graph, root_package_id, max_depth);
+    for path in &paths {
+        eprintln!(
+            "WARNING: Dependency path deeper than {}: {}",
+            max_depth,
+            path.iter()
+                .map(|package_id| package_id.to_string())
+                .collect::<Vec<String>>()
+                .join(" -> ")
+        );
+    }
+    paths.len() as u64
+}
+
+/// Warn about the entries of `--exclude` matching no package of the resolved
+/// dependencies, likely misspelled or left over from a removed dependency.
+/// Returns the number of warnings.
+pub fn warn_excluded_packages_not_found(
+    exclude: &[String],
+    metadata: &Metadata,
+) -> u64 {
+    let mut warning_count = 0;
+    for package_spec in exclude {
+        let found = PkgSpec::from_str(package_spec).map_or(false, |spec| {
+            metadata
+                .packages
+                .iter()
+                .any(|package| spec.matches(package))
+        });
+        if !found {
+            eprintln!(
+                "WARNING: No crate of the dependencies matches --exclude {}",
+                package_spec
+            );
+            warning_count += 1;
+        }
+    }
+    warning_count
+}
+
+/// Exit with an error if the dependency graph has more crates than
+/// `max_total_crates`, for `--max-total-crates`.
+pub fn check_max_total_crates(
+    graph: &Graph,
+    max_total_crates: usize,
+) -> CliResult {
+    let total_crates = graph.graph.node_count();
+    if total_crates > max_total_crates {
+        eprintln!(
+            "The dependency graph has {} crates, more than the maximum of {} set with --max-total-crates",
+            total_crates, max_total_crates
+        );
+        return Err(CliError::code(1));
+    }
+    Ok(())
+}
+
+/// Exit with an error if a package of the dependency graph has no source URL,
+/// listing the offenders, for `--require-source-url`.
+pub fn check_require_source_url(
+    graph: &Graph,
+    metadata: &Metadata,
+) -> CliResult {
+    let packages = packages_without_source_url(graph, metadata);
+    if !packages.is_empty() {
+        eprintln!(
+            "The following crates have no repository in their manifest, nor a git source, as --require-source-url requires:"
+        );
+        for package in packages {
+            eprintln!("    {} {}", package.name, package.version);
+        }
+        return Err(CliError::code(1));
+    }
+    Ok(())
+}
+
+/// The packages of the dependency graph with neither a `repository` in their
+/// manifest nor a git source, sorted.
+pub fn packages_without_source_url<'a>(
+    graph: &Graph,
+    metadata: &'a Metadata,
+) -> Vec<&'a Package> {
+    let mut packages = metadata
+        .packages
+        .iter()
+        .filter(|package| graph.nodes.contains_key(&package.id))
+        .filter(|package| {
+            package
+                .repository
+                .as_ref()
+                .map_or(true, |repository| repository.trim().is_empty())
+                && !package
+                    .source
+                    .as_ref()
+                    .map_or(false, |source| source.repr.starts_with("git+"))
+        })
+        .collect::<Vec<&Package>>();
+    packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    packages
+}
+
+/// The packages which are only built for build scripts: the packages of the
+/// graph which can't be reached from the root package without going through
+/// a build dependency. The dependencies of the build dependencies are
+/// included, unless the root package also depends on them at run time.
+pub fn build_only_package_ids(
+    graph: &Graph,
+    root_package_id: &PackageId,
+) -> HashSet<PackageId> {
+    let mut runtime_indices = HashSet::new();
+    let mut pending_indices = vec![graph.nodes[root_package_id]];
+    while let Some(node_index) = pending_indices.pop() {
+        if !runtime_indices.insert(node_index) {
+            continue;
+        }
+        pending_indices.extend(
+            graph
+                .graph
+                .edges(node_index)
+                .filter(|edge| *edge.weight() != DependencyKind::Build)
+                .map(|edge| edge.target()),
+        );
+    }
+
+    graph
+        .nodes
+        .iter()
+        .filter(|(_, node_index)| !runtime_indices.contains(*node_index))
+        .map(|(package_id, _)| package_id.clone())
+        .collect()
+}
+
 struct GraphConfiguration<'a> {
     target: Option<&'a str>,
     cfgs: Option<&'a [Cfg]>,
     extra_deps: ExtraDeps,
+    /// Leave out the dependencies whose source is not a local path, for
+    /// `--only-path-deps`.
+    only_path_deps: bool,
+    /// The specs of the dependencies left out along with the dependencies
+    /// only reachable through them, for `--exclude`.
+    excluded_packages: Vec<PkgSpec>,
 }
 
 fn add_graph_node_if_not_present_and_edge(
@@ -119,6 +414,21 @@ fn add_package_dependencies_to_graph(
             let package = krates_node.krate.clone();
 
             for (dependency_package_id, _) in dependencies {
+                if graph_configuration.only_path_deps
+                    && !is_path_package(
+                        cargo_metadata_parameters,
+                        &dependency_package_id,
+                    )
+                {
+                    continue;
+                }
+                if is_excluded_package(
+                    cargo_metadata_parameters,
+                    &dependency_package_id,
+                    &graph_configuration.excluded_packages,
+                ) {
+                    continue;
+                }
                 let dependency_iterator = filter_dependencies(
                     cargo_metadata_parameters,
                     &dependency_package_id,
@@ -167,6 +477,34 @@ fn build_graph_prerequisites<'a>(
     (extra_deps, target)
 }
 
+/// Whether the source of the package is a local path. Packages from a registry
+/// or a git repository have a source in the metadata, unless they are patched
+/// with a path.
+fn is_path_package(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    package_id: &PackageId,
+) -> bool {
+    cargo_metadata_parameters
+        .krates
+        .node_for_kid(package_id)
+        .map_or(false, |krates_node| krates_node.krate.source.is_none())
+}
+
+fn is_excluded_package(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    package_id: &PackageId,
+    excluded_packages: &[PkgSpec],
+) -> bool {
+    cargo_metadata_parameters
+        .krates
+        .node_for_kid(package_id)
+        .map_or(false, |krates_node| {
+            excluded_packages
+                .iter()
+                .any(|package_spec| package_spec.matches(&krates_node.krate))
+        })
+}
+
 fn filter_dependencies<'a>(
     cargo_metadata_parameters: &'a CargoMetadataParameters,
     dependency_package_id: &'a PackageId,
@@ -203,8 +541,184 @@ fn filter_dependencies<'a>(
 #[cfg(test)]
 mod graph_tests {
     use super::*;
+
+    use crate::lib_tests::construct_krates_and_metadata;
     use rstest::*;
 
+    #[rstest(
+        input_max_depth,
+        expected_paths,
+        case(3, vec![]),
+        case(2, vec![vec!["root", "a", "b", "c"]]),
+        case(1, vec![vec!["root", "a", "b"]]),
+        case(0, vec![vec!["root", "a"], vec!["root", "d"]])
+    )]
+    fn find_paths_deeper_than_test(
+        input_max_depth: usize,
+        expected_paths: Vec<Vec<&str>>,
+    ) {
+        // root -> a -> b -> c, root -> d -> b, and c -> root as a cycle.
+        let mut graph = Graph {
+            graph: petgraph::Graph::new(),
+            nodes: HashMap::new(),
+        };
+        for name in &["root", "a", "b", "c", "d"] {
+            let package_id = PackageId {
+                repr: String::from(*name),
+            };
+            let node_index = graph.graph.add_node(package_id.clone());
+            graph.nodes.insert(package_id, node_index);
+        }
+        for (from, to) in &[
+            ("root", "d"),
+            ("d", "b"),
+            ("root", "a"),
+            ("a", "b"),
+            ("b", "c"),
+            ("c", "root"),
+        ] {
+            let from_index = graph.nodes[&PackageId {
+                repr: String::from(*from),
+            }];
+            let to_index = graph.nodes[&PackageId {
+                repr: String::from(*to),
+            }];
+            graph
+                .graph
+                .add_edge(from_index, to_index, DependencyKind::Normal);
+        }
+
+        let paths = find_paths_deeper_than(
+            &graph,
+            &PackageId {
+                repr: String::from("root"),
+            },
+            input_max_depth,
+        );
+
+        let mut path_names = paths
+            .iter()
+            .map(|path| {
+                path.iter()
+                    .map(|package_id| package_id.repr.as_str())
+                    .collect::<Vec<&str>>()
+            })
+            .collect::<Vec<Vec<&str>>>();
+        path_names.sort();
+
+        assert_eq!(path_names, expected_paths);
+    }
+
+    #[rstest(
+        input_max_total_crates,
+        expected_is_ok,
+        case(3, true),
+        case(2, true),
+        case(1, false)
+    )]
+    fn check_max_total_crates_test(
+        input_max_total_crates: usize,
+        expected_is_ok: bool,
+    ) {
+        let mut graph = Graph {
+            graph: petgraph::Graph::new(),
+            nodes: HashMap::new(),
+        };
+        for name in &["root", "a"] {
+            let package_id = PackageId {
+                repr: String::from(*name),
+            };
+            let node_index = graph.graph.add_node(package_id.clone());
+            graph.nodes.insert(package_id, node_index);
+        }
+
+        assert_eq!(
+            check_max_total_crates(&graph, input_max_total_crates).is_ok(),
+            expected_is_ok
+        );
+    }
+
+    #[rstest(
+        input_repository,
+        expected_has_source_url,
+        case(None, false),
+        case(Some(""), false),
+        case(Some("https://github.com/rust-secure-code/cargo-geiger"), true)
+    )]
+    fn packages_without_source_url_test(
+        input_repository: Option<&str>,
+        expected_has_source_url: bool,
+    ) {
+        let (_, mut metadata) = construct_krates_and_metadata();
+        let root_package_id = metadata.root_package().unwrap().id.clone();
+        for package in &mut metadata.packages {
+            if package.id == root_package_id {
+                package.repository = input_repository.map(String::from);
+            }
+        }
+        let mut graph = Graph {
+            graph: petgraph::Graph::new(),
+            nodes: HashMap::new(),
+        };
+        let node_index = graph.graph.add_node(root_package_id.clone());
+        graph.nodes.insert(root_package_id.clone(), node_index);
+
+        let packages = packages_without_source_url(&graph, &metadata);
+
+        assert_eq!(packages.is_empty(), expected_has_source_url);
+        assert_eq!(
+            check_require_source_url(&graph, &metadata).is_ok(),
+            expected_has_source_url
+        );
+    }
+
+    #[rstest]
+    fn build_only_package_ids_test() {
+        // root -> a -> b, root -(build)-> c -> b, c -> d and a -(build)-> e.
+        let mut graph = Graph {
+            graph: petgraph::Graph::new(),
+            nodes: HashMap::new(),
+        };
+        for name in &["root", "a", "b", "c", "d", "e"] {
+            let package_id = PackageId {
+                repr: String::from(*name),
+            };
+            let node_index = graph.graph.add_node(package_id.clone());
+            graph.nodes.insert(package_id, node_index);
+        }
+        for (from, to, kind) in &[
+            ("root", "a", DependencyKind::Normal),
+            ("a", "b", DependencyKind::Normal),
+            ("root", "c", DependencyKind::Build),
+            ("c", "b", DependencyKind::Normal),
+            ("c", "d", DependencyKind::Normal),
+            ("a", "e", DependencyKind::Build),
+        ] {
+            let from_index = graph.nodes[&PackageId {
+                repr: String::from(*from),
+            }];
+            let to_index = graph.nodes[&PackageId {
+                repr: String::from(*to),
+            }];
+            graph.graph.add_edge(from_index, to_index, *kind);
+        }
+
+        let package_ids = build_only_package_ids(
+            &graph,
+            &PackageId {
+                repr: String::from("root"),
+            },
+        );
+
+        let mut package_names = package_ids
+            .iter()
+            .map(|package_id| package_id.repr.as_str())
+            .collect::<Vec<&str>>();
+        package_names.sort_unstable();
+
+        assert_eq!(package_names, vec!["c", "d", "e"]);
+    }
+
     #[rstest(
         input_deps_args,
         expected_extra_deps,
@@ -263,20 +777,26 @@ mod graph_tests {
         case(
             TargetArgs {
                 all_targets: true,
-                target: None
+                rustflags: vec![],
+                target: None,
+                target_from_config: false
             },
             None
         ),
         case(
             TargetArgs {
                 all_targets: false,
-                target: None
+                rustflags: vec![],
+                target: None,
+                target_from_config: false
             },
             Some("default_config_host")),
         case(
             TargetArgs {
                 all_targets: false,
+                rustflags: vec![],
                 target: Some(String::from("provided_config_host")),
+                target_from_config: false
             },
             Some("provided_config_host")
         )
@@ -7,18 +7,45 @@
 #![forbid(unsafe_code)]
 //#![deny(warnings)]
 
+/// Cross-referencing of crates with a local RustSec advisory database
+pub mod advisories;
 /// Argument parsing
 pub mod args;
+/// Verification of the downloaded crates against the lockfile checksums
+pub mod checksums;
 /// Bootstrapping functions for structs required by the CLI
 pub mod cli;
+/// Counting of the matches of user given patterns in the scanned files
+pub mod custom_patterns;
+/// Comparison with a baseline report from an earlier run
+pub mod diff;
+/// Summary of the gate which made a run fail, for `--fail-summary-file`
+pub mod fail_summary;
+/// Crates counted as forbidding unsafe code, for `--forbid-whitelist`
+pub mod forbid_whitelist;
+/// Safety grade of a dependency tree
+pub mod grade;
 /// Construction of the dependency graph
 pub mod graph;
 /// Mapping functionality from `cargo::core` to `cargo_metadata`
 pub mod mapping;
+/// Running the `--on-complete` command with the report
+pub mod on_complete;
 /// Interaction with README.md files
 pub mod readme;
 /// Functions for scanning projects for unsafe code
 pub mod scan;
+/// Severity levels of the kinds of unsafe code, for `--severity-map`
+pub mod severity;
+/// Spinner shown while the dependencies are resolved
+pub mod spinner;
+/// Export of the report to a SQLite database, for `--sqlite`
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+/// Scanning of the standard library source, for comparison
+pub mod std_lib;
+/// Weighting of unsafe usage categories into a risk score
+pub mod weights;
 
 /// Inner display formatting
 mod format;
@@ -12,20 +12,40 @@ extern crate petgraph;
 extern crate strum;
 extern crate strum_macros;
 
-use cargo_geiger::args::{Args, HELP};
-use cargo_geiger::cli::{get_cargo_metadata, get_krates, get_workspace};
-use cargo_geiger::graph::build_graph;
+use cargo_geiger::args::{effective_config, format_help, Args, HELP};
+use cargo_geiger::checksums::verify_checksums;
+use cargo_geiger::cli::{
+    check_strict_features, get_cargo_metadata,
+    get_cargo_metadata_without_feature, get_inactive_optional_package_ids,
+    get_krates, get_root_package_ids, get_rustflags_from_config,
+    get_target_from_config, get_workspace, get_workspace_member_names,
+};
+use cargo_geiger::fail_summary::{fail_summary_crates, with_fail_summary};
+use cargo_geiger::format::print_config::PrintConfig;
+use cargo_geiger::graph::{
+    build_graph, check_max_total_crates, check_require_source_url,
+    dump_graph_json, packages_without_source_url,
+    warn_excluded_packages_not_found, warn_paths_deeper_than,
+};
 use cargo_geiger::mapping::{CargoMetadataParameters, QueryResolve};
+use cargo_geiger::on_complete::run_on_complete_command;
 use cargo_geiger::readme::create_or_replace_section_in_readme;
-use cargo_geiger::scan::{scan, FoundWarningsError, ScanResult};
+use cargo_geiger::scan::{
+    check_package_forbids_unsafe, scan, scan_compare_published,
+    scan_compare_targets, scan_feature_impact, FailThresholdExceededError,
+    FoundWarningsError, ScanResult, UnsafeFoundError, UsedUnsafeExprCount,
+};
+use cargo_geiger::spinner::Spinner;
 
 use cargo::core::shell::Shell;
 use cargo::util::important_paths;
 use cargo::{CliError, CliResult, GlobalContext as Config};
+use cargo_geiger_serde::{FailGate, FailSummaryCrate};
+use std::iter;
 
 const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
 
-fn cli_result_main(args: &Args) -> CliResult {
+fn cli_result_main(args: &mut Args) -> CliResult {
     if args.version {
         println!("cargo-geiger {}", VERSION.unwrap_or("unknown version"));
         return Ok(());
@@ -34,12 +54,37 @@ fn cli_result_main(args: &Args) -> CliResult {
         println!("{}", HELP);
         return Ok(());
     }
+    if args.format_help {
+        println!("{}", format_help());
+        return Ok(());
+    }
 
     let mut config = Config::default()?;
     args.update_config(&mut config)?;
 
+    if args.target_args.target_from_config && args.target_args.target.is_none()
+    {
+        args.target_args.target = get_target_from_config(&config)?;
+    }
+    args.target_args.rustflags = get_rustflags_from_config(&config)?;
+
+    if args.print_config {
+        println!("{}", effective_config(args, &PrintConfig::new(args)?));
+        return Ok(());
+    }
+
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .map_err(|e| CliError::new(e.into(), 1))?;
+    }
+
+    let spinner = Spinner::start("Resolving dependencies", &config);
     let cargo_metadata = get_cargo_metadata(args, &config)?;
     let krates = get_krates(&cargo_metadata)?;
+    let inactive_optional_package_ids =
+        get_inactive_optional_package_ids(args, &config, &cargo_metadata)?;
 
     let cargo_metadata_parameters = CargoMetadataParameters {
         metadata: &cargo_metadata,
@@ -48,69 +93,340 @@ fn cli_result_main(args: &Args) -> CliResult {
 
     let workspace = get_workspace(&config, args.manifest_path.clone())?;
 
-    let cargo_metadata_root_package_id = if let Some(
-        cargo_metadata_root_package,
-    ) = cargo_metadata.root_package()
-    {
-        cargo_metadata_root_package.id.clone()
+    let root_package_ids = get_root_package_ids(args, &cargo_metadata, &krates);
+    spinner.stop();
+
+    let root_package_ids = if let Some(root_package_ids) = root_package_ids {
+        root_package_ids
     } else {
         eprintln!(
-            "manifest path `{}` is a virtual manifest, but this command requires running against an actual package in this workspace",
+            "manifest path `{}` is a virtual manifest, but this command requires running against an actual package in this workspace\n\
+             Pass --package <SPEC> to scan one of its members, or --workspace to scan all of them.\n\
+             Workspace members: {}",
             match args.manifest_path.clone() {
                 Some(path) => path,
                 None => important_paths::find_root_manifest_for_wd(config.cwd())?,
-            }.as_os_str().to_str().unwrap()
+            }.as_os_str().to_str().unwrap(),
+            get_workspace_member_names(&cargo_metadata).join(", ")
         );
 
         return Err(CliError::code(1));
     };
 
+    if args.strict_features {
+        check_strict_features(args, &cargo_metadata, &root_package_ids)?;
+    }
+
+    if args.verify_checksums {
+        verify_checksums(&cargo_metadata).map_err(|e| {
+            eprintln!(
+                "The source of a registry crate doesn't match the checksum of Cargo.lock, it may have been tampered with"
+            );
+            CliError::new(anyhow::Error::new(e), 1)
+        })?;
+    }
+
+    if root_package_ids.len() > 1
+        && (args.compare_published
+            || args.compare_targets.is_some()
+            || args.dump_graph_json.is_some()
+            || args.feature_impact.is_some())
+    {
+        eprintln!(
+            "--compare-published, --compare-targets, --dump-graph-json and --feature-impact require a single root package, pass --package instead of --workspace"
+        );
+
+        return Err(CliError::code(1));
+    }
+    let cargo_metadata_root_package_id = root_package_ids[0].clone();
+
     let global_rustc = config.load_global_rustc(Some(&workspace))?;
 
-    let graph = build_graph(
-        args,
-        &cargo_metadata_parameters,
-        &global_rustc.host,
-        &global_rustc.path,
-        cargo_metadata_root_package_id.clone(),
-    )?;
-
-    let query_resolve_root_package_id = args.package.as_ref().map_or(
-        cargo_metadata_root_package_id.clone(),
-        |package_query| {
-            krates
-                .query_resolve(package_query)
-                .map_or(cargo_metadata_root_package_id, |package_id| package_id)
-        },
-    );
-
-    let ScanResult {
-        scan_output_lines,
-        warning_count,
-    } = scan(
-        args,
-        &cargo_metadata_parameters,
-        &config,
-        &graph,
-        query_resolve_root_package_id,
-        &workspace,
-    )?;
-
-    if args.readme_args.update_readme {
-        create_or_replace_section_in_readme(
-            &args.readme_args,
-            &scan_output_lines,
+    if let Some((first_target, second_target)) = args.compare_targets.clone() {
+        args.target_args.all_targets = false;
+        let mut target_graphs = Vec::new();
+        for target in vec![first_target, second_target] {
+            args.target_args.target = Some(target.clone());
+            let graph = build_graph(
+                args,
+                &cargo_metadata_parameters,
+                &global_rustc.host,
+                &global_rustc.path,
+                cargo_metadata_root_package_id.clone(),
+            )?;
+            target_graphs.push((target, graph));
+        }
+
+        let ScanResult {
+            scan_output_lines, ..
+        } = scan_compare_targets(
+            args,
+            &cargo_metadata_parameters,
+            &config,
+            &target_graphs,
         )?;
-    } else {
         for scan_output_line in scan_output_lines {
             println!("{}", scan_output_line);
         }
+        return Ok(());
     }
 
+    if args.compare_published {
+        let ScanResult {
+            scan_output_lines, ..
+        } = scan_compare_published(
+            args,
+            &cargo_metadata_parameters,
+            &cargo_metadata_root_package_id,
+        )?;
+        for scan_output_line in scan_output_lines {
+            println!("{}", scan_output_line);
+        }
+        return Ok(());
+    }
+
+    if let Some(feature) = args.feature_impact.clone() {
+        let graph = build_graph(
+            args,
+            &cargo_metadata_parameters,
+            &global_rustc.host,
+            &global_rustc.path,
+            cargo_metadata_root_package_id.clone(),
+        )?;
+
+        let cargo_metadata_without_feature =
+            get_cargo_metadata_without_feature(
+                args,
+                &config,
+                &cargo_metadata,
+                &cargo_metadata_root_package_id,
+                &feature,
+            )?;
+        let krates_without_feature =
+            get_krates(&cargo_metadata_without_feature)?;
+        let graph_without_feature = build_graph(
+            args,
+            &CargoMetadataParameters {
+                metadata: &cargo_metadata_without_feature,
+                krates: &krates_without_feature,
+            },
+            &global_rustc.host,
+            &global_rustc.path,
+            cargo_metadata_root_package_id,
+        )?;
+
+        let ScanResult {
+            scan_output_lines, ..
+        } = scan_feature_impact(
+            args,
+            &cargo_metadata_parameters,
+            &config,
+            &feature,
+            &graph,
+            &graph_without_feature,
+        )?;
+        for scan_output_line in scan_output_lines {
+            println!("{}", scan_output_line);
+        }
+        return Ok(());
+    }
+
+    let mut scan_output_lines = Vec::new();
+    let mut warning_count =
+        warn_excluded_packages_not_found(&args.exclude, &cargo_metadata);
+    let mut unsafe_free = true;
+    let mut used_unsafe_expr_count: Option<UsedUnsafeExprCount> = None;
+    let mut query_resolve_root_package_ids = Vec::new();
+    let fail_summary_file = args.fail_summary_file.clone();
+    let fail_with_summary = |gate, crates, error| {
+        with_fail_summary(fail_summary_file.as_deref(), gate, crates, error)
+    };
+    for root_package_id in root_package_ids {
+        let graph = build_graph(
+            args,
+            &cargo_metadata_parameters,
+            &global_rustc.host,
+            &global_rustc.path,
+            root_package_id.clone(),
+        )?;
+
+        if let Some(max_total_crates) = args.max_total_crates {
+            check_max_total_crates(&graph, max_total_crates).map_err(|e| {
+                fail_with_summary(FailGate::MaxTotalCrates, vec![], e)
+            })?;
+        }
+
+        if args.require_source_url {
+            check_require_source_url(&graph, &cargo_metadata).map_err(|e| {
+                fail_with_summary(
+                    FailGate::RequireSourceUrl,
+                    fail_summary_crates(
+                        packages_without_source_url(&graph, &cargo_metadata)
+                            .into_iter()
+                            .map(|package| &package.id),
+                        &cargo_metadata,
+                    ),
+                    e,
+                )
+            })?;
+        }
+
+        if let Some(dump_graph_json_path) = &args.dump_graph_json {
+            dump_graph_json(
+                &graph,
+                &cargo_metadata,
+                dump_graph_json_path,
+                &root_package_id,
+            )?;
+        }
+
+        // With --workspace, each member is the root of its own tree.
+        let query_resolve_root_package_id = match &args.package {
+            Some(package_query) if !args.workspace => krates
+                .query_resolve(package_query)
+                .map_or(root_package_id, |package_id| package_id),
+            _ => root_package_id,
+        };
+
+        let scan_result = scan(
+            args,
+            &cargo_metadata_parameters,
+            &config,
+            &graph,
+            &inactive_optional_package_ids,
+            query_resolve_root_package_id.clone(),
+            &workspace,
+        )?;
+        scan_output_lines.extend(scan_result.scan_output_lines);
+        warning_count += scan_result.warning_count;
+        if let Some(max_depth) = args.max_depth_warn {
+            warning_count += warn_paths_deeper_than(
+                &graph,
+                &query_resolve_root_package_id,
+                max_depth,
+            );
+        }
+        unsafe_free &= scan_result.unsafe_free;
+        // With --workspace, each member is compared on its own.
+        if scan_result.used_unsafe_expr_count.as_ref().map(|c| c.total)
+            > used_unsafe_expr_count.as_ref().map(|c| c.total)
+        {
+            used_unsafe_expr_count = scan_result.used_unsafe_expr_count;
+        }
+        query_resolve_root_package_ids.push(query_resolve_root_package_id);
+    }
+
+    let check_root_package_forbids_unsafe = || {
+        if args.fail_if_no_forbid {
+            query_resolve_root_package_ids.iter().try_for_each(
+                |query_resolve_root_package_id| {
+                    check_package_forbids_unsafe(
+                        &cargo_metadata,
+                        query_resolve_root_package_id,
+                    )
+                    .map_err(|e| {
+                        fail_with_summary(
+                            FailGate::FailIfNoForbid,
+                            fail_summary_crates(
+                                iter::once(query_resolve_root_package_id),
+                                &cargo_metadata,
+                            ),
+                            e,
+                        )
+                    })
+                },
+            )
+        } else {
+            Ok(())
+        }
+    };
+
+    // With --fail-threshold, the unsafe usage up to the threshold is clean.
+    let clean = match (args.fail_threshold, &used_unsafe_expr_count) {
+        (Some(fail_threshold), Some(used_unsafe_expr_count)) => {
+            used_unsafe_expr_count.total <= fail_threshold
+        }
+        _ => unsafe_free,
+    };
+    let quiet = args.quiet_unsafe_free && clean;
+
+    if !quiet {
+        if args.readme_args.update_readme {
+            create_or_replace_section_in_readme(
+                &args.readme_args,
+                args.manifest_path.as_deref(),
+                &scan_output_lines,
+            )?;
+        } else {
+            for scan_output_line in &scan_output_lines {
+                println!("{}", scan_output_line);
+            }
+        }
+    }
+
+    // The report is printed first, so that it is not lost when the command
+    // fails.
+    if let Some(on_complete_command) = &args.on_complete {
+        run_on_complete_command(
+            on_complete_command,
+            &scan_output_lines.join("\n"),
+        )?;
+    }
+
+    if quiet {
+        return check_root_package_forbids_unsafe();
+    }
+
+    check_root_package_forbids_unsafe()?;
+
     if warning_count > 0 {
-        return Err(CliError::new(
-            anyhow::Error::new(FoundWarningsError { warning_count }),
-            1,
+        return Err(fail_with_summary(
+            FailGate::Warnings,
+            vec![],
+            CliError::new(
+                anyhow::Error::new(FoundWarningsError { warning_count }),
+                1,
+            ),
+        ));
+    }
+
+    if let (Some(fail_threshold), Some(used_unsafe_expr_count)) =
+        (args.fail_threshold, used_unsafe_expr_count)
+    {
+        if used_unsafe_expr_count.total > fail_threshold {
+            let top_packages = used_unsafe_expr_count.top_packages();
+            return Err(fail_with_summary(
+                FailGate::FailThreshold,
+                used_unsafe_expr_count
+                    .packages
+                    .into_iter()
+                    .map(|(package, used)| FailSummaryCrate {
+                        package,
+                        unsafe_exprs: Some(used.exprs.unsafe_),
+                    })
+                    .collect(),
+                CliError::new(
+                    anyhow::Error::new(FailThresholdExceededError {
+                        fail_threshold,
+                        used_unsafe_expr_count: used_unsafe_expr_count.total,
+                        top_packages,
+                    }),
+                    1,
+                ),
+            ));
+        }
+    }
+
+    if args.quiet_unsafe_free || (args.fail_on_any_unsafe && !unsafe_free) {
+        let gate = if args.quiet_unsafe_free {
+            FailGate::QuietUnsafeFree
+        } else {
+            FailGate::FailOnAnyUnsafe
+        };
+        return Err(fail_with_summary(
+            gate,
+            vec![],
+            CliError::new(anyhow::Error::new(UnsafeFoundError), 1),
         ));
     }
 
@@ -118,9 +434,16 @@ fn cli_result_main(args: &Args) -> CliResult {
 }
 
 fn main() {
-    let args = Args::parse_args(pico_args::Arguments::from_env()).unwrap();
-    if let Err(e) = cli_result_main(&args) {
-        let mut shell = Shell::new();
+    let mut shell = Shell::new();
+    // The invalid combinations of flags are user errors, reported as such.
+    let mut args = match Args::parse_args(pico_args::Arguments::from_env()) {
+        Ok(args) => args,
+        Err(e) => cargo::exit_with_error(
+            CliError::new(anyhow::anyhow!("{}", e), 1),
+            &mut shell,
+        ),
+    };
+    if let Err(e) = cli_result_main(&mut args) {
         cargo::exit_with_error(e, &mut shell)
     }
 }
@@ -0,0 +1,102 @@
+use cargo::{CliError, CliResult};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the `--on-complete` command with the report on its stdin. The command
+/// is split on whitespace and spawned directly rather than through a shell,
+/// so the report can't be used for shell injection. A non-zero exit code of
+/// the command becomes the exit code of cargo-geiger.
+pub fn run_on_complete_command(command: &str, report: &str) -> CliResult {
+    let mut command_parts = command.split_whitespace();
+    let program = command_parts.next().ok_or_else(|| {
+        CliError::new(anyhow::Error::new(OnCompleteError::EmptyCommand), 1)
+    })?;
+
+    let spawn_error = |e: io::Error| {
+        CliError::new(
+            anyhow::Error::new(OnCompleteError::Io(e, command.to_string())),
+            1,
+        )
+    };
+
+    let mut child = Command::new(program)
+        .args(command_parts)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(report.as_bytes()) {
+            // The command is not required to read all of its stdin.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            result => result.map_err(spawn_error)?,
+        }
+    }
+    let exit_status = child.wait().map_err(spawn_error)?;
+
+    if exit_status.success() {
+        Ok(())
+    } else {
+        Err(CliError::new(
+            anyhow::Error::new(OnCompleteError::Failed {
+                command: command.to_string(),
+                exit_code: exit_status.code(),
+            }),
+            exit_status.code().unwrap_or(1),
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub enum OnCompleteError {
+    EmptyCommand,
+    /// The exit code is `None` when the command was terminated by a signal.
+    Failed {
+        command: String,
+        exit_code: Option<i32>,
+    },
+    Io(io::Error, String),
+}
+
+impl Error for OnCompleteError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for OnCompleteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod on_complete_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[cfg(unix)]
+    #[rstest(
+        input_command,
+        expected_exit_code,
+        case("grep -q geiger_version", None),
+        case("grep -q not_in_the_report", Some(1)),
+        case("grep -q geiger_version;false", Some(1))
+    )]
+    fn run_on_complete_command_test(
+        input_command: &str,
+        expected_exit_code: Option<i32>,
+    ) {
+        let report = r#"{"metadata":{"geiger_version":"0.11.7"}}"#;
+
+        let result = run_on_complete_command(input_command, report);
+
+        assert_eq!(result.err().map(|e| e.exit_code), expected_exit_code);
+    }
+
+    #[rstest]
+    fn run_on_complete_command_errors_on_empty_command_test() {
+        assert!(run_on_complete_command("  ", "{}").is_err());
+    }
+}
@@ -4,7 +4,7 @@ use cargo::{CliError, CliResult};
 use regex::Regex;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Error, Write};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 /// Name of README FILE
 pub const README_FILENAME: &str = "README.md";
@@ -14,13 +14,17 @@ const CARGO_GEIGER_SAFETY_REPORT_SECTION_HEADER: &str =
 
 /// Taking a `PathBuf` pointing to the README location, and a `&Vec<String>` containing the result
 /// of a scan, either create a section containing the scan result if one does not exist, or replace
-/// the section if it already exists
+/// the section if it already exists. Without `--readme-path`, the README is the one next to the
+/// manifest given to `--manifest-path`, if any.
 pub fn create_or_replace_section_in_readme(
     readme_args: &ReadmeArgs,
+    manifest_path: Option<&Path>,
     scan_output_lines: &[String],
 ) -> CliResult {
-    let readme_path_buf =
-        get_readme_path_buf_from_arguments_or_default(readme_args);
+    let readme_path_buf = get_readme_path_buf_from_arguments_or_default(
+        readme_args,
+        manifest_path,
+    );
 
     if !readme_path_buf.exists() {
         eprintln!(
@@ -39,7 +43,18 @@ pub fn create_or_replace_section_in_readme(
             anyhow::Error::from(e)
         })?;
 
-    update_readme_content(readme_args, &mut readme_content, scan_output_lines);
+    match &readme_args.relative_to {
+        Some(base) => update_readme_content(
+            readme_args,
+            &mut readme_content,
+            &rewrite_paths_relative_to(base, scan_output_lines),
+        ),
+        None => update_readme_content(
+            readme_args,
+            &mut readme_content,
+            scan_output_lines,
+        ),
+    }
 
     write_lines_to_file(&readme_content, &readme_path_buf).map_err(|e| {
         eprintln!(
@@ -101,14 +116,20 @@ fn construct_regex_expression_for_section_header(
     }
 }
 
-/// Returns the `PathBuf` passed in as an argument value if one exists, otherwise
-/// returns the `PathBuf` to a file `README.md` in the current directory
+/// Returns the `PathBuf` passed in as an argument value if one exists, relative to the current
+/// directory, otherwise returns the `PathBuf` to a file `README.md` in the directory of the
+/// manifest if one is given, or in the current directory
 fn get_readme_path_buf_from_arguments_or_default(
     readme_args: &ReadmeArgs,
+    manifest_path: Option<&Path>,
 ) -> PathBuf {
-    match &readme_args.readme_path {
-        Some(readme_path) => readme_path.to_path_buf(),
-        None => {
+    match (&readme_args.readme_path, manifest_path) {
+        (Some(readme_path), _) => readme_path.to_path_buf(),
+        (None, Some(manifest_path)) => manifest_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(README_FILENAME),
+        (None, None) => {
             let mut current_dir_path_buf = std::env::current_dir().unwrap();
             current_dir_path_buf.push(README_FILENAME);
             current_dir_path_buf
@@ -116,6 +137,59 @@ fn get_readme_path_buf_from_arguments_or_default(
     }
 }
 
+/// The path from the directory `base` to `path`, both absolute, going up
+/// through `..` where they diverge
+pub(crate) fn path_relative_to(base: &Path, path: &Path) -> PathBuf {
+    let mut base_components = base.components().peekable();
+    let mut path_components = path.components().peekable();
+    while let (Some(base_component), Some(path_component)) =
+        (base_components.peek(), path_components.peek())
+    {
+        if base_component != path_component {
+            break;
+        }
+        base_components.next();
+        path_components.next();
+    }
+
+    let relative_path = base_components
+        .map(|_| Component::ParentDir)
+        .chain(path_components)
+        .collect::<PathBuf>();
+    if relative_path.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative_path
+    }
+}
+
+/// Rewrite the absolute paths of the scan output lines, e.g. the files used by
+/// the build printed in verbose mode, relative to the directory `base`, for
+/// `--relative-to`.
+fn rewrite_paths_relative_to(
+    base: &Path,
+    scan_output_lines: &[String],
+) -> Vec<String> {
+    // The paths of the scan output are canonical.
+    let base = base.canonicalize().unwrap_or_else(|_| base.to_path_buf());
+    scan_output_lines
+        .iter()
+        .map(|line| {
+            line.split(' ')
+                .map(|word| {
+                    let path = Path::new(word);
+                    if path.is_absolute() {
+                        path_relative_to(&base, path).display().to_string()
+                    } else {
+                        word.to_string()
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect()
+}
+
 /// Read the contents of a file line by line.
 fn read_file_contents(path: &Path) -> Result<Vec<String>, Error> {
     let file = File::open(path)?;
@@ -223,12 +297,69 @@ mod readme_tests {
 
         let scan_result = vec![];
 
-        let result =
-            create_or_replace_section_in_readme(&readme_args, &scan_result);
+        let result = create_or_replace_section_in_readme(
+            &readme_args,
+            None,
+            &scan_result,
+        );
 
         assert!(result.is_err());
     }
 
+    #[rstest]
+    fn create_or_replace_section_test_paths_relative_to_readme_directory() {
+        let temp_dir = tempdir().unwrap();
+        let docs_dir = temp_dir.path().join("docs");
+        std::fs::create_dir(&docs_dir).unwrap();
+        let readme_path = docs_dir.join("README.md");
+        File::create(&readme_path).unwrap();
+
+        let readme_args = ReadmeArgs {
+            readme_path: Some(readme_path.clone()),
+            relative_to: Some(docs_dir),
+            ..Default::default()
+        };
+
+        let src_path = temp_dir.path().canonicalize().unwrap().join("src");
+        let scan_result = vec![
+            format!(
+                "Used by build (sorted): {}",
+                src_path.join("lib.rs").display()
+            ),
+            String::from("0/0        0/0          0/0    0/0     0/0      ?"),
+        ];
+
+        let result = create_or_replace_section_in_readme(
+            &readme_args,
+            None,
+            &scan_result,
+        );
+
+        assert!(result.is_ok());
+
+        let updated_file_content =
+            BufReader::new(File::open(readme_path).unwrap())
+                .lines()
+                .map(|l| l.unwrap())
+                .collect::<Vec<String>>();
+
+        assert_eq!(
+            updated_file_content,
+            vec![
+                CARGO_GEIGER_SAFETY_REPORT_SECTION_HEADER.to_string(),
+                String::from("```"),
+                format!(
+                    "Used by build (sorted): {}",
+                    Path::new("..").join("src").join("lib.rs").display()
+                ),
+                String::from(
+                    "0/0        0/0          0/0    0/0     0/0      ?"
+                ),
+                String::from("```"),
+            ]
+        );
+    }
+
     #[rstest]
     fn create_or_replace_section_test_readme_doesnt_contain_section() {
         let temp_dir = tempdir().unwrap();
@@ -251,8 +382,11 @@ mod readme_tests {
             "# Readme Header\nSome text\nAnother line\n## Another header\nMore text"
         ).unwrap();
 
-        let result =
-            create_or_replace_section_in_readme(&readme_args, &scan_result);
+        let result = create_or_replace_section_in_readme(
+            &readme_args,
+            None,
+            &scan_result,
+        );
 
         assert!(result.is_ok());
 
@@ -395,11 +529,35 @@ mod readme_tests {
         };
 
         let readme_path_buf =
-            get_readme_path_buf_from_arguments_or_default(&readme_args);
+            get_readme_path_buf_from_arguments_or_default(&readme_args, None);
 
         assert_eq!(readme_path_buf, path_buf)
     }
 
+    #[rstest(
+        input_manifest_path,
+        expected_readme_path_buf,
+        case("/test/crate/Cargo.toml", "/test/crate/README.md"),
+        case("crate/Cargo.toml", "crate/README.md"),
+        case("Cargo.toml", "README.md")
+    )]
+    fn get_readme_path_buf_from_arguments_or_default_test_manifest_path(
+        input_manifest_path: &str,
+        expected_readme_path_buf: &str,
+    ) {
+        let readme_args = ReadmeArgs {
+            readme_path: None,
+            ..Default::default()
+        };
+
+        let readme_path_buf = get_readme_path_buf_from_arguments_or_default(
+            &readme_args,
+            Some(Path::new(input_manifest_path)),
+        );
+
+        assert_eq!(readme_path_buf, PathBuf::from(expected_readme_path_buf));
+    }
+
     #[rstest]
     fn get_readme_path_buf_from_arguments_or_default_test_some() {
         let path_buf = PathBuf::from("/test/path");
@@ -409,8 +567,10 @@ mod readme_tests {
             ..Default::default()
         };
 
-        let readme_path_buf =
-            get_readme_path_buf_from_arguments_or_default(&readme_args);
+        let readme_path_buf = get_readme_path_buf_from_arguments_or_default(
+            &readme_args,
+            Some(Path::new("/test/crate/Cargo.toml")),
+        );
 
         assert_eq!(readme_path_buf, path_buf);
     }
@@ -472,6 +632,26 @@ mod readme_tests {
         assert_eq!(readme_content, expected_readme_content);
     }
 
+    #[rstest(
+        input_base,
+        input_path,
+        expected_relative_path,
+        case("/repo/docs", "/repo/src/lib.rs", "../src/lib.rs"),
+        case("/repo", "/repo/src/lib.rs", "src/lib.rs"),
+        case("/repo/a/b", "/repo/src/lib.rs", "../../src/lib.rs"),
+        case("/repo", "/repo", ".")
+    )]
+    fn path_relative_to_test(
+        input_base: &str,
+        input_path: &str,
+        expected_relative_path: &str,
+    ) {
+        assert_eq!(
+            path_relative_to(Path::new(input_base), Path::new(input_path)),
+            PathBuf::from(expected_relative_path)
+        );
+    }
+
     #[rstest]
     fn update_readme_content_test_safety_report_present_in_middle_of_readme() {
         let readme_args = ReadmeArgs::default();
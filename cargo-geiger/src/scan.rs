@@ -1,34 +1,53 @@
+mod compare_published;
+mod compare_targets;
 mod default;
+mod feature_impact;
 mod find;
 mod forbid;
 mod rs_file;
 
-use crate::args::Args;
+use crate::args::{Args, ThresholdScope};
 use crate::format::print_config::PrintConfig;
 use crate::graph::Graph;
 use crate::mapping::{
     CargoMetadataParameters, ToCargoGeigerDependencyKind,
     ToCargoGeigerPackageId,
 };
+use crate::weights::UnsafeWeights;
 
-pub use rs_file::RsFileMetricsWrapper;
+pub use compare_published::scan_compare_published;
+pub use compare_targets::scan_compare_targets;
+pub use feature_impact::scan_feature_impact;
+pub use rs_file::{RsFileMetricsWrapper, RsFileTargetKind};
+
+use find::find_rs_files_in_package;
+use rs_file::{has_lib_or_bin_target, RsFile};
 
 use default::scan_unsafe;
 use forbid::scan_forbid_unsafe;
 
 use cargo::core::Workspace;
-use cargo::{CliError, GlobalContext as Config};
+use cargo::{CliError, CliResult, GlobalContext as Config};
 use cargo_geiger_serde::{
-    CounterBlock, DependencyKind, PackageInfo, UnsafeInfo,
+    AllowedUnsafeCounts, CounterBlock, DependencyKind, ModuleUnsafety,
+    PackageInfo, StaticMutCounts, StdCrateUnsafety, UnsafeChange, UnsafeInfo,
+    UnsafeKindCounts, UnsafeKinds,
 };
-use cargo_metadata::PackageId;
+use cargo_metadata::semver::Version;
+use cargo_metadata::{Metadata, PackageId};
+use cargo_util::Sha256;
+use geiger::find::find_unsafe_in_file;
+use geiger::{IncludeTests, RsFileMetrics};
 use krates::NodeId;
 use petgraph::prelude::NodeIndex;
 use petgraph::visit::EdgeRef;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
-use std::path::PathBuf;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub struct FoundWarningsError {
@@ -43,9 +62,172 @@ impl fmt::Display for FoundWarningsError {
     }
 }
 
+#[derive(Debug)]
+pub struct UnsafeFoundError;
+
+impl Error for UnsafeFoundError {}
+
+impl fmt::Display for UnsafeFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Found unsafe usage")
+    }
+}
+
+/// The number of crates listed by the message of `--fail-threshold`, the
+/// ones using the most unsafe expressions.
+const FAIL_THRESHOLD_TOP_PACKAGES: usize = 3;
+
+#[derive(Debug)]
+pub struct FailThresholdExceededError {
+    pub fail_threshold: u64,
+    pub used_unsafe_expr_count: u64,
+    /// The crates using the most unsafe expressions, with their used unsafe
+    /// usage by category.
+    pub top_packages: Vec<(cargo_geiger_serde::PackageId, CounterBlock)>,
+}
+
+impl Error for FailThresholdExceededError {}
+
+impl fmt::Display for FailThresholdExceededError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Found {} unsafe expressions used by the build, more than the threshold of {}",
+            self.used_unsafe_expr_count, self.fail_threshold
+        )?;
+        for (index, (package, counters)) in self.top_packages.iter().enumerate()
+        {
+            let categories = [
+                ("expressions", counters.exprs.unsafe_),
+                ("functions", counters.functions.unsafe_),
+                ("methods", counters.methods.unsafe_),
+                ("impls", counters.item_impls.unsafe_),
+                ("traits", counters.item_traits.unsafe_),
+            ]
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(category, count)| format!("{}: {}", category, count))
+            .collect::<Vec<_>>();
+            write!(
+                f,
+                "{}{} {} ({})",
+                if index == 0 { ", the most in " } else { ", " },
+                package.name,
+                package.version,
+                categories.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The entries of the `--forbid-whitelist` matching no crate of the tree,
+/// likely misspelled or left over from a removed dependency.
+#[derive(Debug)]
+pub struct ForbidWhitelistEntriesNotInTreeError {
+    pub entries: Vec<String>,
+}
+
+impl Error for ForbidWhitelistEntriesNotInTreeError {}
+
+impl fmt::Display for ForbidWhitelistEntriesNotInTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "The forbid whitelist lists crates which are not in the dependency tree: {}",
+            self.entries.join(", ")
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct RootPackageAllowsUnsafeError {
+    pub package_id: PackageId,
+}
+
+impl Error for RootPackageAllowsUnsafeError {}
+
+impl fmt::Display for RootPackageAllowsUnsafeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Root package {} does not declare #![forbid(unsafe_code)]",
+            self.package_id
+        )
+    }
+}
+
 pub struct ScanResult {
     pub scan_output_lines: Vec<String>,
     pub warning_count: u64,
+    /// No unsafe usage was found in the code used by the build, and every
+    /// package could be scanned. Always `false` for `--forbid-only`, which
+    /// does not count unsafe usage.
+    pub unsafe_free: bool,
+    /// The unsafe expressions used by the build in the crates of
+    /// `--threshold-scope`, when given a `--fail-threshold`.
+    pub used_unsafe_expr_count: Option<UsedUnsafeExprCount>,
+}
+
+/// The unsafe expressions used by the build in the crates of
+/// `--threshold-scope`, in total and for each crate using some, sorted, which
+/// `--fail-summary-file` lists when the `--fail-threshold` is exceeded. The
+/// other categories of the used unsafe usage of each crate are kept for the
+/// message of the failure.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UsedUnsafeExprCount {
+    pub total: u64,
+    pub packages: Vec<(cargo_geiger_serde::PackageId, CounterBlock)>,
+}
+
+impl UsedUnsafeExprCount {
+    /// The crates using the most unsafe expressions, the ties in name order.
+    pub fn top_packages(
+        &self,
+    ) -> Vec<(cargo_geiger_serde::PackageId, CounterBlock)> {
+        let mut top_packages = self.packages.clone();
+        top_packages
+            .sort_by(|(_, a), (_, b)| b.exprs.unsafe_.cmp(&a.exprs.unsafe_));
+        top_packages.truncate(FAIL_THRESHOLD_TOP_PACKAGES);
+        top_packages
+    }
+}
+
+/// Lines of the scan output. When streaming, each line is printed to stdout as
+/// soon as it is pushed, rather than being collected until the scan is done,
+/// so rows of large trees show up as they are rendered.
+#[derive(Debug, Default)]
+pub struct ScanOutputLines {
+    lines: Vec<String>,
+    stream: bool,
+}
+
+impl ScanOutputLines {
+    pub fn new(stream: bool) -> Self {
+        ScanOutputLines {
+            lines: Vec::new(),
+            stream,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        if self.stream {
+            println!("{}", line);
+        } else {
+            self.lines.push(line);
+        }
+    }
+
+    pub fn append(&mut self, lines: Vec<String>) {
+        for line in lines {
+            self.push(line);
+        }
+    }
+
+    /// The lines which are left to be printed, none when streaming.
+    pub fn into_lines(self) -> Vec<String> {
+        self.lines
+    }
 }
 
 /// Provides a more terse and searchable name for the wrapped generic
@@ -54,6 +236,16 @@ pub struct ScanResult {
 pub struct GeigerContext {
     pub package_id_to_metrics: HashMap<PackageId, PackageMetrics>,
     pub ignored_paths: HashSet<PathBuf>,
+    /// The generated files which were not scanned, for `--exclude-generated`.
+    pub excluded_paths: HashSet<PathBuf>,
+    /// The files larger than `--max-file-size` which were not scanned.
+    pub oversized_paths: HashSet<PathBuf>,
+    /// The packages which were not scanned because the deadline was exceeded
+    /// before their scan started, for `--deadline`.
+    pub unscanned_package_ids: HashSet<PackageId>,
+    /// The packages whose scan was stopped by `--scan-timeout-per-crate`,
+    /// with the metrics of the files scanned before, if any.
+    pub timed_out_package_ids: HashSet<PackageId>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -75,6 +267,9 @@ pub enum ScanMode {
 pub struct ScanParameters<'a> {
     pub args: &'a Args,
     pub config: &'a Config,
+    /// The packages only pulled by inactive optional dependencies, for
+    /// `--include-optional`.
+    pub inactive_optional_package_ids: &'a HashSet<PackageId>,
     pub print_config: &'a PrintConfig,
 }
 
@@ -83,6 +278,7 @@ pub fn scan(
     cargo_metadata_parameters: &CargoMetadataParameters,
     config: &Config,
     graph: &Graph,
+    inactive_optional_package_ids: &HashSet<PackageId>,
     root_package_id: PackageId,
     workspace: &Workspace,
 ) -> Result<ScanResult, CliError> {
@@ -91,6 +287,7 @@ pub fn scan(
     let scan_parameters = ScanParameters {
         args,
         config,
+        inactive_optional_package_ids,
         print_config: &print_config,
     };
 
@@ -145,12 +342,416 @@ pub fn unsafe_stats(
         used,
         unused,
         forbids_unsafe,
+        modules: BTreeMap::new(),
+        kinds: None,
+    }
+}
+
+/// The unsafe usage of each module of a package, in the files used by the
+/// build and in the other files, by module path. The counts of a module
+/// include the ones of its submodules.
+pub fn module_unsafe_stats(
+    package_metrics: &PackageMetrics,
+    rs_files_used: &HashSet<PathBuf>,
+) -> BTreeMap<String, ModuleUnsafety> {
+    let entry_point_dirs = package_metrics
+        .rs_path_to_metrics
+        .iter()
+        .filter(|(_, wrapper)| wrapper.is_crate_entry_point)
+        .filter_map(|(path_buf, _)| path_buf.parent())
+        .collect::<Vec<_>>();
+    let mut modules = BTreeMap::new();
+    for (path_buf, rs_file_metrics_wrapper) in
+        &package_metrics.rs_path_to_metrics
+    {
+        let is_used = rs_files_used.contains(path_buf);
+        let metrics = &rs_file_metrics_wrapper.metrics;
+        let file_module = if rs_file_metrics_wrapper.is_crate_entry_point {
+            vec![String::from("crate")]
+        } else {
+            file_module_path(path_buf, &entry_point_dirs)
+        };
+        // The counts of the file are also the ones of its enclosing modules.
+        for depth in 1..=file_module.len() {
+            add_module_counters(
+                &mut modules,
+                &file_module[..depth],
+                is_used,
+                &metrics.counters,
+            );
+        }
+        for (inline_module_path, counters) in &metrics.module_counters {
+            let mut module_path = file_module.clone();
+            module_path
+                .extend(inline_module_path.split("::").map(String::from));
+            add_module_counters(&mut modules, &module_path, is_used, counters);
+        }
     }
+    modules
+}
+
+fn add_module_counters(
+    modules: &mut BTreeMap<String, ModuleUnsafety>,
+    module_path: &[String],
+    is_used: bool,
+    counters: &CounterBlock,
+) {
+    let module = modules.entry(module_path.join("::")).or_default();
+    let target = if is_used {
+        &mut module.used
+    } else {
+        &mut module.unused
+    };
+    *target += counters.clone();
+}
+
+/// The module path of a file which is not a crate entry point, from its path
+/// relative to the deepest directory of an entry point containing it, so both
+/// `src/a/b.rs` and `src/a/b/mod.rs` are `crate::a::b`. The entry points, e.g.
+/// `src/lib.rs` and `src/main.rs`, are all the `crate` module, and files
+/// included with `#[path]` are attributed by their location.
+fn file_module_path(path: &Path, entry_point_dirs: &[&Path]) -> Vec<String> {
+    let mut module_path = vec![String::from("crate")];
+    let relative_path = entry_point_dirs
+        .iter()
+        .filter(|entry_point_dir| path.starts_with(entry_point_dir))
+        .max_by_key(|entry_point_dir| entry_point_dir.components().count())
+        .and_then(|entry_point_dir| path.strip_prefix(entry_point_dir).ok());
+    let relative_path = match relative_path {
+        Some(relative_path) => relative_path,
+        None => return module_path,
+    };
+    if let Some(parent) = relative_path.parent() {
+        module_path.extend(
+            parent
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .map(String::from),
+        );
+    }
+    if let Some(stem) = relative_path
+        .file_stem()
+        .filter(|stem| stem.to_str() != Some("mod"))
+    {
+        module_path.push(stem.to_string_lossy().into_owned());
+    }
+    module_path
+}
+
+/// The kinds of unsafe usage counted with the flags enabling them, e.g.
+/// `--raw-derefs`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnsafeKindFlags {
+    pub allowed_unsafe: bool,
+    pub drop_unsafe: bool,
+    pub exported_symbols: bool,
+    pub ffi_callbacks: bool,
+    pub latent_macro_unsafe: bool,
+    pub layout_dependent_unsafe: bool,
+    pub memory_lifecycle_unsafe: bool,
+    pub raw_derefs: bool,
+    pub static_mut: bool,
+    pub unsafe_trait_usage: bool,
+}
+
+impl UnsafeKindFlags {
+    pub fn new(args: &Args) -> Self {
+        UnsafeKindFlags {
+            allowed_unsafe: args.allowed_unsafe,
+            drop_unsafe: args.drop_unsafe,
+            exported_symbols: args.exported_symbols,
+            ffi_callbacks: args.ffi_callbacks,
+            latent_macro_unsafe: args.latent_macro_unsafe,
+            layout_dependent_unsafe: args.layout_dependent_unsafe,
+            memory_lifecycle_unsafe: args.memory_lifecycle_unsafe,
+            raw_derefs: args.raw_derefs,
+            static_mut: args.static_mut,
+            unsafe_trait_usage: args.unsafe_trait_usage,
+        }
+    }
+
+    fn any(&self) -> bool {
+        self.allowed_unsafe
+            || self.drop_unsafe
+            || self.exported_symbols
+            || self.ffi_callbacks
+            || self.latent_macro_unsafe
+            || self.layout_dependent_unsafe
+            || self.memory_lifecycle_unsafe
+            || self.raw_derefs
+            || self.static_mut
+            || self.unsafe_trait_usage
+    }
+
+    /// The counts of the enabled kinds of unsafe usage of a file.
+    fn counts(&self, metrics: &RsFileMetrics) -> UnsafeKindCounts {
+        UnsafeKindCounts {
+            raw_derefs: self.raw_derefs.then_some(metrics.raw_pointer_derefs),
+            latent_macro_unsafe: self
+                .latent_macro_unsafe
+                .then_some(metrics.latent_macro_unsafe),
+            exported_symbols: self
+                .exported_symbols
+                .then_some(metrics.exported_symbols),
+            memory_lifecycle_unsafe: self
+                .memory_lifecycle_unsafe
+                .then_some(metrics.memory_lifecycle_unsafe),
+            static_mut: self.static_mut.then_some(StaticMutCounts {
+                definitions: metrics.static_mut_definitions,
+                accesses: metrics.static_mut_accesses,
+            }),
+            allowed_unsafe: self.allowed_unsafe.then_some(
+                AllowedUnsafeCounts {
+                    allowed: metrics.allowed_unsafe,
+                    unannotated: metrics.counters.unsafe_count()
+                        - metrics.allowed_unsafe,
+                },
+            ),
+            unsafe_trait_usage: self
+                .unsafe_trait_usage
+                .then_some(metrics.unsafe_trait_usage),
+            drop_unsafe: self.drop_unsafe.then_some(metrics.drop_unsafe),
+            ffi_callbacks: self.ffi_callbacks.then_some(metrics.ffi_callbacks),
+            layout_dependent_unsafe: self
+                .layout_dependent_unsafe
+                .then_some(metrics.layout_dependent_transmutes),
+        }
+    }
+}
+
+/// The kinds of unsafe usage of a package counted with the flags enabling
+/// them, in the files used by the build and in the other files, `None` when
+/// none of these flags is given.
+pub fn unsafe_kinds(
+    package_metrics: &PackageMetrics,
+    rs_files_used: &HashSet<PathBuf>,
+    unsafe_kind_flags: &UnsafeKindFlags,
+) -> Option<UnsafeKinds> {
+    if !unsafe_kind_flags.any() {
+        return None;
+    }
+    // Zero for the enabled kinds, even for a package without files.
+    let zero_counts = unsafe_kind_flags.counts(&RsFileMetrics::default());
+    let mut unsafe_kinds = UnsafeKinds {
+        used: zero_counts.clone(),
+        unused: zero_counts,
+    };
+    for (path_buf, rs_file_metrics_wrapper) in
+        &package_metrics.rs_path_to_metrics
+    {
+        let target = if rs_files_used.contains(path_buf) {
+            &mut unsafe_kinds.used
+        } else {
+            &mut unsafe_kinds.unused
+        };
+        *target += unsafe_kind_flags.counts(&rs_file_metrics_wrapper.metrics);
+    }
+    Some(unsafe_kinds)
+}
+
+/// The features enabled by the `#![feature(...)]` attributes of the files of
+/// a package used by the build, sorted and without duplicates. The files not
+/// used by the build do not make the package require a nightly toolchain.
+pub fn nightly_features(
+    package_metrics: &PackageMetrics,
+    rs_files_used: &HashSet<PathBuf>,
+) -> Vec<String> {
+    let mut nightly_features = package_metrics
+        .rs_path_to_metrics
+        .iter()
+        .filter(|(path_buf, _)| rs_files_used.contains(*path_buf))
+        .flat_map(|(_, rs_file_metrics_wrapper)| {
+            rs_file_metrics_wrapper
+                .metrics
+                .nightly_features
+                .iter()
+                .cloned()
+        })
+        .collect::<Vec<String>>();
+    nightly_features.sort();
+    nightly_features.dedup();
+    nightly_features
+}
+
+/// The hex encoded Sha-256 of the content of the files scanned in a package,
+/// concatenated in the order of their paths, for
+/// `--json-include-source-hash`. The paths themselves are not hashed, so the
+/// hash doesn't depend on where the package is.
+pub fn source_hash(package_metrics: &PackageMetrics) -> io::Result<String> {
+    let mut paths = package_metrics
+        .rs_path_to_metrics
+        .keys()
+        .collect::<Vec<_>>();
+    paths.sort();
+    let mut sha256 = Sha256::new();
+    for path in paths {
+        sha256.update(&fs::read(path)?);
+    }
+    Ok(sha256.finish_hex())
+}
+
+/// The unsafe usage in the test code of a package, in the files used by the
+/// build and in the other files. Only counted with
+/// `--count-cfg-test-separately`.
+pub fn test_unsafe_stats(
+    package_metrics: &PackageMetrics,
+    rs_files_used: &HashSet<PathBuf>,
+) -> (CounterBlock, CounterBlock) {
+    let mut used = CounterBlock::default();
+    let mut unused = CounterBlock::default();
+    for (path_buf, rs_file_metrics_wrapper) in
+        &package_metrics.rs_path_to_metrics
+    {
+        let target = if rs_files_used.contains(path_buf) {
+            &mut used
+        } else {
+            &mut unused
+        };
+        *target += rs_file_metrics_wrapper.metrics.test_counters.clone();
+    }
+    (used, unused)
+}
+
+/// Whether a crate matches one of the `<crate[@version]>` specifications
+/// passed to `--ignore-unsafe-in`. An ignored crate is still displayed, but
+/// its unsafe usage is left out of the totals.
+pub fn is_unsafe_ignored<V: Display>(
+    ignore_unsafe_in: &[String],
+    name: &str,
+    version: &V,
+) -> bool {
+    ignore_unsafe_in.iter().any(|crate_spec| {
+        let mut parts = crate_spec.splitn(2, '@');
+        let spec_name = parts.next().unwrap_or_default();
+        match parts.next() {
+            Some(spec_version) => {
+                spec_name == name && spec_version == version.to_string()
+            }
+            None => spec_name == name,
+        }
+    })
+}
+
+/// Check that every library and binary entry point of the package declares
+/// `#![forbid(unsafe_code)]`, for `--fail-if-no-forbid`. Only the entry points
+/// of the package itself are parsed, its dependencies are not considered.
+pub fn check_package_forbids_unsafe(
+    metadata: &Metadata,
+    package_id: &PackageId,
+) -> CliResult {
+    let package = match metadata
+        .packages
+        .iter()
+        .find(|package| &package.id == package_id)
+    {
+        Some(package) => package,
+        None => return Ok(()),
+    };
+
+    for (rs_file, _) in find_rs_files_in_package(package) {
+        let path_buf = match rs_file {
+            RsFile::BinRoot(path_buf) | RsFile::LibRoot(path_buf) => path_buf,
+            _ => continue,
+        };
+        let rs_file_metrics = find_unsafe_in_file(&path_buf, IncludeTests::No)
+            .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?;
+        if !rs_file_metrics.forbids_unsafe {
+            return Err(CliError::new(
+                anyhow::Error::new(RootPackageAllowsUnsafeError {
+                    package_id: package_id.clone(),
+                }),
+                1,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The unsafe expressions used by the build in the packages of the
+/// `--threshold-scope`, for `--fail-threshold`: the root package, or every
+/// package of the dependency tree which is not ignored, as in the totals of
+/// the report. `None` without a `--fail-threshold`.
+fn fail_threshold_count(
+    args: &Args,
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    geiger_context: &GeigerContext,
+    graph: &Graph,
+    root_package_id: &PackageId,
+    rs_files_used: &HashSet<PathBuf>,
+) -> Option<UsedUnsafeExprCount> {
+    args.fail_threshold?;
+    let package_ids = match args.threshold_scope.unwrap_or(ThresholdScope::Tree)
+    {
+        ThresholdScope::Root => vec![root_package_id],
+        ThresholdScope::Tree => {
+            let mut ignored_package_ids = ignored_package_ids(
+                cargo_metadata_parameters,
+                &args.ignore_unsafe_in,
+            );
+            if args.exclude_self {
+                ignored_package_ids.insert(root_package_id.clone());
+            }
+            graph
+                .nodes
+                .keys()
+                .filter(|package_id| !ignored_package_ids.contains(*package_id))
+                .collect()
+        }
+    };
+    let mut used_unsafe_expr_count = UsedUnsafeExprCount::default();
+    for package_id in package_ids {
+        let package_metrics =
+            match geiger_context.package_id_to_metrics.get(package_id) {
+                Some(package_metrics) => package_metrics,
+                None => continue,
+            };
+        let used = unsafe_stats(package_metrics, rs_files_used).used;
+        used_unsafe_expr_count.total += used.exprs.unsafe_;
+        if used.exprs.unsafe_ == 0 {
+            continue;
+        }
+        if let Some(package) = package_id
+            .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+        {
+            used_unsafe_expr_count.packages.push((package, used));
+        }
+    }
+    used_unsafe_expr_count
+        .packages
+        .sort_by(|(a, _), (b, _)| a.cmp(b));
+    Some(used_unsafe_expr_count)
+}
+
+fn ignored_package_ids(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    ignore_unsafe_in: &[String],
+) -> HashSet<PackageId> {
+    cargo_metadata_parameters
+        .metadata
+        .packages
+        .iter()
+        .filter(|package| {
+            is_unsafe_ignored(ignore_unsafe_in, &package.name, &package.version)
+        })
+        .map(|package| package.id.clone())
+        .collect()
 }
 
 struct ScanDetails {
+    /// The ids of the advisories affecting each package, for `--advisories`.
+    advisory_ids: HashMap<PackageId, Vec<String>>,
+    /// The matches of each pattern by name, for `--custom-pattern`.
+    custom_pattern_counts: HashMap<PackageId, BTreeMap<String, u64>>,
     rs_files_used: HashSet<PathBuf>,
     geiger_context: GeigerContext,
+    /// The weights of the unsafe usage categories, for `--weights`.
+    unsafe_weights: Option<UnsafeWeights>,
+    /// The unsafe usage of the standard library crates, for `--include-std`.
+    std_crates: Vec<StdCrateUnsafety>,
+    /// The baseline version and the change of the unsafe usage of the
+    /// packages whose version differs from the baseline, for `--diff`.
+    version_changes: HashMap<PackageId, (Version, UnsafeChange)>,
 }
 
 fn construct_rs_files_used_lines(
@@ -170,6 +771,91 @@ fn construct_rs_files_used_lines(
         .collect::<Vec<String>>()
 }
 
+/// The kinds of target files requested to be counted alongside the files used
+/// by the build, through `--include-tests`, `--include-benches` and
+/// `--include-examples`.
+fn included_target_kinds(args: &Args) -> HashSet<RsFileTargetKind> {
+    let mut included_target_kinds = HashSet::new();
+    if args.include_benches {
+        included_target_kinds.insert(RsFileTargetKind::Bench);
+    }
+    if args.include_examples {
+        included_target_kinds.insert(RsFileTargetKind::Example);
+    }
+    if args.include_tests {
+        included_target_kinds.insert(RsFileTargetKind::Test);
+    }
+    included_target_kinds
+}
+
+/// Mark every scanned file of an included target kind as used, since files
+/// belonging to tests, benches and examples are never part of the regular
+/// build.
+fn add_included_target_kind_files(
+    geiger_context: &GeigerContext,
+    included_target_kinds: &HashSet<RsFileTargetKind>,
+    rs_files_used: &mut HashSet<PathBuf>,
+) {
+    for package_metrics in geiger_context.package_id_to_metrics.values() {
+        for (path_buf, rs_file_metrics_wrapper) in
+            &package_metrics.rs_path_to_metrics
+        {
+            if included_target_kinds
+                .contains(&rs_file_metrics_wrapper.target_kind)
+            {
+                rs_files_used.insert(path_buf.clone());
+            }
+        }
+    }
+}
+
+/// Packages which consist of examples only, without any library or binary
+/// target. None of their source files are used by the regular build.
+fn examples_only_package_ids(
+    packages: &[cargo_metadata::Package],
+) -> HashSet<PackageId> {
+    packages
+        .iter()
+        .filter(|package| {
+            let raw_target_kinds = package
+                .targets
+                .iter()
+                .map(|target| target.kind.clone())
+                .collect::<Vec<Vec<String>>>();
+            !has_lib_or_bin_target(&raw_target_kinds)
+                && raw_target_kinds
+                    .iter()
+                    .flatten()
+                    .any(|kind| kind == "example")
+        })
+        .map(|package| package.id.clone())
+        .collect()
+}
+
+/// Mark the example files of the given packages as used, to scan them in lieu
+/// of a library or binary.
+fn add_example_files_of_packages(
+    geiger_context: &GeigerContext,
+    package_ids: &HashSet<PackageId>,
+    rs_files_used: &mut HashSet<PathBuf>,
+) {
+    for package_id in package_ids {
+        if let Some(package_metrics) =
+            geiger_context.package_id_to_metrics.get(package_id)
+        {
+            for (path_buf, rs_file_metrics_wrapper) in
+                &package_metrics.rs_path_to_metrics
+            {
+                if rs_file_metrics_wrapper.target_kind
+                    == RsFileTargetKind::Example
+                {
+                    rs_files_used.insert(path_buf.clone());
+                }
+            }
+        }
+    }
+}
+
 fn list_files_used_but_not_scanned(
     geiger_context: &GeigerContext,
     rs_files_used: &HashSet<PathBuf>,
@@ -188,6 +874,7 @@ fn list_files_used_but_not_scanned(
         .filter(|p| {
             !scanned_files.contains(p)
                 && !geiger_context.ignored_paths.contains(p)
+                && !geiger_context.excluded_paths.contains(p)
         })
         .collect()
 }
@@ -394,6 +1081,7 @@ mod scan_tests {
                 RsFileMetricsWrapper {
                     metrics: Default::default(),
                     is_crate_entry_point: false,
+                    target_kind: Default::default(),
                 },
             )],
             vec![
@@ -412,17 +1100,20 @@ mod scan_tests {
                 RsFileMetricsWrapper {
                     metrics: Default::default(),
                     is_crate_entry_point: false,
+                    target_kind: Default::default(),
                 }),
                 (
                 PathBuf::from("second/file/path.rs"),
                 RsFileMetricsWrapper {
                 metrics: Default::default(),
                 is_crate_entry_point: false,
+                target_kind: Default::default(),
                 }),
                 (PathBuf::from("third/file/path.rs"),
                 RsFileMetricsWrapper {
                     metrics: Default::default(),
                     is_crate_entry_point: false,
+                    target_kind: Default::default(),
                 }
             )],
             vec![
@@ -454,6 +1145,10 @@ mod scan_tests {
             .cloned()
             .collect(),
             ignored_paths: HashSet::new(),
+            excluded_paths: HashSet::new(),
+            oversized_paths: HashSet::new(),
+            unscanned_package_ids: HashSet::new(),
+            timed_out_package_ids: HashSet::new(),
         };
 
         let rs_files_used = input_rs_files_used_vec.iter().cloned().collect();
@@ -469,6 +1164,123 @@ mod scan_tests {
         )
     }
 
+    #[rstest(
+        input_include_benches,
+        input_include_examples,
+        input_include_tests,
+        expected_rs_files_used_vec,
+        case(false, false, false, vec!["src/lib.rs"]),
+        case(true, false, false, vec!["benches/bench.rs", "src/lib.rs"]),
+        case(false, true, false, vec!["examples/example.rs", "src/lib.rs"]),
+        case(false, false, true, vec!["src/lib.rs", "tests/test.rs"]),
+        case(
+            true,
+            true,
+            true,
+            vec![
+                "benches/bench.rs",
+                "examples/example.rs",
+                "src/lib.rs",
+                "tests/test.rs"
+            ]
+        )
+    )]
+    fn add_included_target_kind_files_test(
+        input_include_benches: bool,
+        input_include_examples: bool,
+        input_include_tests: bool,
+        expected_rs_files_used_vec: Vec<&str>,
+    ) {
+        let (_, metadata) = construct_krates_and_metadata();
+        let package_id = metadata.root_package().unwrap().id.clone();
+
+        let rs_path_to_metrics = vec![
+            ("benches/bench.rs", RsFileTargetKind::Bench),
+            ("examples/example.rs", RsFileTargetKind::Example),
+            ("src/lib.rs", RsFileTargetKind::Source),
+            ("tests/test.rs", RsFileTargetKind::Test),
+        ]
+        .into_iter()
+        .map(|(path, target_kind)| {
+            (
+                PathBuf::from(path),
+                RsFileMetricsWrapper {
+                    target_kind,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect::<HashMap<PathBuf, RsFileMetricsWrapper>>();
+
+        let geiger_context = GeigerContext {
+            package_id_to_metrics: vec![(
+                package_id,
+                PackageMetrics { rs_path_to_metrics },
+            )]
+            .into_iter()
+            .collect(),
+            ignored_paths: HashSet::new(),
+            excluded_paths: HashSet::new(),
+            oversized_paths: HashSet::new(),
+            unscanned_package_ids: HashSet::new(),
+            timed_out_package_ids: HashSet::new(),
+        };
+
+        let args = Args {
+            include_benches: input_include_benches,
+            include_examples: input_include_examples,
+            include_tests: input_include_tests,
+            ..Default::default()
+        };
+
+        let mut rs_files_used =
+            vec![PathBuf::from("src/lib.rs")].into_iter().collect();
+
+        add_included_target_kind_files(
+            &geiger_context,
+            &included_target_kinds(&args),
+            &mut rs_files_used,
+        );
+
+        let mut actual_rs_files_used =
+            rs_files_used.into_iter().collect::<Vec<PathBuf>>();
+        actual_rs_files_used.sort();
+
+        assert_eq!(
+            actual_rs_files_used,
+            expected_rs_files_used_vec
+                .into_iter()
+                .map(PathBuf::from)
+                .collect::<Vec<PathBuf>>()
+        );
+    }
+
+    #[rstest(
+        input_ignore_unsafe_in,
+        expected_is_unsafe_ignored,
+        case(vec![], false),
+        case(vec!["itertools"], true),
+        case(vec!["itertools@0.10.5"], true),
+        case(vec!["itertools@0.9.0"], false),
+        case(vec!["either", "itertools"], true),
+        case(vec!["iter"], false)
+    )]
+    fn is_unsafe_ignored_test(
+        input_ignore_unsafe_in: Vec<&str>,
+        expected_is_unsafe_ignored: bool,
+    ) {
+        let ignore_unsafe_in = input_ignore_unsafe_in
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let version = Version::new(0, 10, 5);
+
+        assert_eq!(
+            is_unsafe_ignored(&ignore_unsafe_in, "itertools", &version),
+            expected_is_unsafe_ignored
+        );
+    }
+
     #[rstest]
     fn unsafe_stats_from_nothing_are_empty() {
         let stats = unsafe_stats(&Default::default(), &Default::default());
@@ -539,6 +1351,118 @@ mod scan_tests {
         assert_eq!(stats.unused.functions.unsafe_, 110);
     }
 
+    #[rstest(
+        input_path,
+        expected_module_path,
+        case("/p/src/a.rs", "crate::a"),
+        case("/p/src/a/mod.rs", "crate::a"),
+        case("/p/src/a/b.rs", "crate::a::b"),
+        case("/p/src/bin/c.rs", "crate::c"),
+        case("/p/other/d.rs", "crate")
+    )]
+    fn file_module_path_test(input_path: &str, expected_module_path: &str) {
+        let entry_point_dirs = [Path::new("/p/src"), Path::new("/p/src/bin")];
+
+        assert_eq!(
+            file_module_path(Path::new(input_path), &entry_point_dirs)
+                .join("::"),
+            expected_module_path
+        );
+    }
+
+    #[rstest]
+    fn module_unsafe_stats_test() {
+        let metrics = metrics_from_iter(vec![
+            (
+                "/p/src/lib.rs",
+                MetricsBuilder::default()
+                    .functions(1, 1)
+                    .set_is_crate_entry_point(true)
+                    .build(),
+            ),
+            (
+                "/p/src/a.rs",
+                MetricsBuilder::default()
+                    .functions(2, 1)
+                    .module_functions("inner", 0, 1)
+                    .build(),
+            ),
+            (
+                "/p/src/a/b/mod.rs",
+                MetricsBuilder::default().functions(4, 0).build(),
+            ),
+        ]);
+        let modules = module_unsafe_stats(
+            &metrics,
+            &set_of_paths(&["/p/src/lib.rs", "/p/src/a.rs"]),
+        );
+
+        let functions = |module_path: &str| {
+            let used = &modules[module_path].used.functions;
+            let unused = &modules[module_path].unused.functions;
+            ((used.safe, used.unsafe_), (unused.safe, unused.unsafe_))
+        };
+        assert_eq!(modules.len(), 4);
+        // The counts of a module include the ones of its submodules.
+        assert_eq!(functions("crate"), ((3, 2), (4, 0)));
+        assert_eq!(functions("crate::a"), ((2, 1), (4, 0)));
+        assert_eq!(functions("crate::a::inner"), ((0, 1), (0, 0)));
+        assert_eq!(functions("crate::a::b"), ((0, 0), (4, 0)));
+    }
+
+    #[rstest]
+    fn unsafe_kinds_test() {
+        let mut foo = MetricsBuilder::default().functions(0, 3).build();
+        foo.metrics.raw_pointer_derefs = 2;
+        foo.metrics.allowed_unsafe = 1;
+        let mut bar = MetricsBuilder::default().build();
+        bar.metrics.raw_pointer_derefs = 5;
+        bar.metrics.drop_unsafe = 4;
+        let metrics = metrics_from_iter(vec![("foo.rs", foo), ("bar.rs", bar)]);
+        let unsafe_kind_flags = UnsafeKindFlags {
+            allowed_unsafe: true,
+            raw_derefs: true,
+            ..Default::default()
+        };
+
+        // The unsafe usage inside `impl Drop for` blocks is not counted
+        // without `--drop-unsafe`.
+        assert_eq!(
+            unsafe_kinds(
+                &metrics,
+                &set_of_paths(&["foo.rs"]),
+                &unsafe_kind_flags
+            ),
+            Some(UnsafeKinds {
+                used: UnsafeKindCounts {
+                    raw_derefs: Some(2),
+                    allowed_unsafe: Some(AllowedUnsafeCounts {
+                        allowed: 1,
+                        unannotated: 2,
+                    }),
+                    ..Default::default()
+                },
+                unused: UnsafeKindCounts {
+                    raw_derefs: Some(5),
+                    allowed_unsafe: Some(AllowedUnsafeCounts::default()),
+                    ..Default::default()
+                },
+            })
+        );
+    }
+
+    #[rstest]
+    fn unsafe_kinds_test_without_flags() {
+        assert_eq!(
+            unsafe_kinds(
+                &Default::default(),
+                &Default::default(),
+                &UnsafeKindFlags::default()
+            ),
+            None
+        );
+    }
+
     fn metrics_from_iter<I, P>(it: I) -> PackageMetrics
     where
         I: IntoIterator<Item = (P, RsFileMetricsWrapper)>,
@@ -576,6 +1500,21 @@ mod scan_tests {
             self
         }
 
+        fn module_functions(
+            mut self,
+            module_path: &str,
+            safe: u64,
+            unsafe_: u64,
+        ) -> Self {
+            self.inner
+                .metrics
+                .module_counters
+                .entry(module_path.to_string())
+                .or_default()
+                .functions = Count { safe, unsafe_ };
+            self
+        }
+
         fn set_is_crate_entry_point(mut self, yes: bool) -> Self {
             self.inner.is_crate_entry_point = yes;
             self
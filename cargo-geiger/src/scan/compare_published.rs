@@ -0,0 +1,148 @@
+use crate::args::{Args, DiffFormat};
+use crate::diff::category_deltas;
+use crate::format::print_config::PrintConfig;
+use crate::format::report_delta::render_report_delta;
+use crate::mapping::{CargoMetadataParameters, ToCargoGeigerPackageId};
+
+use super::find::find_rs_files_in_package;
+use super::rs_file::RsFile;
+use super::ScanResult;
+
+use anyhow::Context;
+use cargo::util::CargoResult;
+use cargo::CliError;
+use cargo_geiger_serde::{CounterBlock, PackageDelta, ReportDelta};
+use cargo_metadata::{Metadata, MetadataCommand, Package, PackageId};
+use geiger::find::find_unsafe_in_file;
+use geiger::IncludeTests;
+use std::fs;
+
+/// Compare the unsafe usage of the root package to the one of the latest
+/// version of the same crate published to crates.io, for
+/// `--compare-published`. The published version is downloaded by resolving a
+/// scratch package depending on it, in the target directory. As for
+/// `--compare-targets`, nothing is built, so all the unsafe usage found in the
+/// files of both versions is compared. The delta is printed in the format of
+/// `--diff-format`, the published version being the baseline.
+pub fn scan_compare_published(
+    args: &Args,
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    root_package_id: &PackageId,
+) -> Result<ScanResult, CliError> {
+    let print_config = PrintConfig::new(args)?;
+    let metadata = cargo_metadata_parameters.metadata;
+    let package = &metadata[root_package_id];
+
+    let published_metadata = get_published_metadata(metadata, &package.name)
+        .map_err(|e| CliError::new(e, 1))?;
+    let published_package = match published_metadata
+        .packages
+        .iter()
+        .find(|p| p.name == package.name && p.source.is_some())
+    {
+        Some(published_package) => published_package,
+        None => {
+            eprintln!(
+                "No version of {} is published on crates.io",
+                package.name
+            );
+            return Err(CliError::code(1));
+        }
+    };
+    eprintln!(
+        "Comparing {} {} to the published {} {}",
+        package.name,
+        package.version,
+        published_package.name,
+        published_package.version
+    );
+
+    let local_counters = total_counters(package, print_config.include_tests)?;
+    let published_counters =
+        total_counters(published_package, print_config.include_tests)?;
+
+    let mut report_delta = ReportDelta::default();
+    let categories = category_deltas(&published_counters, &local_counters);
+    if let (false, Some(package_id)) = (
+        categories.is_empty(),
+        root_package_id.to_cargo_geiger_package_id(metadata),
+    ) {
+        report_delta.changed.push(PackageDelta {
+            package: package_id,
+            categories,
+        });
+    }
+
+    Ok(ScanResult {
+        scan_output_lines: vec![render_report_delta(
+            args.diff_format.unwrap_or(DiffFormat::Table),
+            &report_delta,
+        )],
+        warning_count: 0,
+        unsafe_free: false,
+        used_unsafe_expr_count: None,
+    })
+}
+
+/// Resolve a scratch package depending on any version of the crate, which
+/// gives the latest one, downloading it to the registry cache as for the
+/// dependencies of the workspace. The scratch package is written anew, and
+/// its lockfile removed, so that an earlier run doesn't pin the version.
+fn get_published_metadata(
+    metadata: &Metadata,
+    name: &str,
+) -> CargoResult<Metadata> {
+    let scratch_directory = metadata
+        .target_directory
+        .join("cargo-geiger")
+        .join("compare-published");
+    fs::create_dir_all(scratch_directory.join("src"))?;
+    fs::write(
+        scratch_directory.join("Cargo.toml"),
+        format!(
+            "[package]
+name = \"cargo-geiger-compare-published\"
+version = \"0.0.0\"
+edition = \"2018\"
+
+[dependencies]
+{} = \"*\"
+
+[workspace]
+",
+            name
+        ),
+    )?;
+    fs::write(scratch_directory.join("src").join("lib.rs"), "")?;
+    let lockfile_path = scratch_directory.join("Cargo.lock");
+    if lockfile_path.exists() {
+        fs::remove_file(lockfile_path)?;
+    }
+
+    MetadataCommand::new()
+        .manifest_path(scratch_directory.join("Cargo.toml"))
+        .exec()
+        .with_context(|| {
+            format!("Failed to download the published version of {}", name)
+        })
+}
+
+/// The sum of the unsafe usage found in all the files of the package.
+fn total_counters(
+    package: &Package,
+    include_tests: IncludeTests,
+) -> Result<CounterBlock, CliError> {
+    let mut counter_block = CounterBlock::default();
+    for (rs_file, _) in find_rs_files_in_package(package) {
+        let path_buf = match rs_file {
+            RsFile::CustomBuildRoot(_) => continue,
+            RsFile::BinRoot(path_buf)
+            | RsFile::LibRoot(path_buf)
+            | RsFile::Other(path_buf) => path_buf,
+        };
+        let rs_file_metrics = find_unsafe_in_file(&path_buf, include_tests)
+            .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?;
+        counter_block += rs_file_metrics.counters;
+    }
+    Ok(counter_block)
+}
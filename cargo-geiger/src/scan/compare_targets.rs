@@ -0,0 +1,172 @@
+use crate::args::Args;
+use crate::format::print_config::PrintConfig;
+use crate::graph::Graph;
+use crate::mapping::CargoMetadataParameters;
+
+use super::find::find_unsafe;
+use super::{GeigerContext, ScanMode, ScanResult};
+
+use cargo::{CliError, GlobalContext as Config};
+use cargo_geiger_serde::CounterBlock;
+use cargo_metadata::PackageId;
+use std::collections::BTreeMap;
+
+/// Compare the unsafe usage of the dependency trees of two targets, for
+/// `--compare-targets`. The source files are scanned once, as the scan itself
+/// does not evaluate `cfg` attributes, so the comparison shows which crates
+/// each target depends on, with all the unsafe usage found in them.
+pub fn scan_compare_targets(
+    args: &Args,
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    config: &Config,
+    target_graphs: &[(String, Graph)],
+) -> Result<ScanResult, CliError> {
+    let print_config = PrintConfig::new(args)?;
+    let geiger_context = find_unsafe(
+        cargo_metadata_parameters,
+        config,
+        ScanMode::Full,
+        &print_config,
+    )?;
+
+    let mut unsafe_counts_by_package =
+        BTreeMap::<String, Vec<Option<u64>>>::new();
+    for (target_index, (_, graph)) in target_graphs.iter().enumerate() {
+        for package_id in graph.nodes.keys() {
+            let package = print_config
+                .format
+                .display(cargo_metadata_parameters, package_id)
+                .to_string();
+            let unsafe_counts = unsafe_counts_by_package
+                .entry(package)
+                .or_insert_with(|| vec![None; target_graphs.len()]);
+            unsafe_counts[target_index] =
+                Some(total_unsafe_count(&geiger_context, package_id));
+        }
+    }
+
+    let targets = target_graphs
+        .iter()
+        .map(|(target, _)| target.as_str())
+        .collect::<Vec<&str>>();
+
+    Ok(ScanResult {
+        scan_output_lines: construct_comparison_lines(
+            &targets,
+            &unsafe_counts_by_package,
+        ),
+        warning_count: 0,
+        unsafe_free: false,
+        used_unsafe_expr_count: None,
+    })
+}
+
+/// The sum of all the unsafe usage found in the package, whether it is used
+/// by the build or not.
+pub(super) fn total_unsafe_count(
+    geiger_context: &GeigerContext,
+    package_id: &PackageId,
+) -> u64 {
+    let mut counter_block = CounterBlock::default();
+    if let Some(package_metrics) =
+        geiger_context.package_id_to_metrics.get(package_id)
+    {
+        for rs_file_metrics_wrapper in
+            package_metrics.rs_path_to_metrics.values()
+        {
+            counter_block += rs_file_metrics_wrapper.metrics.counters.clone();
+        }
+    }
+    counter_block.functions.unsafe_
+        + counter_block.exprs.unsafe_
+        + counter_block.item_impls.unsafe_
+        + counter_block.item_traits.unsafe_
+        + counter_block.methods.unsafe_
+}
+
+/// One column per target, `-` for the crates which are not a dependency for
+/// that target. Crates whose unsafe usage differs between the targets are
+/// marked with `!`.
+fn construct_comparison_lines(
+    targets: &[&str],
+    unsafe_counts_by_package: &BTreeMap<String, Vec<Option<u64>>>,
+) -> Vec<String> {
+    let column_widths = targets
+        .iter()
+        .map(|target| target.len().max(5))
+        .collect::<Vec<usize>>();
+
+    let mut header = String::new();
+    for (target, column_width) in targets.iter().zip(&column_widths) {
+        header.push_str(&format!("{:<width$}  ", target, width = column_width));
+    }
+    header.push_str("   Crate");
+
+    let mut comparison_lines = vec![
+        String::from("Unsafe usage found in each crate, by target:"),
+        String::new(),
+        header,
+        String::new(),
+    ];
+
+    for (package, unsafe_counts) in unsafe_counts_by_package {
+        let mut line = String::new();
+        for (unsafe_count, column_width) in
+            unsafe_counts.iter().zip(&column_widths)
+        {
+            let cell = match unsafe_count {
+                Some(count) => count.to_string(),
+                None => String::from("-"),
+            };
+            line.push_str(&format!("{:<width$}  ", cell, width = column_width));
+        }
+        let is_target_specific = unsafe_counts
+            .iter()
+            .any(|count| count.unwrap_or(0) != unsafe_counts[0].unwrap_or(0));
+        line.push_str(if is_target_specific { "!  " } else { "   " });
+        line.push_str(package);
+        comparison_lines.push(line);
+    }
+
+    comparison_lines.push(String::new());
+    comparison_lines
+}
+
+#[cfg(test)]
+mod compare_targets_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest]
+    fn construct_comparison_lines_test() {
+        let unsafe_counts_by_package = vec![
+            (String::from("both 0.1.0"), vec![Some(2), Some(2)]),
+            (String::from("gated 0.1.0"), vec![None, Some(3)]),
+            (String::from("safe_gated 0.1.0"), vec![Some(0), None]),
+        ]
+        .into_iter()
+        .collect::<BTreeMap<String, Vec<Option<u64>>>>();
+
+        assert_eq!(
+            construct_comparison_lines(
+                &["x86_64-unknown-linux-gnu", "wasm32"],
+                &unsafe_counts_by_package,
+            ),
+            vec![
+                String::from("Unsafe usage found in each crate, by target:"),
+                String::new(),
+                String::from("x86_64-unknown-linux-gnu  wasm32     Crate"),
+                String::new(),
+                String::from("2                         2          both 0.1.0"),
+                String::from(
+                    "-                         3       !  gated 0.1.0"
+                ),
+                String::from(
+                    "0                         -          safe_gated 0.1.0"
+                ),
+                String::new(),
+            ]
+        );
+    }
+}
@@ -1,26 +1,66 @@
+mod diagnostics;
+mod dry_run;
+mod github_actions;
 mod table;
+mod tree;
 
-use crate::args::FeaturesArgs;
+use crate::advisories::AdvisoryDatabase;
+use crate::args::{Args, FeaturesArgs, MessageFormat};
+use crate::custom_patterns::custom_pattern_counts;
+use crate::diff::{load_report, report_delta, version_changes, Baseline};
+use crate::format::csv::render_csv;
+use crate::format::deny_toml::render_deny_toml;
+use crate::format::path_base::PathBases;
 use crate::format::print_config::OutputFormat;
-use crate::graph::Graph;
-use crate::mapping::CargoMetadataParameters;
-use crate::scan::rs_file::resolve_rs_file_deps;
+use crate::format::prometheus::render_prometheus;
+use crate::format::report_delta::render_report_delta;
+use crate::format::report_fields::{
+    deny_report_entry_fields, remove_zero_counts,
+};
+use crate::format::template::render_template;
+use crate::grade::safety_grade;
+use crate::graph::{build_only_package_ids, Graph};
+use crate::mapping::{CargoMetadataParameters, ToCargoGeigerPackageId};
+use crate::scan::rs_file::{resolve_rs_file_deps, RsFileDeps};
+#[cfg(feature = "sqlite")]
+use crate::sqlite::write_report_to_sqlite;
+use crate::std_lib::{find_std_library_path, std_crates_unsafety};
+use crate::weights::UnsafeWeights;
 
-use super::find::find_unsafe;
+use super::find::{find_unsafe, scan_generated_includes};
 use super::{
-    list_files_used_but_not_scanned, package_metrics, unsafe_stats,
-    ScanDetails, ScanMode, ScanParameters, ScanResult,
+    add_example_files_of_packages, add_included_target_kind_files,
+    examples_only_package_ids, fail_threshold_count, included_target_kinds,
+    is_unsafe_ignored, list_files_used_but_not_scanned, module_unsafe_stats,
+    nightly_features, package_metrics, source_hash, test_unsafe_stats,
+    unsafe_kinds, unsafe_stats, ScanDetails, ScanMode, ScanParameters,
+    ScanResult, UnsafeKindFlags,
 };
 
+use diagnostics::scan_to_diagnostics;
+use dry_run::scan_dry_run;
+use github_actions::scan_to_github_actions;
 use table::scan_to_table;
+use tree::scan_to_tree;
 
-use cargo::core::compiler::CompileMode;
+use cargo::core::compiler::{CompileKind, CompileMode, CompileTarget};
 use cargo::core::resolver::features::CliFeatures;
 use cargo::core::Workspace;
 use cargo::ops::CompileOptions;
 use cargo::{CliError, GlobalContext as Config};
-use cargo_geiger_serde::{ReportEntry, SafetyReport};
-use cargo_metadata::PackageId;
+use cargo_geiger_serde::{
+    CounterBlock, PackageAdvisories, PackageCustomPatternCounts,
+    PackageNightlyFeatures, PackageRiskScore, PackageSourceHash,
+    PackageTestUnsafety, PackageVersionChange, ReportEntry, ReportMetadata,
+    SafetyGrade, SafetyReport, UnsafeFreeCounts,
+};
+use cargo_metadata::{Metadata, PackageId};
+use std::collections::{HashMap, HashSet};
+use std::env;
+#[cfg(feature = "sqlite")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn scan_unsafe(
     cargo_metadata_parameters: &CargoMetadataParameters,
@@ -29,8 +69,29 @@ pub fn scan_unsafe(
     scan_parameters: &ScanParameters,
     workspace: &Workspace,
 ) -> Result<ScanResult, CliError> {
+    if scan_parameters.args.dry_run {
+        return scan_dry_run(
+            cargo_metadata_parameters,
+            graph,
+            scan_parameters,
+            workspace,
+        );
+    }
+    if scan_parameters.args.message_format == Some(MessageFormat::Json) {
+        return scan_to_diagnostics(
+            cargo_metadata_parameters,
+            graph,
+            root_package_id,
+            scan_parameters,
+            workspace,
+        );
+    }
+
     match scan_parameters.args.output_format {
-        OutputFormat::Json => scan_to_report(
+        OutputFormat::Json
+        | OutputFormat::Prometheus
+        | OutputFormat::DenyToml
+        | OutputFormat::Csv => scan_to_report(
             cargo_metadata_parameters,
             graph,
             scan_parameters.args.output_format,
@@ -38,6 +99,20 @@ pub fn scan_unsafe(
             scan_parameters,
             workspace,
         ),
+        OutputFormat::GitHubActions => scan_to_github_actions(
+            cargo_metadata_parameters,
+            graph,
+            root_package_id,
+            scan_parameters,
+            workspace,
+        ),
+        OutputFormat::Tree => scan_to_tree(
+            cargo_metadata_parameters,
+            graph,
+            root_package_id,
+            scan_parameters,
+            workspace,
+        ),
         _ => scan_to_table(
             cargo_metadata_parameters,
             graph,
@@ -52,14 +127,24 @@ pub fn scan_unsafe(
 /// constructed without providing all standard cargo options, TODO: Open an issue
 /// in cargo?
 /// Tracker rust-secure-code/cargo-geiger/issues/226
+///
+/// The build is for `target` when given, so that the features of target
+/// specific dependencies, and the files they gate, are resolved for that
+/// target rather than for the host.
 fn build_compile_options<'a>(
     args: &'a FeaturesArgs,
     config: &'a Config,
+    target: Option<&str>,
 ) -> CompileOptions {
     let mut compile_options =
         CompileOptions::new(config, CompileMode::Check { test: false })
             .unwrap();
 
+    if let Some(target) = target {
+        compile_options.build_config.requested_kinds =
+            vec![CompileKind::Target(CompileTarget::new(target).unwrap())];
+    }
+
     let uses_default_features = !args.no_default_features;
 
     compile_options.cli_features = CliFeatures::from_command_line(
@@ -98,22 +183,110 @@ fn scan(
     scan_parameters: &ScanParameters,
     workspace: &Workspace,
 ) -> Result<ScanDetails, CliError> {
+    let advisory_ids = match &scan_parameters.args.advisories {
+        Some(advisories_path) => AdvisoryDatabase::load(advisories_path)
+            .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?
+            .advisory_ids_by_package(cargo_metadata_parameters.metadata),
+        None => HashMap::new(),
+    };
+    let unsafe_weights = match &scan_parameters.args.weights {
+        Some(weights_path) => Some(
+            UnsafeWeights::load(weights_path)
+                .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?,
+        ),
+        None => None,
+    };
+    let baseline = match &scan_parameters.args.diff {
+        Some(baseline_path) => Some(
+            Baseline::load(baseline_path)
+                .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?,
+        ),
+        None => None,
+    };
+    let std_crates = match scan_parameters.args.include_std {
+        true => {
+            let rustc =
+                scan_parameters.config.load_global_rustc(Some(workspace))?;
+            let library_path =
+                find_std_library_path(&rustc.path).map_err(|e| {
+                    eprintln!(
+                        "--include-std scans the source of the standard library, install it with `rustup component add rust-src`"
+                    );
+                    CliError::new(anyhow::Error::new(e), 1)
+                })?;
+            std_crates_unsafety(
+                &library_path,
+                scan_parameters.print_config.include_tests,
+            )
+        }
+        false => vec![],
+    };
+
     let compile_options = build_compile_options(
         &scan_parameters.args.features_args,
         scan_parameters.config,
+        scan_parameters.args.target_args.target.as_deref(),
     );
 
     match resolve_rs_file_deps(&compile_options, workspace) {
-        Ok(rs_files_used) => {
-            let geiger_context = find_unsafe(
+        Ok(RsFileDeps {
+            mut rs_files_used,
+            out_dirs,
+        }) => {
+            let mut geiger_context = find_unsafe(
                 cargo_metadata_parameters,
                 scan_parameters.config,
                 ScanMode::Full,
                 scan_parameters.print_config,
             )?;
+            if scan_parameters.args.scan_generated_includes {
+                scan_generated_includes(
+                    cargo_metadata_parameters,
+                    &mut geiger_context,
+                    &out_dirs,
+                    scan_parameters.print_config,
+                );
+            }
+            add_included_target_kind_files(
+                &geiger_context,
+                &included_target_kinds(scan_parameters.args),
+                &mut rs_files_used,
+            );
+            let examples_only_package_ids = examples_only_package_ids(
+                &cargo_metadata_parameters.metadata.packages,
+            );
+            for package_id in &examples_only_package_ids {
+                eprintln!(
+                    "WARNING: No library or binary target found for package: {}, scanning its examples instead",
+                    package_id
+                );
+            }
+            add_example_files_of_packages(
+                &geiger_context,
+                &examples_only_package_ids,
+                &mut rs_files_used,
+            );
+            let custom_pattern_counts = custom_pattern_counts(
+                &scan_parameters.args.custom_patterns,
+                &geiger_context,
+            );
+            let version_changes = match &baseline {
+                Some(baseline) => version_changes(
+                    baseline,
+                    &geiger_context,
+                    cargo_metadata_parameters.metadata,
+                    &rs_files_used,
+                ),
+                None => HashMap::new(),
+            };
             Ok(ScanDetails {
+                advisory_ids,
+                custom_pattern_counts,
                 rs_files_used,
                 geiger_context,
+                std_crates,
+                unsafe_weights,
+                version_changes,
             })
         }
         Err(rs_resolve_error) => Err(CliError::new(rs_resolve_error.into(), 1)),
@@ -129,15 +302,42 @@ fn scan_to_report(
     workspace: &Workspace,
 ) -> Result<ScanResult, CliError> {
     let ScanDetails {
+        advisory_ids,
+        custom_pattern_counts,
         rs_files_used,
         geiger_context,
+        std_crates,
+        unsafe_weights,
+        version_changes,
     } = scan(cargo_metadata_parameters, scan_parameters, workspace)?;
-    let mut report = SafetyReport::default();
+    let used_unsafe_expr_count = fail_threshold_count(
+        scan_parameters.args,
+        cargo_metadata_parameters,
+        &geiger_context,
+        graph,
+        &root_package_id,
+        &rs_files_used,
+    );
+    let mut report = SafetyReport {
+        metadata: report_metadata(scan_parameters.config, workspace),
+        std_crates,
+        ..Default::default()
+    };
+    let mut active_features =
+        active_features_by_package_id(cargo_metadata_parameters.metadata);
+    // The root package is reported, but left out of the totals like the
+    // ignored packages.
+    let self_package_id = match scan_parameters.args.exclude_self {
+        true => root_package_id
+            .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata),
+        false => None,
+    };
+    let unsafe_kind_flags = UnsafeKindFlags::new(scan_parameters.args);
     for (package, package_metrics_option) in package_metrics(
         cargo_metadata_parameters,
         &geiger_context,
         graph,
-        root_package_id,
+        root_package_id.clone(),
     ) {
         let package_metrics = match package_metrics_option {
             Some(m) => m,
@@ -146,28 +346,465 @@ fn scan_to_report(
                 continue;
             }
         };
-        let unsafe_info = unsafe_stats(&package_metrics, &rs_files_used);
+        if is_unsafe_ignored(
+            &scan_parameters.args.ignore_unsafe_in,
+            &package.id.name,
+            &package.id.version,
+        ) || self_package_id.as_ref() == Some(&package.id)
+        {
+            report.ignored_packages.insert(package.id.clone());
+        }
+        let mut unsafe_info = unsafe_stats(&package_metrics, &rs_files_used);
+        if scan_parameters.args.module_level {
+            unsafe_info.modules =
+                module_unsafe_stats(&package_metrics, &rs_files_used);
+        }
+        unsafe_info.kinds =
+            unsafe_kinds(&package_metrics, &rs_files_used, &unsafe_kind_flags);
+        if let Some(unsafe_weights) = &unsafe_weights {
+            report.risk_scores.push(PackageRiskScore {
+                package: package.id.clone(),
+                risk_score: unsafe_weights.risk_score(&unsafe_info.used),
+            });
+        }
+        if scan_parameters.args.json_include_source_hash {
+            report.source_hashes.push(PackageSourceHash {
+                package: package.id.clone(),
+                source_hash: source_hash(&package_metrics)
+                    .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?,
+            });
+        }
+        if scan_parameters.args.flag_nightly_features {
+            let features = nightly_features(&package_metrics, &rs_files_used);
+            if !features.is_empty() {
+                report.nightly_features.push(PackageNightlyFeatures {
+                    package: package.id.clone(),
+                    features,
+                });
+            }
+        }
+        if scan_parameters.args.count_cfg_test_separately {
+            let (used, unused) =
+                test_unsafe_stats(&package_metrics, &rs_files_used);
+            report.test_unsafety.push(PackageTestUnsafety {
+                package: package.id.clone(),
+                used,
+                unused,
+            });
+        }
         let entry = ReportEntry {
+            active_features: active_features
+                .remove(&package.id)
+                .unwrap_or_default(),
             package,
             unsafety: unsafe_info,
         };
         report.packages.insert(entry.package.id.clone(), entry);
     }
+    report.advisories = advisory_ids
+        .iter()
+        .filter_map(|(package_id, advisory_ids)| {
+            package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+                .map(|package| PackageAdvisories {
+                    package,
+                    advisory_ids: advisory_ids.clone(),
+                })
+        })
+        .collect();
+    report.custom_pattern_counts = custom_pattern_counts
+        .iter()
+        .filter_map(|(package_id, counts)| {
+            package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+                .filter(|package| report.packages.contains_key(package))
+                .map(|package| PackageCustomPatternCounts {
+                    package,
+                    counts: counts.clone(),
+                })
+        })
+        .collect();
+    report.version_changes = version_changes
+        .iter()
+        .filter_map(|(package_id, (baseline_version, change))| {
+            package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+                .filter(|package| report.packages.contains_key(package))
+                .map(|package| PackageVersionChange {
+                    package,
+                    baseline_version: baseline_version.clone(),
+                    change: *change,
+                })
+        })
+        .collect();
+    report.inactive_optional_packages = scan_parameters
+        .inactive_optional_package_ids
+        .iter()
+        .filter(|package_id| graph.nodes.contains_key(*package_id))
+        .filter_map(|package_id| {
+            package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+        })
+        .collect();
+    report.advisories.sort_by(|a, b| a.package.cmp(&b.package));
+    report.risk_scores.sort_by(|a, b| a.package.cmp(&b.package));
+    report
+        .nightly_features
+        .sort_by(|a, b| a.package.cmp(&b.package));
+    report
+        .source_hashes
+        .sort_by(|a, b| a.package.cmp(&b.package));
+    report
+        .test_unsafety
+        .sort_by(|a, b| a.package.cmp(&b.package));
+    report
+        .custom_pattern_counts
+        .sort_by(|a, b| a.package.cmp(&b.package));
+    report
+        .version_changes
+        .sort_by(|a, b| a.package.cmp(&b.package));
     report.used_but_not_scanned_files =
         list_files_used_but_not_scanned(&geiger_context, &rs_files_used)
             .into_iter()
             .collect();
-    let json_string = match output_format {
-        OutputFormat::Json => serde_json::to_string(&report).unwrap(),
-        _ => panic!("Only implemented for OutputFormat::Json"),
+    report.excluded_files = geiger_context.excluded_paths.clone();
+    report.oversized_files = geiger_context.oversized_paths.clone();
+    report.unscanned_packages = geiger_context
+        .unscanned_package_ids
+        .iter()
+        .filter_map(|package_id| {
+            package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+        })
+        .collect();
+    report.timed_out_packages = geiger_context
+        .timed_out_package_ids
+        .iter()
+        .filter_map(|package_id| {
+            package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+        })
+        .collect();
+    report.partial = !report.unscanned_packages.is_empty()
+        || !report.timed_out_packages.is_empty();
+    if scan_parameters.args.separate_build_report {
+        let build_only_package_ids =
+            build_only_package_ids(graph, &root_package_id);
+        for package_id in build_only_package_ids {
+            if let Some(entry) = package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+                .and_then(|package| report.packages.remove(&package))
+            {
+                report
+                    .build_packages
+                    .insert(entry.package.id.clone(), entry);
+            }
+        }
+    }
+    if let Some(path_base) = scan_parameters.args.path_base {
+        let path_bases = PathBases::new(
+            cargo_metadata_parameters.metadata,
+            scan_parameters.config.cwd(),
+        );
+        report.used_but_not_scanned_files = path_bases
+            .relative_paths(path_base, &report.used_but_not_scanned_files);
+        report.excluded_files =
+            path_bases.relative_paths(path_base, &report.excluded_files);
+        report.oversized_files =
+            path_bases.relative_paths(path_base, &report.oversized_files);
+    }
+    let unsafe_free = report.packages_without_metrics.is_empty()
+        && report.used_but_not_scanned_files.is_empty()
+        && report
+            .packages
+            .values()
+            .chain(report.build_packages.values())
+            .all(|entry| {
+                report.ignored_packages.contains(&entry.package.id)
+                    || !entry.unsafety.used.has_unsafe()
+            });
+    report.safety_grade = Some(report_safety_grade(&report));
+    report.unsafe_free_counts = Some(report_unsafe_free_counts(&report));
+    #[cfg(feature = "sqlite")]
+    if let Some(sqlite_path) = &scan_parameters.args.sqlite {
+        let report_name = match &scan_parameters.args.sqlite_report_name {
+            Some(report_name) => report_name.clone(),
+            None => root_package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+                .map(|package| format!("{}@{}", package.name, package.version))
+                .unwrap_or_default(),
+        };
+        let scan_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        write_report_to_sqlite(
+            sqlite_path,
+            &report_name,
+            scan_timestamp,
+            &report,
+        )
+        .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?;
+    }
+    // Before `--min-unsafe` hides some packages, which would otherwise show
+    // up as removed.
+    if let (Some(diff_format), Some(baseline_path)) =
+        (scan_parameters.args.diff_format, &scan_parameters.args.diff)
+    {
+        let baseline_report = load_report(baseline_path)
+            .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?;
+        return Ok(ScanResult {
+            scan_output_lines: vec![render_report_delta(
+                diff_format,
+                &report_delta(
+                    &baseline_report,
+                    &report,
+                    scan_parameters.args.diff_full,
+                ),
+            )],
+            warning_count: 0,
+            unsafe_free,
+            used_unsafe_expr_count,
+        });
+    }
+    if let Some(min_unsafe) = scan_parameters.args.min_unsafe {
+        hide_packages_below_min_unsafe(&mut report, min_unsafe);
+    }
+    if scan_parameters.args.list_unsafe_crates {
+        return Ok(ScanResult {
+            scan_output_lines: unsafe_crate_lines(&report),
+            warning_count: 0,
+            unsafe_free,
+            used_unsafe_expr_count,
+        });
+    }
+    if scan_parameters.args.ndjson {
+        return Ok(ScanResult {
+            scan_output_lines: report_ndjson_lines(
+                &report,
+                scan_parameters.args,
+            ),
+            warning_count: 0,
+            unsafe_free,
+            used_unsafe_expr_count,
+        });
+    }
+    let json_string = match (output_format, &scan_parameters.args.template) {
+        (OutputFormat::Json, Some(template_path)) => {
+            render_template(template_path, &report)
+                .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?
+        }
+        (OutputFormat::Json, None) => {
+            report_json_string(&report, scan_parameters.args)
+        }
+        (OutputFormat::Prometheus, _) => render_prometheus(&report),
+        (OutputFormat::DenyToml, _) => render_deny_toml(
+            &report,
+            scan_parameters.args.deny_threshold.unwrap_or(0),
+        ),
+        (OutputFormat::Csv, _) => render_csv(&report),
+        _ => panic!(
+            "Only implemented for OutputFormat::Json, OutputFormat::Prometheus, OutputFormat::DenyToml and OutputFormat::Csv"
+        ),
     };
 
     Ok(ScanResult {
         scan_output_lines: vec![json_string],
         warning_count: 0,
+        unsafe_free,
+        used_unsafe_expr_count,
     })
 }
 
+/// The Json report, without the fields given to `--fields-deny`, and reduced
+/// to the array of package entries for `--json-array`
+fn report_json_string(report: &SafetyReport, args: &Args) -> String {
+    if args.fields_deny.is_empty() && !args.json_array && !args.compact_json {
+        return serde_json::to_string(report).unwrap();
+    }
+    let mut report_value = serde_json::to_value(report).unwrap();
+    deny_report_entry_fields(&mut report_value, &args.fields_deny);
+    if args.compact_json {
+        remove_zero_counts(&mut report_value);
+    }
+    if args.json_array {
+        report_value["packages"].take().to_string()
+    } else {
+        report_value.to_string()
+    }
+}
+
+/// The package entries of the Json report, one per line, then the status of
+/// the scan, for `--ndjson`. The status is `deadline-truncated`, along with
+/// the packages left unscanned, when the `--deadline` was exceeded, and
+/// `complete` otherwise, so that a consumer reading the lines can tell a
+/// partial report from a full one.
+fn report_ndjson_lines(report: &SafetyReport, args: &Args) -> Vec<String> {
+    let mut report_value = serde_json::to_value(report).unwrap();
+    deny_report_entry_fields(&mut report_value, &args.fields_deny);
+    if args.compact_json {
+        remove_zero_counts(&mut report_value);
+    }
+    let mut lines = match report_value["packages"].take() {
+        serde_json::Value::Array(entries) => {
+            entries.iter().map(serde_json::Value::to_string).collect()
+        }
+        _ => Vec::new(),
+    };
+    let mut unscanned_packages =
+        report.unscanned_packages.iter().collect::<Vec<_>>();
+    unscanned_packages.sort();
+    let status = match unscanned_packages.is_empty() {
+        true => serde_json::json!({ "status": "complete" }),
+        false => serde_json::json!({
+            "status": "deadline-truncated",
+            "unscanned_packages": unscanned_packages,
+        }),
+    };
+    lines.push(status.to_string());
+    lines
+}
+
+/// The `name@version` of the packages of the report which are not ignored and
+/// use unsafe code, sorted, for `--list-unsafe-crates`
+fn unsafe_crate_lines(report: &SafetyReport) -> Vec<String> {
+    let mut unsafe_package_ids = report
+        .packages
+        .values()
+        .chain(report.build_packages.values())
+        .filter(|entry| {
+            !report.ignored_packages.contains(&entry.package.id)
+                && entry.unsafety.used.has_unsafe()
+        })
+        .map(|entry| &entry.package.id)
+        .collect::<Vec<_>>();
+    unsafe_package_ids.sort();
+    unsafe_package_ids.dedup();
+    unsafe_package_ids
+        .into_iter()
+        .map(|package_id| format!("{}@{}", package_id.name, package_id.version))
+        .collect()
+}
+
+/// The safety grade of the packages of the report which are not ignored
+fn report_safety_grade(report: &SafetyReport) -> SafetyGrade {
+    let mut used = CounterBlock::default();
+    let mut unused = CounterBlock::default();
+    let mut unsafe_package_count = 0;
+    for entry in report
+        .packages
+        .values()
+        .chain(report.build_packages.values())
+        .filter(|entry| !report.ignored_packages.contains(&entry.package.id))
+    {
+        used += entry.unsafety.used.clone();
+        unused += entry.unsafety.unused.clone();
+        unsafe_package_count += entry.unsafety.used.has_unsafe() as u64;
+    }
+    safety_grade(&used, &unused, unsafe_package_count)
+}
+
+/// The features enabled for each package by the dependency resolution, sorted
+fn active_features_by_package_id(
+    metadata: &Metadata,
+) -> HashMap<cargo_geiger_serde::PackageId, Vec<String>> {
+    metadata
+        .resolve
+        .iter()
+        .flat_map(|resolve| resolve.nodes.iter())
+        .filter_map(|node| {
+            let mut features = node.features.clone();
+            features.sort();
+            node.id
+                .to_cargo_geiger_package_id(metadata)
+                .map(|package_id| (package_id, features))
+        })
+        .collect()
+}
+
+/// The number of packages of the report which are not ignored free of unsafe
+/// usage
+fn report_unsafe_free_counts(report: &SafetyReport) -> UnsafeFreeCounts {
+    let mut unsafe_free_counts = UnsafeFreeCounts::default();
+    for entry in report
+        .packages
+        .values()
+        .chain(report.build_packages.values())
+        .filter(|entry| !report.ignored_packages.contains(&entry.package.id))
+    {
+        let used_unsafe_free = !entry.unsafety.used.has_unsafe();
+        unsafe_free_counts.package_count += 1;
+        unsafe_free_counts.used_unsafe_free_count += used_unsafe_free as u64;
+        unsafe_free_counts.unsafe_free_count +=
+            (used_unsafe_free && !entry.unsafety.unused.has_unsafe()) as u64;
+    }
+    unsafe_free_counts
+}
+
+/// Remove the packages with less than `min_unsafe` unsafe items used by the
+/// build from the report, along with their extra statistics, for
+/// `--min-unsafe`.
+fn hide_packages_below_min_unsafe(report: &mut SafetyReport, min_unsafe: u64) {
+    let is_shown =
+        |entry: &ReportEntry| entry.unsafety.used.unsafe_count() >= min_unsafe;
+    report.packages.retain(|_, entry| is_shown(entry));
+    report.build_packages.retain(|_, entry| is_shown(entry));
+
+    let shown_package_ids = report
+        .packages
+        .keys()
+        .chain(report.build_packages.keys())
+        .cloned()
+        .collect::<HashSet<_>>();
+    let is_shown = |package_id: &cargo_geiger_serde::PackageId| {
+        shown_package_ids.contains(package_id)
+    };
+    report.advisories.retain(|a| is_shown(&a.package));
+    report.risk_scores.retain(|r| is_shown(&r.package));
+    report.nightly_features.retain(|n| is_shown(&n.package));
+    report.source_hashes.retain(|s| is_shown(&s.package));
+    report.test_unsafety.retain(|t| is_shown(&t.package));
+    report
+        .custom_pattern_counts
+        .retain(|c| is_shown(&c.package));
+    report.version_changes.retain(|v| is_shown(&v.package));
+}
+
+/// The command line flags of the current invocation, without the program name
+/// and the `geiger` subcommand name passed along by cargo. `--jobs` is left
+/// out, so that the reports of scans with different numbers of threads are
+/// identical.
+fn invocation_flags<I>(raw_args: I) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut flags = raw_args.into_iter().skip(1).peekable();
+    if flags.peek().map(String::as_str) == Some("geiger") {
+        flags.next();
+    }
+    let mut invocation_flags = Vec::new();
+    while let Some(flag) = flags.next() {
+        if flag == "--jobs" {
+            flags.next();
+        } else if !flag.starts_with("--jobs=") {
+            invocation_flags.push(flag);
+        }
+    }
+    invocation_flags
+}
+
+fn report_metadata(config: &Config, workspace: &Workspace) -> ReportMetadata {
+    let rustc_version = config
+        .load_global_rustc(Some(workspace))
+        .ok()
+        .map(|rustc| rustc.version.to_string());
+
+    ReportMetadata {
+        geiger_version: String::from(VERSION),
+        rustc_version,
+        flags: invocation_flags(env::args()),
+    }
+}
+
 #[cfg(test)]
 mod default_tests {
     use super::*;
@@ -184,6 +821,9 @@ mod default_tests {
         ),
         case(
             vec![String::from("")],
+        ),
+        case(
+            vec![String::from("dep/feat"), String::from("dep?/feat")],
         )
     )]
     fn build_compile_options_test(input_features: Vec<String>) {
@@ -194,7 +834,7 @@ mod default_tests {
         };
 
         let config = Config::default().unwrap();
-        let compile_options = build_compile_options(&args, &config);
+        let compile_options = build_compile_options(&args, &config, None);
         let expected_cli_features =
             CliFeatures::from_command_line(&args.features, false, false)
                 .unwrap();
@@ -212,4 +852,58 @@ mod default_tests {
             args.no_default_features
         );
     }
+
+    #[rstest(
+        input_target,
+        expected_requested_kinds,
+        case(
+            Some("wasm32-unknown-unknown"),
+            vec![CompileKind::Target(
+                CompileTarget::new("wasm32-unknown-unknown").unwrap()
+            )]
+        ),
+        case(None, vec![CompileKind::Host])
+    )]
+    fn build_compile_options_test_target(
+        input_target: Option<&str>,
+        expected_requested_kinds: Vec<CompileKind>,
+    ) {
+        let args = FeaturesArgs::default();
+
+        let config = Config::default().unwrap();
+        let compile_options =
+            build_compile_options(&args, &config, input_target);
+
+        assert_eq!(
+            compile_options.build_config.requested_kinds,
+            expected_requested_kinds
+        );
+    }
+
+    #[rstest(
+        input_raw_args,
+        expected_flags,
+        case(
+            vec!["cargo-geiger", "geiger", "--output-format", "Json"],
+            vec!["--output-format", "Json"]
+        ),
+        case(
+            vec!["cargo-geiger", "--all-features"],
+            vec!["--all-features"]
+        ),
+        case(
+            vec!["cargo-geiger", "--jobs", "8", "--all-features", "--jobs=2"],
+            vec!["--all-features"]
+        ),
+        case(vec!["cargo-geiger"], vec![])
+    )]
+    fn invocation_flags_test(
+        input_raw_args: Vec<&str>,
+        expected_flags: Vec<&str>,
+    ) {
+        assert_eq!(
+            invocation_flags(input_raw_args.into_iter().map(String::from)),
+            expected_flags
+        );
+    }
 }
@@ -0,0 +1,433 @@
+use crate::graph::Graph;
+use crate::mapping::CargoMetadataParameters;
+use crate::severity::{Severity, SeverityMap};
+
+use super::super::{
+    fail_threshold_count, is_unsafe_ignored, ScanDetails, ScanParameters,
+    ScanResult,
+};
+use super::scan;
+
+use cargo::core::Workspace;
+use cargo::CliError;
+use cargo_metadata::camino::Utf8PathBuf;
+use cargo_metadata::{Package, PackageId, Target};
+use geiger::{find_unsafe_spans_in_string, UnsafeSpan};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The messages printed for `--message-format json`, in the envelope of the
+/// Json messages of cargo, so that the tools reading the diagnostics of cargo
+/// can show the unsafe code in place.
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum Message<'a> {
+    CompilerMessage {
+        package_id: &'a PackageId,
+        manifest_path: &'a Utf8PathBuf,
+        target: &'a Target,
+        message: Diagnostic,
+    },
+    BuildFinished {
+        success: bool,
+    },
+}
+
+/// A diagnostic as emitted by rustc, with every field, even the ones which
+/// are always empty here, since the tools may require them.
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    message: String,
+    code: Option<String>,
+    level: &'static str,
+    spans: Vec<DiagnosticSpan>,
+    children: Vec<Diagnostic>,
+    rendered: Option<String>,
+}
+
+/// Lines and columns start at 1, the end column is exclusive.
+#[derive(Debug, Eq, PartialEq, Serialize)]
+struct DiagnosticSpan {
+    file_name: String,
+    byte_start: usize,
+    byte_end: usize,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+    text: Vec<DiagnosticSpanLine>,
+    label: Option<String>,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+    expansion: Option<String>,
+}
+
+/// A source line of a span, with the highlighted columns.
+#[derive(Debug, Eq, PartialEq, Serialize)]
+struct DiagnosticSpanLine {
+    text: String,
+    highlight_start: usize,
+    highlight_end: usize,
+}
+
+/// Print a cargo compiler message for each unsafe block, function, method,
+/// impl and trait in the files used by the build, followed by the
+/// `build-finished` message. The packages ignored with `--ignore-unsafe-in`
+/// are left out.
+pub fn scan_to_diagnostics(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    graph: &Graph,
+    root_package_id: PackageId,
+    scan_parameters: &ScanParameters,
+    workspace: &Workspace,
+) -> Result<ScanResult, CliError> {
+    let scan_details =
+        scan(cargo_metadata_parameters, scan_parameters, workspace)?;
+    let used_unsafe_expr_count = fail_threshold_count(
+        scan_parameters.args,
+        cargo_metadata_parameters,
+        &scan_details.geiger_context,
+        graph,
+        &root_package_id,
+        &scan_details.rs_files_used,
+    );
+    let severity_map = severity_map(scan_parameters)?;
+
+    let mut scan_output_lines = Vec::new();
+    for file_unsafe_spans in used_unsafe_spans(
+        cargo_metadata_parameters,
+        graph,
+        scan_parameters,
+        &scan_details,
+    )? {
+        let package = file_unsafe_spans.package;
+        let path = &file_unsafe_spans.path;
+        let target = match file_target(package, path) {
+            Some(target) => target,
+            None => continue,
+        };
+        for unsafe_span in &file_unsafe_spans.unsafe_spans {
+            let message = Message::CompilerMessage {
+                package_id: &package.id,
+                manifest_path: &package.manifest_path,
+                target,
+                message: diagnostic(
+                    path,
+                    &file_unsafe_spans.src,
+                    unsafe_span,
+                    severity_map.severity(unsafe_span.kind),
+                ),
+            };
+            scan_output_lines.push(serde_json::to_string(&message).unwrap());
+        }
+    }
+    let unsafe_free = scan_output_lines.is_empty();
+    scan_output_lines.push(
+        serde_json::to_string(&Message::BuildFinished { success: true })
+            .unwrap(),
+    );
+
+    Ok(ScanResult {
+        scan_output_lines,
+        warning_count: 0,
+        unsafe_free,
+        used_unsafe_expr_count,
+    })
+}
+
+/// The unsafe spans of a file used by the build, with its source to show the
+/// lines of the spans.
+pub(super) struct FileUnsafeSpans<'a> {
+    pub package: &'a Package,
+    pub path: PathBuf,
+    pub src: String,
+    pub unsafe_spans: Vec<UnsafeSpan>,
+}
+
+/// The unsafe spans of the files used by the build, by package and then by
+/// path. The packages ignored with `--ignore-unsafe-in` are left out.
+pub(super) fn used_unsafe_spans<'a>(
+    cargo_metadata_parameters: &'a CargoMetadataParameters,
+    graph: &Graph,
+    scan_parameters: &ScanParameters,
+    scan_details: &ScanDetails,
+) -> Result<Vec<FileUnsafeSpans<'a>>, CliError> {
+    let mut used_unsafe_spans = Vec::new();
+    for package in cargo_metadata_parameters
+        .metadata
+        .packages
+        .iter()
+        .filter(|package| graph.nodes.contains_key(&package.id))
+        .filter(|package| {
+            !is_unsafe_ignored(
+                &scan_parameters.args.ignore_unsafe_in,
+                &package.name,
+                &package.version,
+            )
+        })
+    {
+        let package_metrics = match scan_details
+            .geiger_context
+            .package_id_to_metrics
+            .get(&package.id)
+        {
+            Some(package_metrics) => package_metrics,
+            None => continue,
+        };
+        let mut paths = package_metrics
+            .rs_path_to_metrics
+            .keys()
+            .filter(|path| scan_details.rs_files_used.contains(*path))
+            .collect::<Vec<&PathBuf>>();
+        paths.sort();
+        for path in paths {
+            let src = fs::read_to_string(path)
+                .map_err(|e| CliError::new(e.into(), 1))?;
+            let unsafe_spans = find_unsafe_spans_in_string(
+                &src,
+                scan_parameters.print_config.include_tests,
+            )
+            .map_err(|e| CliError::new(e.into(), 1))?;
+            used_unsafe_spans.push(FileUnsafeSpans {
+                package,
+                path: path.clone(),
+                src,
+                unsafe_spans,
+            });
+        }
+    }
+    Ok(used_unsafe_spans)
+}
+
+/// The severity map given to `--severity-map`, otherwise the default one.
+pub(super) fn severity_map(
+    scan_parameters: &ScanParameters,
+) -> Result<SeverityMap, CliError> {
+    match &scan_parameters.args.severity_map {
+        Some(severity_map_path) => SeverityMap::load(severity_map_path)
+            .map_err(|e| CliError::new(anyhow::Error::new(e), 1)),
+        None => Ok(SeverityMap::default()),
+    }
+}
+
+/// The target of the package which a file belongs to: the target of which it
+/// is the root, otherwise the one with the deepest directory containing it,
+/// e.g. `src/bin` rather than `src`.
+fn file_target<'a>(package: &'a Package, path: &Path) -> Option<&'a Target> {
+    let canonical = |path: &Path| {
+        path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    };
+    package
+        .targets
+        .iter()
+        .find(|target| canonical(target.src_path.as_std_path()) == path)
+        .or_else(|| {
+            package
+                .targets
+                .iter()
+                .filter(|target| {
+                    target.src_path.parent().map_or(false, |directory| {
+                        path.starts_with(canonical(directory.as_std_path()))
+                    })
+                })
+                .max_by_key(|target| target.src_path.components().count())
+        })
+        .or_else(|| package.targets.first())
+}
+
+/// The level of a diagnostic follows the severity of the unsafe code: `note`
+/// for info, `warning` for low and medium, `error` for high and critical.
+fn diagnostic(
+    path: &Path,
+    src: &str,
+    unsafe_span: &UnsafeSpan,
+    severity: Severity,
+) -> Diagnostic {
+    let message = unsafe_span.kind.description().to_string();
+    let level = match severity {
+        Severity::Info => "note",
+        Severity::Low | Severity::Medium => "warning",
+        Severity::High | Severity::Critical => "error",
+    };
+    let span = diagnostic_span(path.display().to_string(), src, unsafe_span);
+    let rendered = format!(
+        "{}: {}\n --> {}:{}:{}\n\n",
+        level, message, span.file_name, span.line_start, span.column_start
+    );
+    Diagnostic {
+        message,
+        code: None,
+        level,
+        spans: vec![span],
+        children: vec![],
+        rendered: Some(rendered),
+    }
+}
+
+fn diagnostic_span(
+    file_name: String,
+    src: &str,
+    unsafe_span: &UnsafeSpan,
+) -> DiagnosticSpan {
+    let column_start = unsafe_span.column_start + 1;
+    let column_end = unsafe_span.column_end + 1;
+    let text = src
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .skip(unsafe_span.line_start - 1)
+        .take(unsafe_span.line_end + 1 - unsafe_span.line_start)
+        .map(|(line_number, line)| DiagnosticSpanLine {
+            text: line.to_string(),
+            highlight_start: match line_number == unsafe_span.line_start {
+                true => column_start,
+                false => 1,
+            },
+            highlight_end: match line_number == unsafe_span.line_end {
+                true => column_end,
+                false => line.chars().count() + 1,
+            },
+        })
+        .collect();
+    DiagnosticSpan {
+        file_name,
+        byte_start: byte_offset(
+            src,
+            unsafe_span.line_start,
+            unsafe_span.column_start,
+        ),
+        byte_end: byte_offset(
+            src,
+            unsafe_span.line_end,
+            unsafe_span.column_end,
+        ),
+        line_start: unsafe_span.line_start,
+        line_end: unsafe_span.line_end,
+        column_start,
+        column_end,
+        is_primary: true,
+        text,
+        label: None,
+        suggested_replacement: None,
+        suggestion_applicability: None,
+        expansion: None,
+    }
+}
+
+/// The byte offset in `src` of a line, starting at 1, and a column, starting
+/// at 0 and counted in characters.
+fn byte_offset(src: &str, line: usize, column: usize) -> usize {
+    let line_offset = src
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum::<usize>();
+    line_offset
+        + src[line_offset..]
+            .chars()
+            .take(column)
+            .map(char::len_utf8)
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use super::*;
+
+    use geiger::UnsafeKind;
+    use rstest::*;
+
+    #[rstest(
+        input_line,
+        input_column,
+        expected_byte_offset,
+        case(1, 0, 0),
+        case(1, 3, 3),
+        case(2, 0, 8),
+        case(2, 2, 12),
+        case(3, 1, 16)
+    )]
+    fn byte_offset_test(
+        input_line: usize,
+        input_column: usize,
+        expected_byte_offset: usize,
+    ) {
+        assert_eq!(
+            byte_offset("fn f()\r\nλλ {\n}\n", input_line, input_column),
+            expected_byte_offset
+        );
+    }
+
+    #[rstest(
+        input_severity,
+        expected_level,
+        case(Severity::Info, "note"),
+        case(Severity::Medium, "warning"),
+        case(Severity::Critical, "error")
+    )]
+    fn diagnostic_test_level(input_severity: Severity, expected_level: &str) {
+        let unsafe_span = UnsafeSpan {
+            kind: UnsafeKind::Block,
+            line_start: 1,
+            column_start: 0,
+            line_end: 1,
+            column_end: 9,
+        };
+
+        let diagnostic = diagnostic(
+            Path::new("src/lib.rs"),
+            "unsafe {}\n",
+            &unsafe_span,
+            input_severity,
+        );
+
+        assert_eq!(diagnostic.level, expected_level);
+        assert_eq!(
+            diagnostic.rendered,
+            Some(format!(
+                "{}: unsafe block\n --> src/lib.rs:1:1\n\n",
+                expected_level
+            ))
+        );
+    }
+
+    #[rstest]
+    fn diagnostic_span_test() {
+        let src = "fn f() {\n    unsafe {\n        g();\n    }\n}\n";
+        let unsafe_span = UnsafeSpan {
+            kind: UnsafeKind::Block,
+            line_start: 2,
+            column_start: 4,
+            line_end: 4,
+            column_end: 5,
+        };
+
+        let span =
+            diagnostic_span(String::from("src/lib.rs"), src, &unsafe_span);
+
+        assert_eq!((span.byte_start, span.byte_end), (13, 40));
+        assert_eq!((span.column_start, span.column_end), (5, 6));
+        assert_eq!(
+            span.text,
+            vec![
+                DiagnosticSpanLine {
+                    text: String::from("    unsafe {"),
+                    highlight_start: 5,
+                    highlight_end: 13,
+                },
+                DiagnosticSpanLine {
+                    text: String::from("        g();"),
+                    highlight_start: 1,
+                    highlight_end: 13,
+                },
+                DiagnosticSpanLine {
+                    text: String::from("    }"),
+                    highlight_start: 1,
+                    highlight_end: 6,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use crate::graph::Graph;
+use crate::mapping::CargoMetadataParameters;
+use crate::scan::rs_file::{
+    into_is_entry_point_and_path_buf, resolve_rs_file_deps,
+};
+
+use super::super::find::find_rs_files_in_package;
+use super::super::{ScanParameters, ScanResult};
+use super::build_compile_options;
+
+use cargo::core::Workspace;
+use cargo::CliError;
+
+/// The `.rs` files a scan would parse for a single package
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct DryRunEntry {
+    package: String,
+    file_count: usize,
+    used_file_count: usize,
+}
+
+/// Resolve the dependency graph and the files used by the build, then list
+/// the packages and the number of files that would be scanned, without
+/// parsing any of them.
+pub fn scan_dry_run(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    graph: &Graph,
+    scan_parameters: &ScanParameters,
+    workspace: &Workspace,
+) -> Result<ScanResult, CliError> {
+    let compile_options = build_compile_options(
+        &scan_parameters.args.features_args,
+        scan_parameters.config,
+        scan_parameters.args.target_args.target.as_deref(),
+    );
+    let rs_files_used = resolve_rs_file_deps(&compile_options, workspace)
+        .map_err(|e| CliError::new(e.into(), 1))?
+        .rs_files_used;
+
+    let mut dry_run_entries = cargo_metadata_parameters
+        .metadata
+        .packages
+        .iter()
+        .filter(|package| graph.nodes.contains_key(&package.id))
+        .map(|package| {
+            let path_bufs = find_rs_files_in_package(package)
+                .into_iter()
+                .map(|(rs_file, _)| into_is_entry_point_and_path_buf(rs_file).1)
+                .collect::<Vec<_>>();
+            DryRunEntry {
+                package: scan_parameters
+                    .print_config
+                    .format
+                    .display(cargo_metadata_parameters, &package.id)
+                    .to_string(),
+                file_count: path_bufs.len(),
+                used_file_count: path_bufs
+                    .iter()
+                    .filter(|path_buf| rs_files_used.contains(*path_buf))
+                    .count(),
+            }
+        })
+        .collect::<Vec<DryRunEntry>>();
+    dry_run_entries.sort_by(|a, b| a.package.cmp(&b.package));
+
+    Ok(ScanResult {
+        scan_output_lines: construct_dry_run_lines(&dry_run_entries),
+        warning_count: 0,
+        unsafe_free: false,
+        used_unsafe_expr_count: None,
+    })
+}
+
+fn construct_dry_run_lines(dry_run_entries: &[DryRunEntry]) -> Vec<String> {
+    let mut dry_run_lines = vec![
+        String::from("Dry run, no files were scanned."),
+        String::new(),
+        String::from("Files  Used by build  Package"),
+        String::new(),
+    ];
+    for dry_run_entry in dry_run_entries {
+        dry_run_lines.push(format!(
+            "{:<5}  {:<13}  {}",
+            dry_run_entry.file_count,
+            dry_run_entry.used_file_count,
+            dry_run_entry.package
+        ));
+    }
+    dry_run_lines.push(String::new());
+    dry_run_lines.push(format!(
+        "{} files in {} packages would be scanned.",
+        dry_run_entries
+            .iter()
+            .map(|dry_run_entry| dry_run_entry.file_count)
+            .sum::<usize>(),
+        dry_run_entries.len()
+    ));
+    dry_run_lines
+}
+
+#[cfg(test)]
+mod dry_run_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest]
+    fn construct_dry_run_lines_test() {
+        let dry_run_entries = vec![
+            DryRunEntry {
+                package: String::from("first 0.1.0"),
+                file_count: 3,
+                used_file_count: 2,
+            },
+            DryRunEntry {
+                package: String::from("second 1.0.0"),
+                file_count: 1,
+                used_file_count: 1,
+            },
+        ];
+
+        assert_eq!(
+            construct_dry_run_lines(&dry_run_entries),
+            vec![
+                String::from("Dry run, no files were scanned."),
+                String::new(),
+                String::from("Files  Used by build  Package"),
+                String::new(),
+                String::from("3      2              first 0.1.0"),
+                String::from("1      1              second 1.0.0"),
+                String::new(),
+                String::from("4 files in 2 packages would be scanned."),
+            ]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::args::PathBase;
+use crate::format::path_base::PathBases;
+use crate::graph::Graph;
+use crate::mapping::CargoMetadataParameters;
+use crate::severity::Severity;
+
+use super::super::{fail_threshold_count, ScanParameters, ScanResult};
+use super::diagnostics::{severity_map, used_unsafe_spans};
+use super::scan;
+
+use cargo::core::Workspace;
+use cargo::CliError;
+use cargo_metadata::PackageId;
+use geiger::UnsafeSpan;
+
+/// Print a GitHub Actions workflow command for each unsafe block, function,
+/// method, impl and trait in the files used by the build, for
+/// `--output-format GitHubActions`, so that the runner annotates the lines in
+/// the diff of the pull request. The paths are relative to the base given to
+/// `--path-base`, the workspace root by default, which is the root of the
+/// repository the annotations are attached to. The packages ignored with
+/// `--ignore-unsafe-in` are left out.
+pub fn scan_to_github_actions(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    graph: &Graph,
+    root_package_id: PackageId,
+    scan_parameters: &ScanParameters,
+    workspace: &Workspace,
+) -> Result<ScanResult, CliError> {
+    let scan_details =
+        scan(cargo_metadata_parameters, scan_parameters, workspace)?;
+    let used_unsafe_expr_count = fail_threshold_count(
+        scan_parameters.args,
+        cargo_metadata_parameters,
+        &scan_details.geiger_context,
+        graph,
+        &root_package_id,
+        &scan_details.rs_files_used,
+    );
+    let severity_map = severity_map(scan_parameters)?;
+    let path_bases = PathBases::new(
+        cargo_metadata_parameters.metadata,
+        scan_parameters.config.cwd(),
+    );
+    let path_base = scan_parameters
+        .args
+        .path_base
+        .unwrap_or(PathBase::Workspace);
+
+    let mut scan_output_lines = Vec::new();
+    for file_unsafe_spans in used_unsafe_spans(
+        cargo_metadata_parameters,
+        graph,
+        scan_parameters,
+        &scan_details,
+    )? {
+        let package = file_unsafe_spans.package;
+        let file_name = path_bases
+            .relative_path(path_base, &file_unsafe_spans.path)
+            .display()
+            .to_string();
+        for unsafe_span in &file_unsafe_spans.unsafe_spans {
+            let severity = severity_map.severity(unsafe_span.kind);
+            scan_output_lines.push(annotation_command(
+                &file_name,
+                unsafe_span,
+                severity,
+                &format!(
+                    "{} in {} {} (severity: {})",
+                    unsafe_span.kind.description(),
+                    package.name,
+                    package.version,
+                    severity.name()
+                ),
+            ));
+        }
+    }
+    let unsafe_free = scan_output_lines.is_empty();
+
+    Ok(ScanResult {
+        scan_output_lines,
+        warning_count: 0,
+        unsafe_free,
+        used_unsafe_expr_count,
+    })
+}
+
+/// The command follows the severity of the unsafe code: `notice` for info,
+/// `warning` for low and medium, `error` for high and critical. The lines of
+/// the command start at 1, as do the columns, which the end column is
+/// exclusive of, like in the diagnostics of rustc.
+fn annotation_command(
+    file_name: &str,
+    unsafe_span: &UnsafeSpan,
+    severity: Severity,
+    message: &str,
+) -> String {
+    let command = match severity {
+        Severity::Info => "notice",
+        Severity::Low | Severity::Medium => "warning",
+        Severity::High | Severity::Critical => "error",
+    };
+    format!(
+        "::{} file={},line={},endLine={},col={},endColumn={},title={}::{}",
+        command,
+        escape_property(file_name),
+        unsafe_span.line_start,
+        unsafe_span.line_end,
+        unsafe_span.column_start + 1,
+        unsafe_span.column_end + 1,
+        escape_property("cargo-geiger"),
+        escape_data(message)
+    )
+}
+
+/// The message of a command ends at the end of the line, so only the line
+/// breaks and the escape character itself are escaped.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// The properties of a command are separated by commas and end at the colons
+/// before the message, so these are also escaped.
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod github_actions_tests {
+    use super::*;
+
+    use geiger::UnsafeKind;
+    use rstest::*;
+
+    #[rstest(
+        input_severity,
+        expected_command,
+        case(Severity::Info, "notice"),
+        case(Severity::Low, "warning"),
+        case(Severity::Medium, "warning"),
+        case(Severity::High, "error"),
+        case(Severity::Critical, "error")
+    )]
+    fn annotation_command_test(
+        input_severity: Severity,
+        expected_command: &str,
+    ) {
+        let unsafe_span = UnsafeSpan {
+            kind: UnsafeKind::Block,
+            line_start: 2,
+            column_start: 4,
+            line_end: 4,
+            column_end: 5,
+        };
+
+        assert_eq!(
+            annotation_command(
+                "src/lib.rs",
+                &unsafe_span,
+                input_severity,
+                "unsafe block in a 1.0.0"
+            ),
+            format!(
+                "::{} file=src/lib.rs,line=2,endLine=4,col=5,endColumn=6,title=cargo-geiger::unsafe block in a 1.0.0",
+                expected_command
+            )
+        );
+    }
+
+    #[rstest(
+        input_property,
+        expected_escaped_property,
+        case("src/lib.rs", "src/lib.rs"),
+        case("C:\\a,b\\lib.rs", "C%3A\\a%2Cb\\lib.rs"),
+        case("100%\n", "100%25%0A")
+    )]
+    fn escape_property_test(
+        input_property: &str,
+        expected_escaped_property: &str,
+    ) {
+        assert_eq!(escape_property(input_property), expected_escaped_property);
+    }
+
+    #[rstest]
+    fn escape_data_test() {
+        assert_eq!(escape_data("a: b, 100%\r\n"), "a: b, 100%25%0D%0A");
+    }
+}
@@ -5,13 +5,14 @@ use crate::format::table::{
     create_table_from_text_tree_lines, TableParameters, UNSAFE_COUNTERS_HEADER,
 };
 use crate::format::SymbolKind;
-use crate::graph::Graph;
-use crate::mapping::CargoMetadataParameters;
+use crate::graph::{build_only_package_ids, Graph};
+use crate::mapping::{CargoMetadataParameters, ToCargoGeigerPackageId};
 use crate::tree::traversal::walk_dependency_tree;
 
 use super::super::{
-    construct_rs_files_used_lines, list_files_used_but_not_scanned,
-    ScanDetails, ScanParameters, ScanResult,
+    construct_rs_files_used_lines, fail_threshold_count, ignored_package_ids,
+    list_files_used_but_not_scanned, nightly_features, ScanDetails,
+    ScanOutputLines, ScanParameters, ScanResult, UnsafeKindFlags,
 };
 use super::scan;
 
@@ -27,27 +28,62 @@ pub fn scan_to_table(
     scan_parameters: &ScanParameters,
     workspace: &Workspace,
 ) -> Result<ScanResult, CliError> {
-    let mut combined_scan_output_lines = Vec::<String>::new();
+    // The output is only printed at the end when it is written to a readme,
+    // or when it is only printed if unsafe usage is found.
+    let stream = !scan_parameters.args.readme_args.update_readme
+        && !scan_parameters.args.quiet_unsafe_free;
+    let mut combined_scan_output_lines = ScanOutputLines::new(stream);
+
+    let emoji_symbols =
+        EmojiSymbols::new(scan_parameters.print_config.output_format);
+    let output_key_lines = construct_key_lines(
+        &emoji_symbols,
+        scan_parameters.print_config.output_format,
+    );
+    // The files used by the build are only known after the scan, otherwise
+    // the key is printed first so that the output starts before the scan.
+    let is_verbose = scan_parameters.args.verbosity != Verbosity::Quiet;
+    if !is_verbose {
+        combined_scan_output_lines.append(output_key_lines.clone());
+    }
 
     let ScanDetails {
+        advisory_ids,
+        custom_pattern_counts,
         rs_files_used,
         geiger_context,
+        std_crates,
+        unsafe_weights,
+        version_changes,
     } = scan(cargo_metadata_parameters, scan_parameters, workspace)?;
 
-    if scan_parameters.args.verbosity != Verbosity::Quiet {
-        let mut rs_files_used_lines =
-            construct_rs_files_used_lines(&rs_files_used);
-        combined_scan_output_lines.append(&mut rs_files_used_lines);
+    if is_verbose {
+        combined_scan_output_lines
+            .append(construct_rs_files_used_lines(&rs_files_used));
+        combined_scan_output_lines.append(output_key_lines);
     }
 
-    let emoji_symbols =
-        EmojiSymbols::new(scan_parameters.print_config.output_format);
-    let mut output_key_lines = construct_key_lines(
-        &emoji_symbols,
-        scan_parameters.print_config.output_format,
-    );
-    combined_scan_output_lines.append(&mut output_key_lines);
+    let build_only_package_ids = if scan_parameters.args.separate_build_report {
+        Some(build_only_package_ids(graph, &root_package_id))
+    } else {
+        None
+    };
 
+    let mut ignored_package_ids = ignored_package_ids(
+        cargo_metadata_parameters,
+        &scan_parameters.args.ignore_unsafe_in,
+    );
+    if scan_parameters.args.exclude_self {
+        ignored_package_ids.insert(root_package_id.clone());
+    }
+    let used_unsafe_expr_count = fail_threshold_count(
+        scan_parameters.args,
+        cargo_metadata_parameters,
+        &geiger_context,
+        graph,
+        &root_package_id,
+        &rs_files_used,
+    );
     let text_tree_lines = walk_dependency_tree(
         cargo_metadata_parameters,
         graph,
@@ -55,20 +91,42 @@ pub fn scan_to_table(
         root_package_id,
     );
     let table_parameters = TableParameters {
+        advisory_ids: &advisory_ids,
+        build_only_package_ids: build_only_package_ids.as_ref(),
+        count_cfg_test_separately: scan_parameters
+            .args
+            .count_cfg_test_separately,
+        custom_pattern_counts: &custom_pattern_counts,
+        flag_nightly_features: scan_parameters.args.flag_nightly_features,
         geiger_context: &geiger_context,
+        group_by: scan_parameters.args.group_by,
+        group_unsafe_by_kind: scan_parameters.args.group_unsafe_by_kind,
+        ignored_package_ids: &ignored_package_ids,
+        inactive_optional_package_ids: scan_parameters
+            .inactive_optional_package_ids,
+        min_unsafe: scan_parameters.args.min_unsafe,
         print_config: scan_parameters.print_config,
+        print_grade: scan_parameters.args.print_grade,
+        print_unsafe_free: scan_parameters.args.print_unsafe_free,
+        rank: scan_parameters.args.rank,
         rs_files_used: &rs_files_used,
+        std_crates: &std_crates,
+        unsafe_kind_flags: UnsafeKindFlags::new(scan_parameters.args),
+        unsafe_weights,
+        version_changes: &version_changes,
     };
 
     let ScanResult {
-        mut scan_output_lines,
+        scan_output_lines,
         mut warning_count,
+        unsafe_free,
+        ..
     } = create_table_from_text_tree_lines(
         cargo_metadata_parameters,
         &table_parameters,
         text_tree_lines,
+        combined_scan_output_lines,
     );
-    combined_scan_output_lines.append(&mut scan_output_lines);
 
     let used_but_not_scanned =
         list_files_used_but_not_scanned(&geiger_context, &rs_files_used);
@@ -80,9 +138,41 @@ pub fn scan_to_table(
         );
     }
 
+    if scan_parameters.args.flag_nightly_features {
+        let mut nightly_packages = geiger_context
+            .package_id_to_metrics
+            .iter()
+            .filter(|(package_id, _)| graph.nodes.contains_key(*package_id))
+            .filter_map(|(package_id, package_metrics)| {
+                let features =
+                    nightly_features(package_metrics, &rs_files_used);
+                if features.is_empty() {
+                    return None;
+                }
+                package_id
+                    .to_cargo_geiger_package_id(
+                        cargo_metadata_parameters.metadata,
+                    )
+                    .map(|package| (package, features))
+            })
+            .collect::<Vec<_>>();
+        nightly_packages.sort_by(|a, b| a.0.cmp(&b.0));
+        warning_count += nightly_packages.len() as u64;
+        for (package, features) in &nightly_packages {
+            eprintln!(
+                "WARNING: {} {} requires a nightly toolchain for its features: {}",
+                package.name,
+                package.version,
+                features.join(", ")
+            );
+        }
+    }
+
     Ok(ScanResult {
-        scan_output_lines: combined_scan_output_lines,
+        scan_output_lines,
         warning_count,
+        unsafe_free: unsafe_free && used_but_not_scanned.is_empty(),
+        used_unsafe_expr_count,
     })
 }
 
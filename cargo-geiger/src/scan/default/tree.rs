@@ -0,0 +1,165 @@
+use crate::format::get_kind_group_name;
+use crate::graph::Graph;
+use crate::mapping::{CargoMetadataParameters, ToCargoGeigerPackageId};
+use crate::tree::traversal::walk_dependency_tree;
+use crate::tree::TextTreeLine;
+
+use super::super::{
+    fail_threshold_count, list_files_used_but_not_scanned, unsafe_stats,
+    ScanDetails, ScanParameters, ScanResult,
+};
+use super::scan;
+
+use cargo::core::Workspace;
+use cargo::CliError;
+use cargo_metadata::PackageId;
+use std::collections::HashSet;
+
+/// Print the dependency tree indented as cargo tree does, without the table
+/// and the box drawing, each crate followed by its number of unsafe items used
+/// by the build, for `--output-format Tree`. The tree is walked as for the
+/// table, so `--invert`, `--all` and `--depth` apply.
+pub fn scan_to_tree(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    graph: &Graph,
+    root_package_id: PackageId,
+    scan_parameters: &ScanParameters,
+    workspace: &Workspace,
+) -> Result<ScanResult, CliError> {
+    let ScanDetails {
+        rs_files_used,
+        geiger_context,
+        ..
+    } = scan(cargo_metadata_parameters, scan_parameters, workspace)?;
+    let used_unsafe_expr_count = fail_threshold_count(
+        scan_parameters.args,
+        cargo_metadata_parameters,
+        &geiger_context,
+        graph,
+        &root_package_id,
+        &rs_files_used,
+    );
+
+    let mut scan_output_lines = Vec::new();
+    let mut warning_count = 0;
+    let mut unsafe_free = true;
+    let mut packages_without_metrics = HashSet::new();
+    for text_tree_line in walk_dependency_tree(
+        cargo_metadata_parameters,
+        graph,
+        scan_parameters.print_config,
+        root_package_id,
+    ) {
+        match text_tree_line {
+            TextTreeLine::ExtraDepsGroup { kind, tree_vines } => {
+                if let Some(name) = get_kind_group_name(kind) {
+                    scan_output_lines.push(format!("{}{}", tree_vines, name));
+                }
+            }
+            TextTreeLine::Package {
+                id,
+                tree_vines,
+                dependencies_elided,
+            } => {
+                let package_metrics =
+                    match geiger_context.package_id_to_metrics.get(&id) {
+                        Some(package_metrics) => package_metrics,
+                        None => {
+                            if packages_without_metrics.insert(id.clone()) {
+                                warning_count += 1;
+                                eprintln!(
+                                    "WARNING: No metrics found for package: {}",
+                                    id
+                                );
+                            }
+                            continue;
+                        }
+                    };
+                let package = match id.to_cargo_geiger_package_id(
+                    cargo_metadata_parameters.metadata,
+                ) {
+                    Some(package) => package,
+                    None => continue,
+                };
+                let unsafe_count =
+                    unsafe_stats(package_metrics, &rs_files_used)
+                        .used
+                        .unsafe_count();
+                unsafe_free &= unsafe_count == 0;
+                scan_output_lines.push(package_line(
+                    &tree_vines,
+                    &package,
+                    unsafe_count,
+                    dependencies_elided,
+                ));
+            }
+        }
+    }
+
+    let used_but_not_scanned =
+        list_files_used_but_not_scanned(&geiger_context, &rs_files_used);
+    warning_count += used_but_not_scanned.len() as u64;
+    for path in &used_but_not_scanned {
+        eprintln!(
+            "WARNING: Dependency file was never scanned: {}",
+            path.display()
+        );
+    }
+
+    Ok(ScanResult {
+        scan_output_lines,
+        warning_count,
+        unsafe_free: unsafe_free && used_but_not_scanned.is_empty(),
+        used_unsafe_expr_count,
+    })
+}
+
+/// The occurrences of a crate after the first are marked with `(*)`, as in
+/// cargo tree, their dependencies not being displayed again.
+fn package_line(
+    tree_vines: &str,
+    package: &cargo_geiger_serde::PackageId,
+    unsafe_count: u64,
+    dependencies_elided: bool,
+) -> String {
+    format!(
+        "{}{} v{} ({} unsafe){}",
+        tree_vines,
+        package.name,
+        package.version,
+        unsafe_count,
+        if dependencies_elided { " (*)" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tree_tests {
+    use super::*;
+
+    use cargo_geiger_serde::Source;
+    use cargo_metadata::semver::Version;
+    use rstest::*;
+    use url::Url;
+
+    #[rstest(
+        input_dependencies_elided,
+        expected_package_line,
+        case(false, "    a v1.0.0 (3 unsafe)"),
+        case(true, "    a v1.0.0 (3 unsafe) (*)")
+    )]
+    fn package_line_test(
+        input_dependencies_elided: bool,
+        expected_package_line: &str,
+    ) {
+        let package = cargo_geiger_serde::PackageId {
+            name: String::from("a"),
+            version: Version::new(1, 0, 0),
+            source: Source::Path(Url::parse("file:///a").unwrap()),
+        };
+
+        assert_eq!(
+            package_line("    ", &package, 3, input_dependencies_elided),
+            expected_package_line
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::args::Args;
+use crate::format::print_config::PrintConfig;
+use crate::graph::Graph;
+use crate::mapping::CargoMetadataParameters;
+
+use super::compare_targets::total_unsafe_count;
+use super::find::find_unsafe;
+use super::{ScanMode, ScanResult};
+
+use cargo::{CliError, GlobalContext as Config};
+use std::collections::BTreeMap;
+
+/// List the crates of the dependency tree resolved with `feature` which are
+/// not part of the tree resolved without it, for `--feature-impact`, with all
+/// the unsafe usage found in them. As for `--compare-targets`, nothing is
+/// built.
+pub fn scan_feature_impact(
+    args: &Args,
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    config: &Config,
+    feature: &str,
+    graph: &Graph,
+    graph_without_feature: &Graph,
+) -> Result<ScanResult, CliError> {
+    let print_config = PrintConfig::new(args)?;
+    let geiger_context = find_unsafe(
+        cargo_metadata_parameters,
+        config,
+        ScanMode::Full,
+        &print_config,
+    )?;
+
+    let unsafe_counts_by_package = graph
+        .nodes
+        .keys()
+        .filter(|package_id| {
+            !graph_without_feature.nodes.contains_key(package_id)
+        })
+        .map(|package_id| {
+            (
+                print_config
+                    .format
+                    .display(cargo_metadata_parameters, package_id)
+                    .to_string(),
+                total_unsafe_count(&geiger_context, package_id),
+            )
+        })
+        .collect::<BTreeMap<String, u64>>();
+
+    Ok(ScanResult {
+        scan_output_lines: construct_feature_impact_lines(
+            feature,
+            &unsafe_counts_by_package,
+        ),
+        warning_count: 0,
+        unsafe_free: false,
+        used_unsafe_expr_count: None,
+    })
+}
+
+fn construct_feature_impact_lines(
+    feature: &str,
+    unsafe_counts_by_package: &BTreeMap<String, u64>,
+) -> Vec<String> {
+    if unsafe_counts_by_package.is_empty() {
+        return vec![
+            format!("No crates are pulled in by the feature `{}`.", feature),
+            String::new(),
+        ];
+    }
+
+    let mut feature_impact_lines = vec![
+        format!(
+            "Unsafe usage found in the crates pulled in by the feature `{}`:",
+            feature
+        ),
+        String::new(),
+        String::from("Unsafe  Crate"),
+        String::new(),
+    ];
+    for (package, unsafe_count) in unsafe_counts_by_package {
+        feature_impact_lines.push(format!("{:<6}  {}", unsafe_count, package));
+    }
+    feature_impact_lines.push(String::new());
+    feature_impact_lines.push(format!(
+        "{} crates, {} unsafe usage in total",
+        unsafe_counts_by_package.len(),
+        unsafe_counts_by_package.values().sum::<u64>()
+    ));
+    feature_impact_lines.push(String::new());
+    feature_impact_lines
+}
+
+#[cfg(test)]
+mod feature_impact_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest]
+    fn construct_feature_impact_lines_test() {
+        let unsafe_counts_by_package = vec![
+            (String::from("safe_dep 0.1.0"), 0),
+            (String::from("unsafe_dep 0.2.0"), 3),
+        ]
+        .into_iter()
+        .collect::<BTreeMap<String, u64>>();
+
+        assert_eq!(
+            construct_feature_impact_lines("unsafe", &unsafe_counts_by_package),
+            vec![
+                String::from(
+                    "Unsafe usage found in the crates pulled in by the feature `unsafe`:"
+                ),
+                String::new(),
+                String::from("Unsafe  Crate"),
+                String::new(),
+                String::from("0       safe_dep 0.1.0"),
+                String::from("3       unsafe_dep 0.2.0"),
+                String::new(),
+                String::from("2 crates, 3 unsafe usage in total"),
+                String::new(),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn construct_feature_impact_lines_test_without_crates() {
+        assert_eq!(
+            construct_feature_impact_lines("std", &BTreeMap::new()),
+            vec![
+                String::from("No crates are pulled in by the feature `std`."),
+                String::new(),
+            ]
+        );
+    }
+}
@@ -1,24 +1,34 @@
+mod parse_cache;
+
 use crate::format::print_config::PrintConfig;
 use crate::mapping::{CargoMetadataParameters, GetPackageRoot};
 use crate::scan::rs_file::{
-    into_is_entry_point_and_path_buf, into_rs_code_file, into_target_kind,
-    is_file_with_ext, RsFile, RsFileMetricsWrapper,
+    into_example_dirs, into_is_entry_point_and_path_buf, into_rs_code_file,
+    into_rs_file_target_kind_from_path,
+    into_rs_file_target_kind_from_target_kind, into_target_kind,
+    is_file_with_ext, RsFile, RsFileMetricsWrapper, RsFileTargetKind,
 };
 use crate::scan::PackageMetrics;
 
 use super::{GeigerContext, ScanMode};
 
+use parse_cache::ParseCache;
+
+use cargo::core::manifest::TargetKind;
 use cargo::{CargoResult, CliError, GlobalContext as Config};
 use cargo_metadata::PackageId;
-use geiger::find::find_unsafe_in_file;
 use geiger::{IncludeTests, RsFileMetrics, ScanFileError};
 use rayon::{in_place_scope, prelude::*};
-use std::collections::{HashMap, HashSet};
+use regex::RegexSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::mpsc::sync_channel;
+use std::sync::mpsc::{channel, sync_channel, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
 pub fn find_unsafe(
@@ -27,26 +37,194 @@ pub fn find_unsafe(
     mode: ScanMode,
     print_config: &PrintConfig,
 ) -> Result<GeigerContext, CliError> {
+    let generated_file_patterns =
+        RegexSet::new(&print_config.generated_file_patterns)
+            .map_err(|e| CliError::new(e.into(), 1))?;
     let mut progress = cargo::util::Progress::new("Scanning", config);
+    let disk_cache_dir =
+        disk_cache_dir(cargo_metadata_parameters, print_config);
     let geiger_context = find_unsafe_in_packages_with_progress(
         print_config.allow_partial_results,
         cargo_metadata_parameters,
+        print_config.deadline,
+        disk_cache_dir.as_deref(),
+        &generated_file_patterns,
         print_config.include_tests,
+        print_config.max_file_size,
         mode,
+        print_config.scan_timeout_per_crate,
+        &print_config.unsafe_macros,
+        &print_config.memory_lifecycle_functions,
         |progress_count, count| {
             progress.tick(progress_count, count, "find_unsafe_tick")
         },
     );
     progress.clear();
+    if !geiger_context.oversized_paths.is_empty() {
+        let mut oversized_paths = geiger_context
+            .oversized_paths
+            .iter()
+            .collect::<Vec<&PathBuf>>();
+        oversized_paths.sort();
+        eprintln!(
+            "WARNING: The files larger than --max-file-size were not scanned:"
+        );
+        for path in oversized_paths {
+            eprintln!("    {}", path.display());
+        }
+    }
+    if !geiger_context.timed_out_package_ids.is_empty() {
+        let mut timed_out_package_ids = geiger_context
+            .timed_out_package_ids
+            .iter()
+            .collect::<Vec<&PackageId>>();
+        timed_out_package_ids.sort();
+        eprintln!(
+            "WARNING: The scan of these packages lasted longer than --scan-timeout-per-crate, their metrics are partial:"
+        );
+        for package_id in timed_out_package_ids {
+            eprintln!("    {}", package_id);
+        }
+    }
+    if !geiger_context.unscanned_package_ids.is_empty() {
+        let mut unscanned_package_ids = geiger_context
+            .unscanned_package_ids
+            .iter()
+            .collect::<Vec<&PackageId>>();
+        unscanned_package_ids.sort();
+        eprintln!(
+            "WARNING: The deadline was exceeded, the report is partial. Packages not scanned:"
+        );
+        for package_id in unscanned_package_ids {
+            eprintln!("    {}", package_id);
+        }
+    }
     config.shell().status("Scanning", "done")?;
     Ok(geiger_context)
 }
 
+/// Scan the files generated in `OUT_DIR` by the build script of a package and
+/// included with `include!(concat!(env!("OUT_DIR"), ...))` by its code, for
+/// `--scan-generated-includes`. `out_dirs` is the `OUT_DIR` of the crates by
+/// the path of their entry point, only known once they are built. A generated
+/// file already found in the directory of another package, e.g. in the
+/// `target` directory of the root package, is moved to the package including
+/// it.
+pub fn scan_generated_includes(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    geiger_context: &mut GeigerContext,
+    out_dirs: &HashMap<PathBuf, PathBuf>,
+    print_config: &PrintConfig,
+) {
+    let parse_cache = ParseCache::new(
+        print_config.unsafe_macros.clone(),
+        print_config.memory_lifecycle_functions.clone(),
+        disk_cache_dir(cargo_metadata_parameters, print_config).as_deref(),
+    );
+    for package in &cargo_metadata_parameters.metadata.packages {
+        let out_dir = match package.targets.iter().find_map(|target| {
+            target
+                .src_path
+                .canonicalize()
+                .ok()
+                .and_then(|path_buf| out_dirs.get(&path_buf))
+        }) {
+            Some(out_dir) => out_dir,
+            None => continue,
+        };
+        let includes =
+            match geiger_context.package_id_to_metrics.get(&package.id) {
+                Some(package_metrics) => package_metrics
+                    .rs_path_to_metrics
+                    .values()
+                    .flat_map(|wrapper| {
+                        wrapper.metrics.out_dir_includes.iter().map(
+                            move |out_dir_include| {
+                                (out_dir_include.clone(), wrapper.target_kind)
+                            },
+                        )
+                    })
+                    .collect::<BTreeMap<String, RsFileTargetKind>>(),
+                None => continue,
+            };
+        for (out_dir_include, rs_file_target_kind) in includes {
+            // The file is missing when the build script did not generate it.
+            let path_buf = match out_dir.join(&out_dir_include).canonicalize() {
+                Ok(path_buf) => path_buf,
+                Err(_) => continue,
+            };
+            if geiger_context.package_id_to_metrics[&package.id]
+                .rs_path_to_metrics
+                .contains_key(&path_buf)
+            {
+                continue;
+            }
+            let found_wrapper = geiger_context
+                .package_id_to_metrics
+                .values_mut()
+                .find_map(|package_metrics| {
+                    package_metrics.rs_path_to_metrics.remove(&path_buf)
+                });
+            let rs_file_metrics = match found_wrapper {
+                Some(wrapper) => wrapper.metrics,
+                None => match parse_cache
+                    .find_unsafe_in_file(&path_buf, print_config.include_tests)
+                {
+                    Ok(rs_file_metrics) => rs_file_metrics,
+                    Err(error) => {
+                        handle_unsafe_in_file_error(
+                            print_config.allow_partial_results,
+                            error,
+                            &path_buf,
+                        );
+                        continue;
+                    }
+                },
+            };
+            geiger_context.excluded_paths.remove(&path_buf);
+            update_package_id_to_metrics_with_rs_file_metrics(
+                false,
+                package.id.clone(),
+                &mut geiger_context.package_id_to_metrics,
+                path_buf,
+                rs_file_metrics,
+                rs_file_target_kind,
+            );
+        }
+    }
+}
+
+/// The directory of the on-disk cache of the metrics of the scanned files,
+/// in the target directory of the workspace, `None` with `--no-cache`.
+fn disk_cache_dir(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    print_config: &PrintConfig,
+) -> Option<PathBuf> {
+    if print_config.no_cache {
+        return None;
+    }
+    Some(
+        cargo_metadata_parameters
+            .metadata
+            .target_directory
+            .join("cargo-geiger")
+            .join("parse-cache")
+            .into_std_path_buf(),
+    )
+}
+
 fn find_unsafe_in_packages_with_progress<F>(
     allow_partial_results: bool,
     cargo_metadata_parameters: &CargoMetadataParameters,
+    deadline: Option<Instant>,
+    disk_cache_dir: Option<&Path>,
+    generated_file_patterns: &RegexSet,
     include_tests: IncludeTests,
+    max_file_size: Option<u64>,
     mode: ScanMode,
+    scan_timeout_per_crate: Option<Duration>,
+    unsafe_macros: &[String],
+    memory_lifecycle_functions: &[String],
     mut progress_fn: F,
 ) -> GeigerContext
 where
@@ -62,8 +240,15 @@ where
             res = Some(find_unsafe_in_packages(
                 allow_partial_results,
                 cargo_metadata_parameters,
+                deadline,
+                disk_cache_dir,
+                generated_file_patterns,
                 include_tests,
+                max_file_size,
                 mode,
+                scan_timeout_per_crate,
+                unsafe_macros,
+                memory_lifecycle_functions,
                 Some(on_processed),
             ))
         });
@@ -78,76 +263,292 @@ where
 fn find_unsafe_in_packages<F>(
     allow_partial_results: bool,
     cargo_metadata_parameters: &CargoMetadataParameters,
+    deadline: Option<Instant>,
+    disk_cache_dir: Option<&Path>,
+    generated_file_patterns: &RegexSet,
     include_tests: IncludeTests,
+    max_file_size: Option<u64>,
     mode: ScanMode,
+    scan_timeout_per_crate: Option<Duration>,
+    unsafe_macros: &[String],
+    memory_lifecycle_functions: &[String],
     on_processed: Option<F>,
 ) -> GeigerContext
 where
     F: Fn(usize, usize) + Send + Sync,
 {
-    let package_id_to_metrics = Arc::new(Mutex::new(HashMap::new()));
     let ignored = Arc::new(Mutex::new(HashSet::new()));
+    let excluded = Arc::new(Mutex::new(HashSet::new()));
+    let oversized = Mutex::new(HashSet::new());
+    let deadline_check = deadline.map(DeadlineCheck::new);
+    let timeout_check = scan_timeout_per_crate.map(TimeoutCheck::new);
     let packages = cargo_metadata_parameters.metadata.packages.to_vec();
+    let package_roots = packages
+        .iter()
+        .filter_map(|package| {
+            package.clone().get_root().map(|root| {
+                let canon_root = root.canonicalize().unwrap_or(root);
+                (package.id.clone(), canon_root)
+            })
+        })
+        .collect::<HashMap<PackageId, PathBuf>>();
     let package_code_files: Vec<_> =
         find_rs_files_in_packages(&packages).collect();
     let package_code_file_count = package_code_files.len();
     let processed_count = AtomicUsize::new(0);
-    package_code_files.into_par_iter().for_each_with(
-        (package_id_to_metrics.clone(), ignored.clone()),
-        |(package_id_to_metrics, ignored), (package_id, rs_code_file)| {
-            if let RsFile::CustomBuildRoot(path_buf) = rs_code_file {
-                let mut ignored = ignored.lock().unwrap();
-                ignored.insert(path_buf);
-                return;
-            }
-            let (is_entry_point, path_buf) =
-                into_is_entry_point_and_path_buf(rs_code_file);
-            if let (false, ScanMode::EntryPointsOnly) = (is_entry_point, &mode)
-            {
-                return;
-            }
-            match find_unsafe_in_file(&path_buf, include_tests) {
-                Err(error) => {
-                    handle_unsafe_in_file_error(
-                        allow_partial_results,
-                        error,
-                        &path_buf,
-                    );
+    let parse_cache = Arc::new(ParseCache::new(
+        unsafe_macros.to_vec(),
+        memory_lifecycle_functions.to_vec(),
+        disk_cache_dir,
+    ));
+    let scanned_files = package_code_files
+        .into_par_iter()
+        .map_with(
+            (ignored.clone(), excluded.clone()),
+            |(ignored, excluded),
+             (package_id, rs_code_file, rs_file_target_kind)| {
+                if let RsFile::CustomBuildRoot(path_buf) = rs_code_file {
+                    let mut ignored = ignored.lock().unwrap();
+                    ignored.insert(path_buf);
+                    return None;
                 }
-                Ok(rs_file_metrics) => {
-                    let package_id_to_metrics =
-                        &mut package_id_to_metrics.lock().unwrap();
-                    update_package_id_to_metrics_with_rs_file_metrics(
+                let (is_entry_point, path_buf) =
+                    into_is_entry_point_and_path_buf(rs_code_file);
+                if let (false, ScanMode::EntryPointsOnly) =
+                    (is_entry_point, &mode)
+                {
+                    return None;
+                }
+                if is_generated_file(
+                    generated_file_patterns,
+                    package_roots.get(&package_id).map(PathBuf::as_path),
+                    &path_buf,
+                ) {
+                    let mut excluded = excluded.lock().unwrap();
+                    excluded.insert(path_buf);
+                    return None;
+                }
+                if is_oversized_file(max_file_size, &path_buf) {
+                    oversized.lock().unwrap().insert(path_buf);
+                    return None;
+                }
+                if let Some(deadline_check) = &deadline_check {
+                    if !deadline_check.can_scan(&package_id) {
+                        return None;
+                    }
+                }
+                let scan_result = match &timeout_check {
+                    None => parse_cache
+                        .find_unsafe_in_file(&path_buf, include_tests),
+                    Some(timeout_check) => {
+                        let scan_result = timeout_check
+                            .remaining(&package_id)
+                            .and_then(|remaining| {
+                                find_unsafe_in_file_with_timeout(
+                                    &parse_cache,
+                                    &path_buf,
+                                    include_tests,
+                                    remaining,
+                                )
+                            });
+                        match scan_result {
+                            Some(scan_result) => scan_result,
+                            None => {
+                                timeout_check.time_out(&package_id);
+                                return None;
+                            }
+                        }
+                    }
+                };
+                let scanned_file = match scan_result {
+                    Err(error) => {
+                        handle_unsafe_in_file_error(
+                            allow_partial_results,
+                            error,
+                            &path_buf,
+                        );
+                        None
+                    }
+                    Ok(rs_file_metrics) => Some((
                         is_entry_point,
                         package_id,
-                        package_id_to_metrics,
                         path_buf,
                         rs_file_metrics,
+                        rs_file_target_kind,
+                    )),
+                };
+
+                if let Some(on_processed) = &on_processed {
+                    on_processed(
+                        processed_count.fetch_add(1, Ordering::Relaxed),
+                        package_code_file_count,
                     );
                 }
-            }
-
-            if let Some(on_processed) = &on_processed {
-                on_processed(
-                    processed_count.fetch_add(1, Ordering::Relaxed),
-                    package_code_file_count,
-                );
-            }
-        },
-    );
-
-    let cargo_core_package_metrics = package_id_to_metrics
-        .lock()
-        .unwrap()
-        .iter()
-        .map(|(cargo_metadata_package_id, package_metrics)| {
-            (cargo_metadata_package_id.clone(), package_metrics.clone())
-        })
-        .collect::<HashMap<PackageId, PackageMetrics>>();
+                scanned_file
+            },
+        )
+        .collect::<Vec<_>>();
+
+    // The metrics are merged in the order the files were found, not in the
+    // order their scan ended, so that a file found twice, e.g. as the entry
+    // point of two targets, gets the same metrics with any number of
+    // threads.
+    let mut package_id_to_metrics = HashMap::new();
+    for (
+        is_entry_point,
+        package_id,
+        path_buf,
+        rs_file_metrics,
+        rs_file_target_kind,
+    ) in scanned_files.into_iter().flatten()
+    {
+        update_package_id_to_metrics_with_rs_file_metrics(
+            is_entry_point,
+            package_id,
+            &mut package_id_to_metrics,
+            path_buf,
+            rs_file_metrics,
+            rs_file_target_kind,
+        );
+    }
 
     GeigerContext {
-        package_id_to_metrics: cargo_core_package_metrics,
+        package_id_to_metrics,
         ignored_paths: Arc::try_unwrap(ignored).unwrap().into_inner().unwrap(),
+        excluded_paths: Arc::try_unwrap(excluded)
+            .unwrap()
+            .into_inner()
+            .unwrap(),
+        oversized_paths: oversized.into_inner().unwrap(),
+        unscanned_package_ids: deadline_check
+            .map(DeadlineCheck::into_unscanned_package_ids)
+            .unwrap_or_default(),
+        timed_out_package_ids: timeout_check
+            .map(TimeoutCheck::into_timed_out_package_ids)
+            .unwrap_or_default(),
+    }
+}
+
+/// Checks the `--deadline` before the scan of each package starts. The
+/// packages whose scan started before the deadline are scanned to the end,
+/// so that none of them is reported with the metrics of only some of its
+/// files.
+struct DeadlineCheck {
+    deadline: Instant,
+    /// The packages whose scan started, and the ones which were not scanned.
+    package_ids: Mutex<(HashSet<PackageId>, HashSet<PackageId>)>,
+}
+
+impl DeadlineCheck {
+    fn new(deadline: Instant) -> Self {
+        DeadlineCheck {
+            deadline,
+            package_ids: Mutex::new((HashSet::new(), HashSet::new())),
+        }
+    }
+
+    /// Whether a file of the package can be scanned, recording the package
+    /// as started or as not scanned.
+    fn can_scan(&self, package_id: &PackageId) -> bool {
+        let (started_package_ids, unscanned_package_ids) =
+            &mut *self.package_ids.lock().unwrap();
+        if started_package_ids.contains(package_id) {
+            return true;
+        }
+        if Instant::now() >= self.deadline {
+            unscanned_package_ids.insert(package_id.clone());
+            return false;
+        }
+        started_package_ids.insert(package_id.clone());
+        true
+    }
+
+    fn into_unscanned_package_ids(self) -> HashSet<PackageId> {
+        self.package_ids.into_inner().unwrap().1
+    }
+}
+
+/// Checks the `--scan-timeout-per-crate` before and while each file is
+/// scanned. The time of a package runs from the start of the scan of its
+/// first file, once it is up the files of the package left are not scanned.
+struct TimeoutCheck {
+    timeout: Duration,
+    /// The start of the scan of each package, and the packages which timed
+    /// out.
+    package_ids: Mutex<(HashMap<PackageId, Instant>, HashSet<PackageId>)>,
+}
+
+impl TimeoutCheck {
+    fn new(timeout: Duration) -> Self {
+        TimeoutCheck {
+            timeout,
+            package_ids: Mutex::new((HashMap::new(), HashSet::new())),
+        }
+    }
+
+    /// The time left to scan the files of the package, `None` once it timed
+    /// out.
+    fn remaining(&self, package_id: &PackageId) -> Option<Duration> {
+        let (start_by_package_id, timed_out_package_ids) =
+            &mut *self.package_ids.lock().unwrap();
+        if timed_out_package_ids.contains(package_id) {
+            return None;
+        }
+        let start = start_by_package_id
+            .entry(package_id.clone())
+            .or_insert_with(Instant::now);
+        self.timeout
+            .checked_sub(start.elapsed())
+            .filter(|remaining| *remaining > Duration::ZERO)
+    }
+
+    fn time_out(&self, package_id: &PackageId) {
+        self.package_ids
+            .lock()
+            .unwrap()
+            .1
+            .insert(package_id.clone());
+    }
+
+    fn into_timed_out_package_ids(self) -> HashSet<PackageId> {
+        self.package_ids.into_inner().unwrap().1
+    }
+}
+
+/// Scan the file on a thread of its own, so that the scan of a pathological
+/// file can be given up after the `timeout`, `None` then. The thread is left
+/// to finish by itself and its result is dropped.
+fn find_unsafe_in_file_with_timeout(
+    parse_cache: &Arc<ParseCache>,
+    path: &Path,
+    include_tests: IncludeTests,
+    timeout: Duration,
+) -> Option<Result<RsFileMetrics, ScanFileError>> {
+    let (sender, receiver) = channel();
+    let parse_cache = parse_cache.clone();
+    let path_buf = path.to_path_buf();
+    thread::spawn(move || {
+        // The receiver is gone once the timeout is exceeded.
+        let _ = sender
+            .send(parse_cache.find_unsafe_in_file(&path_buf, include_tests));
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(scan_result) => Some(scan_result),
+        Err(RecvTimeoutError::Timeout) => None,
+        Err(RecvTimeoutError::Disconnected) => {
+            panic!("The scan of {} panicked", path.display())
+        }
+    }
+}
+
+/// Whether the file is larger than the `--max-file-size`. The size is read
+/// from the file system, so that the file is not read to find out. A file
+/// whose size can't be read is scanned, and the error reported by the scan.
+fn is_oversized_file(max_file_size: Option<u64>, path: &Path) -> bool {
+    match max_file_size {
+        Some(max_file_size) => fs::metadata(path)
+            .map_or(false, |metadata| metadata.len() > max_file_size),
+        None => false,
     }
 }
 
@@ -167,7 +568,9 @@ fn find_rs_files_in_dir(dir: &Path) -> impl Iterator<Item = PathBuf> {
     })
 }
 
-fn find_rs_files_in_package(package: &cargo_metadata::Package) -> Vec<RsFile> {
+pub fn find_rs_files_in_package(
+    package: &cargo_metadata::Package,
+) -> Vec<(RsFile, RsFileTargetKind)> {
     // Find all build target entry point source files.
     let mut canon_targets = HashMap::new();
     for target in &package.targets {
@@ -190,9 +593,37 @@ fn find_rs_files_in_package(package: &cargo_metadata::Package) -> Vec<RsFile> {
     let mut rs_files = Vec::new();
 
     if let Some(root_path) = package.clone().get_root() {
+        // The paths found in the package directory are canonical, so the
+        // root needs to be as well in order to classify them by target kind.
+        let canon_root_path = root_path
+            .canonicalize()
+            .unwrap_or_else(|_| root_path.clone());
+        // The modules of the examples declared at a custom path in the
+        // manifest are not in the conventional `examples/` directory.
+        let target_paths_and_kinds = canon_targets
+            .iter()
+            .flat_map(|(path_buf, targets)| {
+                targets.iter().map(move |target| {
+                    (path_buf.clone(), into_target_kind(target.kind.clone()))
+                })
+            })
+            .collect::<Vec<(PathBuf, TargetKind)>>();
+        let example_dirs =
+            into_example_dirs(&canon_root_path, &target_paths_and_kinds);
         for path_bufs in find_rs_files_in_dir(root_path.as_path()) {
             if !canon_targets.contains_key(&path_bufs) {
-                rs_files.push(RsFile::Other(path_bufs));
+                let rs_file_target_kind = if example_dirs
+                    .iter()
+                    .any(|dir| path_bufs.starts_with(dir))
+                {
+                    RsFileTargetKind::Example
+                } else {
+                    into_rs_file_target_kind_from_path(
+                        &canon_root_path,
+                        &path_bufs,
+                    )
+                };
+                rs_files.push((RsFile::Other(path_bufs), rs_file_target_kind));
             }
         }
     }
@@ -200,7 +631,10 @@ fn find_rs_files_in_package(package: &cargo_metadata::Package) -> Vec<RsFile> {
     for (path_buf, targets) in canon_targets.into_iter() {
         for target in targets {
             let target_kind = into_target_kind(target.clone().kind);
-            rs_files.push(into_rs_code_file(&target_kind, path_buf.clone()));
+            rs_files.push((
+                into_rs_code_file(&target_kind, path_buf.clone()),
+                into_rs_file_target_kind_from_target_kind(&target_kind),
+            ));
         }
     }
 
@@ -209,14 +643,34 @@ fn find_rs_files_in_package(package: &cargo_metadata::Package) -> Vec<RsFile> {
 
 fn find_rs_files_in_packages(
     packages: &[cargo_metadata::Package],
-) -> impl Iterator<Item = (PackageId, RsFile)> + '_ {
+) -> impl Iterator<Item = (PackageId, RsFile, RsFileTargetKind)> + '_ {
     packages.iter().flat_map(|package| {
-        find_rs_files_in_package(package)
-            .into_iter()
-            .map(move |p| (package.id.clone(), p))
+        find_rs_files_in_package(package).into_iter().map(
+            move |(rs_file, rs_file_target_kind)| {
+                (package.id.clone(), rs_file, rs_file_target_kind)
+            },
+        )
     })
 }
 
+/// Whether the path of the file, relative to the root of its package, matches
+/// one of the `--exclude-generated` patterns.
+fn is_generated_file(
+    generated_file_patterns: &RegexSet,
+    package_root: Option<&Path>,
+    path: &Path,
+) -> bool {
+    if generated_file_patterns.is_empty() {
+        return false;
+    }
+    let relative_path = package_root
+        .and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path);
+    // The patterns use `/` as the separator on every platform.
+    let relative_path = relative_path.to_string_lossy().replace('\\', "/");
+    generated_file_patterns.is_match(&relative_path)
+}
+
 fn handle_unsafe_in_file_error(
     allow_partial_results: bool,
     error: ScanFileError,
@@ -235,6 +689,7 @@ fn update_package_id_to_metrics_with_rs_file_metrics(
     package_id_to_metrics: &mut HashMap<PackageId, PackageMetrics>,
     path_buf: PathBuf,
     rs_file_metrics: RsFileMetrics,
+    rs_file_target_kind: RsFileTargetKind,
 ) {
     let package_metrics = package_id_to_metrics
         .entry(package_id)
@@ -245,19 +700,108 @@ fn update_package_id_to_metrics_with_rs_file_metrics(
         .or_insert_with(RsFileMetricsWrapper::default);
     wrapper.metrics = rs_file_metrics;
     wrapper.is_crate_entry_point = is_entry_point;
+    wrapper.target_kind = rs_file_target_kind;
 }
 
 #[cfg(test)]
 mod find_tests {
     use super::*;
 
+    use crate::format::print_config::DEFAULT_GENERATED_FILE_PATTERNS;
+
     use cargo_metadata::{CargoOpt, MetadataCommand};
+    use geiger::find::find_unsafe_in_file;
     use rstest::*;
     use std::fs::File;
     use std::io;
     use std::io::ErrorKind;
     use tempfile::tempdir;
 
+    // The package root itself is in a `target` directory, which does not
+    // make all of its files look generated.
+    #[rstest(
+        input_path,
+        expected_is_generated_file,
+        case("/ci/target/package/src/lib.rs", false),
+        case("/ci/target/package/src/target.rs", false),
+        case("/ci/target/package/src/bindings.generated.rs", true),
+        case(
+            "/ci/target/package/target/debug/build/foo-0123/out/bindings.rs",
+            true
+        ),
+        case("/ci/target/package/build/foo-0123/out/bindings.rs", true)
+    )]
+    fn is_generated_file_test(
+        input_path: &str,
+        expected_is_generated_file: bool,
+    ) {
+        let generated_file_patterns =
+            RegexSet::new(DEFAULT_GENERATED_FILE_PATTERNS).unwrap();
+
+        assert_eq!(
+            is_generated_file(
+                &generated_file_patterns,
+                Some(Path::new("/ci/target/package")),
+                Path::new(input_path),
+            ),
+            expected_is_generated_file
+        );
+    }
+
+    #[rstest]
+    fn is_generated_file_without_patterns_test() {
+        assert!(!is_generated_file(
+            &RegexSet::empty(),
+            None,
+            Path::new("/package/src/bindings.generated.rs"),
+        ));
+    }
+
+    #[rstest(
+        input_max_file_size,
+        expected_is_oversized_file,
+        case(None, false),
+        case(Some(1024 * 1024), false),
+        case(Some(1024), true)
+    )]
+    fn is_oversized_file_test(
+        input_max_file_size: Option<u64>,
+        expected_is_oversized_file: bool,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("generated.rs");
+        fs::write(&path, "pub fn f() {}\n".repeat(1024)).unwrap();
+
+        assert_eq!(
+            is_oversized_file(input_max_file_size, &path),
+            expected_is_oversized_file
+        );
+    }
+
+    #[rstest]
+    fn timeout_check_test() {
+        let package_id = PackageId {
+            repr: String::from("package"),
+        };
+        let other_package_id = PackageId {
+            repr: String::from("other_package"),
+        };
+
+        let timeout_check = TimeoutCheck::new(Duration::from_secs(3600));
+        assert!(timeout_check.remaining(&package_id).is_some());
+        timeout_check.time_out(&package_id);
+        assert!(timeout_check.remaining(&package_id).is_none());
+        assert!(timeout_check.remaining(&other_package_id).is_some());
+        assert_eq!(
+            timeout_check.into_timed_out_package_ids(),
+            vec![package_id.clone()].into_iter().collect()
+        );
+
+        // No time is left to scan any package.
+        let timeout_check = TimeoutCheck::new(Duration::ZERO);
+        assert!(timeout_check.remaining(&package_id).is_none());
+    }
+
     #[rstest]
     fn find_rs_files_in_dir_test() {
         let temp_dir = tempdir().unwrap();
@@ -300,7 +844,7 @@ mod find_tests {
 
         let path_bufs_in_package = rs_files_in_package
             .iter()
-            .map(|f| match f {
+            .map(|(f, _)| match f {
                 RsFile::BinRoot(path_buf) => path_buf,
                 RsFile::CustomBuildRoot(path_buf) => path_buf,
                 RsFile::LibRoot(path_buf) => path_buf,
@@ -358,7 +902,7 @@ mod find_tests {
             HashMap::<PackageId, PackageMetrics>::new();
 
         let mut rs_files_in_package = find_rs_files_in_package(&package);
-        let rs_file = rs_files_in_package.pop().unwrap();
+        let (rs_file, _) = rs_files_in_package.pop().unwrap();
         let (_, path_buf) = into_is_entry_point_and_path_buf(rs_file);
 
         let rs_file_metrics =
@@ -370,6 +914,7 @@ mod find_tests {
             &mut package_id_to_metrics,
             package.manifest_path.clone().into(),
             rs_file_metrics.clone(),
+            RsFileTargetKind::Test,
         );
 
         assert!(package_id_to_metrics.contains_key(&package.id));
@@ -383,6 +928,7 @@ mod find_tests {
 
         assert_eq!(wrapper.metrics, rs_file_metrics);
         assert_eq!(wrapper.is_crate_entry_point, expected_is_crate_entry_point);
+        assert_eq!(wrapper.target_kind, RsFileTargetKind::Test);
     }
 
     #[fixture]
@@ -0,0 +1,315 @@
+use cargo_util::Sha256;
+use geiger::find::find_unsafe_in_string_with_functions;
+use geiger::{IncludeTests, RsFileMetrics, ScanFileError};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Tag of the entries of the on-disk cache, to bump whenever
+/// `RsFileMetrics`, or the way the visitor computes it, changes, so that the
+/// entries written by an earlier version are not used. The version of
+/// `geiger` is also part of the name of their directory, see
+/// `disk_cache_version_dir`.
+const DISK_CACHE_VERSION: &str = "v1";
+
+/// Numbers the temporary files of the entries written by this process.
+static DISK_CACHE_TEMP_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Caches `RsFileMetrics` by the content hash of the scanned source, for the
+/// duration of a single run. Workspaces with several path dependencies
+/// sometimes share identical (e.g. generated) source files, and this avoids
+/// parsing each of them more than once.
+///
+/// The `IncludeTests` setting, the unsafe macros and the memory lifecycle
+/// functions are the same for every file scanned in a run, so they do not
+/// need to be part of the key.
+///
+/// The metrics are also cached on disk, unless `--no-cache` is given, for
+/// the later runs to skip parsing the files which did not change. The key of
+/// an entry on disk also hashes the settings, which may differ between runs.
+#[derive(Debug, Default)]
+pub struct ParseCache {
+    metrics_by_hash: Mutex<HashMap<String, RsFileMetrics>>,
+    parse_count: AtomicUsize,
+    unsafe_macros: Vec<String>,
+    memory_lifecycle_functions: Vec<String>,
+    /// The directory of the entries of the on-disk cache of this version,
+    /// `None` with `--no-cache`.
+    disk_cache_dir: Option<PathBuf>,
+}
+
+impl ParseCache {
+    /// Invocations of the `unsafe_macros` are counted as unsafe expressions,
+    /// see `--count-std-macros`, and the calls of the
+    /// `memory_lifecycle_functions` in unsafe code as memory lifecycle unsafe
+    /// usage, see `--memory-lifecycle-unsafe`. The entries of the on-disk
+    /// cache are stored in a subdirectory of `disk_cache_dir` named after
+    /// `DISK_CACHE_VERSION` and the version of `geiger`.
+    pub fn new(
+        unsafe_macros: Vec<String>,
+        memory_lifecycle_functions: Vec<String>,
+        disk_cache_dir: Option<&Path>,
+    ) -> Self {
+        ParseCache {
+            unsafe_macros,
+            memory_lifecycle_functions,
+            disk_cache_dir: disk_cache_dir.map(disk_cache_version_dir),
+            ..Default::default()
+        }
+    }
+
+    /// Scan a single file for `unsafe` usage, reusing the metrics of a
+    /// previously scanned file with identical content when available, in
+    /// this run or, from the on-disk cache, in an earlier one.
+    pub fn find_unsafe_in_file(
+        &self,
+        path: &Path,
+        include_tests: IncludeTests,
+    ) -> Result<RsFileMetrics, ScanFileError> {
+        let src = fs::read(path)
+            .map_err(|e| ScanFileError::Io(e, path.to_path_buf()))?;
+        let hash = Sha256::new().update(&src).finish_hex();
+
+        if let Some(metrics) = self.metrics_by_hash.lock().unwrap().get(&hash) {
+            return Ok(metrics.clone());
+        }
+
+        let disk_cache_path = self.disk_cache_path(&src, include_tests);
+        if let Some(metrics) =
+            disk_cache_path.as_deref().and_then(read_disk_cache_entry)
+        {
+            self.metrics_by_hash
+                .lock()
+                .unwrap()
+                .insert(hash, metrics.clone());
+            return Ok(metrics);
+        }
+
+        let src = String::from_utf8(src)
+            .map_err(|e| ScanFileError::Utf8(e, path.to_path_buf()))?;
+        self.parse_count.fetch_add(1, Ordering::Relaxed);
+        let metrics = find_unsafe_in_string_with_functions(
+            &src,
+            include_tests,
+            &self.unsafe_macros,
+            &self.memory_lifecycle_functions,
+        )
+        .map_err(|e| ScanFileError::Syn(e, path.to_path_buf()))?;
+
+        if let Some(disk_cache_path) = &disk_cache_path {
+            write_disk_cache_entry(disk_cache_path, &metrics);
+        }
+        self.metrics_by_hash
+            .lock()
+            .unwrap()
+            .insert(hash, metrics.clone());
+
+        Ok(metrics)
+    }
+
+    /// The path of the entry of the on-disk cache for a source, named after
+    /// the hash of the source and of the settings of the scan.
+    fn disk_cache_path(
+        &self,
+        src: &[u8],
+        include_tests: IncludeTests,
+    ) -> Option<PathBuf> {
+        let disk_cache_dir = self.disk_cache_dir.as_ref()?;
+        let settings = format!(
+            "{:?}\0{}\0{}\0",
+            include_tests,
+            self.unsafe_macros.join(","),
+            self.memory_lifecycle_functions.join(",")
+        );
+        let hash = Sha256::new()
+            .update(settings.as_bytes())
+            .update(src)
+            .finish_hex();
+        Some(disk_cache_dir.join(format!("{}.json", hash)))
+    }
+
+    /// The number of files which were actually parsed, as opposed to being
+    /// served from the cache.
+    pub fn parse_count(&self) -> usize {
+        self.parse_count.load(Ordering::Relaxed)
+    }
+}
+
+/// The directory of the entries of the on-disk cache written by this version
+/// of `geiger`.
+fn disk_cache_version_dir(disk_cache_dir: &Path) -> PathBuf {
+    disk_cache_dir.join(format!(
+        "{}-geiger-{}",
+        DISK_CACHE_VERSION,
+        geiger::VERSION
+    ))
+}
+
+/// A missing or unreadable entry, e.g. one partially written by a run which
+/// was interrupted, is a cache miss, the file being parsed again.
+fn read_disk_cache_entry(path: &Path) -> Option<RsFileMetrics> {
+    let content = fs::read(path).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// The entry is written to a temporary file, which is then renamed into
+/// place, so that the runs reading the cache in parallel never see a
+/// partially written entry. The cache only saves time, so a failure to write
+/// an entry is ignored.
+fn write_disk_cache_entry(path: &Path, metrics: &RsFileMetrics) {
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let temp_path = path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        DISK_CACHE_TEMP_FILE_COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    if fs::write(&temp_path, serde_json::to_vec(metrics).unwrap()).is_err()
+        || fs::rename(&temp_path, path).is_err()
+    {
+        let _ = fs::remove_file(&temp_path);
+    }
+}
+
+#[cfg(test)]
+mod parse_cache_tests {
+    use super::*;
+
+    use rstest::*;
+    use tempfile::tempdir;
+
+    #[rstest]
+    fn parse_cache_parses_identical_files_once_test() {
+        let temp_dir = tempdir().unwrap();
+        let duplicated_source = "pub unsafe fn f() {}\npub fn g() { f(); }\n";
+
+        let file_names = ["first.rs", "second.rs", "third.rs"];
+        for file_name in file_names.iter() {
+            fs::write(temp_dir.path().join(file_name), duplicated_source)
+                .unwrap();
+        }
+        fs::write(temp_dir.path().join("other.rs"), "pub fn h() {}").unwrap();
+
+        let parse_cache = ParseCache::default();
+
+        let metrics = file_names
+            .iter()
+            .map(|file_name| {
+                parse_cache
+                    .find_unsafe_in_file(
+                        &temp_dir.path().join(file_name),
+                        IncludeTests::No,
+                    )
+                    .unwrap()
+            })
+            .collect::<Vec<RsFileMetrics>>();
+        let other_metrics = parse_cache
+            .find_unsafe_in_file(
+                &temp_dir.path().join("other.rs"),
+                IncludeTests::No,
+            )
+            .unwrap();
+
+        assert_eq!(parse_cache.parse_count(), 2);
+        assert!(metrics.iter().all(|m| *m == metrics[0]));
+        assert_eq!(metrics[0].counters.functions.unsafe_, 1);
+        assert_eq!(other_metrics.counters.functions.safe, 1);
+    }
+
+    #[rstest]
+    fn parse_cache_reuses_disk_cache_of_earlier_run_test() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(&path, "pub unsafe fn f() {}\n").unwrap();
+        let disk_cache_dir = temp_dir.path().join("parse-cache");
+
+        let first_run = ParseCache::new(vec![], vec![], Some(&disk_cache_dir));
+        let first_metrics = first_run
+            .find_unsafe_in_file(&path, IncludeTests::No)
+            .unwrap();
+        let second_run = ParseCache::new(vec![], vec![], Some(&disk_cache_dir));
+        let second_metrics = second_run
+            .find_unsafe_in_file(&path, IncludeTests::No)
+            .unwrap();
+        // The entry of a run with other settings is not reused.
+        let third_run = ParseCache::new(
+            vec![String::from("thread_local")],
+            vec![],
+            Some(&disk_cache_dir),
+        );
+        third_run
+            .find_unsafe_in_file(&path, IncludeTests::No)
+            .unwrap();
+
+        assert_eq!(first_run.parse_count(), 1);
+        assert_eq!(second_run.parse_count(), 0);
+        assert_eq!(third_run.parse_count(), 1);
+        assert_eq!(second_metrics, first_metrics);
+        assert_eq!(second_metrics.counters.functions.unsafe_, 1);
+    }
+
+    #[rstest]
+    fn parse_cache_parses_changed_file_and_corrupt_entry_again_test() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(&path, "pub unsafe fn f() {}\n").unwrap();
+        let disk_cache_dir = temp_dir.path().join("parse-cache");
+        ParseCache::new(vec![], vec![], Some(&disk_cache_dir))
+            .find_unsafe_in_file(&path, IncludeTests::No)
+            .unwrap();
+
+        fs::write(&path, "pub fn g() {}\n").unwrap();
+        let changed_file_run =
+            ParseCache::new(vec![], vec![], Some(&disk_cache_dir));
+        let changed_file_metrics = changed_file_run
+            .find_unsafe_in_file(&path, IncludeTests::No)
+            .unwrap();
+
+        for entry in
+            fs::read_dir(disk_cache_version_dir(&disk_cache_dir)).unwrap()
+        {
+            fs::write(entry.unwrap().path(), "{").unwrap();
+        }
+        let corrupt_entry_run =
+            ParseCache::new(vec![], vec![], Some(&disk_cache_dir));
+        let corrupt_entry_metrics = corrupt_entry_run
+            .find_unsafe_in_file(&path, IncludeTests::No)
+            .unwrap();
+
+        assert_eq!(changed_file_run.parse_count(), 1);
+        assert_eq!(changed_file_metrics.counters.functions.safe, 1);
+        assert_eq!(corrupt_entry_run.parse_count(), 1);
+        assert_eq!(corrupt_entry_metrics, changed_file_metrics);
+    }
+
+    #[rstest]
+    fn parse_cache_writes_disk_cache_entries_of_geiger_version_test() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(&path, "pub unsafe fn f() {}\n").unwrap();
+        let disk_cache_dir = temp_dir.path().join("parse-cache");
+        ParseCache::new(vec![], vec![], Some(&disk_cache_dir))
+            .find_unsafe_in_file(&path, IncludeTests::No)
+            .unwrap();
+
+        let version_dirs = fs::read_dir(&disk_cache_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        // No temporary file is left behind next to the entry.
+        let entries = fs::read_dir(disk_cache_version_dir(&disk_cache_dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            version_dirs,
+            vec![format!("{}-geiger-{}", DISK_CACHE_VERSION, geiger::VERSION)]
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].extension().unwrap(), "json");
+    }
+}
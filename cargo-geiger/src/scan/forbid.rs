@@ -1,17 +1,24 @@
 mod table;
 
+use crate::diff::{forbid_regressions, ForbidBaseline};
+use crate::forbid_whitelist::ForbidWhitelist;
 use crate::format::print_config::{OutputFormat, PrintConfig};
 use crate::graph::Graph;
 use crate::mapping::CargoMetadataParameters;
 
 use super::find::find_unsafe;
-use super::{package_metrics, ScanMode, ScanParameters, ScanResult};
+use super::{
+    fail_threshold_count, package_metrics,
+    ForbidWhitelistEntriesNotInTreeError, ScanMode, ScanParameters, ScanResult,
+};
 
 use table::scan_forbid_to_table;
 
-use cargo::{CliError, GlobalContext as Config};
+use cargo::{CliError, CliResult, GlobalContext as Config};
 use cargo_geiger_serde::{QuickReportEntry, QuickSafetyReport};
-use cargo_metadata::PackageId;
+use cargo_metadata::{Metadata, PackageId};
+use std::collections::HashSet;
+use std::path::PathBuf;
 
 pub fn scan_forbid_unsafe(
     cargo_metadata_parameters: &CargoMetadataParameters,
@@ -19,33 +26,191 @@ pub fn scan_forbid_unsafe(
     root_package_id: PackageId,
     scan_parameters: &ScanParameters,
 ) -> Result<ScanResult, CliError> {
-    match scan_parameters.args.output_format {
+    let forbid_baseline = match &scan_parameters.args.fail_on_forbid_regression
+    {
+        Some(baseline_path) => Some(
+            ForbidBaseline::load(baseline_path)
+                .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?,
+        ),
+        None => None,
+    };
+    let forbid_whitelist = match &scan_parameters.args.forbid_whitelist {
+        Some(whitelist_path) => {
+            let forbid_whitelist = ForbidWhitelist::load(whitelist_path)
+                .map_err(|e| CliError::new(anyhow::Error::new(e), 1))?;
+            check_forbid_whitelist_entries_in_tree(
+                &forbid_whitelist,
+                cargo_metadata_parameters.metadata,
+                graph,
+            )?;
+            Some(forbid_whitelist)
+        }
+        None => None,
+    };
+    let empty_forbid_whitelist = ForbidWhitelist::default();
+    let forbid_whitelist_or_empty =
+        forbid_whitelist.as_ref().unwrap_or(&empty_forbid_whitelist);
+
+    let mut scan_result = match scan_parameters.args.output_format {
         OutputFormat::Json => scan_forbid_to_report(
             cargo_metadata_parameters,
             scan_parameters.config,
+            forbid_whitelist_or_empty,
             graph,
             scan_parameters.args.output_format,
             scan_parameters.print_config,
-            root_package_id,
+            root_package_id.clone(),
         ),
         _ => scan_forbid_to_table(
             cargo_metadata_parameters,
             scan_parameters.config,
+            forbid_whitelist_or_empty,
             graph,
             scan_parameters.print_config,
-            root_package_id,
+            root_package_id.clone(),
         ),
+    }?;
+
+    if scan_parameters.args.fail_threshold.is_some() {
+        // Nothing is built, so every file scanned counts as used.
+        let geiger_context = find_unsafe(
+            cargo_metadata_parameters,
+            scan_parameters.config,
+            ScanMode::Full,
+            scan_parameters.print_config,
+        )?;
+        let rs_files_scanned = geiger_context
+            .package_id_to_metrics
+            .values()
+            .flat_map(|package_metrics| {
+                package_metrics.rs_path_to_metrics.keys().cloned()
+            })
+            .collect::<HashSet<PathBuf>>();
+        scan_result.used_unsafe_expr_count = fail_threshold_count(
+            scan_parameters.args,
+            cargo_metadata_parameters,
+            &geiger_context,
+            graph,
+            &root_package_id,
+            &rs_files_scanned,
+        );
+    }
+
+    if forbid_whitelist.is_some() {
+        let report = quick_safety_report(
+            cargo_metadata_parameters,
+            scan_parameters.config,
+            forbid_whitelist_or_empty,
+            graph,
+            scan_parameters.print_config,
+            root_package_id.clone(),
+        )?;
+        let mut package_ids = report
+            .packages
+            .values()
+            .filter(|entry| !entry.forbids_unsafe)
+            .map(|entry| &entry.package.id)
+            .collect::<Vec<_>>();
+        package_ids.sort();
+        for package_id in package_ids {
+            eprintln!(
+                "WARNING: {} {} doesn't forbid unsafe code and isn't in the forbid whitelist",
+                package_id.name, package_id.version
+            );
+            scan_result.warning_count += 1;
+        }
+    }
+
+    if let Some(forbid_baseline) = forbid_baseline {
+        let report = quick_safety_report(
+            cargo_metadata_parameters,
+            scan_parameters.config,
+            forbid_whitelist_or_empty,
+            graph,
+            scan_parameters.print_config,
+            root_package_id,
+        )?;
+        for (package_id, baseline_version) in
+            forbid_regressions(&forbid_baseline, &report)
+        {
+            eprintln!(
+                "WARNING: {} {} no longer forbids unsafe code, {} did in the baseline",
+                package_id.name, package_id.version, baseline_version
+            );
+            scan_result.warning_count += 1;
+        }
+    }
+
+    Ok(scan_result)
+}
+
+/// Every entry of the `--forbid-whitelist` has to match a crate of the tree,
+/// so that an entry doesn't outlive the dependency it was added for.
+fn check_forbid_whitelist_entries_in_tree(
+    forbid_whitelist: &ForbidWhitelist,
+    metadata: &Metadata,
+    graph: &Graph,
+) -> CliResult {
+    let missing_entries = forbid_whitelist.missing_entries(
+        graph.nodes.keys().map(|package_id| {
+            let package = &metadata[package_id];
+            (package.name.as_str(), &package.version)
+        }),
+    );
+    if missing_entries.is_empty() {
+        return Ok(());
     }
+    Err(CliError::new(
+        anyhow::Error::new(ForbidWhitelistEntriesNotInTreeError {
+            entries: missing_entries
+                .iter()
+                .map(|entry| entry.to_string())
+                .collect(),
+        }),
+        1,
+    ))
 }
 
 fn scan_forbid_to_report(
     cargo_metadata_parameters: &CargoMetadataParameters,
     config: &Config,
+    forbid_whitelist: &ForbidWhitelist,
     graph: &Graph,
     output_format: OutputFormat,
     print_config: &PrintConfig,
     root_package_id: PackageId,
 ) -> Result<ScanResult, CliError> {
+    let report = quick_safety_report(
+        cargo_metadata_parameters,
+        config,
+        forbid_whitelist,
+        graph,
+        print_config,
+        root_package_id,
+    )?;
+    let json_string = match output_format {
+        OutputFormat::Json => serde_json::to_string(&report).unwrap(),
+        _ => panic!("Only implemented for OutputFormat::Json"),
+    };
+
+    Ok(ScanResult {
+        scan_output_lines: vec![json_string],
+        warning_count: 0,
+        unsafe_free: false,
+        used_unsafe_expr_count: None,
+    })
+}
+
+/// Whether each package of the graph forbids unsafe code in its entry points,
+/// or is counted as forbidding it by the `--forbid-whitelist`.
+fn quick_safety_report(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    config: &Config,
+    forbid_whitelist: &ForbidWhitelist,
+    graph: &Graph,
+    print_config: &PrintConfig,
+    root_package_id: PackageId,
+) -> Result<QuickSafetyReport, CliError> {
     let geiger_context = find_unsafe(
         cargo_metadata_parameters,
         config,
@@ -66,24 +231,18 @@ fn scan_forbid_to_report(
                 continue;
             }
         };
-        let forbids_unsafe = pack_metrics.rs_path_to_metrics.iter().all(
-            |(_, rs_file_metrics_wrapper)| {
-                rs_file_metrics_wrapper.metrics.forbids_unsafe
-            },
-        );
+        let forbids_unsafe = forbid_whitelist
+            .contains(&package.id.name, &package.id.version)
+            || pack_metrics.rs_path_to_metrics.iter().all(
+                |(_, rs_file_metrics_wrapper)| {
+                    rs_file_metrics_wrapper.metrics.forbids_unsafe
+                },
+            );
         let entry = QuickReportEntry {
             package,
             forbids_unsafe,
         };
         report.packages.insert(entry.package.id.clone(), entry);
     }
-    let json_string = match output_format {
-        OutputFormat::Json => serde_json::to_string(&report).unwrap(),
-        _ => panic!("Only implemented for OutputFormat::Json"),
-    };
-
-    Ok(ScanResult {
-        scan_output_lines: vec![json_string],
-        warning_count: 0,
-    })
+    Ok(report)
 }
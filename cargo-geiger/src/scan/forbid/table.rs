@@ -1,3 +1,4 @@
+use crate::forbid_whitelist::ForbidWhitelist;
 use crate::format::emoji_symbols::EmojiSymbols;
 use crate::format::pattern::Pattern;
 use crate::format::print_config::PrintConfig;
@@ -18,6 +19,7 @@ use colored::Colorize;
 pub fn scan_forbid_to_table(
     cargo_metadata_parameters: &CargoMetadataParameters,
     config: &Config,
+    forbid_whitelist: &ForbidWhitelist,
     graph: &Graph,
     print_config: &PrintConfig,
     root_package_id: PackageId,
@@ -49,6 +51,7 @@ pub fn scan_forbid_to_table(
             TextTreeLine::Package {
                 id: package_id,
                 tree_vines,
+                ..
             } => {
                 let geiger_ctx = find_unsafe(
                     cargo_metadata_parameters,
@@ -60,6 +63,7 @@ pub fn scan_forbid_to_table(
                 handle_package_text_tree_line(
                     cargo_metadata_parameters,
                     &emoji_symbols,
+                    forbid_whitelist,
                     &geiger_ctx,
                     package_id,
                     print_config,
@@ -73,6 +77,8 @@ pub fn scan_forbid_to_table(
     Ok(ScanResult {
         scan_output_lines,
         warning_count: 0,
+        unsafe_free: false,
+        used_unsafe_expr_count: None,
     })
 }
 
@@ -110,6 +116,7 @@ fn format_package_name(
 fn handle_package_text_tree_line(
     cargo_metadata_parameters: &CargoMetadataParameters,
     emoji_symbols: &EmojiSymbols,
+    forbid_whitelist: &ForbidWhitelist,
     geiger_ctx: &GeigerContext,
     package_id: PackageId,
     print_config: &PrintConfig,
@@ -124,15 +131,19 @@ fn handle_package_text_tree_line(
         &package_id,
         &print_config.format,
     );
+    let package = &cargo_metadata_parameters.metadata[&package_id];
     let package_metrics = geiger_ctx.package_id_to_metrics.get(&package_id);
-    let package_forbids_unsafe = match package_metrics {
-        None => false, // no metrics available, .rs parsing failed?
-        Some(package_metric) => package_metric.rs_path_to_metrics.iter().all(
-            |(_k, rs_file_metrics_wrapper)| {
-                rs_file_metrics_wrapper.metrics.forbids_unsafe
-            },
-        ),
-    };
+    let package_forbids_unsafe = forbid_whitelist
+        .contains(&package.name, &package.version)
+        || match package_metrics {
+            None => false, // no metrics available, .rs parsing failed?
+            Some(package_metric) => package_metric
+                .rs_path_to_metrics
+                .iter()
+                .all(|(_k, rs_file_metrics_wrapper)| {
+                    rs_file_metrics_wrapper.metrics.forbids_unsafe
+                }),
+        };
     let (symbol, name) = if package_forbids_unsafe {
         (&sym_lock, name.green())
     } else {
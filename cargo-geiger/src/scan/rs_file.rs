@@ -2,7 +2,7 @@ mod custom_executor;
 
 use custom_executor::{CustomExecutor, CustomExecutorInnerContext};
 
-use cargo::core::compiler::Executor;
+use cargo::core::compiler::{CompileKind, Executor};
 use cargo::core::manifest::TargetKind;
 use cargo::core::Workspace;
 use cargo::ops;
@@ -11,7 +11,7 @@ use cargo::util::{interning::InternedString, CargoResult};
 use cargo::GlobalContext as Config;
 use cargo_util::paths;
 use geiger::RsFileMetrics;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::io;
@@ -37,6 +37,22 @@ pub enum RsFile {
     Other(PathBuf),
 }
 
+/// The kind of build target a `.rs` file belongs to. Files belonging to the
+/// tests, benches and examples of a package are not used by a regular build.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RsFileTargetKind {
+    Bench,
+    Example,
+    Source,
+    Test,
+}
+
+impl Default for RsFileTargetKind {
+    fn default() -> Self {
+        RsFileTargetKind::Source
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct RsFileMetricsWrapper {
     /// The information returned by the `geiger` crate for a `.rs` file.
@@ -47,6 +63,9 @@ pub struct RsFileMetricsWrapper {
     /// and cannot know if a file is a crate entry point or not, so we add this
     /// information here.
     pub is_crate_entry_point: bool,
+
+    /// The kind of build target this file belongs to.
+    pub target_kind: RsFileTargetKind,
 }
 
 #[derive(Debug)]
@@ -107,6 +126,82 @@ pub fn into_rs_code_file(target_kind: &TargetKind, path: PathBuf) -> RsFile {
     }
 }
 
+/// Classify a `.rs` file which is not a build target entry point by the
+/// conventional directory it lives in, relative to the package root, i.e.
+/// `benches/`, `examples/` or `tests/`.
+pub fn into_rs_file_target_kind_from_path(
+    package_root: &Path,
+    path: &Path,
+) -> RsFileTargetKind {
+    let first_component = path
+        .strip_prefix(package_root)
+        .ok()
+        .and_then(|relative_path| relative_path.components().next())
+        .and_then(|component| component.as_os_str().to_str());
+
+    match first_component {
+        Some("benches") => RsFileTargetKind::Bench,
+        Some("examples") => RsFileTargetKind::Example,
+        Some("tests") => RsFileTargetKind::Test,
+        _ => RsFileTargetKind::Source,
+    }
+}
+
+/// The directories of the example targets, e.g. `demos/` for an example
+/// declared in the manifest with `path = "demos/demo.rs"`, whose other `.rs`
+/// files are the modules of the examples. The package root and the
+/// directories holding a library, a binary or a build script target, along
+/// with their subdirectories, are left out since their files can belong to
+/// these targets too.
+pub fn into_example_dirs(
+    package_root: &Path,
+    target_paths_and_kinds: &[(PathBuf, TargetKind)],
+) -> Vec<PathBuf> {
+    let is_example = |target_kind: &TargetKind| {
+        matches!(
+            target_kind,
+            TargetKind::ExampleBin | TargetKind::ExampleLib(_)
+        )
+    };
+    let non_example_dirs = target_paths_and_kinds
+        .iter()
+        .filter(|(_, target_kind)| !is_example(target_kind))
+        .filter_map(|(path, _)| path.parent())
+        .filter(|dir| *dir != package_root)
+        .collect::<Vec<&Path>>();
+
+    let mut example_dirs = target_paths_and_kinds
+        .iter()
+        .filter(|(_, target_kind)| is_example(target_kind))
+        .filter_map(|(path, _)| path.parent())
+        .filter(|dir| {
+            dir.starts_with(package_root)
+                && *dir != package_root
+                && !non_example_dirs
+                    .iter()
+                    .any(|non_example_dir| dir.starts_with(non_example_dir))
+        })
+        .map(Path::to_path_buf)
+        .collect::<Vec<PathBuf>>();
+    example_dirs.sort();
+    example_dirs.dedup();
+    example_dirs
+}
+
+pub fn into_rs_file_target_kind_from_target_kind(
+    target_kind: &TargetKind,
+) -> RsFileTargetKind {
+    match target_kind {
+        TargetKind::Bench => RsFileTargetKind::Bench,
+        TargetKind::Bin => RsFileTargetKind::Source,
+        TargetKind::CustomBuild => RsFileTargetKind::Source,
+        TargetKind::ExampleBin => RsFileTargetKind::Example,
+        TargetKind::ExampleLib(_) => RsFileTargetKind::Example,
+        TargetKind::Lib(_) => RsFileTargetKind::Source,
+        TargetKind::Test => RsFileTargetKind::Test,
+    }
+}
+
 /// `cargo_metadata` returns the serialized strings from
 /// <https://github.com/rust-lang/cargo/blob/master/src/cargo/core/manifest.rs#L122>
 /// `TargetKind::ExampleBin` and `TargetKind::ExampleLib`, are both handled in the same manner
@@ -130,6 +225,17 @@ pub fn into_target_kind(raw_target_kind: Vec<String>) -> TargetKind {
     }
 }
 
+/// Whether any of the raw target kinds, as returned by `cargo_metadata`, is a
+/// library or a binary, i.e. a target used by a regular build.
+pub fn has_lib_or_bin_target(raw_target_kinds: &[Vec<String>]) -> bool {
+    raw_target_kinds.iter().any(|raw_target_kind| {
+        matches!(
+            into_target_kind(raw_target_kind.clone()),
+            TargetKind::Bin | TargetKind::Lib(_)
+        )
+    })
+}
+
 pub fn is_file_with_ext(entry: &DirEntry, file_ext: &str) -> bool {
     if !entry.file_type().is_file() {
         return false;
@@ -145,12 +251,20 @@ pub fn is_file_with_ext(entry: &DirEntry, file_ext: &str) -> bool {
     ext.to_string_lossy() == file_ext
 }
 
+/// The source files used by the build, and the `OUT_DIR` of the crates built
+/// with one, by the path of their entry point.
+#[derive(Debug, Default)]
+pub struct RsFileDeps {
+    pub rs_files_used: HashSet<PathBuf>,
+    pub out_dirs: HashMap<PathBuf, PathBuf>,
+}
+
 /// Trigger a `cargo clean` + `cargo check` and listen to the cargo/rustc
 /// communication to figure out which source files were used by the build.
 pub fn resolve_rs_file_deps(
     compile_options: &CompileOptions,
     workspace: &Workspace,
-) -> Result<HashSet<PathBuf>, RsResolveError> {
+) -> Result<RsFileDeps, RsResolveError> {
     let gctx = workspace.gctx();
     let (pkg_set, _) = ops::resolve_ws(workspace, true)
         .map_err(|e| RsResolveError::Cargo(e.to_string()))?;
@@ -161,10 +275,22 @@ pub fn resolve_rs_file_deps(
     // Need to run a cargo clean to identify all new .d deps files.
     // TODO: Figure out how this can be avoided to improve performance, clean
     // Rust builds are __slow__.
+    // The build directory of a `--target` is separate from the host one.
+    let targets = compile_options
+        .build_config
+        .requested_kinds
+        .iter()
+        .filter_map(|compile_kind| match compile_kind {
+            CompileKind::Host => None,
+            CompileKind::Target(compile_target) => {
+                Some(compile_target.rustc_target().to_string())
+            }
+        })
+        .collect();
     let clean_options = CleanOptions {
         gctx,
         spec: packages,
-        targets: vec![],
+        targets,
         profile_specified: false,
         // A temporary hack to get cargo 0.43 to build, TODO: look closer at the updated cargo API
         // later.
@@ -184,9 +310,9 @@ pub fn resolve_rs_file_deps(
     let workspace_root = workspace.root().to_path_buf();
     let inner_mutex =
         Arc::try_unwrap(inner_arc).map_err(|_| RsResolveError::ArcUnwrap())?;
-    let (rs_files, out_dir_args) = {
+    let (rs_files, out_dir_args, out_dirs) = {
         let ctx = inner_mutex.into_inner()?;
-        (ctx.rs_file_args, ctx.out_dir_args)
+        (ctx.rs_file_args, ctx.out_dir_args, ctx.out_dir_envs)
     };
     let mut path_buf_hash_set = HashSet::<PathBuf>::new();
     for out_dir in out_dir_args {
@@ -205,7 +331,10 @@ pub fn resolve_rs_file_deps(
         path_buf_hash_set.insert(path_buf);
     }
 
-    Ok(path_buf_hash_set)
+    Ok(RsFileDeps {
+        rs_files_used: path_buf_hash_set,
+        out_dirs,
+    })
 }
 
 fn add_dir_entries_to_path_buf_hash_set(
@@ -370,6 +499,125 @@ mod rs_file_tests {
         );
     }
 
+    #[rstest(
+        input_raw_target_kinds,
+        expected_has_lib_or_bin_target,
+        case(vec![vec!["lib"]], true),
+        case(vec![vec!["example"], vec!["bin"]], true),
+        case(vec![vec!["proc-macro"]], true),
+        case(vec![vec!["example"], vec!["example"]], false),
+        case(vec![vec!["custom-build"], vec!["example"]], false),
+        case(vec![], false)
+    )]
+    fn has_lib_or_bin_target_test(
+        input_raw_target_kinds: Vec<Vec<&str>>,
+        expected_has_lib_or_bin_target: bool,
+    ) {
+        let raw_target_kinds = input_raw_target_kinds
+            .into_iter()
+            .map(|raw_target_kind| {
+                raw_target_kind.into_iter().map(String::from).collect()
+            })
+            .collect::<Vec<Vec<String>>>();
+
+        assert_eq!(
+            has_lib_or_bin_target(&raw_target_kinds),
+            expected_has_lib_or_bin_target
+        );
+    }
+
+    #[rstest(
+        input_path,
+        expected_rs_file_target_kind,
+        case("/package/benches/bench.rs", RsFileTargetKind::Bench),
+        case("/package/benches/common/mod.rs", RsFileTargetKind::Bench),
+        case("/package/examples/example.rs", RsFileTargetKind::Example),
+        case("/package/tests/test.rs", RsFileTargetKind::Test),
+        case("/package/src/lib.rs", RsFileTargetKind::Source),
+        case("/package/src/tests/mod.rs", RsFileTargetKind::Source),
+        case("/elsewhere/tests/test.rs", RsFileTargetKind::Source)
+    )]
+    fn into_rs_file_target_kind_from_path_test(
+        input_path: &str,
+        expected_rs_file_target_kind: RsFileTargetKind,
+    ) {
+        assert_eq!(
+            into_rs_file_target_kind_from_path(
+                Path::new("/package"),
+                Path::new(input_path)
+            ),
+            expected_rs_file_target_kind
+        );
+    }
+
+    #[rstest(
+        input_target_paths_and_kinds,
+        expected_example_dirs,
+        case(
+            vec![
+                ("/package/src/lib.rs", TargetKind::Lib(vec![])),
+                ("/package/demos/demo/main.rs", TargetKind::ExampleBin),
+                ("/package/demos/other.rs", TargetKind::ExampleBin)
+            ],
+            vec!["/package/demos", "/package/demos/demo"]
+        ),
+        case(
+            vec![
+                ("/package/build.rs", TargetKind::CustomBuild),
+                ("/package/examples/example.rs", TargetKind::ExampleBin)
+            ],
+            vec!["/package/examples"]
+        ),
+        case(
+            vec![
+                ("/package/src/lib.rs", TargetKind::Lib(vec![])),
+                ("/package/src/demo.rs", TargetKind::ExampleBin),
+                ("/package/src/bin/demo/main.rs", TargetKind::ExampleBin),
+                ("/package/demo.rs", TargetKind::ExampleBin),
+                ("/elsewhere/demo.rs", TargetKind::ExampleBin)
+            ],
+            vec![]
+        )
+    )]
+    fn into_example_dirs_test(
+        input_target_paths_and_kinds: Vec<(&str, TargetKind)>,
+        expected_example_dirs: Vec<&str>,
+    ) {
+        let target_paths_and_kinds = input_target_paths_and_kinds
+            .into_iter()
+            .map(|(path, target_kind)| (PathBuf::from(path), target_kind))
+            .collect::<Vec<(PathBuf, TargetKind)>>();
+
+        assert_eq!(
+            into_example_dirs(Path::new("/package"), &target_paths_and_kinds),
+            expected_example_dirs
+                .into_iter()
+                .map(PathBuf::from)
+                .collect::<Vec<PathBuf>>()
+        );
+    }
+
+    #[rstest(
+        input_target_kind,
+        expected_rs_file_target_kind,
+        case(TargetKind::Bench, RsFileTargetKind::Bench),
+        case(TargetKind::Bin, RsFileTargetKind::Source),
+        case(TargetKind::CustomBuild, RsFileTargetKind::Source),
+        case(TargetKind::ExampleBin, RsFileTargetKind::Example),
+        case(TargetKind::ExampleLib(vec![]), RsFileTargetKind::Example),
+        case(TargetKind::Lib(vec![]), RsFileTargetKind::Source),
+        case(TargetKind::Test, RsFileTargetKind::Test)
+    )]
+    fn into_rs_file_target_kind_from_target_kind_test(
+        input_target_kind: TargetKind,
+        expected_rs_file_target_kind: RsFileTargetKind,
+    ) {
+        assert_eq!(
+            into_rs_file_target_kind_from_target_kind(&input_target_kind),
+            expected_rs_file_target_kind
+        );
+    }
+
     #[rstest(
         input_raw_target_kind,
         expected_target_kind,
@@ -2,7 +2,7 @@ use cargo::core::compiler::{CompileMode, Executor, Unit};
 use cargo::core::{PackageId, Target};
 use cargo::util::CargoResult;
 use cargo_util::ProcessBuilder;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::ffi::OsString;
 use std::fmt;
@@ -71,6 +71,9 @@ impl Executor for CustomExecutor {
             .map(PathBuf::from)
             .unwrap_or_else(|| self.cwd.to_owned());
 
+        // Only set for the crates of a package with a build script.
+        let out_dir_env = cmd.get_env("OUT_DIR").map(PathBuf::from);
+
         {
             // Scope to drop and release the mutex before calling rustc.
             let mut ctx = self.inner_ctx.lock().map_err(|e| {
@@ -84,6 +87,9 @@ impl Executor for CustomExecutor {
                 let path = raw_path
                     .canonicalize()
                     .map_err(|e| CustomExecutorError::Io(e, raw_path))?;
+                if let Some(out_dir_env) = &out_dir_env {
+                    ctx.out_dir_envs.insert(path.clone(), out_dir_env.clone());
+                }
                 ctx.rs_file_args.insert(path);
             }
             ctx.out_dir_args.insert(out_dir);
@@ -116,4 +122,8 @@ pub struct CustomExecutorInnerContext {
     /// Investigate if this needs to be intercepted like this or if it can be
     /// looked up in a nicer way.
     pub out_dir_args: HashSet<PathBuf>,
+
+    /// The `OUT_DIR` given by cargo to the build of each crate, by the path
+    /// of the `.rs` files passed to rustc.
+    pub out_dir_envs: HashMap<PathBuf, PathBuf>,
 }
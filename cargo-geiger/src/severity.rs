@@ -0,0 +1,157 @@
+use geiger::UnsafeKind;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The severity of a finding, from the least to the most severe.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// Severities of the kinds of unsafe code, which set the level of the
+/// findings of the outputs with one, `--output-format GitHubActions` and
+/// `--message-format json`. Kinds missing from the severity map file keep
+/// their default severity, so an empty file gives the default severities.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeverityMap {
+    pub block: Severity,
+    pub transmute: Severity,
+    pub asm: Severity,
+    pub function: Severity,
+    pub method: Severity,
+    #[serde(rename = "impl")]
+    pub impl_: Severity,
+    #[serde(rename = "trait")]
+    pub trait_: Severity,
+}
+
+/// A plain unsafe block is the least severe, reinterpreting bits and inline
+/// assembly escape most of the checks of the compiler.
+impl Default for SeverityMap {
+    fn default() -> Self {
+        SeverityMap {
+            block: Severity::Low,
+            transmute: Severity::High,
+            asm: Severity::High,
+            function: Severity::Medium,
+            method: Severity::Medium,
+            impl_: Severity::Medium,
+            trait_: Severity::Medium,
+        }
+    }
+}
+
+impl SeverityMap {
+    /// Load the severity map from a TOML file, e.g.
+    /// ```toml
+    /// block = "medium"
+    /// impl = "critical"
+    /// ```
+    pub fn load(path: &Path) -> Result<SeverityMap, SeverityMapError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| SeverityMapError::Io(e, path.to_path_buf()))?;
+        toml::from_str(&content)
+            .map_err(|e| SeverityMapError::Toml(e, path.to_path_buf()))
+    }
+
+    pub fn severity(&self, unsafe_kind: UnsafeKind) -> Severity {
+        match unsafe_kind {
+            UnsafeKind::Block => self.block,
+            UnsafeKind::Transmute => self.transmute,
+            UnsafeKind::Asm => self.asm,
+            UnsafeKind::Function => self.function,
+            UnsafeKind::Method => self.method,
+            UnsafeKind::Impl => self.impl_,
+            UnsafeKind::Trait => self.trait_,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SeverityMapError {
+    Io(io::Error, PathBuf),
+    Toml(toml::de::Error, PathBuf),
+}
+
+impl Error for SeverityMapError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for SeverityMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod severity_tests {
+    use super::*;
+
+    use rstest::*;
+    use tempfile::tempdir;
+
+    #[rstest(
+        input_severity_map,
+        input_unsafe_kind,
+        expected_severity,
+        case("", UnsafeKind::Block, Severity::Low),
+        case("", UnsafeKind::Transmute, Severity::High),
+        case("", UnsafeKind::Impl, Severity::Medium),
+        case("block = \"critical\"\n", UnsafeKind::Block, Severity::Critical),
+        case("block = \"critical\"\n", UnsafeKind::Asm, Severity::High),
+        case("trait = \"info\"\n", UnsafeKind::Trait, Severity::Info)
+    )]
+    fn severity_map_severity_test(
+        input_severity_map: &str,
+        input_unsafe_kind: UnsafeKind,
+        expected_severity: Severity,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let severity_map_path = temp_dir.path().join("severity.toml");
+        fs::write(&severity_map_path, input_severity_map).unwrap();
+
+        let severity_map = SeverityMap::load(&severity_map_path).unwrap();
+
+        assert_eq!(severity_map.severity(input_unsafe_kind), expected_severity);
+    }
+
+    #[rstest(
+        input_severity_map,
+        case("blocks = \"high\"\n"),
+        case("block = \"severe\"\n")
+    )]
+    fn severity_map_load_rejects_invalid_entries_test(
+        input_severity_map: &str,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let severity_map_path = temp_dir.path().join("severity.toml");
+        fs::write(&severity_map_path, input_severity_map).unwrap();
+
+        assert!(matches!(
+            SeverityMap::load(&severity_map_path),
+            Err(SeverityMapError::Toml(_, _))
+        ));
+    }
+}
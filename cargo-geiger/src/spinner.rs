@@ -0,0 +1,130 @@
+use cargo::core::shell::Verbosity;
+use cargo::GlobalContext as Config;
+use console::Term;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The frames drawn in turn by the spinner.
+pub const SPINNER_FRAMES: [char; 10] =
+    ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A spinner drawn on stderr while the dependencies are resolved, a slow
+/// phase during which neither cargo nor cargo-geiger print anything. The
+/// spinner is cleared when stopped or dropped, so it has to be stopped before
+/// anything else is printed.
+pub struct Spinner {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Start the spinner, unless it is disabled, see `is_spinner_enabled`.
+    pub fn start(message: &str, config: &Config) -> Spinner {
+        let running = Arc::new(AtomicBool::new(false));
+        let enabled = is_spinner_enabled(
+            config.shell().verbosity(),
+            Term::stdout().is_term(),
+            Term::stderr().is_term(),
+            env::var_os("CI").is_some(),
+        );
+        if !enabled {
+            return Spinner {
+                running,
+                handle: None,
+            };
+        }
+
+        running.store(true, Ordering::SeqCst);
+        let thread_running = running.clone();
+        let message = message.to_string();
+        let handle = thread::spawn(move || {
+            let term = Term::stderr();
+            for frame in SPINNER_FRAMES.iter().cycle() {
+                if !thread_running.load(Ordering::SeqCst) {
+                    break;
+                }
+                // The spinner is cosmetic, failing to draw it is ignored.
+                let _ = term.write_str(&format!("\r{} {}", frame, message));
+                thread::sleep(SPINNER_INTERVAL);
+            }
+            let _ = term.clear_line();
+        });
+        Spinner {
+            running,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop the spinner and clear its line.
+    pub fn stop(mut self) {
+        self.stop_and_join();
+    }
+
+    fn stop_and_join(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
+
+/// The spinner is only drawn for interactive use: both stdout and stderr are
+/// terminals, `--quiet` is not given, and cargo-geiger does not run in CI,
+/// where it would only clutter the logs.
+fn is_spinner_enabled(
+    verbosity: Verbosity,
+    stdout_is_term: bool,
+    stderr_is_term: bool,
+    is_ci: bool,
+) -> bool {
+    verbosity != Verbosity::Quiet && stdout_is_term && stderr_is_term && !is_ci
+}
+
+#[cfg(test)]
+mod spinner_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest(
+        input_verbosity,
+        input_stdout_is_term,
+        input_stderr_is_term,
+        input_is_ci,
+        expected_is_spinner_enabled,
+        case(Verbosity::Normal, true, true, false, true),
+        case(Verbosity::Verbose, true, true, false, true),
+        case(Verbosity::Quiet, true, true, false, false),
+        case(Verbosity::Normal, false, true, false, false),
+        case(Verbosity::Normal, true, false, false, false),
+        case(Verbosity::Normal, true, true, true, false)
+    )]
+    fn is_spinner_enabled_test(
+        input_verbosity: Verbosity,
+        input_stdout_is_term: bool,
+        input_stderr_is_term: bool,
+        input_is_ci: bool,
+        expected_is_spinner_enabled: bool,
+    ) {
+        assert_eq!(
+            is_spinner_enabled(
+                input_verbosity,
+                input_stdout_is_term,
+                input_stderr_is_term,
+                input_is_ci
+            ),
+            expected_is_spinner_enabled
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use cargo_geiger_serde::{Count, SafetyReport, Source};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+/// The tables are only created when missing, so that the scans of a project
+/// accumulate in the same database and can be compared with SQL queries.
+const CREATE_TABLES: &str = "
+    CREATE TABLE IF NOT EXISTS crates (
+        id INTEGER PRIMARY KEY,
+        report_name TEXT NOT NULL,
+        scan_timestamp INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        version TEXT NOT NULL,
+        source TEXT NOT NULL,
+        build_only INTEGER NOT NULL,
+        ignored INTEGER NOT NULL,
+        forbids_unsafe INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS counts (
+        crate_id INTEGER NOT NULL REFERENCES crates (id),
+        category TEXT NOT NULL,
+        used_safe INTEGER NOT NULL,
+        used_unsafe INTEGER NOT NULL,
+        unused_safe INTEGER NOT NULL,
+        unused_unsafe INTEGER NOT NULL
+    );
+";
+
+/// Append the packages of the report to the SQLite database at `path`, which
+/// is created if missing, for `--sqlite`. Each package is a row of `crates`,
+/// tagged with the `report_name` and the `scan_timestamp` in seconds since
+/// the Unix epoch, with a row of `counts` for each category of unsafe usage.
+pub fn write_report_to_sqlite(
+    path: &Path,
+    report_name: &str,
+    scan_timestamp: u64,
+    report: &SafetyReport,
+) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(path)?;
+    insert_report(&mut connection, report_name, scan_timestamp, report)
+}
+
+fn insert_report(
+    connection: &mut Connection,
+    report_name: &str,
+    scan_timestamp: u64,
+    report: &SafetyReport,
+) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(CREATE_TABLES)?;
+
+    let mut entries = report
+        .packages
+        .values()
+        .map(|entry| (entry, false))
+        .chain(report.build_packages.values().map(|entry| (entry, true)))
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.package.id.cmp(&b.0.package.id));
+
+    for (entry, build_only) in entries {
+        transaction.execute(
+            "INSERT INTO crates (report_name, scan_timestamp, name, version, \
+             source, build_only, ignored, forbids_unsafe) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                report_name,
+                scan_timestamp,
+                entry.package.id.name,
+                entry.package.id.version.to_string(),
+                source_string(&entry.package.id.source),
+                build_only,
+                report.ignored_packages.contains(&entry.package.id),
+                entry.unsafety.forbids_unsafe,
+            ],
+        )?;
+        let crate_id = transaction.last_insert_rowid();
+
+        let used = &entry.unsafety.used;
+        let unused = &entry.unsafety.unused;
+        let categories: [(&str, &Count, &Count); 5] = [
+            ("functions", &used.functions, &unused.functions),
+            ("exprs", &used.exprs, &unused.exprs),
+            ("item_impls", &used.item_impls, &unused.item_impls),
+            ("item_traits", &used.item_traits, &unused.item_traits),
+            ("methods", &used.methods, &unused.methods),
+        ];
+        for (category, used_count, unused_count) in categories {
+            transaction.execute(
+                "INSERT INTO counts (crate_id, category, used_safe, \
+                 used_unsafe, unused_safe, unused_unsafe) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    crate_id,
+                    category,
+                    used_count.safe,
+                    used_count.unsafe_,
+                    unused_count.safe,
+                    unused_count.unsafe_,
+                ],
+            )?;
+        }
+    }
+
+    transaction.commit()
+}
+
+/// The source in the style of the source ids of cargo, such as
+/// `registry+https://github.com/rust-lang/crates.io-index`.
+fn source_string(source: &Source) -> String {
+    match source {
+        Source::Git { url, rev } => format!("git+{}#{}", url, rev),
+        Source::Registry { url, .. } => format!("registry+{}", url),
+        Source::Path(url) => format!("path+{}", url),
+    }
+}
+
+#[cfg(test)]
+mod sqlite_tests {
+    use super::*;
+
+    use cargo_geiger_serde::{
+        CounterBlock, PackageId, PackageInfo, ReportEntry, UnsafeInfo,
+    };
+    use rstest::*;
+    use semver::Version;
+    use tempfile::tempdir;
+    use url::Url;
+
+    fn report() -> SafetyReport {
+        let mut report = SafetyReport::default();
+        let package_id = PackageId {
+            name: String::from("a"),
+            version: Version::new(1, 2, 3),
+            source: Source::Registry {
+                name: String::from("crates.io"),
+                url: Url::parse("https://github.com/rust-lang/crates.io-index")
+                    .unwrap(),
+            },
+        };
+        report.packages.insert(
+            package_id.clone(),
+            ReportEntry {
+                package: PackageInfo::new(package_id),
+                active_features: vec![],
+                unsafety: UnsafeInfo {
+                    used: CounterBlock {
+                        exprs: Count {
+                            safe: 4,
+                            unsafe_: 3,
+                        },
+                        ..Default::default()
+                    },
+                    unused: CounterBlock {
+                        exprs: Count {
+                            safe: 2,
+                            unsafe_: 1,
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            },
+        );
+        report
+    }
+
+    #[rstest]
+    fn insert_report_test() {
+        let mut connection = Connection::open_in_memory().unwrap();
+
+        insert_report(&mut connection, "scan", 1_700_000_000, &report())
+            .unwrap();
+
+        let crate_row = connection
+            .query_row(
+                "SELECT report_name, scan_timestamp, name, version, source, \
+                 build_only, ignored FROM crates",
+                [],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, u64>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, bool>(5)?,
+                        row.get::<_, bool>(6)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            crate_row,
+            (
+                String::from("scan"),
+                1_700_000_000,
+                String::from("a"),
+                String::from("1.2.3"),
+                String::from(
+                    "registry+https://github.com/rust-lang/crates.io-index"
+                ),
+                false,
+                false
+            )
+        );
+
+        let exprs_counts = connection
+            .query_row(
+                "SELECT used_safe, used_unsafe, unused_safe, unused_unsafe \
+                 FROM counts WHERE category = 'exprs'",
+                [],
+                |row| {
+                    Ok((
+                        row.get::<_, u64>(0)?,
+                        row.get::<_, u64>(1)?,
+                        row.get::<_, u64>(2)?,
+                        row.get::<_, u64>(3)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(exprs_counts, (4, 3, 2, 1));
+    }
+
+    #[rstest]
+    fn write_report_to_sqlite_test_appends_the_scans() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("geiger.sqlite");
+
+        write_report_to_sqlite(&path, "first", 1, &report()).unwrap();
+        write_report_to_sqlite(&path, "second", 2, &report()).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let mut statement = connection
+            .prepare(
+                "SELECT report_name, SUM(used_unsafe) FROM crates \
+                 JOIN counts ON counts.crate_id = crates.id \
+                 GROUP BY report_name ORDER BY scan_timestamp",
+            )
+            .unwrap();
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![(String::from("first"), 3), (String::from("second"), 3)]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use cargo_geiger_serde::{CounterBlock, StdCrateUnsafety};
+use geiger::find::find_unsafe_in_file;
+use geiger::IncludeTests;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// The crates of the standard library scanned by `--include-std`.
+pub const STD_CRATES: &[&str] = &["core", "alloc", "std"];
+
+/// The `library` directory of the standard library source installed by the
+/// `rust-src` component, in the sysroot of `rustc_path`.
+pub fn find_std_library_path(
+    rustc_path: &Path,
+) -> Result<PathBuf, StdLibError> {
+    let output = Command::new(rustc_path)
+        .args(["--print", "sysroot"])
+        .output()
+        .map_err(|e| StdLibError::Io(e, rustc_path.to_path_buf()))?;
+    let sysroot = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let library_path = Path::new(&sysroot)
+        .join("lib")
+        .join("rustlib")
+        .join("src")
+        .join("rust")
+        .join("library");
+    if !library_path.join("core").is_dir() {
+        return Err(StdLibError::RustSrcNotInstalled(library_path));
+    }
+    Ok(library_path)
+}
+
+/// The unsafe usage found in all the `.rs` files of each crate of
+/// `STD_CRATES`, none of them being built. The files which can't be parsed
+/// are skipped.
+pub fn std_crates_unsafety(
+    library_path: &Path,
+    include_tests: IncludeTests,
+) -> Vec<StdCrateUnsafety> {
+    STD_CRATES
+        .iter()
+        .map(|name| {
+            let mut unsafety = CounterBlock::default();
+            for entry in WalkDir::new(library_path.join(name).join("src"))
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| {
+                    entry.file_type().is_file()
+                        && entry
+                            .path()
+                            .extension()
+                            .map_or(false, |ext| ext == "rs")
+                })
+            {
+                if let Ok(rs_file_metrics) =
+                    find_unsafe_in_file(entry.path(), include_tests)
+                {
+                    unsafety += rs_file_metrics.counters;
+                }
+            }
+            StdCrateUnsafety {
+                name: name.to_string(),
+                unsafety,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum StdLibError {
+    Io(io::Error, PathBuf),
+    RustSrcNotInstalled(PathBuf),
+}
+
+impl Error for StdLibError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for StdLibError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod std_lib_tests {
+    use super::*;
+
+    use rstest::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[rstest]
+    fn std_crates_unsafety_test() {
+        let temp_dir = tempdir().unwrap();
+        let core_src_path = temp_dir.path().join("core").join("src");
+        fs::create_dir_all(core_src_path.join("ptr")).unwrap();
+        fs::write(core_src_path.join("lib.rs"), "pub mod ptr;").unwrap();
+        fs::write(
+            core_src_path.join("ptr").join("mod.rs"),
+            "pub unsafe fn read() {}\npub fn f() { unsafe { read() } }",
+        )
+        .unwrap();
+        fs::write(core_src_path.join("invalid.rs"), "pub fn {").unwrap();
+
+        let std_crates = std_crates_unsafety(temp_dir.path(), IncludeTests::No);
+
+        assert_eq!(
+            std_crates
+                .iter()
+                .map(|std_crate| std_crate.name.as_str())
+                .collect::<Vec<&str>>(),
+            STD_CRATES
+        );
+        assert_eq!(std_crates[0].unsafety.functions.unsafe_, 1);
+        assert_eq!(std_crates[0].unsafety.functions.safe, 1);
+        assert!(std_crates[0].unsafety.exprs.unsafe_ > 0);
+        assert_eq!(std_crates[1].unsafety, CounterBlock::default());
+    }
+
+    #[rstest]
+    fn find_std_library_path_test_without_rustc() {
+        assert!(matches!(
+            find_std_library_path(Path::new("/nonexistent/rustc")),
+            Err(StdLibError::Io(..))
+        ));
+    }
+}
@@ -10,7 +10,13 @@ use cargo_metadata::{DependencyKind, PackageId};
 #[derive(Debug, Eq, PartialEq)]
 pub enum TextTreeLine {
     /// A text line for a package
-    Package { id: PackageId, tree_vines: String },
+    Package {
+        id: PackageId,
+        tree_vines: String,
+        /// The package was already displayed, and its dependencies are not
+        /// displayed again. Never set with `--all`.
+        dependencies_elided: bool,
+    },
     /// There are extra dependencies coming and we should print a group header,
     /// eg. "[build-dependencies]".
     ExtraDepsGroup {
@@ -59,6 +65,7 @@ fn construct_tree_vines_string(
 pub fn get_tree_symbols(output_format: OutputFormat) -> TreeSymbols {
     match output_format {
         OutputFormat::Ascii => ASCII_TREE_SYMBOLS,
+        OutputFormat::Tree => PLAIN_TREE_SYMBOLS,
         _ => UTF8_TREE_SYMBOLS,
     }
 }
@@ -70,6 +77,14 @@ const ASCII_TREE_SYMBOLS: TreeSymbols = TreeSymbols {
     right: "-",
 };
 
+/// The dependencies are only indented, for `--output-format Tree`.
+const PLAIN_TREE_SYMBOLS: TreeSymbols = TreeSymbols {
+    down: " ",
+    tee: " ",
+    ell: " ",
+    right: " ",
+};
+
 const UTF8_TREE_SYMBOLS: TreeSymbols = TreeSymbols {
     down: "│",
     tee: "├",
@@ -113,6 +128,7 @@ mod tree_tests {
         expected_tree_symbols,
         case(OutputFormat::Ascii, ASCII_TREE_SYMBOLS),
         case(OutputFormat::GitHubMarkdown, UTF8_TREE_SYMBOLS),
+        case(OutputFormat::Tree, PLAIN_TREE_SYMBOLS),
         case(OutputFormat::Utf8, UTF8_TREE_SYMBOLS)
     )]
     fn get_tree_symbols_test(
@@ -132,9 +148,17 @@ mod tree_tests {
             direction: EdgeDirection::Outgoing,
             prefix,
             format: pattern,
+            generated_file_patterns: vec![],
             allow_partial_results: false,
+            deadline: None,
             include_tests: IncludeTests::Yes,
+            max_depth: None,
+            max_file_size: None,
+            memory_lifecycle_functions: vec![],
+            no_cache: false,
             output_format: OutputFormat::Ascii,
+            scan_timeout_per_crate: None,
+            unsafe_macros: vec![],
         }
     }
 }
@@ -17,7 +17,16 @@ pub fn walk_dependency_node(
     package: &PackageId,
     walk_dependency_parameters: &mut WalkDependencyParameters,
 ) -> Vec<TextTreeLine> {
+    // The dependencies of a package at the `--depth` are not displayed, so it
+    // isn't marked as visited, and is displayed in full if found higher up.
+    let at_max_depth = walk_dependency_parameters
+        .print_config
+        .max_depth
+        .map_or(false, |max_depth| {
+            walk_dependency_parameters.levels_continue.len() >= max_depth
+        });
     let new = walk_dependency_parameters.print_config.all
+        || at_max_depth
         || walk_dependency_parameters
             .visited_deps
             .insert(package.clone());
@@ -26,21 +35,25 @@ pub fn walk_dependency_node(
         walk_dependency_parameters.print_config,
     );
 
+    let mut dependency_type_nodes = construct_dependency_type_nodes_hashmap(
+        walk_dependency_parameters.graph,
+        package,
+        walk_dependency_parameters.print_config,
+    );
+
     let mut all_out_text_tree_lines = vec![TextTreeLine::Package {
         id: package.clone(),
         tree_vines,
+        dependencies_elided: !new
+            && dependency_type_nodes
+                .values()
+                .any(|nodes| !nodes.is_empty()),
     }];
 
-    if !new {
+    if !new || at_max_depth {
         return all_out_text_tree_lines;
     }
 
-    let mut dependency_type_nodes = construct_dependency_type_nodes_hashmap(
-        walk_dependency_parameters.graph,
-        package,
-        walk_dependency_parameters.print_config,
-    );
-
     for (dependency_kind, nodes) in dependency_type_nodes.iter_mut() {
         let mut dep_kind_out = walk_dependency_kind(
             cargo_metadata_parameters,
@@ -215,11 +228,19 @@ mod dependency_node_tests {
         PrintConfig {
             all: false,
             allow_partial_results: false,
+            deadline: None,
             direction: edge_direction,
             format: Pattern::new(vec![]),
+            generated_file_patterns: vec![],
             include_tests: IncludeTests::Yes,
+            max_depth: None,
+            max_file_size: None,
+            memory_lifecycle_functions: vec![],
+            no_cache: false,
             prefix: Prefix::Depth,
             output_format: OutputFormat::Ascii,
+            scan_timeout_per_crate: None,
+            unsafe_macros: vec![],
         }
     }
 }
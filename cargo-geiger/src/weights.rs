@@ -0,0 +1,141 @@
+use cargo_geiger_serde::CounterBlock;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Weights of the unsafe usage categories, used to compute a single risk
+/// score per crate. Categories missing from the weights file keep a weight of
+/// 1, so an empty file scores a crate by its plain count of unsafe usage.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnsafeWeights {
+    pub functions: u64,
+    pub exprs: u64,
+    pub impls: u64,
+    pub traits: u64,
+    pub methods: u64,
+}
+
+impl Default for UnsafeWeights {
+    fn default() -> Self {
+        UnsafeWeights {
+            functions: 1,
+            exprs: 1,
+            impls: 1,
+            traits: 1,
+            methods: 1,
+        }
+    }
+}
+
+impl UnsafeWeights {
+    /// Load the weights from a TOML file, e.g.
+    /// ```toml
+    /// functions = 2
+    /// exprs = 5
+    /// ```
+    pub fn load(path: &Path) -> Result<UnsafeWeights, WeightsError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| WeightsError::Io(e, path.to_path_buf()))?;
+        toml::from_str(&content)
+            .map_err(|e| WeightsError::Toml(e, path.to_path_buf()))
+    }
+
+    /// The weighted sum of the unsafe usage in the counter block.
+    pub fn risk_score(&self, counter_block: &CounterBlock) -> u64 {
+        self.functions * counter_block.functions.unsafe_
+            + self.exprs * counter_block.exprs.unsafe_
+            + self.impls * counter_block.item_impls.unsafe_
+            + self.traits * counter_block.item_traits.unsafe_
+            + self.methods * counter_block.methods.unsafe_
+    }
+}
+
+#[derive(Debug)]
+pub enum WeightsError {
+    Io(io::Error, PathBuf),
+    Toml(toml::de::Error, PathBuf),
+}
+
+impl Error for WeightsError {}
+
+/// Forward Display to Debug, probably good enough for
+/// programmer facing error messages.
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod weights_tests {
+    use super::*;
+
+    use cargo_geiger_serde::Count;
+    use rstest::*;
+    use tempfile::tempdir;
+
+    #[rstest(
+        input_weights,
+        expected_risk_score,
+        case("", 15),
+        case("functions = 3\nexprs = 5\n", 33),
+        case(
+            "functions = 0\nexprs = 0\nimpls = 0\ntraits = 0\nmethods = 10\n",
+            50
+        )
+    )]
+    fn unsafe_weights_risk_score_test(
+        input_weights: &str,
+        expected_risk_score: u64,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let weights_path = temp_dir.path().join("weights.toml");
+        fs::write(&weights_path, input_weights).unwrap();
+
+        let counter_block = CounterBlock {
+            functions: Count {
+                safe: 7,
+                unsafe_: 1,
+            },
+            exprs: Count {
+                safe: 7,
+                unsafe_: 4,
+            },
+            item_impls: Count {
+                safe: 7,
+                unsafe_: 2,
+            },
+            item_traits: Count {
+                safe: 7,
+                unsafe_: 3,
+            },
+            methods: Count {
+                safe: 7,
+                unsafe_: 5,
+            },
+        };
+
+        let unsafe_weights = UnsafeWeights::load(&weights_path).unwrap();
+
+        assert_eq!(
+            unsafe_weights.risk_score(&counter_block),
+            expected_risk_score
+        );
+    }
+
+    #[rstest]
+    fn unsafe_weights_load_rejects_unknown_category_test() {
+        let temp_dir = tempdir().unwrap();
+        let weights_path = temp_dir.path().join("weights.toml");
+        fs::write(&weights_path, "transmutes = 10\n").unwrap();
+
+        assert!(matches!(
+            UnsafeWeights::load(&weights_path),
+            Err(WeightsError::Toml(_, _))
+        ));
+    }
+}
@@ -27,6 +27,7 @@ pub fn ref_slice_package_id() -> PackageId {
 pub fn ref_slice_safety_report() -> SafetyReport {
     let entry = ReportEntry {
         package: PackageInfo::new(ref_slice_package_id()),
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -56,6 +57,7 @@ pub fn either_package_id() -> PackageId {
 pub fn either_safety_report() -> SafetyReport {
     let entry = ReportEntry {
         package: PackageInfo::new(either_package_id()),
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -93,6 +95,7 @@ pub fn doc_comment_package_id() -> PackageId {
 pub fn doc_comment_safety_report() -> SafetyReport {
     let entry = ReportEntry {
         package: PackageInfo::new(doc_comment_package_id()),
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -129,6 +132,7 @@ pub fn itertools_safety_report() -> SafetyReport {
             dependencies: to_set(vec![either_package_id()]),
             ..PackageInfo::new(itertools_package_id())
         },
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -193,6 +197,7 @@ pub fn cfg_if_package_id() -> PackageId {
 pub fn cfg_if_safety_report() -> SafetyReport {
     let entry = ReportEntry {
         package: PackageInfo::new(cfg_if_package_id()),
+        active_features: vec![],
         unsafety: Default::default(),
     };
     single_entry_safety_report(entry)
@@ -212,6 +217,7 @@ pub fn generational_arena_safety_report() -> SafetyReport {
             dependencies: to_set(vec![cfg_if_package_id()]),
             ..PackageInfo::new(generational_arena_package_id())
         },
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 exprs: Count {
@@ -248,6 +254,7 @@ pub fn generational_arena_safety_report() -> SafetyReport {
                 ..Default::default()
             },
             forbids_unsafe: true,
+            ..Default::default()
         },
     };
     let mut report = single_entry_safety_report(entry);
@@ -273,6 +280,7 @@ pub fn idna_safety_report() -> SafetyReport {
             ]),
             ..PackageInfo::new(idna_package_id())
         },
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -317,6 +325,7 @@ pub fn matches_package_id() -> PackageId {
 pub fn matches_safety_report() -> SafetyReport {
     let entry = ReportEntry {
         package: PackageInfo::new(matches_package_id()),
+        active_features: vec![],
         unsafety: Default::default(),
     };
     single_entry_safety_report(entry)
@@ -333,6 +342,7 @@ pub fn smallvec_package_id() -> PackageId {
 pub fn smallvec_safety_report() -> SafetyReport {
     let entry = ReportEntry {
         package: PackageInfo::new(smallvec_package_id()),
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -398,6 +408,7 @@ pub fn unicode_bidi_safety_report() -> SafetyReport {
             dependencies: to_set(vec![matches_package_id()]),
             ..PackageInfo::new(unicode_bidi_package_id())
         },
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -441,6 +452,7 @@ pub(super) fn unicode_normalization_safety_report() -> SafetyReport {
             dependencies: to_set(vec![smallvec_package_id()]),
             ..PackageInfo::new(unicode_normalization_package_id())
         },
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -497,6 +509,7 @@ pub fn num_cpus_safety_report(cx: &Context) -> SafetyReport {
             dependencies: to_set(vec![make_package_id(cx, super::Test1::NAME)]),
             ..PackageInfo::new(num_cpus_package_id(cx))
         },
+        active_features: vec![],
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
@@ -20,6 +20,20 @@ pub trait IntegrationTest {
         assert!(output.status.success());
         let actual =
             serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+        // The metadata depends on the toolchain used for the run, it is
+        // covered by a dedicated test, as are the safety grade and the
+        // unsafe-free counts. The active features depend on the
+        // `--all-features` of the run, they are covered by a dedicated test
+        // too.
+        let mut actual = SafetyReport {
+            metadata: Default::default(),
+            safety_grade: None,
+            unsafe_free_counts: None,
+            ..actual
+        };
+        for entry in actual.packages.values_mut() {
+            entry.active_features.clear();
+        }
         assert_eq!(actual, self.expected_report(&cx));
     }
 
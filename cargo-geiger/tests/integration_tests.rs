@@ -4,12 +4,17 @@
 mod context;
 mod run;
 
+use self::context::Context;
 use self::run::run_geiger_with;
 
+use assert_cmd::prelude::*;
+use cargo_geiger_serde::{CounterBlock, FailGate, FailSummary, SafetyReport};
+use cargo_metadata::diagnostic::DiagnosticLevel;
+use cargo_metadata::Message;
 use insta::assert_snapshot;
 use rstest::rstest;
 use std::env;
-use std::process::Output;
+use std::process::{Command, Output};
 
 #[rstest(
     name,
@@ -57,3 +62,1299 @@ fn test_package(name: &str) {
         run_geiger_with(test_name, None::<&str>).0
     }
 }
+
+#[rstest]
+fn test_ci_preset() {
+    let cx = Context::new();
+    let run_geiger_with_ci = |extra_args: &[&str]| {
+        Command::cargo_bin("cargo-geiger")
+            .unwrap()
+            .arg("geiger")
+            .arg("--ci")
+            .args(extra_args)
+            .current_dir(cx.crate_dir("test1_package_with_no_deps"))
+            .output()
+            .expect("failed to run `cargo-geiger`")
+    };
+
+    let first_output = run_geiger_with_ci(&[]);
+    let second_output = run_geiger_with_ci(&[]);
+    assert!(first_output.status.success());
+    assert_eq!(first_output.stdout, second_output.stdout);
+    let stdout = String::from_utf8(first_output.stdout)
+        .expect("output should have been valid utf-8");
+    assert!(!stdout.contains('\u{1b}'));
+    let report = serde_json::from_str::<serde_json::Value>(&stdout)
+        .expect("output should have been a Json report");
+    assert!(report["packages"].is_array());
+
+    // An explicit flag overrides the default of the preset.
+    let ascii_output = run_geiger_with_ci(&["--output-format", "Ascii"]);
+    assert!(ascii_output.status.success());
+    assert!(
+        serde_json::from_slice::<serde_json::Value>(&ascii_output.stdout)
+            .is_err()
+    );
+}
+
+#[rstest(
+    name,
+    expected_success,
+    case("test1_package_with_no_deps", false),
+    case("test10_package_with_bench_and_example", true)
+)]
+fn test_package_with_quiet_unsafe_free(name: &str, expected_success: bool) {
+    let (output, _cx) = run_geiger_with(name, &["--quiet-unsafe-free"]);
+
+    assert_eq!(output.status.success(), expected_success);
+    // The report is only printed when unsafe usage is found.
+    assert_eq!(output.stdout.is_empty(), expected_success);
+}
+
+// test2 uses 8 unsafe expressions, with its dependencies.
+#[rstest(fail_threshold, expected_success, case("8", true), case("7", false))]
+fn test_package_with_quiet_unsafe_free_and_fail_threshold(
+    fail_threshold: &str,
+    expected_success: bool,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--quiet-unsafe-free", "--fail-threshold", fail_threshold],
+    );
+
+    assert_eq!(output.status.success(), expected_success);
+    // The unsafe usage up to the threshold is clean, and not reported.
+    assert_eq!(output.stdout.is_empty(), expected_success);
+}
+
+#[rstest(
+    input_args,
+    expected_success,
+    case(
+        vec![
+            "--ignore-unsafe-in",
+            "test2_package_with_shallow_deps",
+            "--ignore-unsafe-in",
+            "test1_package_with_no_deps",
+            "--ignore-unsafe-in",
+            "ref_slice",
+        ],
+        true
+    ),
+    case(
+        vec![
+            "--ignore-unsafe-in",
+            "test2_package_with_shallow_deps",
+            "--ignore-unsafe-in",
+            "test1_package_with_no_deps",
+        ],
+        false
+    )
+)]
+fn test_package_with_fail_on_any_unsafe(
+    input_args: Vec<&str>,
+    expected_success: bool,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        std::iter::once("--fail-on-any-unsafe").chain(input_args),
+    );
+
+    // Only the unsafe usage of ref_slice, when it is not ignored, fails.
+    assert_eq!(output.status.success(), expected_success);
+    // The report is printed either way.
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert!(stdout.contains("ref_slice 1.1.1"));
+}
+
+// test2 uses 4 unsafe expressions, test1 2 and ref_slice 2.
+#[rstest(
+    input_args,
+    expected_success,
+    case(vec!["--fail-threshold", "4", "--threshold-scope", "root"], true),
+    case(vec!["--fail-threshold", "3", "--threshold-scope", "root"], false),
+    case(vec!["--fail-threshold", "6", "--ignore-unsafe-in", "ref_slice"], true),
+    case(vec!["--fail-threshold", "6"], false),
+    case(
+        vec![
+            "--fail-threshold",
+            "3",
+            "--threshold-scope",
+            "root",
+            "--forbid-only"
+        ],
+        false
+    )
+)]
+fn test_package_with_fail_threshold(
+    input_args: Vec<&str>,
+    expected_success: bool,
+) {
+    let (output, _cx) =
+        run_geiger_with("test2_package_with_shallow_deps", input_args);
+
+    assert_eq!(output.status.success(), expected_success);
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert_eq!(
+        stderr.contains("more than the threshold of"),
+        !expected_success
+    );
+}
+
+#[rstest]
+fn test_package_with_fail_threshold_names_top_crate() {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &[
+            "--fail-threshold",
+            "1",
+            "--exclude-self",
+            "--ignore-unsafe-in",
+            "ref_slice",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains(
+        "more than the threshold of 1, the most in test1_package_with_no_deps 0.1.0 (expressions: 2, functions: 1)"
+    ));
+}
+
+#[rstest]
+fn test_package_with_fail_threshold_and_forbid_only() {
+    let fail_threshold_stderr = |input_args: &[&str]| {
+        let (output, _cx) = run_geiger_with(
+            "test24_package_with_unsafe_modules",
+            ["--fail-threshold", "0"].iter().chain(input_args),
+        );
+        assert!(!output.status.success());
+        String::from_utf8(output.stderr)
+            .expect("output should have been valid utf-8")
+            .lines()
+            .find(|line| line.contains("more than the threshold of"))
+            .map(String::from)
+            .expect("the threshold should have been exceeded")
+    };
+
+    // The unsafe function of the module outside the entry point is counted,
+    // as in the default scan.
+    assert_eq!(
+        fail_threshold_stderr(&["--forbid-only"]),
+        fail_threshold_stderr(&[])
+    );
+}
+
+#[rstest]
+fn test_package_with_fail_summary_file() {
+    let fail_summary_dir = tempfile::tempdir().unwrap();
+    let fail_summary_path = fail_summary_dir.path().join("fail-summary.json");
+
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &[
+            "--output-format",
+            "Json",
+            "--fail-threshold",
+            "6",
+            "--fail-summary-file",
+            fail_summary_path.to_str().unwrap(),
+        ],
+    );
+
+    assert!(!output.status.success());
+    // The summary is written next to the report, not instead of it.
+    assert!(serde_json::from_slice::<SafetyReport>(&output.stdout).is_ok());
+    let fail_summary = serde_json::from_str::<FailSummary>(
+        &std::fs::read_to_string(&fail_summary_path).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(fail_summary.gate, FailGate::FailThreshold);
+    assert_eq!(
+        fail_summary.message.as_deref(),
+        Some("Found 8 unsafe expressions used by the build, more than the threshold of 6, the most in test2_package_with_shallow_deps 0.1.0 (expressions: 4, functions: 1), ref_slice 1.1.1 (expressions: 2), test1_package_with_no_deps 0.1.0 (expressions: 2, functions: 1)")
+    );
+    assert_eq!(
+        fail_summary
+            .crates
+            .iter()
+            .map(|fail_summary_crate| (
+                fail_summary_crate.package.name.as_str(),
+                fail_summary_crate.unsafe_exprs
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            ("ref_slice", Some(2)),
+            ("test1_package_with_no_deps", Some(2)),
+            ("test2_package_with_shallow_deps", Some(4)),
+        ]
+    );
+}
+
+#[rstest]
+fn test_package_with_fail_summary_file_without_failure() {
+    let fail_summary_dir = tempfile::tempdir().unwrap();
+    let fail_summary_path = fail_summary_dir.path().join("fail-summary.json");
+
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &[
+            "--fail-threshold",
+            "8",
+            "--fail-summary-file",
+            fail_summary_path.to_str().unwrap(),
+        ],
+    );
+
+    assert!(output.status.success());
+    assert!(!fail_summary_path.exists());
+}
+
+#[cfg(unix)]
+#[rstest(
+    input_command,
+    expected_exit_code,
+    case("grep -q test1_package_with_no_deps", Some(0)),
+    case("grep -q not_in_the_report", Some(1))
+)]
+fn test_package_with_on_complete(
+    input_command: &str,
+    expected_exit_code: Option<i32>,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test1_package_with_no_deps",
+        &["--output-format", "Json", "--on-complete", input_command],
+    );
+
+    assert_eq!(output.status.code(), expected_exit_code);
+    // The report is printed whether the command succeeds or not.
+    assert!(serde_json::from_slice::<SafetyReport>(&output.stdout).is_ok());
+}
+
+#[rstest]
+fn test_package_with_on_complete_requires_json() {
+    let (output, _cx) = run_geiger_with(
+        "test1_package_with_no_deps",
+        &["--on-complete", "cat"],
+    );
+
+    // The invalid flags are reported as an error, rather than a panic.
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains("`--on-complete` requires `--output-format Json`"));
+    assert!(!stderr.contains("panicked"));
+}
+
+#[test]
+fn test_package_table_rows_in_scan_order_with_summary_last() {
+    let (output, _cx) =
+        run_geiger_with("test2_package_with_shallow_deps", None::<&str>);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let line_index = |needle: &str| {
+        stdout
+            .lines()
+            .position(|line| line.contains(needle))
+            .unwrap_or_else(|| panic!("{} not found", needle))
+    };
+
+    let header_index = line_index("Dependency");
+    let row_indices = [
+        line_index("test2_package_with_shallow_deps 0.1.0"),
+        line_index("ref_slice 1.1.1"),
+        line_index("test1_package_with_no_deps 0.1.0"),
+    ];
+    assert!(header_index < row_indices[0]);
+    assert!(row_indices.windows(2).all(|w| w[0] < w[1]));
+    // The summary of the totals is the last non empty line.
+    let summary_index = stdout
+        .lines()
+        .rposition(|line| !line.trim().is_empty())
+        .unwrap();
+    assert!(row_indices[2] < summary_index);
+    assert!(stdout.lines().nth(summary_index).unwrap().starts_with("2/2"));
+}
+
+#[test]
+fn test_package_with_group_by_source() {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--group-by", "source"],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let source_totals_line = |source: &str| {
+        stdout
+            .lines()
+            .find(|line| line.ends_with(source))
+            .unwrap_or_else(|| panic!("{} not found", source))
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+    };
+
+    // test1_package_with_no_deps is a path dependency, ref_slice comes from
+    // crates.io.
+    assert_eq!(
+        source_totals_line("path (2 crates)"),
+        vec!["2/2", "6/6", "0/0", "0/0", "0/0", "path", "(2", "crates)"]
+    );
+    assert_eq!(
+        source_totals_line("registry (1 crate)"),
+        vec!["0/0", "2/2", "0/0", "0/0", "0/0", "registry", "(1", "crate)"]
+    );
+    assert!(!stdout.contains(" git ("));
+}
+
+// The dependencies of `shared` are only displayed below its first occurrence,
+// either under `left` or under `right`.
+#[rstest(
+    input_args,
+    expected_notice,
+    case(
+        vec![],
+        Some("1 repeated crate occurrence is displayed without its dependencies, use -a to display all of them.")
+    ),
+    case(vec!["--all"], None)
+)]
+fn test_package_with_diamond_deps_elided_occurrences_notice(
+    input_args: Vec<&str>,
+    expected_notice: Option<&str>,
+) {
+    let (output, _cx) =
+        run_geiger_with("test17_package_with_diamond_deps", &input_args);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert_eq!(
+        stdout.lines().find(|line| line.contains("repeated crate")),
+        expected_notice
+    );
+    assert_eq!(
+        stdout.lines().filter(|line| line.ends_with(" leaf 0.1.0")).count(),
+        if expected_notice.is_some() { 1 } else { 2 }
+    );
+}
+
+// `shared` is still reachable through `right` when only `left` is excluded.
+#[rstest(
+    input_args,
+    expected_packages,
+    case(
+        vec!["--exclude", "left"],
+        vec!["test17_package_with_diamond_deps", "right", "shared", "leaf"]
+    ),
+    case(
+        vec!["--exclude", "left", "--exclude", "right"],
+        vec!["test17_package_with_diamond_deps"]
+    ),
+    case(
+        vec!["--exclude", "left:0.1.0"],
+        vec!["test17_package_with_diamond_deps", "right", "shared", "leaf"]
+    )
+)]
+fn test_package_with_excluded_deps(
+    input_args: Vec<&str>,
+    expected_packages: Vec<&str>,
+) {
+    let (output, _cx) =
+        run_geiger_with("test17_package_with_diamond_deps", &input_args);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let packages = stdout
+        .lines()
+        .filter_map(|line| line.strip_suffix(" 0.1.0"))
+        .map(|line| line.rsplit(' ').next().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(packages, expected_packages);
+}
+
+#[rstest]
+fn test_package_with_excluded_deps_not_found() {
+    let (output, _cx) = run_geiger_with(
+        "test17_package_with_diamond_deps",
+        &["--exclude", "left:0.2.0", "--exclude", "right"],
+    );
+
+    // The misspelled entries are warned about, which fails the run.
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains(
+        "WARNING: No crate of the dependencies matches --exclude left:0.2.0"
+    ));
+    assert!(!stderr.contains("matches --exclude right"));
+}
+
+// test3 uses 24 of the 101 unsafe usages found, in 5 packages.
+#[rstest(
+    name,
+    expected_safety_grade_line,
+    case("test1_package_with_no_deps", "Safety grade: F"),
+    case("test3_package_with_nested_deps", "Safety grade: C"),
+    case("test8_package_with_build_rs_no_deps", "Safety grade: A")
+)]
+fn test_package_with_print_grade(
+    name: &str,
+    expected_safety_grade_line: &str,
+) {
+    let (output, _cx) = run_geiger_with(name, &["--print-grade"]);
+
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert!(
+        stdout.lines().any(|line| line == expected_safety_grade_line),
+        "{} not found",
+        expected_safety_grade_line
+    );
+}
+
+// test3 depends on test2, which depends on test1, at depth 2.
+#[rstest(
+    input_args,
+    case(vec![]),
+    case(vec!["--output-format", "Json"]),
+    case(vec!["--forbid-only"])
+)]
+fn test_package_with_max_depth_warn(input_args: Vec<&str>) {
+    let name = "test3_package_with_nested_deps";
+    let deep_path_lines = |output: &Output| {
+        String::from_utf8(output.stderr.clone())
+            .expect("output should have been valid utf-8")
+            .lines()
+            .filter(|line| line.starts_with("WARNING: Dependency path deeper"))
+            .map(String::from)
+            .collect::<Vec<String>>()
+    };
+
+    let (output, _cx) = run_geiger_with(
+        name,
+        ["--max-depth-warn", "1"].iter().chain(&input_args),
+    );
+    let (high_max_depth_output, _cx) = run_geiger_with(
+        name,
+        ["--max-depth-warn", "10"].iter().chain(&input_args),
+    );
+
+    // The warnings fail the run, whatever the output format.
+    assert_eq!(output.status.code(), Some(1));
+    assert!(deep_path_lines(&output).iter().any(|line| line
+        .starts_with("WARNING: Dependency path deeper than 1: ")
+        && line.contains("test2_package_with_shallow_deps")
+        && line.contains("test1_package_with_no_deps")));
+    assert!(deep_path_lines(&high_max_depth_output).is_empty());
+}
+
+#[rstest]
+fn test_package_with_group_unsafe_by_kind() {
+    let name = "test3_package_with_nested_deps";
+    let (json_output, _cx) =
+        run_geiger_with(name, &["--output-format", "Json"]);
+    let report =
+        serde_json::from_slice::<SafetyReport>(&json_output.stdout).unwrap();
+    let mut used = CounterBlock::default();
+    let mut unused = CounterBlock::default();
+    for entry in report.packages.values() {
+        used += entry.unsafety.used.clone();
+        unused += entry.unsafety.unused.clone();
+    }
+
+    let (output, _cx) = run_geiger_with(name, &["--group-unsafe-by-kind"]);
+
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    // The totals of each kind are the sums over the crates of the report.
+    for (kind, used_count, found_count) in vec![
+        (
+            "Functions",
+            used.functions.unsafe_,
+            used.functions.unsafe_ + unused.functions.unsafe_,
+        ),
+        (
+            "Expressions",
+            used.exprs.unsafe_,
+            used.exprs.unsafe_ + unused.exprs.unsafe_,
+        ),
+        (
+            "Methods",
+            used.methods.unsafe_,
+            used.methods.unsafe_ + unused.methods.unsafe_,
+        ),
+        (
+            "Total",
+            used.unsafe_count(),
+            used.unsafe_count() + unused.unsafe_count(),
+        ),
+    ] {
+        let prefix =
+            format!("{: <12} {: <6} {: <6} ", kind, used_count, found_count);
+        assert!(
+            stdout.lines().any(|line| line.starts_with(&prefix)),
+            "{} not found",
+            prefix
+        );
+    }
+}
+
+// test2 uses 5 unsafe items, test1 3 and ref_slice 2.
+#[rstest]
+fn test_package_with_min_unsafe() {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--min-unsafe", "3", "--no-indent"],
+    );
+
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let has_line_ending_with =
+        |suffix: &str| stdout.lines().any(|line| line.ends_with(suffix));
+    assert!(has_line_ending_with(
+        "test2_package_with_shallow_deps 0.1.0"
+    ));
+    assert!(has_line_ending_with("test1_package_with_no_deps 0.1.0"));
+    assert!(!stdout.lines().any(|line| line.contains("ref_slice")));
+}
+
+// The dependencies of test2 are listed sorted by name.
+#[rstest]
+fn test_package_with_rank() {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--no-indent", "--rank"],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let ranked_lines = stdout
+        .lines()
+        .filter_map(|line| line.find(" #").map(|index| &line[index + 1..]))
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        ranked_lines,
+        vec![
+            "#1 (5 unsafe) test2_package_with_shallow_deps 0.1.0",
+            "#2 (2 unsafe) ref_slice 1.1.1",
+            "#3 (3 unsafe) test1_package_with_no_deps 0.1.0",
+        ]
+    );
+}
+
+#[rstest]
+fn test_package_with_rank_requires_no_indent() {
+    let (output, _cx) =
+        run_geiger_with("test2_package_with_shallow_deps", &["--rank"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains("`--rank` requires `--no-indent`"));
+}
+
+// test4 has unsafe usage in its unused code only, test1 in its used code.
+#[rstest]
+fn test_package_with_print_unsafe_free() {
+    let (output, _cx) = run_geiger_with(
+        "test4_workspace_with_top_level_package",
+        &["--print-unsafe-free"],
+    );
+
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let expected_line = "Unsafe-free crates: 1/2 (50%) in the code used by the build, 0/2 (0%) in all the code";
+    assert!(
+        stdout.lines().any(|line| line == expected_line),
+        "{} not found",
+        expected_line
+    );
+}
+
+#[rstest(input_include_optional, case(false), case(true))]
+fn test_package_with_include_optional(input_include_optional: bool) {
+    // Unlike `run_geiger_with`, without `--all-features`, so that the optional
+    // dependency stays inactive.
+    let cx = Context::new();
+    let mut command = Command::cargo_bin("cargo-geiger").unwrap();
+    command
+        .arg("geiger")
+        .arg("--color=never")
+        .arg("--quiet")
+        .arg("--output-format=Ascii")
+        .current_dir(cx.crate_dir("test16_package_with_optional_dep"));
+    if input_include_optional {
+        command.arg("--include-optional");
+    }
+    let output = command.output().expect("failed to run `cargo-geiger`");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let has_line_ending_with =
+        |suffix: &str| stdout.lines().any(|line| line.ends_with(suffix));
+    assert!(has_line_ending_with("test16_package_with_optional_dep 0.1.0"));
+    assert_eq!(
+        has_line_ending_with(
+            "test1_package_with_no_deps 0.1.0 (optional/inactive)"
+        ),
+        input_include_optional
+    );
+    assert_eq!(
+        stdout.contains("test1_package_with_no_deps"),
+        input_include_optional
+    );
+}
+
+#[rstest(
+    name,
+    expected_success,
+    case("test1_package_with_no_deps", false),
+    case("test7_package_with_patched_dep", true)
+)]
+fn test_package_with_fail_if_no_forbid(name: &str, expected_success: bool) {
+    let (output, _cx) =
+        run_geiger_with(name, &["--forbid-only", "--fail-if-no-forbid"]);
+
+    assert_eq!(output.status.success(), expected_success);
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert_eq!(
+        stderr.contains("does not declare #![forbid(unsafe_code)]"),
+        !expected_success
+    );
+}
+
+// None of the crates of test2 forbid unsafe code.
+#[rstest(
+    input_forbid_whitelist,
+    expected_success,
+    expected_stderr,
+    case(
+        "# Audited\nref_slice:1.1.1\ntest1_package_with_no_deps\n",
+        false,
+        "WARNING: test2_package_with_shallow_deps 0.1.0 doesn't forbid unsafe code and isn't in the forbid whitelist"
+    ),
+    case(
+        "ref_slice # Audited\ntest1_package_with_no_deps\ntest2_package_with_shallow_deps\n",
+        true,
+        ""
+    ),
+    case(
+        "ref_slice:1.0.0\nunknown_crate\n",
+        false,
+        "The forbid whitelist lists crates which are not in the dependency tree: ref_slice:1.0.0, unknown_crate"
+    )
+)]
+fn test_package_with_forbid_whitelist(
+    input_forbid_whitelist: &str,
+    expected_success: bool,
+    expected_stderr: &str,
+) {
+    let forbid_whitelist_dir = tempfile::tempdir().unwrap();
+    let forbid_whitelist_path =
+        forbid_whitelist_dir.path().join("forbid-whitelist");
+    std::fs::write(&forbid_whitelist_path, input_forbid_whitelist).unwrap();
+
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &[
+            "--forbid-only",
+            "--forbid-whitelist",
+            forbid_whitelist_path.to_str().unwrap(),
+        ],
+    );
+
+    assert_eq!(output.status.success(), expected_success);
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains(expected_stderr));
+    assert!(!stderr.contains("ref_slice 1.1.1 doesn't forbid"));
+}
+
+// The optional dependency of the package is its only, implicit, feature.
+#[rstest(
+    input_feature,
+    expected_success,
+    case("test1_package_with_no_deps", true),
+    case("test1_package_with_no_dep", false)
+)]
+fn test_package_with_strict_features(
+    input_feature: &str,
+    expected_success: bool,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test16_package_with_optional_dep",
+        &[
+            "--forbid-only",
+            "--strict-features",
+            "--features",
+            input_feature,
+        ],
+    );
+
+    assert_eq!(output.status.success(), expected_success);
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert_eq!(
+        stderr.contains(
+            "Unknown features given to --features: test1_package_with_no_dep\n\
+             Valid features: test1_package_with_no_deps"
+        ),
+        !expected_success
+    );
+}
+
+#[test]
+fn test_package_with_feature_impact() {
+    let (output, _cx) = run_geiger_with(
+        "test16_package_with_optional_dep",
+        &["--feature-impact", "test1_package_with_no_deps"],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let lines = stdout.lines().collect::<Vec<&str>>();
+
+    // Only the optional dependency is pulled in by its implicit feature, with
+    // its unsafe function and the unsafe expressions of its block.
+    assert!(lines.contains(&"3       test1_package_with_no_deps 0.1.0"));
+    assert!(!lines
+        .iter()
+        .any(|line| line.contains("test16_package_with_optional_dep")));
+    assert!(lines.contains(&"1 crates, 3 unsafe usage in total"));
+}
+
+#[test]
+fn test_package_with_prometheus_output_format() {
+    let (output, _cx) = run_geiger_with(
+        "test1_package_with_no_deps",
+        &["--output-format", "Prometheus"],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let sample_regex = regex::Regex::new(
+        r#"^[a-zA-Z_:][a-zA-Z0-9_:]*(\{([a-zA-Z_][a-zA-Z0-9_]*="[^"]*",?)*\})? [0-9]+$"#,
+    )
+    .unwrap();
+    let lines = stdout.lines().collect::<Vec<&str>>();
+    for line in &lines {
+        assert!(
+            line.starts_with("# ") || sample_regex.is_match(line),
+            "invalid exposition line: {}",
+            line
+        );
+    }
+    assert!(lines[0].starts_with("# Scraped by cargo-geiger at "));
+    assert!(lines.contains(
+        &r#"cargo_geiger_unsafe_total{crate="test1_package_with_no_deps",version="0.1.0"} 3"#
+    ));
+    assert!(lines.contains(&"cargo_geiger_unsafe_sum 3"));
+}
+
+#[test]
+fn test_package_with_deny_toml_output_format() {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--output-format", "DenyToml", "--deny-threshold", "2"],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let deny_toml = stdout
+        .parse::<toml::Table>()
+        .expect("output should have been valid toml");
+    let crates = deny_toml["bans"]["deny"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["crate"].as_str().unwrap())
+        .collect::<Vec<&str>>();
+    // `ref_slice` has 2 unsafe items, which doesn't exceed the threshold.
+    assert_eq!(
+        crates,
+        vec![
+            "test1_package_with_no_deps@0.1.0",
+            "test2_package_with_shallow_deps@0.1.0"
+        ]
+    );
+    assert!(stdout.contains(
+        r#"{ crate = "test1_package_with_no_deps@0.1.0", reason = "3 unsafe items used by the build" },"#
+    ));
+}
+
+#[test]
+fn test_package_with_csv_output_format() {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--output-format", "Csv"],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let lines = stdout.lines().collect::<Vec<&str>>();
+    assert_eq!(
+        lines[0],
+        "name,version,kind,functions,exprs,item_impls,item_traits,methods,total"
+    );
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1..].iter().all(|line| line.split(',').count() == 9));
+    assert!(
+        lines.contains(&"test1_package_with_no_deps,0.1.0,normal,1,2,0,0,0,3")
+    );
+}
+
+#[test]
+fn test_package_with_tree_output_format() {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--output-format", "Tree"],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert_snapshot!("test2_package_with_shallow_deps.tree", stdout);
+}
+
+#[rstest(
+    input_args,
+    expected_lines,
+    case(
+        vec![],
+        vec![
+            "test17_package_with_diamond_deps v0.1.0 (0 unsafe)",
+            "    left v0.1.0 (0 unsafe)",
+            "        shared v0.1.0 (0 unsafe)",
+            "            leaf v0.1.0 (0 unsafe)",
+            "    right v0.1.0 (0 unsafe)",
+            "        shared v0.1.0 (0 unsafe) (*)"
+        ]
+    ),
+    case(
+        vec!["--all"],
+        vec![
+            "test17_package_with_diamond_deps v0.1.0 (0 unsafe)",
+            "    left v0.1.0 (0 unsafe)",
+            "        shared v0.1.0 (0 unsafe)",
+            "            leaf v0.1.0 (0 unsafe)",
+            "    right v0.1.0 (0 unsafe)",
+            "        shared v0.1.0 (0 unsafe)",
+            "            leaf v0.1.0 (0 unsafe)"
+        ]
+    ),
+    case(
+        vec!["--depth", "1"],
+        vec![
+            "test17_package_with_diamond_deps v0.1.0 (0 unsafe)",
+            "    left v0.1.0 (0 unsafe)",
+            "    right v0.1.0 (0 unsafe)"
+        ]
+    )
+)]
+fn test_package_with_tree_output_format_options(
+    input_args: Vec<&str>,
+    expected_lines: Vec<&str>,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test17_package_with_diamond_deps",
+        vec!["--output-format", "Tree"]
+            .into_iter()
+            .chain(input_args),
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert_eq!(stdout.lines().collect::<Vec<&str>>(), expected_lines);
+}
+
+#[test]
+fn test_package_with_compare_targets() {
+    let (output, _cx) = run_geiger_with(
+        "test13_package_with_target_gated_dep",
+        &[
+            "--compare-targets",
+            "x86_64-unknown-linux-gnu,wasm32-unknown-unknown",
+        ],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let line_of = |package: &str| {
+        stdout
+            .lines()
+            .find(|line| line.ends_with(package))
+            .unwrap_or_else(|| panic!("{} not listed", package))
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+    };
+
+    // The root package is a dependency for both targets, without unsafe.
+    assert_eq!(
+        line_of("test13_package_with_target_gated_dep 0.1.0"),
+        vec!["0", "0", "test13_package_with_target_gated_dep", "0.1.0"]
+    );
+    // The unsafe function of the wasm only dependency, and its dereference.
+    assert_eq!(
+        line_of("wasm_only_dep 0.1.0"),
+        vec!["-", "2", "!", "wasm_only_dep", "0.1.0"]
+    );
+}
+
+// The fixture is a fork of `ref_slice` with an unsafe function more than the
+// published crate, run with `cargo test -- --ignored` when crates.io can be
+// reached.
+#[test]
+#[ignore = "downloads the published version from crates.io"]
+fn test_package_with_compare_published() {
+    let (output, _cx) = run_geiger_with(
+        "test35_package_with_published_fork",
+        &["--compare-published"],
+    );
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains("Comparing ref_slice 1.1.1 to the published"));
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let functions_line = stdout
+        .lines()
+        .find(|line| line.contains("functions"))
+        .expect("the unsafe functions should have changed");
+    assert_eq!(
+        functions_line.split_whitespace().collect::<Vec<&str>>(),
+        vec!["ref_slice", "1.1.1", "functions", "0", "1", "+1"]
+    );
+}
+
+#[test]
+fn test_package_with_compare_published_offline() {
+    let (output, _cx) = run_geiger_with(
+        "test35_package_with_published_fork",
+        &["--compare-published", "--offline"],
+    );
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains("`--compare-published` downloads the published"));
+}
+
+// The `wasm` feature of the dependency is only enabled for wasm32, which
+// requires the wasm32-unknown-unknown target to be installed.
+#[rstest(
+    input_args,
+    expected_unsafe_functions,
+    case(vec![], "0/1"),
+    case(vec!["--target", "wasm32-unknown-unknown"], "1/1")
+)]
+fn test_package_with_target_gated_feature(
+    input_args: Vec<&str>,
+    expected_unsafe_functions: &str,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test18_package_with_target_gated_feature",
+        &input_args,
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let gated_feature_dep_line = stdout
+        .lines()
+        .find(|line| line.ends_with("gated_feature_dep 0.1.0"))
+        .expect("gated_feature_dep not listed");
+    assert!(gated_feature_dep_line.starts_with(expected_unsafe_functions));
+}
+
+#[test]
+fn test_package_with_dry_run() {
+    let (output, _cx) =
+        run_geiger_with("test2_package_with_shallow_deps", &["--dry-run"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert!(stdout.starts_with("Dry run, no files were scanned."));
+    for package_name in &[
+        "ref_slice 1.1.1",
+        "test1_package_with_no_deps 0.1.0",
+        "test2_package_with_shallow_deps 0.1.0",
+    ] {
+        assert!(stdout.contains(package_name), "{} not listed", package_name);
+    }
+    // Nothing was parsed, so no unsafe usage table is printed.
+    assert!(!stdout.contains("Metric output format"));
+    assert!(stdout.contains("in 3 packages would be scanned."));
+}
+
+#[test]
+fn test_package_with_message_format_json() {
+    let (output, _cx) = run_geiger_with(
+        "test25_package_with_assume_init",
+        &["--message-format", "json"],
+    );
+
+    assert!(output.status.success());
+    let messages = Message::parse_stream(&output.stdout[..])
+        .collect::<Result<Vec<Message>, _>>()
+        .expect("output should have been cargo Json messages");
+    assert_eq!(messages.len(), 2);
+    match &messages[0] {
+        Message::CompilerMessage(compiler_message) => {
+            assert_eq!(
+                compiler_message.target.name,
+                "test25_package_with_assume_init"
+            );
+            let diagnostic = &compiler_message.message;
+            assert_eq!(diagnostic.message, "unsafe block");
+            assert_eq!(diagnostic.level, DiagnosticLevel::Warning);
+            assert_eq!(diagnostic.spans.len(), 1);
+            let span = &diagnostic.spans[0];
+            assert!(span.file_name.ends_with("lib.rs"));
+            assert_eq!((span.line_start, span.column_start), (6, 5));
+            assert_eq!((span.line_end, span.column_end), (6, 35));
+            assert_eq!(span.byte_end - span.byte_start, 30);
+            assert_eq!(span.text[0].text, "    unsafe { value.assume_init() }");
+        }
+        message => panic!("unexpected message: {:?}", message),
+    }
+    assert!(matches!(
+        &messages[1],
+        Message::BuildFinished(build_finished) if build_finished.success
+    ));
+}
+
+#[test]
+fn test_package_with_github_actions_output_format() {
+    let (output, _cx) = run_geiger_with(
+        "test25_package_with_assume_init",
+        &["--output-format", "GitHubActions"],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert_eq!(
+        stdout.lines().collect::<Vec<&str>>(),
+        vec![
+            "::warning file=src/lib.rs,line=6,endLine=6,col=5,endColumn=35,title=cargo-geiger::unsafe block in test25_package_with_assume_init 0.1.0 (severity: low)"
+        ]
+    );
+}
+
+#[test]
+fn test_package_with_github_actions_output_format_and_severity_map() {
+    let severity_map_dir = tempfile::tempdir().unwrap();
+    let severity_map_path = severity_map_dir.path().join("severity.toml");
+    std::fs::write(&severity_map_path, "block = \"critical\"\n").unwrap();
+
+    let (output, _cx) = run_geiger_with(
+        "test25_package_with_assume_init",
+        &[
+            "--output-format",
+            "GitHubActions",
+            "--severity-map",
+            severity_map_path.to_str().unwrap(),
+        ],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    // The plain unsafe block is annotated as an error instead of a warning.
+    assert_eq!(
+        stdout.lines().collect::<Vec<&str>>(),
+        vec![
+            "::error file=src/lib.rs,line=6,endLine=6,col=5,endColumn=35,title=cargo-geiger::unsafe block in test25_package_with_assume_init 0.1.0 (severity: critical)"
+        ]
+    );
+}
+
+#[rstest(
+    input_args,
+    case(vec!["--offline"]),
+    case(vec!["--frozen", "--offline"])
+)]
+fn test_package_with_uncached_dep_offline(input_args: Vec<&str>) {
+    let (output, _cx) =
+        run_geiger_with("test20_package_with_uncached_dep", &input_args);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains(
+        "Failed to resolve the dependencies without accessing the network"
+    ));
+}
+
+#[rstest(
+    input_args,
+    case(vec!["--workspace"]),
+    case(vec!["--package", "member1"])
+)]
+fn test_virtual_workspace_with_root_packages(input_args: Vec<&str>) {
+    let (output, _cx) =
+        run_geiger_with("test5_workspace_with_virtual_manifest", &input_args);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert!(stdout.contains("member1 0.1.0"));
+}
+
+#[rstest]
+fn test_no_spinner_without_terminal() {
+    let (output, _cx) = run_geiger_with("test1_package_with_no_deps", &[]);
+
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    for frame in cargo_geiger::spinner::SPINNER_FRAMES.iter() {
+        assert!(!stdout.contains(*frame));
+        assert!(!stderr.contains(*frame));
+    }
+}
+
+#[rstest]
+fn test_separate_build_report_totals() {
+    let (output, _cx) = run_geiger_with(
+        "test22_package_with_unsafe_build_dep",
+        &["--separate-build-report"],
+    );
+
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert!(stdout.lines().any(|line| line == "Run time dependencies:"));
+    assert!(stdout.lines().any(|line| line == "Build dependencies:"));
+}
+
+#[rstest(
+    input_max_total_crates,
+    expected_success,
+    case("2", true),
+    case("1", false)
+)]
+fn test_workspace_with_max_total_crates(
+    input_max_total_crates: &str,
+    expected_success: bool,
+) {
+    // The root package and its path dependency.
+    let (output, _cx) = run_geiger_with(
+        "test4_workspace_with_top_level_package",
+        &["--max-total-crates", input_max_total_crates],
+    );
+
+    assert_eq!(output.status.success(), expected_success);
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert_eq!(
+        stderr.contains("The dependency graph has 2 crates"),
+        !expected_success
+    );
+}
+
+#[rstest]
+fn test_workspace_with_require_source_url() {
+    // Neither the root package nor its path dependency declare a repository.
+    let (output, _cx) = run_geiger_with(
+        "test4_workspace_with_top_level_package",
+        &["--require-source-url"],
+    );
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)
+        .expect("output should have been valid utf-8");
+    assert!(stderr.contains("have no repository in their manifest"));
+    assert!(stderr.contains("test1_package_with_no_deps 0.1.0"));
+    assert!(stderr.contains("test4_workspace_with_top_level_package 0.1.0"));
+}
+
+#[rstest(
+    input_args,
+    expected_stdout,
+    case(
+        vec!["--list-unsafe-crates"],
+        "ref_slice@1.1.1\n\
+         test1_package_with_no_deps@0.1.0\n\
+         test2_package_with_shallow_deps@0.1.0\n"
+    ),
+    case(
+        vec!["--list-unsafe-crates", "--ignore-unsafe-in", "ref_slice"],
+        "test1_package_with_no_deps@0.1.0\n\
+         test2_package_with_shallow_deps@0.1.0\n"
+    )
+)]
+fn test_package_with_list_unsafe_crates(
+    input_args: Vec<&str>,
+    expected_stdout: &str,
+) {
+    let (output, _cx) =
+        run_geiger_with("test2_package_with_shallow_deps", input_args);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    assert_eq!(stdout, expected_stdout);
+}
+
+#[rstest]
+fn test_package_with_print_config() {
+    let (output, _cx) = run_geiger_with(
+        "test1_package_with_no_deps",
+        vec![
+            "--print-config",
+            "--min-unsafe",
+            "3",
+            "--ignore-unsafe-in",
+            "ref_slice",
+            "--max-file-size",
+            "1024",
+        ],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)
+        .expect("output should have been valid utf-8");
+    let lines = stdout.lines().collect::<Vec<_>>();
+    for expected_line in &[
+        "output-format: Ascii",
+        "all-features: true",
+        "no-default-features: false",
+        "all-targets: true",
+        "target: none",
+        "include-tests: No",
+        "min-unsafe: 3",
+        "exclude: none",
+        "ignore-unsafe-in: ref_slice",
+        "max-file-size: 1024",
+        "max-total-crates: none",
+        "no-cache: false",
+    ] {
+        assert!(
+            lines.contains(expected_line),
+            "missing {:?} in:\n{}",
+            expected_line,
+            stdout
+        );
+    }
+    // The settings are printed instead of scanning the package.
+    assert!(!stdout.contains("Metric output format"));
+}
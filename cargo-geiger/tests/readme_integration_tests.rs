@@ -4,11 +4,14 @@
 mod context;
 mod run;
 
+use self::context::Context;
 use self::run::run_geiger_with;
 
+use assert_cmd::prelude::*;
 use insta::assert_snapshot;
 use rstest::rstest;
 use std::fs::read_to_string;
+use std::process::Command;
 
 #[rstest(
     input_crate_name,
@@ -70,3 +73,26 @@ fn test_package_update_readme(
     let readme_content = read_to_string(readme_location).unwrap();
     assert_snapshot!(readme_snapshot_filename, readme_content);
 }
+
+#[rstest]
+fn test_package_update_readme_next_to_manifest_path() {
+    let cx = Context::new();
+    let crate_dir = cx.crate_dir("test1_package_with_no_deps");
+    let output = Command::cargo_bin("cargo-geiger")
+        .unwrap()
+        .arg("geiger")
+        .arg("--color=never")
+        .arg("--quiet")
+        .arg("--update-readme")
+        .arg("--manifest-path")
+        .arg(crate_dir.join("Cargo.toml"))
+        .current_dir(&cx.path)
+        .output()
+        .expect("failed to run `cargo-geiger`");
+
+    assert!(output.status.success());
+    let readme_content = read_to_string(crate_dir.join("README.md")).unwrap();
+    assert!(readme_content.contains("## Cargo Geiger Safety Report"));
+    assert!(readme_content.contains("test1_package_with_no_deps 0.1.0"));
+    assert!(!cx.path.join("README.md").exists());
+}
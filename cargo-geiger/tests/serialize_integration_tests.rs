@@ -11,13 +11,23 @@ use self::context::Context;
 use self::external_package_reports::make_package_id;
 use self::integration_test::IntegrationTest;
 use self::report::{merge_test_reports, single_entry_safety_report, to_set};
+use self::run::run_geiger_with;
 
+use assert_cmd::prelude::*;
 use cargo_geiger_serde::{
-    Count, CounterBlock, PackageInfo, ReportEntry, SafetyReport, Source,
-    UnsafeInfo,
+    AllowedUnsafeCounts, Count, CounterBlock, DependencyEdge, DependencyGraph,
+    DependencyKind, ModuleUnsafety, PackageAdvisories,
+    PackageCustomPatternCounts, PackageId, PackageInfo, PackageNightlyFeatures,
+    PackageRiskScore, PackageTestUnsafety, PackageVersionChange,
+    QuickReportEntry, QuickSafetyReport, ReportEntry, SafetyGrade,
+    SafetyReport, Source, StaticMutCounts, UnsafeChange, UnsafeFreeCounts,
+    UnsafeInfo, UnsafeKindCounts, UnsafeKinds,
 };
 use rstest::rstest;
+use semver::Version;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::process::Command;
 use url::Url;
 
 #[rstest]
@@ -80,6 +90,1606 @@ fn serialize_test7_quick_report() {
     Test7.run_quick();
 }
 
+#[rstest]
+fn serialize_test1_report_metadata() {
+    let (output, _cx) = run_geiger_with(
+        Test1::NAME,
+        &["--output-format", "Json", "--include-tests"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    assert_eq!(report.metadata.geiger_version, env!("CARGO_PKG_VERSION"));
+    assert!(report.metadata.rustc_version.is_some());
+    for flag in &[
+        "--all-features",
+        "--output-format",
+        "Json",
+        "--include-tests",
+    ] {
+        assert!(report.metadata.flags.iter().any(|f| f == flag));
+    }
+    assert!(!report.metadata.flags.iter().any(|f| f == "geiger"));
+}
+
+#[rstest]
+fn serialize_test1_report_with_advisories() {
+    let advisory_db = tempfile::tempdir().unwrap();
+    let crate_dir = advisory_db.path().join("crates").join(Test1::NAME);
+    std::fs::create_dir_all(&crate_dir).unwrap();
+    std::fs::write(
+        crate_dir.join("RUSTSEC-2020-0001.md"),
+        format!(
+            "```toml\n[advisory]\nid = \"RUSTSEC-2020-0001\"\npackage = \"{}\"\n\n[versions]\npatched = [\">= 0.2.0\"]\n```\n",
+            Test1::NAME
+        ),
+    )
+    .unwrap();
+
+    let (output, cx) = run_geiger_with(
+        Test1::NAME,
+        &[
+            "--output-format",
+            "Json",
+            "--advisories",
+            advisory_db.path().to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    assert_eq!(
+        report.advisories,
+        vec![PackageAdvisories {
+            package: make_package_id(&cx, Test1::NAME),
+            advisory_ids: vec![String::from("RUSTSEC-2020-0001")],
+        }]
+    );
+}
+
+#[rstest]
+fn serialize_test1_report_with_weights() {
+    let weights_dir = tempfile::tempdir().unwrap();
+    let weights_path = weights_dir.path().join("weights.toml");
+    std::fs::write(&weights_path, "functions = 3\nexprs = 5\nmethods = 7\n")
+        .unwrap();
+
+    let (output, cx) = run_geiger_with(
+        Test1::NAME,
+        &[
+            "--output-format",
+            "Json",
+            "--weights",
+            weights_path.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // Test1 uses 1 unsafe function and 2 unsafe expressions: 3 * 1 + 5 * 2.
+    assert_eq!(
+        report.risk_scores,
+        vec![PackageRiskScore {
+            package: make_package_id(&cx, Test1::NAME),
+            risk_score: 13,
+        }]
+    );
+}
+
+#[rstest]
+fn serialize_test1_report_with_template() {
+    let template_dir = tempfile::tempdir().unwrap();
+    let template_path = template_dir.path().join("report.tpl");
+    std::fs::write(
+        &template_path,
+        "{{ for package in packages }}{package.name}: \
+        {package.used.functions.unsafe_} {package.used.exprs.unsafe_}\n\
+        {{ endfor }}total: {used_unsafe_count}",
+    )
+    .unwrap();
+
+    let (output, _cx) = run_geiger_with(
+        Test1::NAME,
+        &["--template", template_path.to_str().unwrap()],
+    );
+    assert!(output.status.success());
+
+    // Test1 uses 1 unsafe function and 2 unsafe expressions.
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap().trim_end(),
+        "test1_package_with_no_deps: 1 2\ntotal: 3"
+    );
+}
+
+#[rstest]
+fn serialize_test1_report_with_fields_deny() {
+    let (output, _cx) = run_geiger_with(
+        Test1::NAME,
+        &[
+            "--output-format",
+            "Json",
+            "--fields-deny",
+            "package.dependencies,unsafety.unused",
+        ],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    let entry = &report["packages"][0];
+
+    assert!(entry["package"].get("dependencies").is_none());
+    assert!(entry["unsafety"].get("unused").is_none());
+    assert!(entry["package"].get("id").is_some());
+    assert!(entry["package"].get("dev_dependencies").is_some());
+    assert!(entry["unsafety"].get("used").is_some());
+    assert!(entry["unsafety"].get("forbids_unsafe").is_some());
+    assert!(report.get("packages_without_metrics").is_some());
+}
+
+#[rstest]
+fn serialize_test1_report_with_custom_patterns() {
+    let (output, cx) = run_geiger_with(
+        Test1::NAME,
+        &[
+            "--output-format",
+            "Json",
+            "--custom-pattern",
+            "unimplemented!:todos",
+            "--custom-pattern",
+            "\\bunsafe\\b:unsafe_keywords",
+        ],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // Test1 has an `unimplemented!()` and two lines with the `unsafe` keyword.
+    assert_eq!(
+        report.custom_pattern_counts,
+        vec![PackageCustomPatternCounts {
+            package: make_package_id(&cx, Test1::NAME),
+            counts: vec![
+                (String::from("todos"), 1),
+                (String::from("unsafe_keywords"), 2),
+            ]
+            .into_iter()
+            .collect(),
+        }]
+    );
+}
+
+#[rstest]
+fn serialize_test2_report_with_only_path_deps() {
+    let (output, cx) = run_geiger_with(
+        Test2::NAME,
+        &["--output-format", "Json", "--only-path-deps"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The `ref_slice` registry dependency is left out.
+    assert_eq!(
+        to_set(report.packages.keys().cloned()),
+        to_set(vec![
+            make_package_id(&cx, Test1::NAME),
+            make_package_id(&cx, Test2::NAME),
+        ])
+    );
+}
+
+#[rstest]
+fn serialize_test1_report_with_diff() {
+    // The baseline only needs the name of the crate to match.
+    let baseline_cx = Context::new();
+    let baseline_entry = ReportEntry {
+        package: PackageInfo::new(PackageId {
+            version: Version::new(0, 0, 1),
+            ..make_package_id(&baseline_cx, Test1::NAME)
+        }),
+        active_features: vec![],
+        unsafety: UnsafeInfo {
+            used: CounterBlock {
+                exprs: Count {
+                    safe: 0,
+                    unsafe_: 1,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    };
+    let baseline_path = baseline_cx.path.join("baseline.json");
+    std::fs::write(
+        &baseline_path,
+        serde_json::to_vec(&single_entry_safety_report(baseline_entry))
+            .unwrap(),
+    )
+    .unwrap();
+
+    let (output, cx) = run_geiger_with(
+        Test1::NAME,
+        &[
+            "--output-format",
+            "Json",
+            "--diff",
+            baseline_path.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The unsafe usage of the crate went from 1 item in 0.0.1 to 3 in 0.1.0.
+    assert_eq!(
+        report.version_changes,
+        vec![PackageVersionChange {
+            package: make_package_id(&cx, Test1::NAME),
+            baseline_version: Version::new(0, 0, 1),
+            change: UnsafeChange::Increased,
+        }]
+    );
+}
+
+#[rstest(
+    input_baseline_forbids_unsafe,
+    expected_success,
+    case(true, false),
+    case(false, true)
+)]
+fn serialize_test1_quick_report_with_forbid_regression(
+    input_baseline_forbids_unsafe: bool,
+    expected_success: bool,
+) {
+    let baseline_cx = Context::new();
+    let baseline_entry = QuickReportEntry {
+        package: PackageInfo::new(make_package_id(&baseline_cx, Test1::NAME)),
+        forbids_unsafe: input_baseline_forbids_unsafe,
+    };
+    let mut baseline = QuickSafetyReport::default();
+    baseline
+        .packages
+        .insert(baseline_entry.package.id.clone(), baseline_entry);
+    let baseline_path = baseline_cx.path.join("forbid_baseline.json");
+    std::fs::write(&baseline_path, serde_json::to_vec(&baseline).unwrap())
+        .unwrap();
+
+    let (output, _cx) = run_geiger_with(
+        Test1::NAME,
+        &[
+            "--output-format",
+            "Json",
+            "--fail-on-forbid-regression",
+            baseline_path.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.success(), expected_success);
+
+    // The report is still printed, the crate doesn't forbid unsafe code.
+    let report =
+        serde_json::from_slice::<QuickSafetyReport>(&output.stdout).unwrap();
+    assert!(report.packages.values().all(|entry| !entry.forbids_unsafe));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr.contains("test1_package_with_no_deps 0.1.0 no longer forbids"),
+        !expected_success
+    );
+}
+
+#[rstest]
+fn serialize_test2_report_with_json_array() {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--output-format", "Json", "--json-array"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    assert!(report.is_array());
+    let entries = serde_json::from_value::<Vec<ReportEntry>>(report).unwrap();
+
+    let mut package_names = entries
+        .iter()
+        .map(|entry| entry.package.id.name.as_str())
+        .collect::<Vec<&str>>();
+    package_names.sort_unstable();
+    assert_eq!(
+        package_names,
+        vec![
+            "ref_slice",
+            "test1_package_with_no_deps",
+            "test2_package_with_shallow_deps"
+        ]
+    );
+}
+
+#[rstest]
+fn serialize_test2_report_with_compact_json() {
+    let name = "test2_package_with_shallow_deps";
+    let (output, _cx) = run_geiger_with(name, &["--output-format", "Json"]);
+    assert!(output.status.success());
+    let (compact_output, _cx) =
+        run_geiger_with(name, &["--output-format", "Json", "--compact-json"]);
+    assert!(compact_output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let compact_report_value =
+        serde_json::from_slice::<serde_json::Value>(&compact_output.stdout)
+            .unwrap();
+    for compact_entry in compact_report_value["packages"].as_array().unwrap() {
+        let entry =
+            serde_json::from_value::<ReportEntry>(compact_entry.clone())
+                .unwrap();
+        let full_entry = &report.packages[&entry.package.id];
+        for (block_key, block) in &[
+            ("used", &full_entry.unsafety.used),
+            ("unused", &full_entry.unsafety.unused),
+        ] {
+            let compact_block = &compact_entry["unsafety"][*block_key];
+            for (field, count) in &[
+                ("functions", &block.functions),
+                ("exprs", &block.exprs),
+                ("item_impls", &block.item_impls),
+                ("item_traits", &block.item_traits),
+                ("methods", &block.methods),
+            ] {
+                match (count.safe, count.unsafe_) {
+                    (0, 0) => assert!(compact_block.get(*field).is_none()),
+                    _ => assert!(compact_block.get(*field).is_some()),
+                }
+            }
+        }
+        // The left out categories are deserialized back to zero counts.
+        assert_eq!(entry.unsafety, full_entry.unsafety);
+    }
+    assert!(compact_report_value["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|entry| entry["unsafety"]["used"].as_object().unwrap().len() < 5));
+}
+
+#[rstest]
+fn serialize_test1_report_without_flags_leaves_out_their_fields() {
+    let (output, _cx) = run_geiger_with(
+        "test1_package_with_no_deps",
+        &["--output-format", "Json"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    for field in &[
+        "build_packages",
+        "excluded_files",
+        "oversized_files",
+        "ignored_packages",
+        "inactive_optional_packages",
+        "advisories",
+        "risk_scores",
+        "nightly_features",
+        "source_hashes",
+        "test_unsafety",
+        "custom_pattern_counts",
+        "version_changes",
+        "std_crates",
+        "partial",
+        "unscanned_packages",
+        "timed_out_packages",
+    ] {
+        assert!(report.get(*field).is_none(), "{} is serialized", field);
+    }
+    assert!(report.get("safety_grade").is_some());
+}
+
+// No package can be scanned before a zero deadline.
+#[rstest(
+    input_deadline,
+    expected_unscanned_package_names,
+    case(
+        "0",
+        vec![
+            "ref_slice",
+            "test1_package_with_no_deps",
+            "test2_package_with_shallow_deps"
+        ]
+    ),
+    case("3600", vec![])
+)]
+fn serialize_test2_report_with_deadline(
+    input_deadline: &str,
+    expected_unscanned_package_names: Vec<&str>,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &["--output-format", "Json", "--deadline", input_deadline],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let mut unscanned_package_names = report
+        .unscanned_packages
+        .iter()
+        .map(|package_id| package_id.name.as_str())
+        .collect::<Vec<&str>>();
+    unscanned_package_names.sort_unstable();
+
+    assert_eq!(unscanned_package_names, expected_unscanned_package_names);
+    assert_eq!(report.partial, !expected_unscanned_package_names.is_empty());
+    assert_eq!(
+        report.packages.len(),
+        3 - expected_unscanned_package_names.len()
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.contains("the report is partial"), report.partial);
+}
+
+// The last line tells a report cut short by the deadline from a full one.
+#[rstest(
+    input_deadline,
+    expected_entry_count,
+    expected_status,
+    case("0", 0, "deadline-truncated"),
+    case("3600", 3, "complete")
+)]
+fn serialize_test2_report_with_ndjson(
+    input_deadline: &str,
+    expected_entry_count: usize,
+    expected_status: &str,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test2_package_with_shallow_deps",
+        &[
+            "--output-format",
+            "Json",
+            "--ndjson",
+            "--deadline",
+            input_deadline,
+        ],
+    );
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines().collect::<Vec<_>>();
+    let status_line = lines.pop().unwrap();
+    assert_eq!(lines.len(), expected_entry_count);
+    for line in lines {
+        serde_json::from_str::<ReportEntry>(line).unwrap();
+    }
+
+    let status =
+        serde_json::from_str::<serde_json::Value>(status_line).unwrap();
+    assert_eq!(status["status"], expected_status);
+    let unscanned_package_count = status
+        .get("unscanned_packages")
+        .map_or(0, |packages| packages.as_array().unwrap().len());
+    assert_eq!(unscanned_package_count, 3 - expected_entry_count);
+}
+
+#[rstest]
+fn serialize_test2_report_with_scan_timeout_per_crate() {
+    // A synthetic file too large to be scanned within the timeout. It is not
+    // used by the build, which doesn't need to compile it, but all the files
+    // of a package are scanned.
+    let name = "test2_package_with_shallow_deps";
+    let cx = Context::new();
+    let big_file = (0..200_000)
+        .map(|i| format!("pub fn f{}() {{ unsafe {{ g({}); }} }}\n", i, i))
+        .collect::<String>();
+    std::fs::write(cx.crate_dir(name).join("src").join("big.rs"), big_file)
+        .unwrap();
+
+    let output = Command::cargo_bin("cargo-geiger")
+        .unwrap()
+        .arg("geiger")
+        .arg("--quiet")
+        .arg("--output-format=Json")
+        .arg("--scan-timeout-per-crate=1")
+        .current_dir(cx.crate_dir(name))
+        .output()
+        .expect("failed to run `cargo-geiger`");
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("longer than --scan-timeout-per-crate"));
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let timed_out_package_names = report
+        .timed_out_packages
+        .iter()
+        .map(|package_id| package_id.name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(timed_out_package_names, vec![name]);
+    assert!(report.partial);
+    // The scan moved on to the dependencies.
+    let package_names = report
+        .packages
+        .keys()
+        .map(|package_id| package_id.name.as_str())
+        .collect::<HashSet<&str>>();
+    assert!(package_names.contains("ref_slice"));
+    assert!(package_names.contains("test1_package_with_no_deps"));
+}
+
+#[rstest]
+fn serialize_test3_report_with_any_number_of_jobs() {
+    // The scans run in the same directory, for the paths of the reports to
+    // be the same.
+    let cx = Context::new();
+    let run_with_jobs = |jobs: &str| {
+        let output = Command::cargo_bin("cargo-geiger")
+            .unwrap()
+            .arg("geiger")
+            .arg("--quiet")
+            .arg("--output-format=Json")
+            .arg("--all-targets")
+            .arg("--all-features")
+            .arg("--jobs")
+            .arg(jobs)
+            .current_dir(cx.crate_dir("test3_package_with_nested_deps"))
+            .output()
+            .expect("failed to run `cargo-geiger`");
+        assert!(output.status.success());
+        output.stdout
+    };
+
+    let single_threaded_report = run_with_jobs("1");
+    let multi_threaded_report = run_with_jobs("8");
+
+    assert_eq!(
+        String::from_utf8(multi_threaded_report).unwrap(),
+        String::from_utf8(single_threaded_report).unwrap()
+    );
+}
+
+// test4 uses 3 of the 4 unsafe usages found, in a single package.
+#[rstest(
+    name,
+    expected_safety_grade,
+    case(Test1::NAME, SafetyGrade::F),
+    case(Test4::NAME, SafetyGrade::D),
+    case("test8_package_with_build_rs_no_deps", SafetyGrade::A)
+)]
+fn serialize_report_safety_grade(
+    name: &str,
+    expected_safety_grade: SafetyGrade,
+) {
+    let (output, _cx) = run_geiger_with(name, &["--output-format", "Json"]);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    assert_eq!(report.safety_grade, Some(expected_safety_grade));
+}
+
+// test4 has no unsafe usage in the code used by the build, but has some in
+// its unused code, unlike test1.
+#[rstest]
+fn serialize_report_unsafe_free_counts() {
+    assert_unsafe_free_counts(Test1);
+    assert_unsafe_free_counts(Test2);
+    assert_unsafe_free_counts(Test3);
+    assert_unsafe_free_counts(Test4);
+}
+
+/// Assert that the unsafe-free counts of the report are the number of
+/// packages of the expected report with no unsafe usage.
+fn assert_unsafe_free_counts<T: IntegrationTest>(test: T) {
+    let (output, cx) = run_geiger_with(T::NAME, &["--output-format", "Json"]);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let expected_report = test.expected_report(&cx);
+    let count_packages = |predicate: fn(&UnsafeInfo) -> bool| {
+        expected_report
+            .packages
+            .values()
+            .filter(|entry| predicate(&entry.unsafety))
+            .count() as u64
+    };
+
+    assert_eq!(
+        report.unsafe_free_counts,
+        Some(UnsafeFreeCounts {
+            package_count: expected_report.packages.len() as u64,
+            used_unsafe_free_count: count_packages(|unsafety| {
+                !unsafety.used.has_unsafe()
+            }),
+            unsafe_free_count: count_packages(|unsafety| {
+                !unsafety.used.has_unsafe() && !unsafety.unused.has_unsafe()
+            }),
+        }),
+        "{}",
+        T::NAME
+    );
+}
+
+#[rstest]
+fn serialize_test2_report_with_min_unsafe() {
+    let (output, cx) = run_geiger_with(
+        Test2::NAME,
+        &["--output-format", "Json", "--min-unsafe", "3"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // ref_slice, with 2 unsafe items used by the build, is hidden.
+    let mut expected_report = Test2.expected_report(&cx);
+    expected_report
+        .packages
+        .remove(&external_package_reports::ref_slice_package_id());
+    assert_eq!(report.packages, expected_report.packages);
+}
+
+#[rstest(
+    input_features,
+    expected_active_features,
+    case(None, vec![]),
+    case(
+        Some("test1_package_with_no_deps"),
+        vec!["test1_package_with_no_deps"]
+    )
+)]
+fn serialize_test16_report_active_features(
+    input_features: Option<&str>,
+    expected_active_features: Vec<&str>,
+) {
+    // Unlike `run_geiger_with`, without `--all-features`, so that only the
+    // requested features are enabled.
+    let cx = Context::new();
+    let mut command = Command::cargo_bin("cargo-geiger").unwrap();
+    command
+        .arg("geiger")
+        .arg("--color=never")
+        .arg("--quiet")
+        .arg("--output-format=Json")
+        .current_dir(cx.crate_dir("test16_package_with_optional_dep"));
+    if let Some(features) = input_features {
+        command.arg("--features").arg(features);
+    }
+    let output = command.output().expect("failed to run `cargo-geiger`");
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    let entry = report
+        .packages
+        .values()
+        .find(|entry| {
+            entry.package.id.name == "test16_package_with_optional_dep"
+        })
+        .unwrap();
+    assert_eq!(entry.active_features, expected_active_features);
+}
+
+#[rstest]
+fn serialize_test1_report_with_ignored_package() {
+    let (output, cx) = run_geiger_with(
+        Test1::NAME,
+        &["--output-format", "Json", "--ignore-unsafe-in", Test1::NAME],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    assert_eq!(
+        report.ignored_packages,
+        to_set(vec![make_package_id(&cx, Test1::NAME)])
+    );
+    // Ignored packages are still reported with their unsafe usage.
+    assert_eq!(
+        report.packages.values().next().unwrap(),
+        &Test1.expected_report_entry(&cx)
+    );
+}
+
+#[rstest]
+fn serialize_test1_report_with_exclude_self() {
+    let (output, cx) = run_geiger_with(
+        Test1::NAME,
+        &["--output-format", "Json", "--exclude-self"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    assert_eq!(
+        report.ignored_packages,
+        to_set(vec![make_package_id(&cx, Test1::NAME)])
+    );
+    // The root package is still reported, but its unsafe usage doesn't bring
+    // the grade down from A to F.
+    assert_eq!(
+        report.packages.values().next().unwrap(),
+        &Test1.expected_report_entry(&cx)
+    );
+    assert_eq!(report.safety_grade, Some(SafetyGrade::A));
+}
+
+// Needs the source of the standard library, run with `cargo test -- --ignored`
+// once the component is installed.
+#[rstest]
+#[ignore = "requires the rust-src component"]
+fn serialize_test1_report_with_include_std() {
+    let (output, cx) = run_geiger_with(
+        Test1::NAME,
+        &["--output-format", "Json", "--include-std"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    assert_eq!(
+        report
+            .std_crates
+            .iter()
+            .map(|std_crate| std_crate.name.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["core", "alloc", "std"]
+    );
+    assert!(report
+        .std_crates
+        .iter()
+        .all(|std_crate| std_crate.unsafety.unsafe_count() > 0));
+    // The standard library is reported apart from the packages.
+    assert_eq!(
+        report.packages.values().collect::<Vec<&ReportEntry>>(),
+        vec![&Test1.expected_report_entry(&cx)]
+    );
+}
+
+#[rstest(
+    input_extra_args,
+    expected_used_unsafe_functions,
+    expected_unused_unsafe_functions,
+    case(vec![], 0, 2),
+    case(vec!["--include-benches"], 1, 1),
+    case(vec!["--include-examples"], 1, 1),
+    case(vec!["--include-benches", "--include-examples"], 2, 0)
+)]
+fn serialize_test10_report_with_included_target_kinds(
+    input_extra_args: Vec<&str>,
+    expected_used_unsafe_functions: u64,
+    expected_unused_unsafe_functions: u64,
+) {
+    let mut extra_args = vec!["--output-format", "Json"];
+    extra_args.extend(input_extra_args);
+
+    let (output, _cx) =
+        run_geiger_with("test10_package_with_bench_and_example", extra_args);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry = report
+        .packages
+        .values()
+        .find(|entry| {
+            entry.package.id.name == "test10_package_with_bench_and_example"
+        })
+        .unwrap();
+
+    assert_eq!(
+        entry.unsafety.used.functions.unsafe_,
+        expected_used_unsafe_functions
+    );
+    assert_eq!(
+        entry.unsafety.unused.functions.unsafe_,
+        expected_unused_unsafe_functions
+    );
+}
+
+// The unsafe function is in a module of an example declared in the manifest
+// at a custom path, outside of the conventional `examples/` directory.
+#[rstest(
+    input_extra_args,
+    expected_used_unsafe_functions,
+    expected_unused_unsafe_functions,
+    case(vec![], 0, 1),
+    case(vec!["--include-examples"], 1, 0)
+)]
+fn serialize_test26_report_with_custom_path_example(
+    input_extra_args: Vec<&str>,
+    expected_used_unsafe_functions: u64,
+    expected_unused_unsafe_functions: u64,
+) {
+    let mut extra_args = vec!["--output-format", "Json"];
+    extra_args.extend(input_extra_args);
+
+    let (output, _cx) =
+        run_geiger_with("test26_package_with_custom_path_example", extra_args);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry = report.packages.values().next().unwrap();
+
+    assert_eq!(
+        entry.unsafety.used.functions.unsafe_,
+        expected_used_unsafe_functions
+    );
+    assert_eq!(
+        entry.unsafety.unused.functions.unsafe_,
+        expected_unused_unsafe_functions
+    );
+}
+
+// The `.cargo/config.toml` of the package passes `--cfg geiger_cfg` to rustc,
+// which gates both a module and a target specific dependency.
+#[rstest]
+fn serialize_test27_report_with_config_rustflags() {
+    let (output, _cx) = run_geiger_with(
+        "test27_package_with_config_rustflags",
+        &["--output-format", "Json"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry_of = |name: &str| {
+        report
+            .packages
+            .values()
+            .find(|entry| entry.package.id.name == name)
+            .unwrap_or_else(|| panic!("{} not in the report", name))
+    };
+
+    // The module gated by the cfg is built, the one gated by its negation
+    // is not.
+    let root_entry = entry_of("test27_package_with_config_rustflags");
+    assert_eq!(root_entry.unsafety.used.functions.unsafe_, 1);
+    assert_eq!(root_entry.unsafety.unused.functions.unsafe_, 1);
+    // The dependency gated by the cfg is part of the dependency graph.
+    assert_eq!(entry_of("cfg_only_dep").unsafety.used.functions.unsafe_, 1);
+}
+
+#[rstest]
+fn serialize_test11_report_scans_examples_in_lieu_of_a_library() {
+    let (output, _cx) = run_geiger_with(
+        "test11_package_with_examples_only",
+        &["--output-format", "Json"],
+    );
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("scanning its examples instead"));
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry = report
+        .packages
+        .values()
+        .find(|entry| {
+            entry.package.id.name == "test11_package_with_examples_only"
+        })
+        .unwrap();
+
+    assert_eq!(entry.unsafety.used.functions.unsafe_, 1);
+    assert_eq!(entry.unsafety.unused.functions.unsafe_, 0);
+}
+
+#[rstest(
+    input_extra_args,
+    expected_used_unsafe_exprs,
+    case(vec![], 0),
+    case(vec!["--count-std-macros"], 1)
+)]
+fn serialize_test12_report_with_std_macros(
+    input_extra_args: Vec<&str>,
+    expected_used_unsafe_exprs: u64,
+) {
+    let mut extra_args = vec!["--output-format", "Json"];
+    extra_args.extend(input_extra_args);
+
+    let (output, _cx) =
+        run_geiger_with("test12_package_with_thread_local", extra_args);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry = report
+        .packages
+        .values()
+        .find(|entry| {
+            entry.package.id.name == "test12_package_with_thread_local"
+        })
+        .unwrap();
+
+    // The single `thread_local!` invocation is the only unsafe usage.
+    assert_eq!(
+        entry.unsafety.used.exprs.unsafe_,
+        expected_used_unsafe_exprs
+    );
+}
+
+#[rstest(
+    input_extra_args,
+    expected_used_unsafe_functions,
+    expected_excluded_file_count,
+    case(vec![], 1, 0),
+    case(vec!["--exclude-generated"], 0, 1),
+    case(
+        vec!["--exclude-generated", "--generated-pattern", "lib\\.rs$"],
+        1,
+        1
+    )
+)]
+fn serialize_test14_report_with_generated_file(
+    input_extra_args: Vec<&str>,
+    expected_used_unsafe_functions: u64,
+    expected_excluded_file_count: usize,
+) {
+    let mut extra_args = vec!["--output-format", "Json"];
+    extra_args.extend(input_extra_args);
+
+    let (output, _cx) =
+        run_geiger_with("test14_package_with_generated_file", extra_args);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry = report
+        .packages
+        .values()
+        .find(|entry| {
+            entry.package.id.name == "test14_package_with_generated_file"
+        })
+        .unwrap();
+
+    // The only unsafe function is in src/bindings.generated.rs.
+    assert_eq!(
+        entry.unsafety.used.functions.unsafe_,
+        expected_used_unsafe_functions
+    );
+    assert_eq!(report.excluded_files.len(), expected_excluded_file_count);
+    // Excluded files are not reported as used but not scanned.
+    assert!(report.used_but_not_scanned_files.is_empty());
+}
+
+#[rstest(
+    input_path_base,
+    expected_excluded_file,
+    case("workspace", "../test1_package_with_no_deps/src/lib.rs"),
+    case("crate", "src/lib.rs"),
+    case("cwd", "../test1_package_with_no_deps/src/lib.rs")
+)]
+fn serialize_test4_report_with_path_base(
+    input_path_base: &str,
+    expected_excluded_file: &str,
+) {
+    let (output, _cx) = run_geiger_with(
+        "test4_workspace_with_top_level_package",
+        &[
+            "--output-format",
+            "Json",
+            "--exclude-generated",
+            "--generated-pattern",
+            "lib\\.rs$",
+            "--path-base",
+            input_path_base,
+        ],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The only library is the one of the path dependency, outside of the
+    // workspace.
+    assert_eq!(
+        report.excluded_files,
+        to_set(vec![PathBuf::from(expected_excluded_file)])
+    );
+}
+
+#[rstest]
+fn serialize_test15_report_with_raw_derefs() {
+    let name = "test15_package_with_raw_pointer_deref";
+    let (output, cx) =
+        run_geiger_with(name, &["--output-format", "Json", "--raw-derefs"]);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The dereference of the `&u32` reference is not counted.
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                raw_derefs: Some(2),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                raw_derefs: Some(0),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test21_report_with_latent_macro_unsafe() {
+    let name = "test21_package_with_unsafe_macro";
+    let (output, cx) = run_geiger_with(
+        name,
+        &["--output-format", "Json", "--latent-macro-unsafe"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry = report.packages.values().next().unwrap();
+
+    // The macros are never invoked, their unsafe usage is only latent.
+    assert_eq!(entry.unsafety.used.unsafe_count(), 0);
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                latent_macro_unsafe: Some(2),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                latent_macro_unsafe: Some(0),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test23_report_with_exported_symbols() {
+    let name = "test23_package_with_exported_symbol";
+    let (output, cx) = run_geiger_with(
+        name,
+        &["--output-format", "Json", "--exported-symbols"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                exported_symbols: Some(1),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                exported_symbols: Some(0),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test24_report_with_module_level() {
+    let (output, _cx) = run_geiger_with(
+        "test24_package_with_unsafe_modules",
+        &["--output-format", "Json", "--module-level"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry = report.packages.values().next().unwrap();
+    let used_module =
+        |functions: (u64, u64), exprs: (u64, u64)| ModuleUnsafety {
+            used: CounterBlock {
+                functions: Count {
+                    safe: functions.0,
+                    unsafe_: functions.1,
+                },
+                exprs: Count {
+                    safe: exprs.0,
+                    unsafe_: exprs.1,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+    // `reader` is a file of the crate and `writer` an inline module, the
+    // counts of the crate include both.
+    assert_eq!(
+        entry.unsafety.modules,
+        vec![
+            (String::from("crate"), used_module((2, 1), (0, 3))),
+            (String::from("crate::reader"), used_module((0, 1), (0, 1))),
+            (String::from("crate::writer"), used_module((1, 0), (0, 2))),
+        ]
+        .into_iter()
+        .collect()
+    );
+}
+
+#[rstest]
+fn serialize_test25_report_with_memory_lifecycle_unsafe() {
+    let name = "test25_package_with_assume_init";
+    let (output, cx) = run_geiger_with(
+        name,
+        &["--output-format", "Json", "--memory-lifecycle-unsafe"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                memory_lifecycle_unsafe: Some(1),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                memory_lifecycle_unsafe: Some(0),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test28_report_with_static_mut() {
+    let name = "test28_package_with_static_mut";
+    let (output, cx) =
+        run_geiger_with(name, &["--output-format", "Json", "--static-mut"]);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The declaration of `COUNTER`, its increment and its read.
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                static_mut: Some(StaticMutCounts {
+                    definitions: 1,
+                    accesses: 2,
+                }),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                static_mut: Some(StaticMutCounts::default()),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test1_report_with_source_hash() {
+    // Unlike `run_geiger_with`, the runs share the same copy of the package,
+    // so that it can be edited between them.
+    let cx = Context::new();
+    let source_hash = || {
+        let output = Command::cargo_bin("cargo-geiger")
+            .unwrap()
+            .arg("geiger")
+            .arg("--quiet")
+            .arg("--output-format=Json")
+            .arg("--json-include-source-hash")
+            .current_dir(cx.crate_dir(Test1::NAME))
+            .output()
+            .expect("failed to run `cargo-geiger`");
+        assert!(output.status.success());
+        let report =
+            serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+        assert_eq!(report.source_hashes.len(), 1);
+        report.source_hashes[0].source_hash.clone()
+    };
+
+    let first_source_hash = source_hash();
+    assert_eq!(first_source_hash.len(), 64);
+    assert_eq!(source_hash(), first_source_hash);
+
+    let lib_path = cx.crate_dir(Test1::NAME).join("src").join("lib.rs");
+    let mut lib = std::fs::read_to_string(&lib_path).unwrap();
+    lib.push_str("\npub fn added() {}\n");
+    std::fs::write(&lib_path, lib).unwrap();
+    assert_ne!(source_hash(), first_source_hash);
+}
+
+#[rstest]
+fn serialize_test1_report_with_max_file_size() {
+    // A large synthetic file, used by the build.
+    let cx = Context::new();
+    let src_dir = cx.crate_dir(Test1::NAME).join("src");
+    let big_file = (0..100_000)
+        .map(|i| format!("pub fn f{}() {{}}\n", i))
+        .collect::<String>();
+    std::fs::write(src_dir.join("big.rs"), big_file).unwrap();
+    let mut lib = std::fs::read_to_string(src_dir.join("lib.rs")).unwrap();
+    lib.push_str("\npub mod big;\n");
+    std::fs::write(src_dir.join("lib.rs"), lib).unwrap();
+
+    let output = Command::cargo_bin("cargo-geiger")
+        .unwrap()
+        .arg("geiger")
+        .arg("--quiet")
+        .arg("--output-format=Json")
+        .arg("--max-file-size=1000000")
+        .current_dir(cx.crate_dir(Test1::NAME))
+        .output()
+        .expect("failed to run `cargo-geiger`");
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("larger than --max-file-size were not scanned"));
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let big_path = src_dir.join("big.rs").canonicalize().unwrap();
+    assert_eq!(report.oversized_files, to_set(vec![big_path.clone()]));
+    // The file is used by the build, so the crate isn't unsafe free.
+    assert!(report.used_but_not_scanned_files.contains(&big_path));
+}
+
+#[rstest]
+fn serialize_test29_report_with_allowed_unsafe() {
+    let name = "test29_package_with_allowed_unsafe";
+    let (output, cx) =
+        run_geiger_with(name, &["--output-format", "Json", "--allowed-unsafe"]);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // `zeroed` and its call in `ffi` sit under the `#[allow(unsafe_code)]`,
+    // the dereference and the `as_ptr` call in `first` do not.
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                allowed_unsafe: Some(AllowedUnsafeCounts {
+                    allowed: 2,
+                    unannotated: 2,
+                }),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                allowed_unsafe: Some(AllowedUnsafeCounts::default()),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test30_report_with_unsafe_trait_usage() {
+    let name = "test30_package_with_unsafe_trait_bound";
+    let (output, cx) = run_geiger_with(
+        name,
+        &["--output-format", "Json", "--unsafe-trait-usage"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The `Zeroable` bound of `zeroed`, the `Send` of the trait object and
+    // the one of the `where` clause of `boxed`.
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                unsafe_trait_usage: Some(3),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                unsafe_trait_usage: Some(0),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test31_report_with_drop_unsafe() {
+    let name = "test31_package_with_unsafe_drop";
+    let (output, cx) =
+        run_geiger_with(name, &["--output-format", "Json", "--drop-unsafe"]);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The calls and the field accesses of the unsafe block of `drop`, the
+    // dereference in `first` staying in the ordinary unsafe usage only.
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                drop_unsafe: Some(5),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                drop_unsafe: Some(0),
+                ..Default::default()
+            },
+        }
+    );
+    let entry = report
+        .packages
+        .values()
+        .find(|entry| entry.package.id.name == name)
+        .unwrap();
+    assert_eq!(entry.unsafety.used.exprs.unsafe_, 7);
+}
+
+#[rstest]
+fn serialize_test32_report_with_nightly_features() {
+    let name = "test32_package_with_nightly_feature";
+    let (output, cx) = run_geiger_with(
+        name,
+        &["--output-format", "Json", "--flag-nightly-features"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The feature is only enabled under `cfg_attr(docsrs, ...)`, but it still
+    // takes a nightly toolchain to build the crate with it.
+    assert_eq!(
+        report.nightly_features,
+        vec![PackageNightlyFeatures {
+            package: make_package_id(&cx, name),
+            features: vec![String::from("doc_cfg")],
+        }]
+    );
+
+    let (output, _cx) = run_geiger_with(name, &["--flag-nightly-features"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(
+        "test32_package_with_nightly_feature 0.1.0 (nightly features: doc_cfg)"
+    ));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(
+        "WARNING: test32_package_with_nightly_feature 0.1.0 requires a nightly \
+         toolchain for its features: doc_cfg"
+    ));
+}
+
+#[rstest]
+fn serialize_test33_report_with_generated_includes() {
+    let name = "test33_package_with_generated_include";
+    let is_generated_file =
+        |path: &PathBuf| path.file_name().unwrap() == "bindings.rs";
+
+    let (output, _cx) = run_geiger_with(name, &["--output-format", "Json"]);
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    // The generated file is used by the build, but outside of the package.
+    assert!(report
+        .used_but_not_scanned_files
+        .iter()
+        .any(is_generated_file));
+    let entry = report.packages.values().next().unwrap();
+    assert_eq!(entry.unsafety.used.functions.unsafe_, 0);
+
+    let (output, _cx) = run_geiger_with(
+        name,
+        &["--output-format", "Json", "--scan-generated-includes"],
+    );
+    assert!(output.status.success());
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    assert!(!report
+        .used_but_not_scanned_files
+        .iter()
+        .any(is_generated_file));
+    // The `unsafe fn` of the generated file.
+    let entry = report.packages.values().next().unwrap();
+    assert_eq!(entry.unsafety.used.functions.unsafe_, 1);
+}
+
+#[rstest]
+fn serialize_test34_report_with_ffi_callbacks() {
+    let name = "test34_package_with_ffi_callback";
+    let (output, cx) =
+        run_geiger_with(name, &["--output-format", "Json", "--ffi-callbacks"]);
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // `compare`, passed to `qsort` as an `extern "C" fn` pointer.
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                ffi_callbacks: Some(1),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                ffi_callbacks: Some(0),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test36_report_with_layout_dependent_unsafe() {
+    let name = "test36_package_with_repr_transmute";
+    let (output, cx) = run_geiger_with(
+        name,
+        &["--output-format", "Json", "--layout-dependent-unsafe"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+
+    // The transmute to the `#[repr(C)]` `Header`, not the one to `u32`.
+    assert_eq!(
+        package_unsafe_kinds(&report, &make_package_id(&cx, name)),
+        UnsafeKinds {
+            used: UnsafeKindCounts {
+                layout_dependent_unsafe: Some(1),
+                ..Default::default()
+            },
+            unused: UnsafeKindCounts {
+                layout_dependent_unsafe: Some(0),
+                ..Default::default()
+            },
+        }
+    );
+}
+
+#[rstest]
+fn serialize_test19_report_with_test_unsafety() {
+    let name = "test19_package_with_cfg_test_unsafe";
+    let (output, cx) = run_geiger_with(
+        name,
+        &["--output-format", "Json", "--count-cfg-test-separately"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let entry = report.packages.values().next().unwrap();
+
+    // The unsafe block of the `#[cfg(test)]` module is left out of the
+    // package counters.
+    assert_eq!(
+        entry.unsafety.used,
+        CounterBlock {
+            functions: Count {
+                safe: 1,
+                unsafe_: 1
+            },
+            exprs: Count {
+                safe: 1,
+                unsafe_: 1
+            },
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        report.test_unsafety,
+        vec![PackageTestUnsafety {
+            package: make_package_id(&cx, name),
+            used: CounterBlock {
+                functions: Count {
+                    safe: 1,
+                    unsafe_: 0
+                },
+                exprs: Count {
+                    safe: 0,
+                    unsafe_: 2
+                },
+                ..Default::default()
+            },
+            unused: Default::default(),
+        }]
+    );
+}
+
+#[rstest]
+fn serialize_test22_report_with_separate_build_report() {
+    let (output, _cx) = run_geiger_with(
+        "test22_package_with_unsafe_build_dep",
+        &["--output-format", "Json", "--separate-build-report"],
+    );
+    assert!(output.status.success());
+
+    let report =
+        serde_json::from_slice::<SafetyReport>(&output.stdout).unwrap();
+    let package_names = |entries: &HashMap<PackageId, ReportEntry>| {
+        to_set(entries.keys().map(|package_id| package_id.name.clone()))
+    };
+
+    assert_eq!(
+        package_names(&report.packages),
+        to_set(vec![
+            String::from("runtime_dep"),
+            String::from("test22_package_with_unsafe_build_dep"),
+        ])
+    );
+    assert_eq!(
+        package_names(&report.build_packages),
+        to_set(vec![String::from("build_dep")])
+    );
+    assert!(report
+        .build_packages
+        .values()
+        .all(|entry| entry.unsafety.used.has_unsafe()));
+}
+
+#[rstest]
+fn serialize_test17_dependency_graph() {
+    let name = "test17_package_with_diamond_deps";
+    let (output, cx) =
+        run_geiger_with(name, &["--dump-graph-json", "graph.json"]);
+    assert!(output.status.success());
+
+    let dependency_graph = serde_json::from_slice::<DependencyGraph>(
+        &std::fs::read(cx.crate_dir(name).join("graph.json")).unwrap(),
+    )
+    .unwrap();
+
+    let make_dependency_package_id = |dependency_name: &str| PackageId {
+        name: dependency_name.into(),
+        version: Version::new(0, 1, 0),
+        source: Source::Path(
+            Url::from_file_path(cx.crate_dir(name).join(dependency_name))
+                .unwrap(),
+        ),
+    };
+    let root = make_package_id(&cx, name);
+    let left = make_dependency_package_id("left");
+    let right = make_dependency_package_id("right");
+    let shared = make_dependency_package_id("shared");
+    let leaf = make_dependency_package_id("leaf");
+    let make_edge = |from: &PackageId, to: &PackageId| DependencyEdge {
+        from: from.clone(),
+        to: to.clone(),
+        kind: DependencyKind::Normal,
+    };
+
+    assert_eq!(dependency_graph.root, root);
+    assert_eq!(dependency_graph.nodes.len(), 5);
+    assert_eq!(
+        to_set(dependency_graph.edges),
+        to_set(vec![
+            make_edge(&root, &left),
+            make_edge(&root, &right),
+            make_edge(&left, &shared),
+            make_edge(&right, &shared),
+            make_edge(&shared, &leaf),
+        ])
+    );
+}
+
 struct Test1;
 
 impl IntegrationTest for Test1 {
@@ -92,6 +1702,7 @@ impl IntegrationTest for Test1 {
     fn expected_report_entry(&self, cx: &Context) -> ReportEntry {
         ReportEntry {
             package: PackageInfo::new(make_package_id(cx, Self::NAME)),
+            active_features: vec![],
             unsafety: UnsafeInfo {
                 used: CounterBlock {
                     functions: Count {
@@ -135,6 +1746,7 @@ impl IntegrationTest for Test2 {
                 ]),
                 ..PackageInfo::new(make_package_id(cx, Self::NAME))
             },
+            active_features: vec![],
             unsafety: UnsafeInfo {
                 used: CounterBlock {
                     functions: Count {
@@ -183,6 +1795,7 @@ impl IntegrationTest for Test3 {
                 ]),
                 ..PackageInfo::new(make_package_id(cx, Self::NAME))
             },
+            active_features: vec![],
             unsafety: UnsafeInfo {
                 used: CounterBlock {
                     functions: Count {
@@ -219,6 +1832,7 @@ impl IntegrationTest for Test4 {
                 dependencies: to_set(vec![make_package_id(cx, Test1::NAME)]),
                 ..PackageInfo::new(make_package_id(cx, Self::NAME))
             },
+            active_features: vec![],
             unsafety: UnsafeInfo {
                 used: CounterBlock {
                     functions: Count {
@@ -276,6 +1890,7 @@ impl IntegrationTest for Test6 {
                 ]),
                 ..PackageInfo::new(make_package_id(cx, Self::NAME))
             },
+            active_features: vec![],
             unsafety: UnsafeInfo {
                 used: CounterBlock {
                     functions: Count {
@@ -318,6 +1933,7 @@ impl IntegrationTest for Test7 {
                 ]),
                 ..PackageInfo::new(make_package_id(cx, Self::NAME))
             },
+            active_features: vec![],
             unsafety: UnsafeInfo {
                 used: CounterBlock {
                     functions: Count {
@@ -343,6 +1959,15 @@ fn make_workspace_source(cx: &Context, workspace: &str, name: &str) -> Source {
     )
 }
 
+/// The kinds of unsafe usage of a package of the report, counted with the
+/// flags enabling them.
+fn package_unsafe_kinds(
+    report: &SafetyReport,
+    package: &PackageId,
+) -> UnsafeKinds {
+    report.packages[package].unsafety.kinds.clone().unwrap()
+}
+
 trait WorkspaceCrateDir {
     fn workspace_crate_dir(&self, workspace: &str, name: &str) -> PathBuf;
 }
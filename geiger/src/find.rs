@@ -1,4 +1,7 @@
-use super::{IncludeTests, RsFileMetrics, ScanFileError};
+use super::{
+    IncludeTests, RsFileMetrics, ScanFileError, UnsafeSpan,
+    MEMORY_LIFECYCLE_FUNCTIONS,
+};
 
 use crate::geiger_syn_visitor::GeigerSynVisitor;
 
@@ -25,19 +28,73 @@ pub fn find_unsafe_in_file(
 pub fn find_unsafe_in_string(
     src: &str,
     include_tests: IncludeTests,
+) -> Result<RsFileMetrics, syn::Error> {
+    find_unsafe_in_string_with_macros(src, include_tests, &[])
+}
+
+/// Scan a single source string for `unsafe` usage, additionally counting each
+/// invocation of the given macros as an unsafe expression. This is meant for
+/// macros like `thread_local!`, which expand to `unsafe` code.
+pub fn find_unsafe_in_string_with_macros(
+    src: &str,
+    include_tests: IncludeTests,
+    unsafe_macros: &[String],
+) -> Result<RsFileMetrics, syn::Error> {
+    let memory_lifecycle_functions = MEMORY_LIFECYCLE_FUNCTIONS
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    find_unsafe_in_string_with_functions(
+        src,
+        include_tests,
+        unsafe_macros,
+        &memory_lifecycle_functions,
+    )
+}
+
+/// Scan a single source string for `unsafe` usage, like
+/// `find_unsafe_in_string_with_macros`, counting the calls of the given
+/// functions instead of `MEMORY_LIFECYCLE_FUNCTIONS` as memory lifecycle
+/// unsafe usage.
+pub fn find_unsafe_in_string_with_functions(
+    src: &str,
+    include_tests: IncludeTests,
+    unsafe_macros: &[String],
+    memory_lifecycle_functions: &[String],
 ) -> Result<RsFileMetrics, syn::Error> {
     use syn::visit::Visit;
     let syntax = syn::parse_file(src)?;
-    let mut vis = GeigerSynVisitor::new(include_tests);
+    let mut vis = GeigerSynVisitor::new(
+        include_tests,
+        unsafe_macros,
+        memory_lifecycle_functions,
+    );
     vis.visit_file(&syntax);
     Ok(vis.metrics)
 }
 
+/// Scan a single source string for the locations of its `unsafe` code, see
+/// `UnsafeSpan`. As for the metrics, the `unsafe` code in tests is left out
+/// with `IncludeTests::Separately`.
+pub fn find_unsafe_spans_in_string(
+    src: &str,
+    include_tests: IncludeTests,
+) -> Result<Vec<UnsafeSpan>, syn::Error> {
+    use syn::visit::Visit;
+    let syntax = syn::parse_file(src)?;
+    let mut vis = GeigerSynVisitor::new(include_tests, &[], &[]);
+    vis.visit_file(&syntax);
+    Ok(vis.unsafe_spans)
+}
+
 #[cfg(test)]
 mod find_tests {
     use super::*;
 
+    use crate::UnsafeKind;
+
     use cargo_geiger_serde::{Count, CounterBlock};
+    use std::collections::BTreeMap;
     use tempfile::tempdir;
 
     const DEFAULT_COUNTERS: CounterBlock = CounterBlock {
@@ -49,7 +106,22 @@ mod find_tests {
     };
     const DEFAULT_METRICS: RsFileMetrics = RsFileMetrics {
         counters: DEFAULT_COUNTERS,
+        test_counters: DEFAULT_COUNTERS,
         forbids_unsafe: false,
+        raw_pointer_derefs: 0,
+        latent_macro_unsafe: 0,
+        exported_symbols: 0,
+        memory_lifecycle_unsafe: 0,
+        static_mut_definitions: 0,
+        static_mut_accesses: 0,
+        allowed_unsafe: 0,
+        unsafe_trait_usage: 0,
+        drop_unsafe: 0,
+        ffi_callbacks: 0,
+        layout_dependent_transmutes: 0,
+        nightly_features: Vec::new(),
+        out_dir_includes: Vec::new(),
+        module_counters: BTreeMap::new(),
     };
 
     const FILE_CONTENT_STRING: &str = "use std::io::Write;
@@ -87,6 +159,30 @@ mod tests {
 }
 ";
 
+    #[test]
+    fn find_unsafe_in_string_with_macros_counts_unsafe_macros() {
+        let file = "
+            thread_local! { static X: u32 = 0; }
+
+            pub fn f() {
+                std::thread_local! { static Y: u32 = 0; }
+                let _s = format!(\"{}\", 1);
+            }
+        ";
+
+        let without_macros =
+            find_unsafe_in_string(file, IncludeTests::No).unwrap();
+        let with_macros = find_unsafe_in_string_with_macros(
+            file,
+            IncludeTests::No,
+            &[String::from("thread_local")],
+        )
+        .unwrap();
+
+        assert_eq!(without_macros.counters.exprs, Count { safe: 1, unsafe_: 0 });
+        assert_eq!(with_macros.counters.exprs, Count { safe: 1, unsafe_: 2 });
+    }
+
     #[test]
     fn find_unsafe() {
         let temp_dir = tempdir().unwrap();
@@ -103,6 +199,7 @@ mod tests {
                 exprs: Count { safe: 4, unsafe_: 4 },
                 ..DEFAULT_COUNTERS
             },
+            exported_symbols: 2,
             ..DEFAULT_METRICS
         };
         assert_eq!(from_file, expected);
@@ -118,6 +215,17 @@ mod tests {
                 exprs: Count { safe: 4, unsafe_: 5 },
                 ..DEFAULT_COUNTERS
             },
+            module_counters: vec![(
+                String::from("tests"),
+                CounterBlock {
+                    functions: Count { safe: 1, unsafe_: 0 },
+                    exprs: Count { safe: 0, unsafe_: 1 },
+                    ..DEFAULT_COUNTERS
+                },
+            )]
+            .into_iter()
+            .collect(),
+            exported_symbols: 2,
             ..DEFAULT_METRICS
         };
         assert_eq!(from_file, expected);
@@ -139,6 +247,7 @@ mod tests {
                 exprs: Count { safe: 2, unsafe_: 3 },
                 ..DEFAULT_COUNTERS
             },
+            exported_symbols: 2,
             ..DEFAULT_METRICS
         };
         let file = "
@@ -210,9 +319,464 @@ mod tests {
                 exprs: Count { safe: 3, unsafe_: 4 },
                 ..DEFAULT_COUNTERS
             },
+            module_counters: vec![(
+                String::from("tests"),
+                CounterBlock {
+                    functions: Count { safe: 1, unsafe_: 1 },
+                    exprs: Count { safe: 1, unsafe_: 2 },
+                    ..DEFAULT_COUNTERS
+                },
+            )]
+            .into_iter()
+            .collect(),
             ..DEFAULT_METRICS
         };
         let actual = find_unsafe_in_string(file, IncludeTests::Yes).unwrap();
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn counters_exprs_count_tests_separately() {
+        let file = "
+            pub fn f() { f(); }
+            pub unsafe fn f() { f(); }
+            #[cfg(test)]
+            mod tests {
+                pub unsafe fn f() { f(); }
+                pub fn f() {
+                    f();
+                    unsafe { f(); }
+                }
+            }
+            #[test]
+            pub fn f() {
+                f();
+                unsafe { f(); }
+            }
+        ";
+        let expected = RsFileMetrics {
+            counters: CounterBlock {
+                functions: Count { safe: 1, unsafe_: 1 },
+                exprs: Count { safe: 1, unsafe_: 1 },
+                ..DEFAULT_COUNTERS
+            },
+            test_counters: CounterBlock {
+                functions: Count { safe: 2, unsafe_: 1 },
+                exprs: Count { safe: 2, unsafe_: 3 },
+                ..DEFAULT_COUNTERS
+            },
+            ..DEFAULT_METRICS
+        };
+        let actual =
+            find_unsafe_in_string(file, IncludeTests::Separately).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn raw_pointer_derefs() {
+        let file = "
+            pub fn f(values: &[u32], value: &u32, raw: *const u32) -> u32 {
+                let pointer = values.as_ptr();
+                let typed: *mut u32 = std::ptr::null_mut();
+                let sum = unsafe {
+                    *pointer + *values.as_ptr().add(1) + *raw + *typed
+                };
+                let not_a_pointer = *value;
+                unsafe { *(value as *const u32) + *value }
+            }
+            pub fn g(pointer: &u32) -> u32 {
+                unsafe { *pointer }
+            }
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::No).unwrap();
+        // `*value` is a dereference of a reference, and `*pointer` in `g`
+        // does not use the binding of `f`.
+        assert_eq!(actual.raw_pointer_derefs, 5);
+    }
+
+    #[test]
+    fn latent_macro_unsafe() {
+        let file = "
+            macro_rules! read {
+                ($pointer:expr) => { unsafe { *$pointer } };
+                ($pointer:expr, $offset:expr) => {
+                    unsafe { *$pointer.add($offset) }
+                };
+            }
+            macro_rules! safe {
+                () => { 1 + 1 };
+            }
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::No).unwrap();
+        // The macros are not expanded, so their bodies are not counted as
+        // unsafe usage.
+        assert_eq!(
+            actual,
+            RsFileMetrics {
+                latent_macro_unsafe: 2,
+                ..DEFAULT_METRICS
+            }
+        );
+    }
+
+    #[test]
+    fn exported_symbols() {
+        let file = "
+            #[no_mangle]
+            pub extern \"C\" fn first() {}
+            #[unsafe(no_mangle)]
+            pub extern \"C\" fn second() {}
+            #[export_name = \"third\"]
+            pub extern \"C\" fn renamed() {}
+            #[unsafe(export_name = \"fourth\")]
+            pub static FOURTH: u32 = 4;
+            pub struct S;
+            impl S {
+                #[no_mangle]
+                pub extern \"C\" fn fifth() {}
+                pub fn not_exported() {}
+            }
+            pub fn not_exported() {}
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::No).unwrap();
+        assert_eq!(actual.exported_symbols, 5);
+        // The exported functions are counted as unsafe, in both attribute
+        // forms.
+        assert_eq!(actual.counters.functions, Count { safe: 1, unsafe_: 3 });
+    }
+
+    #[test]
+    fn exported_function_unsafe_scope() {
+        let file = "
+            #[no_mangle]
+            pub extern \"C\" fn exported() { f(); }
+            pub fn after() { f(); }
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::No).unwrap();
+        // The unsafe scope of the exported function ends with it.
+        assert_eq!(actual.counters.exprs, Count { safe: 1, unsafe_: 1 });
+    }
+
+    #[test]
+    fn memory_lifecycle_unsafe() {
+        let file = "
+            use std::mem::{ManuallyDrop, MaybeUninit};
+            use std::pin::Pin;
+
+            pub fn f(value: MaybeUninit<u32>, slot: &mut ManuallyDrop<u32>) {
+                let pinned = unsafe { Pin::new_unchecked(&mut 1) };
+                let a = unsafe { value.assume_init() };
+                let b = unsafe { MaybeUninit::<u32>::assume_init(value) };
+                unsafe { ManuallyDrop::drop(slot) };
+                unsafe { std::ptr::drop_in_place(slot) };
+                unsafe { reclaim(slot) };
+                let not_unsafe = Pin::new(&mut 1);
+            }
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::No).unwrap();
+        // The calls outside of `unsafe` code are not counted.
+        assert_eq!(actual.memory_lifecycle_unsafe, 5);
+
+        let with_functions = find_unsafe_in_string_with_functions(
+            file,
+            IncludeTests::No,
+            &[],
+            &[String::from("reclaim"), String::from("Pin::new")],
+        )
+        .unwrap();
+        assert_eq!(with_functions.memory_lifecycle_unsafe, 1);
+    }
+
+    #[test]
+    fn static_mut() {
+        let file = "
+            pub fn f() -> u32 {
+                unsafe {
+                    COUNTER += 1;
+                    state::FLAG = COUNTER > 1;
+                    COUNTER
+                }
+            }
+            static mut COUNTER: u32 = 0;
+            static SHARED: u32 = 0;
+            mod state {
+                pub static mut FLAG: bool = false;
+            }
+            #[cfg(test)]
+            mod tests {
+                static mut TEST_COUNTER: u32 = 0;
+                #[test]
+                fn t() { unsafe { super::COUNTER = TEST_COUNTER; } }
+            }
+        ";
+        let actual =
+            find_unsafe_in_string(file, IncludeTests::Separately).unwrap();
+        // `COUNTER` is used before it is declared, and `SHARED` is not
+        // mutable. The test code is left out when counted separately.
+        assert_eq!(actual.static_mut_definitions, 2);
+        assert_eq!(actual.static_mut_accesses, 4);
+    }
+
+    #[test]
+    fn allowed_unsafe() {
+        let file = "
+            #![deny(unsafe_code)]
+            pub fn f(p: *const u8) -> u8 { unsafe { *p } }
+            #[allow(unsafe_code)]
+            mod ffi {
+                pub unsafe fn g() {}
+                pub fn h() -> u8 { unsafe { g(); 0 } }
+                #[warn(unsafe_code)]
+                pub unsafe fn i() {}
+            }
+            struct S;
+            #[expect(unsafe_code)]
+            unsafe impl Send for S {}
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::No).unwrap();
+        // `g`, the call of `g` and the impl are under an explicit allow, the
+        // dereference and `i` are not.
+        assert_eq!(actual.counters.unsafe_count(), 5);
+        assert_eq!(actual.allowed_unsafe, 3);
+    }
+
+    #[test]
+    fn unsafe_trait_usage() {
+        let file = "
+            pub unsafe trait Zeroable: Copy {}
+            pub trait Shared: Send + Sync {}
+            pub fn spawn<F>(f: F) where F: FnOnce() + std::marker::Send {}
+            pub fn zeroed<T: Zeroable>() -> Box<dyn Zeroable> { todo!() }
+            pub fn boxed() -> impl Sync { 0 }
+            unsafe impl Send for Handle {}
+            struct Handle;
+            #[cfg(test)]
+            mod tests {
+                fn t<T: Send>() {}
+            }
+        ";
+        let actual =
+            find_unsafe_in_string(file, IncludeTests::Separately).unwrap();
+        // The supertraits of `Shared`, the `where` clause, the bound and the
+        // trait object of `Zeroable`, and the `impl Sync`. The `unsafe impl`
+        // is not a reference in a bound, and the test code is left out when
+        // counted separately.
+        assert_eq!(actual.unsafe_trait_usage, 6);
+    }
+
+    #[test]
+    fn drop_unsafe() {
+        let file = "
+            pub struct Buffer(*mut u8);
+            impl Drop for Buffer {
+                fn drop(&mut self) {
+                    unsafe { free(self.0) }
+                }
+            }
+            impl std::ops::Drop for Handle {
+                fn drop(&mut self) {
+                    let _ = 1;
+                }
+            }
+            impl Buffer {
+                pub unsafe fn get(&self) -> u8 { *self.0 }
+            }
+            pub fn free(p: *mut u8) {
+                unsafe { std::ptr::drop_in_place(p) }
+            }
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::Yes).unwrap();
+        // The call and the field access in the unsafe block of `drop`. The
+        // unsafe method and the unsafe block outside of the `Drop` impls are
+        // counted in `counters` only.
+        assert_eq!(actual.drop_unsafe, 2);
+        assert_eq!(actual.counters.exprs.unsafe_, 5);
+        assert_eq!(actual.counters.methods.unsafe_, 1);
+    }
+
+    #[test]
+    fn nightly_features() {
+        let file = "
+            #![feature(never_type, allocator_api)]
+            #![cfg_attr(docsrs, feature(doc_cfg))]
+            #![cfg_attr(all(feature = \"nightly\", test), feature(test))]
+            #![cfg_attr(feature = \"std\", allow(dead_code))]
+            #[feature(ignored)]
+            pub fn f() {}
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::Yes).unwrap();
+        // The `feature` condition of a `cfg_attr` is not a feature of the
+        // toolchain, and `feature` is only a crate attribute.
+        assert_eq!(
+            actual.nightly_features,
+            vec!["never_type", "allocator_api", "doc_cfg", "test"]
+        );
+    }
+
+    #[test]
+    fn ffi_callbacks() {
+        let file = "
+            extern \"C\" {
+                fn register(callback: Option<extern \"C\" fn(i32)>);
+                fn call(data: *mut u8, callback: extern \"C\" fn(i32));
+            }
+            extern \"C\" fn on_event(code: i32) {}
+            fn on_rust_event(code: i32) {}
+            struct Handler;
+            impl Handler {
+                extern \"C\" fn on_data(code: i32) {}
+            }
+            pub fn f(data: *mut u8) {
+                unsafe {
+                    register(Some(on_event));
+                    call(data, Handler::on_data);
+                    call(data, (on_rust_event as extern \"C\" fn(i32)));
+                    register(None);
+                }
+                let _ = Some(on_event);
+            }
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::Yes).unwrap();
+        // The call outside of the unsafe block is left out.
+        assert_eq!(actual.ffi_callbacks, 3);
+    }
+
+    #[test]
+    fn layout_dependent_transmutes() {
+        let file = "
+            #[repr(C)]
+            struct Header {
+                len: u32,
+            }
+            #[repr(u8)]
+            enum Kind {
+                A,
+            }
+            struct Plain(u32);
+            pub fn f(bytes: [u8; 4], kind: u8) {
+                unsafe {
+                    mem::transmute::<[u8; 4], Header>(bytes);
+                    transmute::<*const u8, *const Header>(bytes.as_ptr());
+                    let kind: Kind = mem::transmute(kind);
+                    transmute::<[u8; 4], Plain>(bytes);
+                    transmute::<[u8; 4], u32>(bytes);
+                }
+                let header: Header = unsafe { transmute(bytes) };
+                let plain: Plain = unsafe { transmute(bytes) };
+                let _ = mem::transmute::<[u8; 4], Header>;
+            }
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::Yes).unwrap();
+        // `Plain` has no `#[repr]`, and the function not called is left out.
+        assert_eq!(actual.layout_dependent_transmutes, 4);
+    }
+
+    #[test]
+    fn out_dir_includes() {
+        let file = "
+            include!(concat!(env!(\"OUT_DIR\"), \"/bindings.rs\"));
+            include!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/src/a.rs\"));
+            include!(\"b.rs\");
+            pub fn f() -> &'static str {
+                include!(concat!(env!(\"OUT_DIR\"), \"/gen/\", \"c.rs\"))
+            }
+        ";
+        let actual = find_unsafe_in_string(file, IncludeTests::Yes).unwrap();
+        assert_eq!(actual.out_dir_includes, vec!["bindings.rs", "gen/c.rs"]);
+    }
+
+    #[test]
+    fn module_counters() {
+        let file = "
+            pub fn f() { unsafe { f(); } }
+            mod a {
+                pub unsafe fn f() { f(); }
+                mod b {
+                    pub fn f() { unsafe { f(); } }
+                }
+            }
+            mod c;
+            #[cfg(test)]
+            mod tests {
+                pub fn f() { unsafe { f(); } }
+            }
+        ";
+        let actual =
+            find_unsafe_in_string(file, IncludeTests::Separately).unwrap();
+        // The counts of `a` include the ones of `a::b`, the content of `c` is
+        // in another file and the test module is counted apart.
+        let expected: BTreeMap<String, CounterBlock> = vec![
+            (
+                String::from("a"),
+                CounterBlock {
+                    functions: Count { safe: 1, unsafe_: 1 },
+                    exprs: Count { safe: 0, unsafe_: 2 },
+                    ..DEFAULT_COUNTERS
+                },
+            ),
+            (
+                String::from("a::b"),
+                CounterBlock {
+                    functions: Count { safe: 1, unsafe_: 0 },
+                    exprs: Count { safe: 0, unsafe_: 1 },
+                    ..DEFAULT_COUNTERS
+                },
+            ),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(actual.module_counters, expected);
+        assert_eq!(actual.counters.functions, Count { safe: 2, unsafe_: 1 });
+        assert_eq!(actual.counters.exprs, Count { safe: 0, unsafe_: 3 });
+    }
+
+    #[test]
+    fn unsafe_spans() {
+        let file = "pub fn f() {
+    unsafe { f(); }
+}
+unsafe impl Send for S {}
+impl S {
+    pub unsafe fn g() {}
+}
+#[cfg(test)]
+mod tests {
+    fn t() { unsafe {} }
+}
+";
+        let span = |kind, line_start, column_start, line_end, column_end| {
+            UnsafeSpan { kind, line_start, column_start, line_end, column_end }
+        };
+        let actual =
+            find_unsafe_spans_in_string(file, IncludeTests::Separately).unwrap();
+        assert_eq!(
+            actual,
+            vec![
+                span(UnsafeKind::Block, 2, 4, 2, 19),
+                span(UnsafeKind::Impl, 4, 0, 4, 25),
+                span(UnsafeKind::Method, 6, 8, 6, 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsafe_block_kinds() {
+        let file = "pub fn f(x: u32) -> f32 {
+    unsafe { std::mem::transmute(x) }
+}
+pub fn g() {
+    unsafe { std::arch::asm!(\"nop\") }
+    unsafe { f(transmute_copy(&0)); asm!(\"nop\"); }
+}
+";
+        let kinds = find_unsafe_spans_in_string(file, IncludeTests::Yes)
+            .unwrap()
+            .into_iter()
+            .map(|unsafe_span| unsafe_span.kind)
+            .collect::<Vec<UnsafeKind>>();
+        assert_eq!(
+            kinds,
+            vec![UnsafeKind::Transmute, UnsafeKind::Asm, UnsafeKind::Asm]
+        );
+    }
 }
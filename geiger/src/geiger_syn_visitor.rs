@@ -1,9 +1,50 @@
 use super::{
-    file_forbids_unsafe, has_unsafe_attributes, is_test_fn, is_test_mod,
-    IncludeTests, RsFileMetrics,
+    allows_unsafe_code, file_forbids_unsafe, file_nightly_features,
+    has_export_attribute, has_unsafe_attributes, is_test_fn, is_test_mod,
+    IncludeTests, RsFileMetrics, UnsafeKind, UnsafeSpan, STD_UNSAFE_TRAITS,
 };
 
-use syn::{visit, Expr, ItemFn, ItemImpl, ItemMod, ItemTrait, ImplItemFn, ExprUnsafe};
+use cargo_geiger_serde::CounterBlock;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use std::collections::HashSet;
+use syn::punctuated::Punctuated;
+use syn::visit::Visit;
+use syn::{
+    visit, Attribute, Expr, ExprCall, ExprMethodCall, ExprPath, ExprUnary,
+    ExprUnsafe, FnArg, GenericArgument, Ident, ImplItemFn, ItemEnum, ItemFn,
+    ItemImpl, ItemMacro, ItemMod, ItemStatic, ItemStruct, ItemTrait, ItemUnion,
+    Lit, LitStr, Local, Macro, Pat, PathArguments, Signature, StaticMutability,
+    Token, TraitBound, Type, TypePath, UnOp,
+};
+
+/// Methods returning a raw pointer, either from a slice or a string, or from
+/// another raw pointer.
+const RAW_POINTER_METHODS: &[&str] = &[
+    "add",
+    "as_mut_ptr",
+    "as_ptr",
+    "byte_add",
+    "byte_offset",
+    "byte_sub",
+    "cast",
+    "cast_const",
+    "cast_mut",
+    "offset",
+    "sub",
+    "wrapping_add",
+    "wrapping_offset",
+    "wrapping_sub",
+];
+
+/// Functions and macros of `std::ptr` returning a raw pointer.
+const RAW_POINTER_FUNCTIONS: &[&str] =
+    &["addr_of", "addr_of_mut", "null", "null_mut"];
+
+/// Functions of `std::mem` reinterpreting the bits of a value as another type.
+const TRANSMUTE_FUNCTIONS: &[&str] = &["transmute", "transmute_copy"];
+
+/// Macros of `std::arch` for inline assembly.
+const ASM_MACROS: &[&str] = &["asm", "global_asm", "naked_asm"];
 
 pub struct GeigerSynVisitor {
     /// Count unsafe usage inside tests
@@ -12,6 +53,17 @@ pub struct GeigerSynVisitor {
     /// The resulting data from a single file scan.
     pub metrics: RsFileMetrics,
 
+    /// The locations of the `unsafe` code of the file.
+    pub unsafe_spans: Vec<UnsafeSpan>,
+
+    /// Names of the macros whose invocations are counted as unsafe
+    /// expressions, since they expand to `unsafe` code.
+    unsafe_macros: HashSet<String>,
+
+    /// Functions whose calls in unsafe code are counted as memory lifecycle
+    /// unsafe usage, see `MEMORY_LIFECYCLE_FUNCTIONS`.
+    memory_lifecycle_functions: HashSet<String>,
+
     /// The number of nested unsafe scopes that the GeigerSynVisitor are
     /// currently in. For example, if the visitor is inside an unsafe function
     /// and inside an unnecessary unsafe block inside that function, then this
@@ -20,14 +72,82 @@ pub struct GeigerSynVisitor {
     /// This is needed since unsafe scopes can be nested and we need to know
     /// when we leave the outmost unsafe scope and get back into a safe scope.
     unsafe_scopes: u32,
+
+    /// The number of nested `#[cfg(test)]` modules and `#[test]` functions
+    /// that the GeigerSynVisitor is currently in, the unsafe usage inside them
+    /// is counted in `test_counters` with `IncludeTests::Separately`. The
+    /// other metrics, e.g. `raw_pointer_derefs`, and the unsafe spans leave
+    /// out the code inside them.
+    test_scopes: u32,
+
+    /// Names of the parameters and local variables of the function being
+    /// visited which are raw pointers.
+    raw_pointer_bindings: HashSet<String>,
+
+    /// Names of the nested inline modules being visited.
+    module_path: Vec<String>,
+
+    /// Names of the `static mut` declared anywhere in the file, collected
+    /// before visiting it since a `static mut` can be used before it is
+    /// declared.
+    static_mut_names: HashSet<String>,
+
+    /// Names of the unsafe traits whose references in bounds are counted,
+    /// the ones of the standard library and the ones declared anywhere in
+    /// the file.
+    unsafe_trait_names: HashSet<String>,
+
+    /// Names of the functions with an ABI declared anywhere in the file, see
+    /// `RsFileMetrics::ffi_callbacks`.
+    extern_fn_names: HashSet<String>,
+
+    /// Names of the structs, enums and unions declared anywhere in the file
+    /// with a `#[repr(...)]` attribute, see
+    /// `RsFileMetrics::layout_dependent_transmutes`.
+    repr_type_names: HashSet<String>,
+
+    /// Whether the expression being visited initializes a `let` binding whose
+    /// type mentions one of `repr_type_names`.
+    in_repr_typed_let: bool,
+
+    /// Whether the innermost `unsafe_code` lint attribute of the code being
+    /// visited is `allow` or `expect`, see `RsFileMetrics::allowed_unsafe`.
+    unsafe_code_allowed: bool,
+
+    /// Whether the code being visited is inside an `impl Drop for` block, see
+    /// `RsFileMetrics::drop_unsafe`.
+    in_drop_impl: bool,
 }
 
 impl GeigerSynVisitor {
-    pub fn new(include_tests: IncludeTests) -> Self {
+    pub fn new(
+        include_tests: IncludeTests,
+        unsafe_macros: &[String],
+        memory_lifecycle_functions: &[String],
+    ) -> Self {
         GeigerSynVisitor {
             include_tests,
             metrics: Default::default(),
+            unsafe_spans: Vec::new(),
+            unsafe_macros: unsafe_macros.iter().cloned().collect(),
+            memory_lifecycle_functions: memory_lifecycle_functions
+                .iter()
+                .cloned()
+                .collect(),
             unsafe_scopes: 0,
+            test_scopes: 0,
+            raw_pointer_bindings: HashSet::new(),
+            module_path: Vec::new(),
+            static_mut_names: HashSet::new(),
+            unsafe_trait_names: STD_UNSAFE_TRAITS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            unsafe_code_allowed: false,
+            in_drop_impl: false,
+            extern_fn_names: HashSet::new(),
+            repr_type_names: HashSet::new(),
+            in_repr_typed_let: false,
         }
     }
 
@@ -38,29 +158,327 @@ impl GeigerSynVisitor {
     pub fn exit_unsafe_scope(&mut self) {
         self.unsafe_scopes -= 1;
     }
+
+    /// The counters of the code being visited, test code being counted apart
+    /// when scanning with `IncludeTests::Separately`.
+    fn counters(&mut self) -> &mut CounterBlock {
+        if self.test_scopes > 0 {
+            &mut self.metrics.test_counters
+        } else {
+            &mut self.metrics.counters
+        }
+    }
+
+    /// Count an exported symbol for an item with `#[no_mangle]` or
+    /// `#[export_name]`.
+    fn count_exported_symbol(&mut self, attrs: &[Attribute]) {
+        if self.test_scopes == 0 && has_export_attribute(attrs) {
+            self.metrics.exported_symbols += 1;
+        }
+    }
+
+    /// Count unsafe usage under an explicit `#[allow(unsafe_code)]`.
+    fn count_allowed_unsafe(&mut self, is_unsafe: bool) {
+        if is_unsafe && self.unsafe_code_allowed && self.test_scopes == 0 {
+            self.metrics.allowed_unsafe += 1;
+        }
+    }
+
+    /// Count unsafe usage inside an `impl Drop for` block.
+    fn count_drop_unsafe(&mut self, is_unsafe: bool) {
+        if is_unsafe && self.in_drop_impl && self.test_scopes == 0 {
+            self.metrics.drop_unsafe += 1;
+        }
+    }
+
+    /// Visit code with the `unsafe_code` lint level given by its attributes,
+    /// restoring the level of the enclosing code afterwards.
+    fn with_lint_attributes<F>(&mut self, attrs: &[Attribute], visit_fn: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let enclosing_unsafe_code_allowed = self.unsafe_code_allowed;
+        if let Some(allowed) = allows_unsafe_code(attrs) {
+            self.unsafe_code_allowed = allowed;
+        }
+        visit_fn(self);
+        self.unsafe_code_allowed = enclosing_unsafe_code_allowed;
+    }
+
+    /// Record the location of `unsafe` code.
+    fn record_unsafe_span(&mut self, kind: UnsafeKind, start: Span, end: Span) {
+        if self.test_scopes == 0 {
+            let (start, end) = (start.start(), end.end());
+            self.unsafe_spans.push(UnsafeSpan {
+                kind,
+                line_start: start.line,
+                column_start: start.column,
+                line_end: end.line,
+                column_end: end.column,
+            });
+        }
+    }
+
+    /// Visit an item with the given visit function, unless the item is test
+    /// code which is not included.
+    fn visit_test_item<F>(&mut self, is_test_item: bool, visit_fn: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        match (is_test_item, self.include_tests) {
+            (false, _) | (true, IncludeTests::Yes) => visit_fn(self),
+            (true, IncludeTests::No) => {}
+            (true, IncludeTests::Separately) => {
+                self.test_scopes += 1;
+                visit_fn(self);
+                self.test_scopes -= 1;
+            }
+        }
+    }
+
+    /// Whether the expression is a raw pointer as far as the syntax tells:
+    /// a cast to a raw pointer type, a binding of a raw pointer, or the result
+    /// of a function or method returning a raw pointer.
+    fn is_raw_pointer_expr(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Paren(expr_paren) => {
+                self.is_raw_pointer_expr(&expr_paren.expr)
+            }
+            Expr::Cast(expr_cast) => matches!(*expr_cast.ty, Type::Ptr(_)),
+            Expr::Path(expr_path) => {
+                expr_path.path.get_ident().map_or(false, |ident| {
+                    self.raw_pointer_bindings.contains(&ident.to_string())
+                })
+            }
+            Expr::MethodCall(expr_method_call) => RAW_POINTER_METHODS
+                .contains(&expr_method_call.method.to_string().as_str()),
+            Expr::Call(expr_call) => match &*expr_call.func {
+                Expr::Path(expr_path) => {
+                    is_raw_pointer_function(&expr_path.path)
+                }
+                _ => false,
+            },
+            Expr::Macro(expr_macro) => {
+                is_raw_pointer_function(&expr_macro.mac.path)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the expression is coerced to an `extern` function pointer as
+    /// far as the syntax tells: a path to a function with an ABI declared in
+    /// the file, matched by its last segment, or a cast to an `extern`
+    /// function pointer type.
+    fn is_ffi_callback_expr(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Paren(expr_paren) => {
+                self.is_ffi_callback_expr(&expr_paren.expr)
+            }
+            Expr::Cast(expr_cast) => matches!(
+                &*expr_cast.ty,
+                Type::BareFn(type_bare_fn) if type_bare_fn.abi.is_some()
+            ),
+            Expr::Path(expr_path) => {
+                expr_path.path.segments.last().map_or(false, |segment| {
+                    self.extern_fn_names.contains(&segment.ident.to_string())
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// Count the arguments of a call in unsafe code which are FFI callbacks.
+    fn count_ffi_callbacks<'a, I>(&mut self, args: I)
+    where
+        I: IntoIterator<Item = &'a Expr>,
+    {
+        if self.unsafe_scopes > 0 && self.test_scopes == 0 {
+            let ffi_callbacks = args
+                .into_iter()
+                .filter(|arg| self.is_ffi_callback_expr(arg))
+                .count();
+            self.metrics.ffi_callbacks += ffi_callbacks as u64;
+        }
+    }
+
+    /// Whether the type mentions a type declared with `#[repr(...)]` in the
+    /// file, matched by the last segment of its path, e.g. `*const Header`.
+    fn mentions_repr_type(&self, ty: &Type) -> bool {
+        let mut type_path_names = TypePathNames::default();
+        type_path_names.visit_type(ty);
+        type_path_names
+            .0
+            .iter()
+            .any(|name| self.repr_type_names.contains(name))
+    }
+
+    /// Count a call of `transmute` in unsafe code which depends on the layout
+    /// of a type declared with `#[repr(...)]`.
+    fn count_layout_dependent_transmute(&mut self, func: &Expr) {
+        if self.unsafe_scopes == 0 || self.test_scopes > 0 {
+            return;
+        }
+        let segment = match func {
+            Expr::Path(expr_path) => match expr_path.path.segments.last() {
+                Some(segment) => segment,
+                None => return,
+            },
+            _ => return,
+        };
+        if !TRANSMUTE_FUNCTIONS.contains(&segment.ident.to_string().as_str()) {
+            return;
+        }
+        let turbofish_mentions_repr_type = match &segment.arguments {
+            PathArguments::AngleBracketed(arguments) => {
+                arguments.args.iter().any(|argument| {
+                    matches!(
+                        argument,
+                        GenericArgument::Type(ty) if self.mentions_repr_type(ty)
+                    )
+                })
+            }
+            _ => false,
+        };
+        if turbofish_mentions_repr_type || self.in_repr_typed_let {
+            self.metrics.layout_dependent_transmutes += 1;
+        }
+    }
+
+    /// Visit a function body with the raw pointer bindings of its parameters,
+    /// restoring the bindings of the enclosing function afterwards.
+    fn with_raw_pointer_parameters<F>(&mut self, sig: &Signature, visit_fn: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let enclosing_raw_pointer_bindings =
+            std::mem::take(&mut self.raw_pointer_bindings);
+        for input in &sig.inputs {
+            if let FnArg::Typed(pat_type) = input {
+                if let (Pat::Ident(pat_ident), Type::Ptr(_)) =
+                    (&*pat_type.pat, &*pat_type.ty)
+                {
+                    self.raw_pointer_bindings
+                        .insert(pat_ident.ident.to_string());
+                }
+            }
+        }
+        visit_fn(self);
+        self.raw_pointer_bindings = enclosing_raw_pointer_bindings;
+    }
+
+    /// Visit an inline module, counting its unsafe usage both in
+    /// `module_counters` and in the counters of the enclosing code.
+    fn with_module<F>(&mut self, ident: &Ident, visit_fn: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        if self.test_scopes > 0 {
+            // Counted in `test_counters` only.
+            visit_fn(self);
+            return;
+        }
+        self.module_path.push(ident.to_string());
+        let enclosing_counters = std::mem::take(&mut self.metrics.counters);
+        visit_fn(self);
+        let module_counters =
+            std::mem::replace(&mut self.metrics.counters, enclosing_counters);
+        self.metrics.counters += module_counters.clone();
+        *self
+            .metrics
+            .module_counters
+            .entry(self.module_path.join("::"))
+            .or_default() += module_counters;
+        self.module_path.pop();
+    }
+
+    /// Count a call of a memory lifecycle function in unsafe code.
+    fn count_memory_lifecycle_call(&mut self, is_memory_lifecycle_call: bool) {
+        if is_memory_lifecycle_call
+            && self.unsafe_scopes > 0
+            && self.test_scopes == 0
+        {
+            self.metrics.memory_lifecycle_unsafe += 1;
+        }
+    }
+
+    /// A function is matched either by the last segment of the path, or by
+    /// its last two segments, e.g. `Pin::new_unchecked`.
+    fn is_memory_lifecycle_function(&self, path: &syn::Path) -> bool {
+        let mut segments = path.segments.iter().rev();
+        let function = match segments.next() {
+            Some(segment) => segment.ident.to_string(),
+            None => return false,
+        };
+        self.memory_lifecycle_functions.contains(&function)
+            || segments.next().map_or(false, |segment| {
+                self.memory_lifecycle_functions
+                    .contains(&format!("{}::{}", segment.ident, function))
+            })
+    }
+
+    /// Macros are matched by the last segment of their path, so both
+    /// `thread_local!` and `std::thread_local!` are recognized.
+    fn is_unsafe_macro(&self, mac: &Macro) -> bool {
+        mac.path.segments.last().map_or(false, |segment| {
+            self.unsafe_macros.contains(&segment.ident.to_string())
+        })
+    }
 }
 
 impl<'ast> visit::Visit<'ast> for GeigerSynVisitor {
     fn visit_file(&mut self, i: &'ast syn::File) {
         self.metrics.forbids_unsafe = file_forbids_unsafe(i);
+        self.metrics.nightly_features = file_nightly_features(i);
+        self.unsafe_code_allowed =
+            allows_unsafe_code(&i.attrs).unwrap_or(false);
+        let mut static_mut_names = StaticMutNames::default();
+        static_mut_names.visit_file(i);
+        self.static_mut_names = static_mut_names.0;
+        let mut unsafe_trait_names = UnsafeTraitNames::default();
+        unsafe_trait_names.visit_file(i);
+        self.unsafe_trait_names.extend(unsafe_trait_names.0);
+        let mut extern_fn_names = ExternFnNames::default();
+        extern_fn_names.visit_file(i);
+        self.extern_fn_names = extern_fn_names.0;
+        let mut repr_type_names = ReprTypeNames::default();
+        repr_type_names.visit_file(i);
+        self.repr_type_names = repr_type_names.0;
         visit::visit_file(self, i);
     }
 
     /// Free-standing functions
     fn visit_item_fn(&mut self, item_fn: &ItemFn) {
-        if IncludeTests::No == self.include_tests && is_test_fn(item_fn) {
-            return;
-        }
-        let unsafe_fn =
-            item_fn.sig.unsafety.is_some() || has_unsafe_attributes(item_fn);
-        if unsafe_fn {
-            self.enter_unsafe_scope()
-        }
-        self.metrics.counters.functions.count(unsafe_fn);
-        visit::visit_item_fn(self, item_fn);
-        if item_fn.sig.unsafety.is_some() {
-            self.exit_unsafe_scope()
-        }
+        self.visit_test_item(is_test_fn(item_fn), |visitor| {
+            visitor.with_lint_attributes(&item_fn.attrs, |visitor| {
+                let unsafe_fn = item_fn.sig.unsafety.is_some()
+                    || has_unsafe_attributes(item_fn);
+                if unsafe_fn {
+                    visitor.enter_unsafe_scope()
+                }
+                visitor.counters().functions.count(unsafe_fn);
+                visitor.count_allowed_unsafe(unsafe_fn);
+                visitor.count_drop_unsafe(unsafe_fn);
+                if unsafe_fn {
+                    visitor.record_unsafe_span(
+                        UnsafeKind::Function,
+                        item_fn
+                            .sig
+                            .unsafety
+                            .map_or(item_fn.sig.fn_token.span, |unsafety| {
+                                unsafety.span
+                            }),
+                        item_fn.block.brace_token.span.close(),
+                    );
+                }
+                visitor.count_exported_symbol(&item_fn.attrs);
+                visitor.with_raw_pointer_parameters(&item_fn.sig, |visitor| {
+                    visit::visit_item_fn(visitor, item_fn)
+                });
+                if unsafe_fn {
+                    visitor.exit_unsafe_scope()
+                }
+            });
+        });
     }
 
     fn visit_expr(&mut self, i: &Expr) {
@@ -69,57 +487,445 @@ impl<'ast> visit::Visit<'ast> for GeigerSynVisitor {
             Expr::Lit(..) | Expr::Path(..) | Expr::Unsafe(..) => {
                 // Do not count.
             }
+            Expr::Macro(expr_macro)
+                if self.is_unsafe_macro(&expr_macro.mac) =>
+            {
+                // Counted as unsafe by `visit_macro`.
+            }
             _ => {
-                self.metrics.counters.exprs.count(self.unsafe_scopes > 0);
+                let is_unsafe = self.unsafe_scopes > 0;
+                self.counters().exprs.count(is_unsafe);
+                self.count_allowed_unsafe(is_unsafe);
+                self.count_drop_unsafe(is_unsafe);
             }
         }
         visit::visit_expr(self, i);
     }
 
+    fn visit_expr_unary(&mut self, i: &ExprUnary) {
+        if let UnOp::Deref(_) = i.op {
+            if self.unsafe_scopes > 0
+                && self.test_scopes == 0
+                && self.is_raw_pointer_expr(&i.expr)
+            {
+                self.metrics.raw_pointer_derefs += 1;
+            }
+        }
+        visit::visit_expr_unary(self, i);
+    }
+
+    /// Bindings of raw pointers, either with an explicit raw pointer type or
+    /// initialized with a raw pointer.
+    fn visit_local(&mut self, i: &Local) {
+        let binding = match &i.pat {
+            Pat::Type(pat_type) => match (&*pat_type.pat, &*pat_type.ty) {
+                (Pat::Ident(pat_ident), ty) => {
+                    Some((&pat_ident.ident, matches!(ty, Type::Ptr(_))))
+                }
+                _ => None,
+            },
+            Pat::Ident(pat_ident) => Some((
+                &pat_ident.ident,
+                i.init.as_ref().map_or(false, |local_init| {
+                    self.is_raw_pointer_expr(&local_init.expr)
+                }),
+            )),
+            _ => None,
+        };
+        // A shadowing binding which is not a raw pointer hides the previous
+        // one.
+        if let Some((ident, is_raw_pointer)) = binding {
+            if is_raw_pointer {
+                self.raw_pointer_bindings.insert(ident.to_string());
+            } else {
+                self.raw_pointer_bindings.remove(&ident.to_string());
+            }
+        }
+        let in_repr_typed_let = self.in_repr_typed_let;
+        self.in_repr_typed_let = matches!(
+            &i.pat,
+            Pat::Type(pat_type) if self.mentions_repr_type(&pat_type.ty)
+        );
+        visit::visit_local(self, i);
+        self.in_repr_typed_let = in_repr_typed_let;
+    }
+
+    fn visit_expr_call(&mut self, i: &ExprCall) {
+        let is_memory_lifecycle_call = match &*i.func {
+            Expr::Path(expr_path) => {
+                self.is_memory_lifecycle_function(&expr_path.path)
+            }
+            _ => false,
+        };
+        self.count_memory_lifecycle_call(is_memory_lifecycle_call);
+        self.count_ffi_callbacks(&i.args);
+        self.count_layout_dependent_transmute(&i.func);
+        visit::visit_expr_call(self, i);
+    }
+
+    /// Methods are matched by their name only, the type of the receiver is
+    /// not known.
+    fn visit_expr_method_call(&mut self, i: &ExprMethodCall) {
+        let is_memory_lifecycle_call = self
+            .memory_lifecycle_functions
+            .contains(&i.method.to_string());
+        self.count_memory_lifecycle_call(is_memory_lifecycle_call);
+        self.count_ffi_callbacks(&i.args);
+        visit::visit_expr_method_call(self, i);
+    }
+
     fn visit_expr_unsafe(&mut self, i: &ExprUnsafe) {
-        self.enter_unsafe_scope();
-        visit::visit_expr_unsafe(self, i);
-        self.exit_unsafe_scope();
+        let mut unsafe_block_content = UnsafeBlockContent::default();
+        unsafe_block_content.visit_expr_unsafe(i);
+        self.record_unsafe_span(
+            unsafe_block_content.kind(),
+            i.unsafe_token.span,
+            i.block.brace_token.span.close(),
+        );
+        self.with_lint_attributes(&i.attrs, |visitor| {
+            visitor.enter_unsafe_scope();
+            visit::visit_expr_unsafe(visitor, i);
+            visitor.exit_unsafe_scope();
+        });
     }
 
+    /// Only inline modules are counted in `module_counters`, the content of
+    /// `mod a;` is in another file.
     fn visit_item_mod(&mut self, i: &ItemMod) {
-        if IncludeTests::No == self.include_tests && is_test_mod(i) {
-            return;
+        self.visit_test_item(is_test_mod(i), |visitor| {
+            visitor.with_lint_attributes(&i.attrs, |visitor| {
+                if i.content.is_some() {
+                    visitor.with_module(&i.ident, |visitor| {
+                        visit::visit_item_mod(visitor, i)
+                    });
+                } else {
+                    visit::visit_item_mod(visitor, i);
+                }
+            });
+        });
+    }
+
+    fn visit_item_static(&mut self, i: &ItemStatic) {
+        self.count_exported_symbol(&i.attrs);
+        if is_static_mut(i) && self.test_scopes == 0 {
+            self.metrics.static_mut_definitions += 1;
+        }
+        visit::visit_item_static(self, i);
+    }
+
+    fn visit_expr_path(&mut self, i: &ExprPath) {
+        if self.test_scopes == 0
+            && i.path.segments.last().map_or(false, |segment| {
+                self.static_mut_names.contains(&segment.ident.to_string())
+            })
+        {
+            self.metrics.static_mut_accesses += 1;
         }
-        visit::visit_item_mod(self, i);
+        visit::visit_expr_path(self, i);
+    }
+
+    /// Bounds of generic parameters, `where` clauses, supertraits, `impl
+    /// Trait` and `dyn Trait` types.
+    fn visit_trait_bound(&mut self, i: &TraitBound) {
+        if self.test_scopes == 0
+            && i.path.segments.last().map_or(false, |segment| {
+                self.unsafe_trait_names.contains(&segment.ident.to_string())
+            })
+        {
+            self.metrics.unsafe_trait_usage += 1;
+        }
+        visit::visit_trait_bound(self, i);
     }
 
     fn visit_item_impl(&mut self, i: &ItemImpl) {
-        // unsafe trait impl's
-        self.metrics.counters.item_impls.count(i.unsafety.is_some());
-        visit::visit_item_impl(self, i);
+        self.with_lint_attributes(&i.attrs, |visitor| {
+            // unsafe trait impl's
+            visitor.counters().item_impls.count(i.unsafety.is_some());
+            visitor.count_allowed_unsafe(i.unsafety.is_some());
+            visitor.count_drop_unsafe(i.unsafety.is_some());
+            if let Some(unsafety) = i.unsafety {
+                visitor.record_unsafe_span(
+                    UnsafeKind::Impl,
+                    unsafety.span,
+                    i.brace_token.span.close(),
+                );
+            }
+            // Traits are matched by the last segment of their path, so both
+            // `Drop` and `std::ops::Drop` are recognized.
+            let enclosing_in_drop_impl = visitor.in_drop_impl;
+            visitor.in_drop_impl =
+                i.trait_.as_ref().map_or(false, |(_, path, _)| {
+                    path.segments
+                        .last()
+                        .map_or(false, |segment| segment.ident == "Drop")
+                });
+            visit::visit_item_impl(visitor, i);
+            visitor.in_drop_impl = enclosing_in_drop_impl;
+        });
     }
 
     fn visit_item_trait(&mut self, i: &ItemTrait) {
-        // Unsafe traits
-        self.metrics
-            .counters
-            .item_traits
-            .count(i.unsafety.is_some());
-        visit::visit_item_trait(self, i);
+        self.with_lint_attributes(&i.attrs, |visitor| {
+            // Unsafe traits
+            visitor.counters().item_traits.count(i.unsafety.is_some());
+            visitor.count_allowed_unsafe(i.unsafety.is_some());
+            visitor.count_drop_unsafe(i.unsafety.is_some());
+            if let Some(unsafety) = i.unsafety {
+                visitor.record_unsafe_span(
+                    UnsafeKind::Trait,
+                    unsafety.span,
+                    i.brace_token.span.close(),
+                );
+            }
+            visit::visit_item_trait(visitor, i);
+        });
     }
 
     fn visit_impl_item_fn(&mut self, i: &ImplItemFn) {
-        if i.sig.unsafety.is_some() {
-            self.enter_unsafe_scope()
+        self.with_lint_attributes(&i.attrs, |visitor| {
+            if i.sig.unsafety.is_some() {
+                visitor.enter_unsafe_scope()
+            }
+            visitor.counters().methods.count(i.sig.unsafety.is_some());
+            visitor.count_allowed_unsafe(i.sig.unsafety.is_some());
+            visitor.count_drop_unsafe(i.sig.unsafety.is_some());
+            if let Some(unsafety) = i.sig.unsafety {
+                visitor.record_unsafe_span(
+                    UnsafeKind::Method,
+                    unsafety.span,
+                    i.block.brace_token.span.close(),
+                );
+            }
+            visitor.count_exported_symbol(&i.attrs);
+            visitor.with_raw_pointer_parameters(&i.sig, |visitor| {
+                visit::visit_impl_item_fn(visitor, i)
+            });
+            if i.sig.unsafety.is_some() {
+                visitor.exit_unsafe_scope()
+            }
+        });
+    }
+
+    /// Only the invocations of the unsafe macros are counted, the tokens of
+    /// macro invocations are not parsed. The files included from `OUT_DIR`
+    /// are recorded, to be scanned with `--scan-generated-includes`.
+    fn visit_macro(&mut self, i: &Macro) {
+        if self.is_unsafe_macro(i) {
+            self.counters().exprs.count(true);
+            self.count_allowed_unsafe(true);
+            self.count_drop_unsafe(true);
         }
-        self.metrics
-            .counters
-            .methods
-            .count(i.sig.unsafety.is_some());
-        visit::visit_impl_item_fn(self, i);
-        if i.sig.unsafety.is_some() {
-            self.exit_unsafe_scope()
+        if let Some(out_dir_include) = out_dir_include(i) {
+            self.metrics.out_dir_includes.push(out_dir_include);
+        }
+        visit::visit_macro(self, i);
+    }
+
+    /// The bodies of `macro_rules!` definitions are not parsed, the `unsafe`
+    /// tokens inside them are counted as latent unsafe usage instead.
+    fn visit_item_macro(&mut self, i: &ItemMacro) {
+        if i.mac.path.is_ident("macro_rules") && self.test_scopes == 0 {
+            self.metrics.latent_macro_unsafe +=
+                count_unsafe_tokens(i.mac.tokens.clone());
         }
+        visit::visit_item_macro(self, i);
     }
 
-    // TODO: Visit macros.
-    //
     // TODO: Figure out if there are other visit methods that should be
     // implemented here.
 }
+
+/// Collects the names of the `static mut` of a file, see
+/// `GeigerSynVisitor::static_mut_names`.
+#[derive(Default)]
+struct StaticMutNames(HashSet<String>);
+
+impl<'ast> Visit<'ast> for StaticMutNames {
+    fn visit_item_static(&mut self, i: &'ast ItemStatic) {
+        if is_static_mut(i) {
+            self.0.insert(i.ident.to_string());
+        }
+        visit::visit_item_static(self, i);
+    }
+}
+
+/// Whether an unsafe block calls `transmute` or has inline assembly, which
+/// tells its `UnsafeKind`. Functions are matched by the last segment of their
+/// path and macros by their name, like the memory lifecycle functions.
+#[derive(Default)]
+struct UnsafeBlockContent {
+    transmute: bool,
+    asm: bool,
+}
+
+impl UnsafeBlockContent {
+    fn kind(&self) -> UnsafeKind {
+        match (self.asm, self.transmute) {
+            (true, _) => UnsafeKind::Asm,
+            (false, true) => UnsafeKind::Transmute,
+            (false, false) => UnsafeKind::Block,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UnsafeBlockContent {
+    fn visit_expr_call(&mut self, i: &'ast ExprCall) {
+        if let Expr::Path(expr_path) = &*i.func {
+            self.transmute |=
+                expr_path.path.segments.last().map_or(false, |segment| {
+                    TRANSMUTE_FUNCTIONS
+                        .contains(&segment.ident.to_string().as_str())
+                });
+        }
+        visit::visit_expr_call(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &'ast Macro) {
+        self.asm |= i.path.segments.last().map_or(false, |segment| {
+            ASM_MACROS.contains(&segment.ident.to_string().as_str())
+        });
+        visit::visit_macro(self, i);
+    }
+}
+
+/// Collects the names of the functions and methods with an ABI declared in a
+/// file, see `GeigerSynVisitor::extern_fn_names`.
+#[derive(Default)]
+struct ExternFnNames(HashSet<String>);
+
+impl<'ast> Visit<'ast> for ExternFnNames {
+    fn visit_item_fn(&mut self, i: &'ast ItemFn) {
+        if i.sig.abi.is_some() {
+            self.0.insert(i.sig.ident.to_string());
+        }
+        visit::visit_item_fn(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast ImplItemFn) {
+        if i.sig.abi.is_some() {
+            self.0.insert(i.sig.ident.to_string());
+        }
+        visit::visit_impl_item_fn(self, i);
+    }
+}
+
+/// Collects the names of the structs, enums and unions declared with a
+/// `#[repr(...)]` attribute in a file, see
+/// `GeigerSynVisitor::repr_type_names`.
+#[derive(Default)]
+struct ReprTypeNames(HashSet<String>);
+
+impl<'ast> Visit<'ast> for ReprTypeNames {
+    fn visit_item_struct(&mut self, i: &'ast ItemStruct) {
+        if has_repr_attribute(&i.attrs) {
+            self.0.insert(i.ident.to_string());
+        }
+        visit::visit_item_struct(self, i);
+    }
+
+    fn visit_item_enum(&mut self, i: &'ast ItemEnum) {
+        if has_repr_attribute(&i.attrs) {
+            self.0.insert(i.ident.to_string());
+        }
+        visit::visit_item_enum(self, i);
+    }
+
+    fn visit_item_union(&mut self, i: &'ast ItemUnion) {
+        if has_repr_attribute(&i.attrs) {
+            self.0.insert(i.ident.to_string());
+        }
+        visit::visit_item_union(self, i);
+    }
+}
+
+/// Collects the last segments of the paths of a type and of its generic
+/// arguments, e.g. `Box` and `Header` for `Box<Header>`.
+#[derive(Default)]
+struct TypePathNames(HashSet<String>);
+
+impl<'ast> Visit<'ast> for TypePathNames {
+    fn visit_type_path(&mut self, i: &'ast TypePath) {
+        if let Some(segment) = i.path.segments.last() {
+            self.0.insert(segment.ident.to_string());
+        }
+        visit::visit_type_path(self, i);
+    }
+}
+
+/// Collects the names of the unsafe traits declared in a file, see
+/// `GeigerSynVisitor::unsafe_trait_names`.
+#[derive(Default)]
+struct UnsafeTraitNames(HashSet<String>);
+
+impl<'ast> Visit<'ast> for UnsafeTraitNames {
+    fn visit_item_trait(&mut self, i: &'ast ItemTrait) {
+        if i.unsafety.is_some() {
+            self.0.insert(i.ident.to_string());
+        }
+        visit::visit_item_trait(self, i);
+    }
+}
+
+fn has_repr_attribute(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("repr"))
+}
+
+fn is_static_mut(item_static: &ItemStatic) -> bool {
+    matches!(item_static.mutability, StaticMutability::Mut(_))
+}
+
+fn is_raw_pointer_function(path: &syn::Path) -> bool {
+    path.segments.last().map_or(false, |segment| {
+        RAW_POINTER_FUNCTIONS.contains(&segment.ident.to_string().as_str())
+    })
+}
+
+/// The path relative to `OUT_DIR` of an
+/// `include!(concat!(env!("OUT_DIR"), "/bindings.rs"))`, the path being the
+/// concatenation of the string literals following `env!("OUT_DIR")`.
+fn out_dir_include(mac: &Macro) -> Option<String> {
+    if !mac.path.is_ident("include") {
+        return None;
+    }
+    let concat = match mac.parse_body::<Expr>().ok()? {
+        Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("concat") => {
+            expr_macro.mac
+        }
+        _ => return None,
+    };
+    let parts = concat
+        .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+        .ok()?;
+    let mut parts = parts.iter();
+    match parts.next()? {
+        Expr::Macro(expr_macro)
+            if expr_macro.mac.path.is_ident("env")
+                && expr_macro
+                    .mac
+                    .parse_body::<LitStr>()
+                    .map_or(false, |name| name.value() == "OUT_DIR") => {}
+        _ => return None,
+    }
+    let mut path = String::new();
+    for part in parts {
+        match part {
+            Expr::Lit(expr_lit) => match &expr_lit.lit {
+                Lit::Str(lit_str) => path.push_str(&lit_str.value()),
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    Some(path.trim_start_matches('/').to_string())
+}
+
+fn count_unsafe_tokens(tokens: TokenStream) -> u64 {
+    tokens
+        .into_iter()
+        .map(|token_tree| match token_tree {
+            TokenTree::Ident(ident) => u64::from(ident == "unsafe"),
+            TokenTree::Group(group) => count_unsafe_tokens(group.stream()),
+            TokenTree::Punct(_) | TokenTree::Literal(_) => 0,
+        })
+        .sum()
+}
@@ -12,27 +12,189 @@ pub use find::*; // preserve APIs
 mod geiger_syn_visitor;
 
 use cargo_geiger_serde::CounterBlock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
 use std::io;
 use std::path::PathBuf;
 use std::string::FromUtf8Error;
-use syn::{AttrStyle, ItemFn, ItemMod};
+use syn::punctuated::Punctuated;
+use syn::{AttrStyle, Attribute, ItemFn, ItemMod, Meta, MetaList, Token};
+
+/// The version of this crate, whose visitor computes the metrics of the
+/// scanned files.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Macros of the standard library which expand to `unsafe` code that users
+/// don't write themselves.
+pub const STD_UNSAFE_MACROS: &[&str] = &["thread_local"];
+
+/// Unsafe traits of the standard library, whose references in bounds and
+/// trait objects are counted in `RsFileMetrics::unsafe_trait_usage`.
+pub const STD_UNSAFE_TRAITS: &[&str] = &["Send", "Sync"];
+
+/// Functions of the standard library which manage the lifecycle of values in
+/// memory by hand, whose calls in `unsafe` code are counted in
+/// `RsFileMetrics::memory_lifecycle_unsafe`. `Type::function` matches the
+/// calls of a path ending with it, `function` also matches the method calls.
+pub const MEMORY_LIFECYCLE_FUNCTIONS: &[&str] = &[
+    "ManuallyDrop::drop",
+    "ManuallyDrop::take",
+    "Pin::get_unchecked_mut",
+    "Pin::into_inner_unchecked",
+    "Pin::map_unchecked",
+    "Pin::map_unchecked_mut",
+    "Pin::new_unchecked",
+    "assume_init",
+    "assume_init_drop",
+    "assume_init_mut",
+    "assume_init_read",
+    "assume_init_ref",
+    "drop_in_place",
+];
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum IncludeTests {
     Yes,
     No,
+    /// Count unsafe usage inside `#[cfg(test)]` modules and `#[test]`
+    /// functions apart, in `RsFileMetrics::test_counters`.
+    Separately,
 }
 
 /// Scan result for a single `.rs` file.
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct RsFileMetrics {
     /// Metrics storage.
     pub counters: CounterBlock,
 
+    /// Metrics of the code inside `#[cfg(test)]` modules and `#[test]`
+    /// functions, only counted with `IncludeTests::Separately`.
+    pub test_counters: CounterBlock,
+
     /// This file is decorated with `#![forbid(unsafe_code)]`
     pub forbids_unsafe: bool,
+
+    /// Dereferences of raw pointers inside `unsafe` code. Types are not
+    /// resolved, so a dereference is counted when its operand is recognized
+    /// as a raw pointer from the syntax alone, see `is_raw_pointer_expr`.
+    pub raw_pointer_derefs: u64,
+
+    /// `unsafe` tokens inside the `macro_rules!` definitions of this file.
+    /// They are latent unsafe usage, only used where the macros are invoked,
+    /// which is not known without expanding them.
+    pub latent_macro_unsafe: u64,
+
+    /// Functions, methods and statics exported under a symbol name with
+    /// `#[no_mangle]` or `#[export_name]`, which inject global symbols into
+    /// the final binary.
+    pub exported_symbols: u64,
+
+    /// Calls of the memory lifecycle functions, e.g. `MaybeUninit::assume_init`
+    /// or `Pin::new_unchecked`, inside `unsafe` code. They are a stronger
+    /// signal than other unsafe usage for reviewers.
+    pub memory_lifecycle_unsafe: u64,
+
+    /// `static mut` declarations of this file, which edition 2024 steers
+    /// away from.
+    pub static_mut_definitions: u64,
+
+    /// Accesses to the `static mut` declared in this file. Names are not
+    /// resolved, so a path is counted when its last segment is the name of
+    /// one of them, and the accesses from other files are not counted.
+    pub static_mut_accesses: u64,
+
+    /// Unsafe usage counted in `counters` which sits under an explicit
+    /// `#[allow(unsafe_code)]` or `#[expect(unsafe_code)]`, the innermost
+    /// `unsafe_code` lint attribute of the enclosing items, or of the file,
+    /// deciding. The attributes of `mod a;` declarations are in another file
+    /// and not taken into account.
+    pub allowed_unsafe: u64,
+
+    /// References to unsafe traits in bounds, e.g. `T: Send`, `impl Sync` or
+    /// `dyn Send`, which rely on the contract of an unsafe trait. The traits
+    /// are `STD_UNSAFE_TRAITS` and the unsafe traits declared in this file,
+    /// matched by the last segment of their path.
+    pub unsafe_trait_usage: u64,
+
+    /// Unsafe usage counted in `counters` which sits inside an `impl Drop
+    /// for` block, where it can easily lead to a double free or a use after
+    /// free. `Drop` is matched by the last segment of its path.
+    pub drop_unsafe: u64,
+
+    /// Functions passed as arguments of calls in unsafe code which are
+    /// coerced to an `extern` function pointer, the callbacks given to
+    /// foreign code: paths to the functions with an ABI declared in this
+    /// file, e.g. `extern "C" fn`, and casts to an `extern` function pointer
+    /// type. Closures can't be coerced to an `extern` function pointer.
+    pub ffi_callbacks: u64,
+
+    /// Calls of `transmute` and `transmute_copy` in unsafe code reinterpreting
+    /// a struct, enum or union declared with a `#[repr(...)]` attribute in
+    /// this file, which depend on its layout, e.g. casts between `#[repr(C)]`
+    /// types at FFI boundaries. Types are not inferred, so a call is counted
+    /// when such a type is mentioned in its turbofish, or in the type of the
+    /// `let` binding it initializes.
+    pub layout_dependent_transmutes: u64,
+
+    /// Features enabled by the `#![feature(...)]` attributes of this file,
+    /// also when they are under a `#![cfg_attr(...)]`, which only a nightly
+    /// toolchain compiles.
+    pub nightly_features: Vec<String>,
+
+    /// Paths relative to `OUT_DIR` of the files included with
+    /// `include!(concat!(env!("OUT_DIR"), ...))`, generated by the build
+    /// script of the package.
+    pub out_dir_includes: Vec<String>,
+
+    /// Metrics of the inline modules of this file, by module path relative
+    /// to the file, e.g. `a::b` for `mod b` inside `mod a`. The metrics of a
+    /// module include the ones of its nested modules, and are also part of
+    /// `counters`. Test modules counted apart are left out.
+    pub module_counters: BTreeMap<String, CounterBlock>,
+}
+
+/// The kinds of `unsafe` code located by `UnsafeSpan`. An unsafe block with
+/// inline assembly is an `Asm` block, otherwise one calling `transmute` is a
+/// `Transmute` block, including the code of its nested blocks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnsafeKind {
+    Block,
+    Transmute,
+    Asm,
+    Function,
+    Method,
+    Impl,
+    Trait,
+}
+
+impl UnsafeKind {
+    pub fn description(self) -> &'static str {
+        match self {
+            UnsafeKind::Block => "unsafe block",
+            UnsafeKind::Transmute => "unsafe block calling transmute",
+            UnsafeKind::Asm => "unsafe block with inline assembly",
+            UnsafeKind::Function => "unsafe function",
+            UnsafeKind::Method => "unsafe method",
+            UnsafeKind::Impl => "unsafe impl",
+            UnsafeKind::Trait => "unsafe trait",
+        }
+    }
+}
+
+/// The location of an `unsafe` block, function, method, impl or trait in a
+/// source file. It starts at the `unsafe` keyword, or at `fn` for a function
+/// which is only unsafe because of its export attribute, and ends after the
+/// closing brace. As for `proc_macro2::LineColumn`, lines start at 1 and
+/// columns, counted in characters, at 0.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnsafeSpan {
+    pub kind: UnsafeKind,
+    pub line_start: usize,
+    pub column_start: usize,
+    pub line_end: usize,
+    pub column_end: usize,
 }
 
 #[derive(Debug)]
@@ -75,6 +237,87 @@ fn file_forbids_unsafe(f: &syn::File) -> bool {
     })
 }
 
+/// The features of `#![feature(...)]` and `#![cfg_attr(..., feature(...))]`
+/// inner attributes, whatever the condition of the `cfg_attr`.
+fn file_nightly_features(f: &syn::File) -> Vec<String> {
+    f.attrs
+        .iter()
+        .filter(|attr| matches!(attr.style, AttrStyle::Inner(_)))
+        .flat_map(|attr| {
+            if attr.path().is_ident("feature") {
+                attr.meta
+                    .require_list()
+                    .map(feature_names)
+                    .unwrap_or_default()
+            } else if attr.path().is_ident("cfg_attr") {
+                // The condition comes first, then the attributes.
+                attr.parse_args_with(
+                    Punctuated::<Meta, Token![,]>::parse_terminated,
+                )
+                .map(|metas| {
+                    metas
+                        .iter()
+                        .skip(1)
+                        .flat_map(|meta| match meta {
+                            Meta::List(meta_list)
+                                if meta_list.path.is_ident("feature") =>
+                            {
+                                feature_names(meta_list)
+                            }
+                            _ => vec![],
+                        })
+                        .collect::<Vec<String>>()
+                })
+                .unwrap_or_default()
+            } else {
+                vec![]
+            }
+        })
+        .collect()
+}
+
+fn feature_names(meta_list: &MetaList) -> Vec<String> {
+    let mut feature_names = Vec::new();
+    let _ = meta_list.parse_nested_meta(|meta| {
+        if let Some(ident) = meta.path.get_ident() {
+            feature_names.push(ident.to_string());
+        }
+        Ok(())
+    });
+    feature_names
+}
+
+/// Will return `Some(true)` when the last `unsafe_code` lint attribute is
+/// `allow` or `expect`, `Some(false)` when it is `warn`, `deny` or `forbid`,
+/// and `None` without such attribute.
+fn allows_unsafe_code(attrs: &[Attribute]) -> Option<bool> {
+    attrs.iter().fold(None, |allows, attr| {
+        let path = attr.path();
+        let allowed = if path.is_ident("allow") || path.is_ident("expect") {
+            true
+        } else if path.is_ident("warn")
+            || path.is_ident("deny")
+            || path.is_ident("forbid")
+        {
+            false
+        } else {
+            return allows;
+        };
+        let mut is_unsafe_code = false;
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("unsafe_code") {
+                is_unsafe_code = true;
+            }
+            Ok(())
+        });
+        if is_unsafe_code {
+            Some(allowed)
+        } else {
+            allows
+        }
+    })
+}
+
 fn is_test_fn(item_fn: &ItemFn) -> bool {
     item_fn
         .attrs
@@ -83,14 +326,33 @@ fn is_test_fn(item_fn: &ItemFn) -> bool {
 }
 
 fn has_unsafe_attributes(item_fn: &ItemFn) -> bool {
-    item_fn.attrs.iter().any(|attr| {
-        if attr.path().is_ident("no_mangle") {
+    has_export_attribute(&item_fn.attrs)
+}
+
+/// Will return true for `#[no_mangle]` and `#[export_name = "..."]`, as well
+/// as their `#[unsafe(no_mangle)]` and `#[unsafe(export_name = "...")]` forms.
+fn has_export_attribute(attrs: &[Attribute]) -> bool {
+    let is_export_path = |path: &syn::Path| {
+        path.is_ident("no_mangle") || path.is_ident("export_name")
+    };
+    attrs.iter().any(|attr| {
+        if is_export_path(attr.path()) {
             return true;
         }
-        if attr.path().is_ident("export_name") {
-            return true;
+        let mut found_export = false;
+        if attr.path().is_ident("unsafe") {
+            let _ = attr.parse_nested_meta(|meta| {
+                if is_export_path(&meta.path) {
+                    found_export = true;
+                }
+                // Skip the `= "..."` of `export_name`.
+                if meta.input.peek(syn::Token![=]) {
+                    meta.value()?.parse::<syn::Expr>()?;
+                }
+                Ok(())
+            });
         }
-        false
+        found_export
     })
 }
 
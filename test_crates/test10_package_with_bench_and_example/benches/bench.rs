@@ -0,0 +1,9 @@
+pub unsafe fn bench_unsafe_fn() -> u32 {
+    test10_package_with_bench_and_example::f()
+}
+
+fn main() {
+    unsafe {
+        bench_unsafe_fn();
+    }
+}
@@ -0,0 +1,9 @@
+pub unsafe fn example_unsafe_fn() -> u32 {
+    test10_package_with_bench_and_example::f()
+}
+
+fn main() {
+    unsafe {
+        example_unsafe_fn();
+    }
+}
@@ -0,0 +1,7 @@
+unsafe fn example_unsafe_fn() -> u32 {
+    1
+}
+
+fn main() {
+    let _ = unsafe { example_unsafe_fn() };
+}
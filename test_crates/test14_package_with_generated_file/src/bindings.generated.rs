@@ -0,0 +1,9 @@
+// Stands in for the output of a bindings generator.
+
+pub fn answer() -> u32 {
+    unsafe { raw_answer() }
+}
+
+unsafe fn raw_answer() -> u32 {
+    42
+}
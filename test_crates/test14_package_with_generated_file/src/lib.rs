@@ -0,0 +1,6 @@
+#[path = "bindings.generated.rs"]
+mod bindings;
+
+pub fn answer() -> u32 {
+    bindings::answer()
+}
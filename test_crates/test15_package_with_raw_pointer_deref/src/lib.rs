@@ -0,0 +1,13 @@
+pub fn first(values: &[u32]) -> u32 {
+    let pointer = values.as_ptr();
+    unsafe { *pointer }
+}
+
+pub fn second(values: &[u32]) -> u32 {
+    unsafe { *values.as_ptr().add(1) }
+}
+
+#[allow(unused_unsafe)]
+pub fn not_a_raw_pointer(value: &u32) -> u32 {
+    unsafe { *value }
+}
@@ -0,0 +1,2 @@
+#[cfg(feature = "test1_package_with_no_deps")]
+pub use test1_package_with_no_deps::*;
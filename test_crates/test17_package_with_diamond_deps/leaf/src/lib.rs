@@ -0,0 +1,3 @@
+pub fn leaf() -> u32 {
+    0
+}
@@ -0,0 +1,3 @@
+pub fn left() -> u32 {
+    shared::shared() + 1
+}
@@ -0,0 +1,3 @@
+pub fn right() -> u32 {
+    shared::shared() + 2
+}
@@ -0,0 +1,2 @@
+pub use left::left;
+pub use right::right;
@@ -0,0 +1,6 @@
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub fn f() -> u32 {
+    0
+}
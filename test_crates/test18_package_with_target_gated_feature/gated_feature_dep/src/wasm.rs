@@ -0,0 +1 @@
+pub unsafe fn wasm_only() {}
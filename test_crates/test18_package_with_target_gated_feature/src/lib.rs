@@ -0,0 +1 @@
+pub use gated_feature_dep::f;
@@ -0,0 +1,19 @@
+pub unsafe fn read(pointer: *const u32) -> u32 {
+    *pointer
+}
+
+pub fn add_one(value: u32) -> u32 {
+    value + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_test() {
+        let value = 1;
+        let read_value = unsafe { read(&value) };
+        assert_eq!(read_value, 1);
+    }
+}
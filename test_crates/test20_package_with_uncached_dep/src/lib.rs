@@ -0,0 +1,3 @@
+pub fn f() {
+    geiger_uncached_dep::g();
+}
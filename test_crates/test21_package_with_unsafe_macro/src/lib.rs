@@ -0,0 +1,17 @@
+#[macro_export]
+macro_rules! read_unaligned {
+    ($pointer:expr) => {
+        unsafe { std::ptr::read_unaligned($pointer) }
+    };
+}
+
+#[macro_export]
+macro_rules! zeroed {
+    ($type:ty) => {
+        unsafe { std::mem::zeroed::<$type>() }
+    };
+}
+
+pub fn first(values: &[u32]) -> u32 {
+    values[0]
+}
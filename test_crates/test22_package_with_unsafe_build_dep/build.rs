@@ -0,0 +1,3 @@
+fn main() {
+    println!("cargo:rustc-env=BUILD_VALUE={}", build_dep::value());
+}
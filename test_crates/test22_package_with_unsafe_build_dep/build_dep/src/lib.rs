@@ -0,0 +1,4 @@
+pub fn value() -> u32 {
+    let values = [3, 4];
+    unsafe { *values.as_ptr().add(1) }
+}
@@ -0,0 +1,4 @@
+pub fn value() -> u32 {
+    let values = [1, 2];
+    unsafe { *values.as_ptr() }
+}
@@ -0,0 +1,3 @@
+pub fn values() -> (u32, &'static str) {
+    (runtime_dep::value(), env!("BUILD_VALUE"))
+}
@@ -0,0 +1,8 @@
+#[no_mangle]
+pub extern "C" fn test23_add(first: u32, second: u32) -> u32 {
+    first + second
+}
+
+pub fn add(first: u32, second: u32) -> u32 {
+    test23_add(first, second)
+}
@@ -0,0 +1,11 @@
+pub mod reader;
+
+pub fn answer() -> u32 {
+    42
+}
+
+pub mod writer {
+    pub fn write(pointer: *mut u32, value: u32) {
+        unsafe { *pointer = value }
+    }
+}
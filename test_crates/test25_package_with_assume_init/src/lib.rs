@@ -0,0 +1,7 @@
+use std::mem::MaybeUninit;
+
+pub fn initialized() -> u32 {
+    let mut value = MaybeUninit::<u32>::uninit();
+    value.write(1);
+    unsafe { value.assume_init() }
+}
@@ -0,0 +1,9 @@
+pub unsafe fn helper_unsafe_fn() -> u32 {
+    test26_package_with_custom_path_example::f()
+}
+
+pub fn run() {
+    unsafe {
+        helper_unsafe_fn();
+    }
+}
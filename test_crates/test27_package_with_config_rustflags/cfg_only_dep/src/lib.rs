@@ -0,0 +1,3 @@
+pub unsafe fn read(pointer: *const u32) -> u32 {
+    *pointer
+}
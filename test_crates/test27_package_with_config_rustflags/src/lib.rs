@@ -0,0 +1,5 @@
+#[cfg(geiger_cfg)]
+mod with_cfg;
+
+#[cfg(not(geiger_cfg))]
+mod without_cfg;
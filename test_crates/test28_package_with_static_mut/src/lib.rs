@@ -0,0 +1,8 @@
+static mut COUNTER: u32 = 0;
+
+pub fn increment() -> u32 {
+    unsafe {
+        COUNTER += 1;
+        COUNTER
+    }
+}
@@ -0,0 +1,18 @@
+#[allow(unsafe_code)]
+pub mod ffi {
+    /// # Safety
+    ///
+    /// Always safe to call, only here to be called from unsafe code.
+    pub unsafe fn zeroed() -> u32 {
+        0
+    }
+
+    pub fn value() -> u32 {
+        unsafe { zeroed() }
+    }
+}
+
+pub fn first(bytes: &[u8]) -> u8 {
+    assert!(!bytes.is_empty());
+    unsafe { *bytes.as_ptr() }
+}
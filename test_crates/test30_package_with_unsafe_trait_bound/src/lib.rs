@@ -0,0 +1,17 @@
+/// # Safety
+///
+/// The all zero bit pattern must be a valid value of the type.
+pub unsafe trait Zeroable: Copy {}
+
+unsafe impl Zeroable for u32 {}
+
+pub fn zeroed<T: Zeroable>() -> T {
+    unsafe { std::mem::zeroed() }
+}
+
+pub fn boxed<F>(f: F) -> Box<dyn FnOnce() + Send>
+where
+    F: FnOnce() + Send + 'static,
+{
+    Box::new(f)
+}
@@ -0,0 +1,31 @@
+pub struct Buffer {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Buffer {
+    pub fn new(len: usize) -> Self {
+        let mut bytes = vec![0u8; len].into_boxed_slice();
+        let ptr = bytes.as_mut_ptr();
+        std::mem::forget(bytes);
+        Buffer { ptr, len }
+    }
+
+    pub fn first(&self) -> Option<u8> {
+        if self.len == 0 {
+            None
+        } else {
+            Some(unsafe { *self.ptr })
+        }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                self.ptr, self.len,
+            )));
+        }
+    }
+}
@@ -0,0 +1,13 @@
+// Only enabled when building the documentation on docs.rs, with a nightly
+// toolchain, so that the crate still builds on stable.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[cfg(unix)]
+pub fn first(bytes: &[u8]) -> Option<u8> {
+    if bytes.is_empty() {
+        None
+    } else {
+        Some(unsafe { *bytes.as_ptr() })
+    }
+}
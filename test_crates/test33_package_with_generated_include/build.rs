@@ -0,0 +1,12 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(
+        Path::new(&out_dir).join("bindings.rs"),
+        "pub unsafe fn first(bytes: *const u8) -> u8 {\n    *bytes\n}\n",
+    )
+    .unwrap();
+}
@@ -0,0 +1,9 @@
+include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+pub fn first_or_zero(bytes: &[u8]) -> u8 {
+    if bytes.is_empty() {
+        0
+    } else {
+        unsafe { first(bytes.as_ptr()) }
+    }
+}
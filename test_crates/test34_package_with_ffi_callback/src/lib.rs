@@ -0,0 +1,28 @@
+use std::os::raw::{c_int, c_void};
+
+extern "C" {
+    fn qsort(
+        base: *mut c_void,
+        nmemb: usize,
+        size: usize,
+        compar: Option<
+            unsafe extern "C" fn(*const c_void, *const c_void) -> c_int,
+        >,
+    );
+}
+
+unsafe extern "C" fn compare(a: *const c_void, b: *const c_void) -> c_int {
+    let (a, b) = (*(a as *const i32), *(b as *const i32));
+    a.cmp(&b) as c_int
+}
+
+pub fn sort(values: &mut [i32]) {
+    unsafe {
+        qsort(
+            values.as_mut_ptr() as *mut c_void,
+            values.len(),
+            std::mem::size_of::<i32>(),
+            Some(compare),
+        );
+    }
+}
@@ -0,0 +1,19 @@
+//! A local fork of ref_slice, with an unsafe function the published crate
+//! doesn't have.
+
+use std::slice;
+
+pub fn ref_slice<A>(s: &A) -> &[A] {
+    unsafe { slice::from_raw_parts(s, 1) }
+}
+
+pub fn mut_ref_slice<A>(s: &mut A) -> &mut [A] {
+    unsafe { slice::from_raw_parts_mut(s, 1) }
+}
+
+/// # Safety
+///
+/// `p` must be valid for reads of a value of type `A`.
+pub unsafe fn ptr_slice<'a, A>(p: *const A) -> &'a [A] {
+    slice::from_raw_parts(p, 1)
+}
@@ -0,0 +1,16 @@
+use std::mem;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Header {
+    pub magic: u32,
+    pub len: u32,
+}
+
+pub fn parse_header(bytes: [u8; 8]) -> Header {
+    unsafe { mem::transmute::<[u8; 8], Header>(bytes) }
+}
+
+pub fn to_bits(value: f32) -> u32 {
+    unsafe { mem::transmute::<f32, u32>(value) }
+}